@@ -0,0 +1,10 @@
+//! Prints out the share of the final DOM attributable to ads and trackers, as matched by a filter
+//! list, along with a single comparable score for the page.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, filter_rules: &[String], output_format: OutputFormat) {
+    println!("{}", config::to_json(output_format, &graph.ad_dom_share(filter_rules)));
+}
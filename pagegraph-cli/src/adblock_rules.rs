@@ -2,7 +2,9 @@
 
 use pagegraph::graph::PageGraph;
 
-pub fn main(graph: &PageGraph, filter_rules: Vec<String>) {
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, filter_rules: Vec<String>, output_format: OutputFormat) {
     let matching_elements = graph.resources_matching_filters(graph, filter_rules);
-    println!("{}", serde_json::to_string(&matching_elements).unwrap())
+    println!("{}", config::to_json(output_format, &matching_elements))
 }
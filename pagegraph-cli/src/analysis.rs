@@ -0,0 +1,28 @@
+//! Runs an analysis from the registry by name, or lists every registered analysis.
+
+use pagegraph::analysis::registry::AnalysisRegistry;
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+/// A registered analysis, as listed in the output.
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct AnalysisListing<'a> {
+    name: &'a str,
+    description: &'a str,
+}
+
+pub fn main(graph: &PageGraph, registry: &AnalysisRegistry, name: Option<&str>, output_format: OutputFormat) {
+    match name {
+        Some(name) => {
+            let results = registry.run(name, graph).unwrap_or_else(|| panic!("No analysis named {} is registered; run without a name to list them", name));
+            println!("{}", config::to_json(output_format, &results));
+        }
+        None => {
+            let analyses = registry.analyses().into_iter()
+                .map(|analysis| AnalysisListing { name: analysis.name(), description: analysis.description() })
+                .collect::<Vec<_>>();
+            println!("{}", config::to_json(output_format, &analyses));
+        }
+    }
+}
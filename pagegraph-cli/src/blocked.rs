@@ -0,0 +1,9 @@
+//! Prints out every resource blocked by Brave Shields during the page load.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, output_format: OutputFormat) {
+    println!("{}", config::to_json(output_format, &graph.blocked_resources()));
+}
@@ -0,0 +1,58 @@
+//! Prints out first-party requests whose hosts are CNAME aliases for known tracker domains.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::process::Command;
+
+use pagegraph::analysis::cname_cloaking::CnameResolver;
+use pagegraph::analysis::trackers::TrackerCategories;
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+/// Resolves CNAME chains live, using the system `dig`. Hosts that can't be resolved, including
+/// every host if `dig` can't be run, have no chain.
+#[derive(Default)]
+struct DigResolver {
+    /// Whether a failure to resolve a host has been reported, so that it's only reported once.
+    warned: Cell<bool>,
+}
+
+impl DigResolver {
+    fn warn(&self, message: &str) {
+        if !self.warned.replace(true) {
+            eprintln!("Warning: {}; treating unresolved hosts as having no CNAME records", message);
+        }
+    }
+}
+
+impl CnameResolver for DigResolver {
+    fn cname_chain(&self, host: &str) -> Vec<String> {
+        let output = match Command::new("dig").args(["+short", host]).output() {
+            Ok(output) => output,
+            Err(e) => {
+                self.warn(&format!("could not run dig: {}", e));
+                return vec![];
+            }
+        };
+        if !output.status.success() {
+            self.warn(&format!("dig exited with {} while resolving {}", output.status, host));
+            return vec![];
+        }
+        // `+short` prints the CNAME chain as fully-qualified names, followed by the addresses.
+        String::from_utf8_lossy(&output.stdout).lines()
+            .map(|line| line.trim())
+            .filter(|line| line.ends_with('.'))
+            .map(|line| line.to_string())
+            .collect()
+    }
+}
+
+/// If `cnames` is given, it is used instead of resolving hosts live.
+pub fn main(graph: &PageGraph, trackers: &TrackerCategories, cnames: Option<&HashMap<String, Vec<String>>>, output_format: OutputFormat) {
+    let cloaked = match cnames {
+        Some(cnames) => graph.cname_cloaked_requests(cnames, trackers),
+        None => graph.cname_cloaked_requests(&DigResolver::default(), trackers),
+    };
+    println!("{}", config::to_json(output_format, &cloaked));
+}
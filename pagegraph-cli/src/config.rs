@@ -0,0 +1,464 @@
+//! Defaults for command line options, read from a `pagegraph.toml` file.
+//!
+//! The file is read from the path given with `--config`, or otherwise from `pagegraph.toml` in
+//! the working directory, if there is one. Options given on the command line are used alongside,
+//! or instead of, those in the file. Every setting is optional:
+//!
+//! ```toml
+//! # Filter lists used by subcommands that take adblock rules, along with any given with --list
+//! filter_lists = ["lists/custom.txt"]
+//! # Also use the well-known lists, as with --default-lists, with the default-lists feature
+//! default_lists = true
+//! # "json", the default, or "pretty" for indented JSON
+//! format = "pretty"
+//! # Where downloaded filter lists are cached, instead of $XDG_CACHE_HOME/pagegraph
+//! cache_dir = "/var/cache/pagegraph"
+//! # How many remote frame graphs are read at once, instead of one per CPU
+//! parallelism = 4
+//!
+//! # Revisions of the well-known lists, as with --pin; pins on the command line take precedence
+//! [pins]
+//! easylist = "<commit>"
+//!
+//! # Cutoffs used by analyses, see pagegraph::analysis::Thresholds
+//! [thresholds]
+//! likely_fingerprinting_categories = 2
+//! min_identifier_length = 12
+//! ```
+//!
+//! There's no TOML parser among the CLI's dependencies, so only the subset of TOML needed for
+//! these settings is supported: comments, `[table]` headers, and `key = value` pairs, where values
+//! are strings, integers, booleans, or arrays of them.
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+
+use pagegraph::analysis::Thresholds;
+
+/// The configuration file read from the working directory if `--config` isn't given.
+const DEFAULT_CONFIG_FILE: &str = "pagegraph.toml";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Compact JSON on a single line.
+    #[default]
+    Json,
+    /// Indented JSON.
+    Pretty,
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub filter_lists: Vec<PathBuf>,
+    pub default_lists: bool,
+    /// Pins for the default lists, as `NAME=REVISION`.
+    pub pins: Vec<String>,
+    pub format: OutputFormat,
+    pub cache_dir: Option<PathBuf>,
+    pub parallelism: Option<usize>,
+    pub thresholds: Thresholds,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+/// A parser for the supported subset of TOML, producing each value keyed by its table and key,
+/// like `thresholds.min_identifier_length`.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { chars: text.chars().peekable(), line: 1 }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line, message))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skips spaces and tabs, and a comment running to the end of the line.
+    fn skip_inline_whitespace(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' => { self.next(); }
+                '#' => while self.chars.peek().map(|&c| c != '\n').unwrap_or(false) {
+                    self.next();
+                },
+                _ => break,
+            }
+        }
+    }
+
+    /// Skips whitespace, comments, and line breaks.
+    fn skip_whitespace(&mut self) {
+        loop {
+            self.skip_inline_whitespace();
+            match self.chars.peek() {
+                Some('\n') | Some('\r') => { self.next(); }
+                _ => break,
+            }
+        }
+    }
+
+    /// Expects nothing but a comment before the end of the line.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_inline_whitespace();
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some('\r') if self.chars.peek() == Some(&'\n') => Ok(()),
+            Some(c) => self.error(&format!("unexpected {:?} after value", c)),
+        }
+    }
+
+    /// Reads a bare key, or a dotted table name for headers.
+    fn key(&mut self, dotted: bool) -> Result<String, String> {
+        let mut key = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || (dotted && c == '.') {
+                key.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        if key.is_empty() {
+            return self.error("expected a key");
+        }
+        Ok(key)
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, String> {
+        let mut string = String::new();
+        loop {
+            // Line breaks aren't consumed, so the error is reported on the string's line.
+            let c = match self.chars.peek() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some(_) => self.next().unwrap(),
+            };
+            match c {
+                c if c == quote => return Ok(string),
+                // Literal strings, in single quotes, don't have escapes.
+                '\\' if quote == '"' => match self.next() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('r') => string.push('\r'),
+                    Some('u') => {
+                        let hex = (0..4).filter_map(|_| self.next()).collect::<String>();
+                        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => string.push(c),
+                            None => return self.error("invalid unicode escape"),
+                        }
+                    }
+                    _ => return self.error("unsupported escape sequence"),
+                },
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.chars.peek() {
+            Some(&quote) if quote == '"' || quote == '\'' => {
+                self.next();
+                self.string(quote).map(Value::String)
+            }
+            Some('[') => {
+                self.next();
+                let mut items = vec![];
+                loop {
+                    self.skip_whitespace();
+                    if self.chars.peek() == Some(&']') {
+                        self.next();
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.next() {
+                        Some(',') => (),
+                        Some(']') => return Ok(Value::Array(items)),
+                        _ => return self.error("expected , or ] in array"),
+                    }
+                }
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+' || c == '.' {
+                        word.push(c);
+                        self.next();
+                    } else {
+                        break;
+                    }
+                }
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => match word.replace('_', "").parse::<i64>() {
+                        Ok(integer) => Ok(Value::Integer(integer)),
+                        Err(_) => self.error(&format!("unsupported value {:?}", word)),
+                    },
+                }
+            }
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn parse(mut self) -> Result<BTreeMap<String, Value>, String> {
+        let mut values = BTreeMap::new();
+        let mut table = None;
+        loop {
+            self.skip_whitespace();
+            match self.chars.peek() {
+                None => return Ok(values),
+                Some('[') => {
+                    self.next();
+                    self.skip_inline_whitespace();
+                    table = Some(self.key(true)?);
+                    self.skip_inline_whitespace();
+                    if self.chars.peek() != Some(&']') {
+                        return self.error("expected ] after table name");
+                    }
+                    self.next();
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let line = self.line;
+                    let key = self.key(false)?;
+                    self.skip_inline_whitespace();
+                    if self.chars.peek() != Some(&'=') {
+                        return self.error(&format!("expected = after {}", key));
+                    }
+                    self.next();
+                    self.skip_inline_whitespace();
+                    let value = self.value()?;
+                    self.end_of_line()?;
+                    let key = match &table {
+                        Some(table) => format!("{}.{}", table, key),
+                        None => key,
+                    };
+                    if values.insert(key.clone(), value).is_some() {
+                        return Err(format!("line {}: {} is set more than once", line, key));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Value {
+    fn as_string(&self, key: &str) -> &str {
+        match self {
+            Value::String(string) => string,
+            _ => panic!("{} should be a string in the configuration file", key),
+        }
+    }
+
+    fn as_bool(&self, key: &str) -> bool {
+        match self {
+            Value::Boolean(boolean) => *boolean,
+            _ => panic!("{} should be true or false in the configuration file", key),
+        }
+    }
+
+    fn as_usize(&self, key: &str) -> usize {
+        match self {
+            Value::Integer(integer) if *integer >= 0 => *integer as usize,
+            _ => panic!("{} should be a non-negative integer in the configuration file", key),
+        }
+    }
+
+    fn as_strings(&self, key: &str) -> Vec<String> {
+        match self {
+            Value::Array(items) => items.iter().map(|item| item.as_string(key).to_string()).collect(),
+            _ => panic!("{} should be an array of strings in the configuration file", key),
+        }
+    }
+}
+
+impl Config {
+    /// Parses the contents of a configuration file. Relative paths are resolved from `base_dir`.
+    /// Panics on unknown settings or values of the wrong type.
+    pub fn parse(text: &str, base_dir: &Path) -> Self {
+        let values = Parser::new(text).parse().unwrap_or_else(|e| panic!("Could not parse the configuration file: {}", e));
+        let mut config = Self::default();
+        for (key, value) in values.iter() {
+            match key.as_str() {
+                "filter_lists" => config.filter_lists = value.as_strings(key).into_iter().map(|path| base_dir.join(path)).collect(),
+                "default_lists" => config.default_lists = value.as_bool(key),
+                "format" => config.format = match value.as_string(key) {
+                    "json" => OutputFormat::Json,
+                    "pretty" => OutputFormat::Pretty,
+                    format => panic!("Unsupported output format {}; expected json or pretty", format),
+                },
+                "cache_dir" => config.cache_dir = Some(base_dir.join(value.as_string(key))),
+                "parallelism" => config.parallelism = Some(value.as_usize(key).max(1)),
+                "thresholds.likely_fingerprinting_categories" => config.thresholds.likely_fingerprinting_categories = value.as_usize(key),
+                "thresholds.min_identifier_length" => config.thresholds.min_identifier_length = value.as_usize(key),
+                _ => match key.strip_prefix("pins.") {
+                    Some(list) => config.pins.push(format!("{}={}", list, value.as_string(key))),
+                    None => panic!("Unknown setting {} in the configuration file", key),
+                },
+            }
+        }
+        config
+    }
+
+    /// Reads the configuration file at `path`, or `pagegraph.toml` in the working directory if
+    /// no path is given. Without either, every setting has its default value.
+    pub fn load(path: Option<&str>) -> Self {
+        let path = match path {
+            Some(path) => Path::new(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Path::new(DEFAULT_CONFIG_FILE),
+            None => return Self::default(),
+        };
+        let text = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Could not read {}: {}", path.display(), e));
+        Self::parse(&text, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    /// How many remote frame graphs should be read at once.
+    pub fn parallelism(&self) -> usize {
+        self.parallelism.unwrap_or_else(|| std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1))
+    }
+}
+
+/// Serializes subcommand output in the given format.
+pub fn to_json<T: serde::Serialize + ?Sized>(format: OutputFormat, value: &T) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string(value).unwrap(),
+        OutputFormat::Pretty => serde_json::to_string_pretty(value).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<BTreeMap<String, Value>, String> {
+        Parser::new(text).parse()
+    }
+
+    #[test]
+    fn test_strings() {
+        let values = parse(concat!(
+            "basic = \"a \\\"quoted\\\" \\\\ path\\n\\t\\u00e9\" # comment\n",
+            "literal = 'C:\\lists\\custom.txt'\n",
+            "hash = \"not # a comment\"\n",
+        )).unwrap();
+        assert_eq!(values["basic"], Value::String("a \"quoted\" \\ path\n\té".to_string()));
+        assert_eq!(values["literal"], Value::String("C:\\lists\\custom.txt".to_string()));
+        assert_eq!(values["hash"], Value::String("not # a comment".to_string()));
+
+        assert_eq!(parse("key = \"open\n"), Err("line 1: unterminated string".to_string()));
+        assert_eq!(parse("key = \"\\x\""), Err("line 1: unsupported escape sequence".to_string()));
+        assert_eq!(parse("key = \"\\uzzzz\""), Err("line 1: invalid unicode escape".to_string()));
+    }
+
+    #[test]
+    fn test_values_and_arrays() {
+        let values = parse(concat!(
+            "enabled = true\r\n",
+            "count = 1_000\n",
+            "offset = -2\n",
+            "empty = []\n",
+            "lists = [\n",
+            "  \"a.txt\", # first\n",
+            "  'b.txt',\n",
+            "]\n",
+            "nested = [[1, 2], [false]]\n",
+        )).unwrap();
+        assert_eq!(values["enabled"], Value::Boolean(true));
+        assert_eq!(values["count"], Value::Integer(1000));
+        assert_eq!(values["offset"], Value::Integer(-2));
+        assert_eq!(values["empty"], Value::Array(vec![]));
+        assert_eq!(values["lists"], Value::Array(vec![Value::String("a.txt".to_string()), Value::String("b.txt".to_string())]));
+        assert_eq!(values["nested"], Value::Array(vec![
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+            Value::Array(vec![Value::Boolean(false)]),
+        ]));
+
+        assert_eq!(parse("lists = [\"a\" \"b\"]"), Err("line 1: expected , or ] in array".to_string()));
+        assert_eq!(parse("key = 1.5"), Err("line 1: unsupported value \"1.5\"".to_string()));
+        assert_eq!(parse("key = yes"), Err("line 1: unsupported value \"yes\"".to_string()));
+        assert_eq!(parse("key = true false"), Err("line 1: unexpected 'f' after value".to_string()));
+    }
+
+    #[test]
+    fn test_tables() {
+        let values = parse(concat!(
+            "format = \"pretty\"\n",
+            "[pins]\n",
+            "easylist = \"abc123\"\n",
+            "\n",
+            "[ thresholds ] # cutoffs\n",
+            "min_identifier_length = 12\n",
+            "[a.b]\n",
+            "c = 1\n",
+        )).unwrap();
+        assert_eq!(values.keys().collect::<Vec<_>>(), ["a.b.c", "format", "pins.easylist", "thresholds.min_identifier_length"]);
+
+        assert_eq!(parse("[pins"), Err("line 1: expected ] after table name".to_string()));
+        assert_eq!(parse("[]"), Err("line 1: expected a key".to_string()));
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        assert_eq!(parse("a = 1\nb = 2\na = 3\n"), Err("line 3: a is set more than once".to_string()));
+        assert_eq!(parse("[pins]\neasylist = 'a'\n\n# again\neasylist = 'b'\n"), Err("line 5: pins.easylist is set more than once".to_string()));
+        // The same key in different tables is fine.
+        assert!(parse("key = 1\n[pins]\nkey = 'a'\n").is_ok());
+    }
+
+    #[test]
+    fn test_error_lines() {
+        assert_eq!(parse("# settings\n\nformat = \"json\"\ncache_dir\n"), Err("line 4: expected = after cache_dir".to_string()));
+        assert_eq!(parse("lists = [\n  'a',\n  'b'\n  'c'\n]\n"), Err("line 4: expected , or ] in array".to_string()));
+        assert_eq!(parse("a = 1\r\n= 2\r\n"), Err("line 2: expected a key".to_string()));
+    }
+
+    #[test]
+    fn test_config() {
+        let config = Config::parse(concat!(
+            "filter_lists = [\"lists/custom.txt\"]\n",
+            "default_lists = true\n",
+            "format = \"pretty\"\n",
+            "parallelism = 0\n",
+            "[pins]\n",
+            "easylist = \"abc123\"\n",
+            "[thresholds]\n",
+            "min_identifier_length = 12\n",
+        ), Path::new("/etc/pagegraph"));
+        assert_eq!(config.filter_lists, [PathBuf::from("/etc/pagegraph/lists/custom.txt")]);
+        assert!(config.default_lists);
+        assert_eq!(config.format, OutputFormat::Pretty);
+        assert_eq!(config.parallelism, Some(1));
+        assert_eq!(config.pins, ["easylist=abc123"]);
+        assert_eq!(config.thresholds.min_identifier_length, 12);
+        assert_eq!(to_json(config.format, &[1]), "[\n  1\n]");
+        assert_eq!(to_json(OutputFormat::Json, &[1]), "[1]");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown setting thresholds.unknown in the configuration file")]
+    fn test_unknown_setting() {
+        Config::parse("[thresholds]\nunknown = 1\n", Path::new(""));
+    }
+}
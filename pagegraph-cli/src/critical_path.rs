@@ -0,0 +1,9 @@
+//! Prints out the approximate chain of render-blocking requests delaying `DOMContentLoaded`.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, output_format: OutputFormat) {
+    println!("{}", config::to_json(output_format, &graph.critical_path()));
+}
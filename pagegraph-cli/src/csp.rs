@@ -0,0 +1,14 @@
+//! Prints out the minimal Content Security Policy the page needs, or the loads a given policy
+//! would block.
+
+use pagegraph::analysis::csp::ContentSecurityPolicy;
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, policy: Option<&str>, output_format: OutputFormat) {
+    match policy {
+        Some(policy) => println!("{}", config::to_json(output_format, &graph.csp_violations(&ContentSecurityPolicy::from(policy)))),
+        None => println!("{}", graph.infer_csp()),
+    }
+}
@@ -5,7 +5,9 @@ use pagegraph::graph::DownstreamRequests;
 use pagegraph::types::{NodeType, RequestType};
 use std::collections::HashSet;
 
-pub fn main(graph: &PageGraph, edge_id: EdgeId, just_requests: bool) {
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, edge_id: EdgeId, just_requests: bool, output_format: OutputFormat) {
     let edge = graph.edges.get(&edge_id).unwrap();
     if just_requests {
         let mut request_ids = HashSet::new();
@@ -25,7 +27,7 @@ pub fn main(graph: &PageGraph, edge_id: EdgeId, just_requests: bool) {
                     }
                 }
             });
-        println!("{}", serde_json::to_string(&request_ids).unwrap());
+        println!("{}", config::to_json(output_format, &request_ids));
         return;
     }
     let all_downstream_requests = graph
@@ -44,7 +46,7 @@ pub fn main(graph: &PageGraph, edge_id: EdgeId, just_requests: bool) {
                 node_id: node.id,
                 children: all_downstream_requests
             };
-            println!("{}", serde_json::to_string(&top_level).unwrap());
+            println!("{}", config::to_json(output_format, &top_level));
         },
         _ => panic!("Edge is not a RequestStart!")
     };
@@ -0,0 +1,104 @@
+//! Exports the graph, optionally reduced to the part connected to a node or pruned of DOM
+//! detail, for viewing in other tools. Parts of the graph can be highlighted, either within the
+//! HTML viewer or as a list of ids for other viewers. The numeric format instead writes files for
+//! network analysis and graph machine learning tools:
+//!
+//! - `edges.txt`, an edge list of node rows;
+//! - `adjacency.mtx`, the sparse adjacency matrix in Matrix Market format;
+//! - `nodes.csv`, mapping rows to node ids and kinds;
+//! - `edges.csv`, mapping edge list lines to edge ids and kinds.
+
+use std::path::Path;
+
+use pagegraph::export::{self, Highlight, NodeRows};
+use pagegraph::graph::{EdgeId, NodeId, PageGraph};
+use pagegraph::prune::{Reachability, TypeMask};
+use pagegraph::types::{EdgeKind, NodeKind};
+use pagegraph::url_search::UrlPattern;
+
+use crate::config::{self, OutputFormat};
+
+pub enum Format {
+    Html,
+    /// Serialized as JSON in `output_format`.
+    Highlight { output_format: OutputFormat },
+    /// With one-hot encodings of node and edge kinds if `one_hot` is set.
+    Numeric { one_hot: bool },
+}
+
+impl Format {
+    pub fn new(name: &str, one_hot: bool, output_format: OutputFormat) -> Self {
+        match name {
+            "html" => Self::Html,
+            "highlight" => Self::Highlight { output_format },
+            "numeric" => Self::Numeric { one_hot },
+            _ => panic!("Unsupported export format {}", name),
+        }
+    }
+}
+
+/// Writes the files of the numeric format into `dir`.
+fn write_numeric(graph: &PageGraph, dir: &Path, one_hot: bool) {
+    std::fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Could not create {}: {}", dir.display(), e));
+    let rows = NodeRows::of(graph);
+    let files = [
+        ("edges.txt", export::edge_list(graph, &rows)),
+        ("adjacency.mtx", export::adjacency_matrix(graph, &rows)),
+        ("nodes.csv", export::node_table(graph, &rows, one_hot)),
+        ("edges.csv", export::edge_table(graph, one_hot)),
+    ];
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap_or_else(|e| panic!("Could not write {}: {}", path.display(), e));
+    }
+}
+
+pub fn main(graph: PageGraph, format: Format, from: Option<NodeId>, prune: bool, downstream: Option<EdgeId>, url: Option<&str>, out: Option<&Path>) {
+    // Highlights are found in the full graph, so that pruned nodes don't break up the effects of
+    // an edge.
+    let highlight = match (downstream, url) {
+        (None, None) => None,
+        _ => {
+            let mut highlight = Highlight::new();
+            if let Some(edge_id) = downstream {
+                let edge = graph.edges.get(&edge_id).unwrap_or_else(|| panic!("No edge with id {} was found in this graph", edge_id));
+                highlight.extend(Highlight::downstream_of(&graph, edge));
+            }
+            if let Some(url) = url {
+                highlight.extend(Highlight::url_matches(&graph, &UrlPattern::glob(url)));
+            }
+            Some(highlight)
+        }
+    };
+
+    let mut graph = match from {
+        Some(node_id) => {
+            if !graph.nodes.contains_key(&node_id) {
+                panic!("No node with id {} was found in this graph", node_id);
+            }
+            graph.reachable_from(node_id, Reachability::Both)
+        }
+        None => graph,
+    };
+    if prune {
+        graph = graph.prune(TypeMask::all()
+            .without_node(NodeKind::TextNode)
+            .without_edge(EdgeKind::SetAttribute)
+            .without_edge(EdgeKind::DeleteAttribute));
+    }
+
+    let contents = match format {
+        Format::Html => export::html(&graph, highlight.as_ref()),
+        // Without an explicit selection, the highlight is whatever remains after reducing.
+        Format::Highlight { output_format } => format!("{}\n", config::to_json(output_format, &highlight.unwrap_or_else(|| Highlight::of(&graph)))),
+        Format::Numeric { one_hot } => {
+            let dir = out.expect("The numeric format is written to a directory, which must be given with --out");
+            write_numeric(&graph, dir, one_hot);
+            return;
+        }
+    };
+    match out {
+        Some(path) => std::fs::write(path, contents).unwrap_or_else(|e| panic!("Could not write {}: {}", path.display(), e)),
+        None => print!("{}", contents),
+    }
+}
@@ -0,0 +1,104 @@
+//! Downloading and caching of well-known filter lists, for subcommands that match requests
+//! against adblock rules.
+//!
+//! Lists are fetched from their git repositories with the system `curl`, and cached under
+//! `$XDG_CACHE_HOME/pagegraph/filter_lists` (or `~/.cache/pagegraph/filter_lists`), unless another
+//! cache directory is configured. Each list is fetched at a revision, which defaults to the branch
+//! the list is published from, but can be pinned to a commit or tag for reproducible results.
+//! Cached copies of pinned revisions are reused indefinitely, while the default revision is
+//! refreshed once its copy is a day old.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// How long a cached copy of a list's default revision is used before it's fetched again.
+const DEFAULT_REVISION_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub struct FilterList {
+    pub name: &'static str,
+    /// The URL of the list, with `{revision}` in place of the git revision.
+    url: &'static str,
+    default_revision: &'static str,
+}
+
+pub const DEFAULT_LISTS: [FilterList; 3] = [
+    FilterList {
+        name: "easylist",
+        url: "https://raw.githubusercontent.com/easylist/easylist/{revision}/easylist.txt",
+        default_revision: "gh-pages",
+    },
+    FilterList {
+        name: "easyprivacy",
+        url: "https://raw.githubusercontent.com/easylist/easylist/{revision}/easyprivacy.txt",
+        default_revision: "gh-pages",
+    },
+    FilterList {
+        name: "ublock-filters",
+        url: "https://raw.githubusercontent.com/uBlockOrigin/uAssets/{revision}/filters/filters.txt",
+        default_revision: "master",
+    },
+];
+
+/// The directory downloaded lists are cached in, under `cache_dir` if one is configured.
+fn lists_dir(cache_dir: Option<&Path>) -> Result<PathBuf, String> {
+    let base = match cache_dir {
+        Some(cache_dir) => cache_dir.to_path_buf(),
+        None => std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .ok_or("could not determine a cache directory; set XDG_CACHE_HOME or HOME")?
+            .join("pagegraph"),
+    };
+    Ok(base.join("filter_lists"))
+}
+
+impl FilterList {
+    /// Returns the rules in the list at the given revision, or the default revision if none is
+    /// given, downloading it if there's no usable cached copy.
+    pub fn rules(&self, revision: Option<&str>, cache_dir: Option<&Path>) -> Result<Vec<String>, String> {
+        let revision = revision.unwrap_or(self.default_revision);
+        // Revisions may be branch names containing `/`.
+        let path = lists_dir(cache_dir)?.join(self.name).join(format!("{}.txt", revision.replace('/', "_")));
+
+        let is_fresh = std::fs::metadata(&path).and_then(|metadata| metadata.modified())
+            .map(|modified| revision != self.default_revision || modified.elapsed().map(|age| age < DEFAULT_REVISION_MAX_AGE).unwrap_or(false))
+            .unwrap_or(false);
+        if !is_fresh {
+            let dir = path.parent().unwrap();
+            std::fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+            let url = self.url.replace("{revision}", revision);
+            let download_path = path.with_extension("download");
+            let status = Command::new("curl")
+                .args(["--silent", "--show-error", "--fail", "--location", "--output"])
+                .arg(&download_path)
+                .arg(&url)
+                .status()
+                .map_err(|e| format!("could not run curl to download the {} filter list: {}", self.name, e))?;
+            if !status.success() {
+                return Err(format!("failed to download the {} filter list from {}", self.name, url));
+            }
+            std::fs::rename(&download_path, &path).map_err(|e| format!("could not move {} into the cache: {}", download_path.display(), e))?;
+        }
+
+        let list = std::fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Ok(list.lines().map(|line| line.to_string()).collect())
+    }
+}
+
+/// Parses `NAME=REVISION` pins for the default lists. Unknown list names are an error.
+pub fn parse_pins<'a>(pins: impl Iterator<Item=&'a str>) -> Result<HashMap<&'a str, &'a str>, String> {
+    pins.map(|pin| {
+        let (name, revision) = pin.split_once('=').ok_or_else(|| format!("pin {} should be given as NAME=REVISION", pin))?;
+        if !DEFAULT_LISTS.iter().any(|list| list.name == name) {
+            return Err(format!("unknown filter list {}; expected one of {}", name, DEFAULT_LISTS.iter().map(|list| list.name).collect::<Vec<_>>().join(", ")));
+        }
+        Ok((name, revision))
+    }).collect()
+}
+
+/// Returns the rules of every default list, at the pinned revision for each list that has one.
+pub fn default_list_rules(pins: &HashMap<&str, &str>, cache_dir: Option<&Path>) -> Result<Vec<String>, String> {
+    let lists = DEFAULT_LISTS.iter().map(|list| list.rules(pins.get(list.name).copied(), cache_dir)).collect::<Result<Vec<_>, _>>()?;
+    Ok(lists.concat())
+}
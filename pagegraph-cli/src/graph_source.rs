@@ -0,0 +1,221 @@
+//! Opening graphs given as local paths or URLs.
+//!
+//! Graphs can be given as `http://` or `https://` URLs, or as `s3://` URLs with the `s3` feature.
+//! Gzipped graphs, with a `.gz` extension, are decompressed as they're read. Remote graphs are
+//! streamed into the parser rather than downloaded first.
+//!
+//! Downloads rely on system tools rather than library dependencies: `curl` for HTTP(S), and the
+//! AWS CLI for S3.
+
+use std::io::Read;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use flate2::read::GzDecoder;
+
+/// Where a graph is read from.
+enum Location<'a> {
+    File(&'a str),
+    Http(&'a str),
+    S3(&'a str),
+}
+
+impl<'a> Location<'a> {
+    fn parse(location: &'a str) -> Self {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            Location::Http(location)
+        } else if location.starts_with("s3://") {
+            Location::S3(location)
+        } else {
+            Location::File(location)
+        }
+    }
+
+    /// The program and command writing the graph to its standard output, for remote graphs.
+    /// Errors for S3 URLs without the `s3` feature.
+    fn download_command(&self) -> std::io::Result<Option<(&'static str, Command)>> {
+        match self {
+            Location::File(_) => Ok(None),
+            Location::Http(url) => {
+                let mut command = Command::new("curl");
+                command.args(["--silent", "--show-error", "--fail", "--location"]).arg(url);
+                Ok(Some(("curl", command)))
+            }
+            #[cfg(feature = "s3")]
+            Location::S3(url) => {
+                let mut command = Command::new("aws");
+                command.args(["s3", "cp", "--quiet"]).arg(url).arg("-");
+                Ok(Some(("aws", command)))
+            }
+            #[cfg(not(feature = "s3"))]
+            Location::S3(_) => Err(s3_unsupported()),
+        }
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+fn s3_unsupported() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Unsupported, "reading graphs from S3 requires building pagegraph-cli with the s3 feature")
+}
+
+/// The output of a download command. Reaching the end of the output is an error if the command
+/// failed, since the output is likely to be truncated.
+struct DownloadReader {
+    program: &'static str,
+    stdout: ChildStdout,
+    child: Child,
+}
+
+impl DownloadReader {
+    fn spawn(program: &'static str, mut command: Command) -> std::io::Result<Self> {
+        let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).spawn()
+            .map_err(|e| std::io::Error::new(e.kind(), format!("could not run {}: {}", program, e)))?;
+        let stdout = child.stdout.take().unwrap();
+        Ok(DownloadReader { program, stdout, child })
+    }
+}
+
+impl Read for DownloadReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("{} exited with {}", self.program, status)));
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// Whether the graph at the location is gzipped, going by its extension. Query strings, such as
+/// those of presigned URLs, are ignored.
+fn is_gzipped(location: &str) -> bool {
+    location.split('?').next().unwrap().ends_with(".gz")
+}
+
+/// Opens the graph at the given path or URL for reading, decompressing it if it's gzipped.
+pub fn open(location: &str) -> std::io::Result<Box<dyn Read>> {
+    let reader: Box<dyn Read> = match Location::parse(location).download_command()? {
+        Some((program, command)) => Box::new(DownloadReader::spawn(program, command)?),
+        None => Box::new(std::fs::File::open(location)?),
+    };
+    Ok(if is_gzipped(location) { Box::new(GzDecoder::new(reader)) } else { reader })
+}
+
+/// Whether there's a graph at the given path or URL.
+pub fn exists(location: &str) -> bool {
+    let mut command = match Location::parse(location) {
+        Location::File(path) => return std::path::Path::new(path).exists(),
+        Location::Http(url) => {
+            let mut command = Command::new("curl");
+            command.args(["--silent", "--fail", "--location", "--head"]).arg(url);
+            command
+        }
+        #[cfg(feature = "s3")]
+        Location::S3(url) => {
+            let mut command = Command::new("aws");
+            command.args(["s3", "ls"]).arg(url);
+            command
+        }
+        #[cfg(not(feature = "s3"))]
+        Location::S3(_) => return false,
+    };
+    // Only the exit status matters, not the headers or listing written to standard output.
+    command.stdout(Stdio::null()).status().map(|status| status.success()).unwrap_or(false)
+}
+
+/// The location of another file in the same directory as `location`, compressed in the same way.
+pub fn sibling(location: &str, file_name: &str) -> String {
+    let file_name = if is_gzipped(location) { format!("{}.gz", file_name) } else { file_name.to_string() };
+    match Location::parse(location) {
+        Location::File(path) => {
+            let mut path = std::path::Path::new(path).to_path_buf();
+            path.set_file_name(file_name);
+            path.to_str().expect("failed to convert frame path to a string").to_string()
+        }
+        Location::Http(url) | Location::S3(url) => {
+            // Query strings don't carry over to other files.
+            let url = url.split('?').next().unwrap();
+            match url.rsplit_once('/') {
+                Some((dir, _)) => format!("{}/{}", dir, file_name),
+                None => file_name,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn test_is_gzipped() {
+        assert!(is_gzipped("page_graph.graphml.gz"));
+        assert!(is_gzipped("https://example.com/graphs/page_graph.graphml.gz?X-Amz-Signature=abc"));
+        assert!(!is_gzipped("page_graph.graphml"));
+        assert!(!is_gzipped("https://example.com/page_graph.graphml?name=a.gz"));
+        assert!(!is_gzipped("graphs.gz/page_graph.graphml"));
+    }
+
+    #[test]
+    fn test_sibling() {
+        assert_eq!(sibling("graphs/page_graph.graphml", "frame.graphml"), "graphs/frame.graphml");
+        assert_eq!(sibling("page_graph.graphml", "frame.graphml"), "frame.graphml");
+        assert_eq!(sibling("graphs/page_graph.graphml.gz", "frame.graphml"), "graphs/frame.graphml.gz");
+        assert_eq!(sibling("https://example.com/graphs/page_graph.graphml.gz?X-Amz-Signature=abc", "frame.graphml"), "https://example.com/graphs/frame.graphml.gz");
+        assert_eq!(sibling("s3://bucket/crawl/page_graph.graphml", "frame.graphml"), "s3://bucket/crawl/frame.graphml");
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = std::env::temp_dir().join(format!("pagegraph-graph-source-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plain = dir.join("page_graph.graphml");
+        std::fs::write(&plain, "<graphml/>").unwrap();
+        let gzipped = dir.join("page_graph.graphml.gz");
+        let mut encoder = GzEncoder::new(std::fs::File::create(&gzipped).unwrap(), Compression::default());
+        encoder.write_all(b"<graphml/>").unwrap();
+        encoder.finish().unwrap();
+
+        for path in [&plain, &gzipped] {
+            let mut contents = String::new();
+            open(path.to_str().unwrap()).unwrap().read_to_string(&mut contents).unwrap();
+            assert_eq!(contents, "<graphml/>");
+        }
+        let missing = dir.join("missing.graphml");
+        assert!(!exists(missing.to_str().unwrap()));
+        assert_eq!(open(missing.to_str().unwrap()).err().map(|e| e.kind()), Some(std::io::ErrorKind::NotFound));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_download() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "printf '<graphml'; exit 3"]);
+        let mut contents = String::new();
+        let error = DownloadReader::spawn("sh", command).unwrap().read_to_string(&mut contents).unwrap_err();
+        assert_eq!(contents, "<graphml");
+        assert_eq!(error.to_string(), "sh exited with exit status: 3");
+    }
+
+    #[test]
+    fn test_open_failed_download() {
+        // Nothing listens on port 1, so curl fails, or can't be run at all.
+        let mut contents = vec![];
+        let result = open("http://127.0.0.1:1/page_graph.graphml").and_then(|mut reader| reader.read_to_end(&mut contents));
+        assert!(result.is_err());
+    }
+
+    #[cfg(not(feature = "s3"))]
+    #[test]
+    fn test_s3_unsupported() {
+        assert_eq!(open("s3://bucket/crawl/page_graph.graphml").err().map(|e| e.kind()), Some(std::io::ErrorKind::Unsupported));
+        assert!(!exists("s3://bucket/crawl/page_graph.graphml"));
+    }
+}
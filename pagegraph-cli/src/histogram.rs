@@ -0,0 +1,31 @@
+//! Prints out counts of requests, script executions, and DOM mutations over the page load, as
+//! JSON on stdout, along with ASCII sparklines of each count on stderr.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+/// Sparkline levels, from no activity to the busiest bucket.
+const LEVELS: &[u8] = b" .:-=+*#%@";
+
+fn sparkline(counts: impl Iterator<Item=usize> + Clone) -> String {
+    let max = counts.clone().max().unwrap_or(0);
+    counts.map(|count| match count {
+        0 => LEVELS[0] as char,
+        // Any activity at all shows up as at least the lowest level.
+        _ => LEVELS[1 + (count * (LEVELS.len() - 1) - 1) / max] as char,
+    }).collect()
+}
+
+pub fn main(graph: &PageGraph, bucket_ms: usize, output_format: OutputFormat) {
+    let histogram = graph.activity_histogram(bucket_ms);
+    println!("{}", config::to_json(output_format, &histogram));
+
+    let buckets = &histogram.buckets;
+    if let (Some(first), Some(last)) = (buckets.first(), buckets.last()) {
+        eprintln!("{}ms to {}ms, {}ms per character", first.start, last.start + bucket_ms as isize, bucket_ms);
+    }
+    eprintln!("requests          |{}|", sparkline(buckets.iter().map(|bucket| bucket.requests)));
+    eprintln!("script executions |{}|", sparkline(buckets.iter().map(|bucket| bucket.script_executions)));
+    eprintln!("DOM mutations     |{}|", sparkline(buckets.iter().map(|bucket| bucket.dom_mutations)));
+}
@@ -1,25 +1,168 @@
 //! CLI for pagegraph-rust
 
-use pagegraph::from_xml::read_from_file;
-use pagegraph::graph::{EdgeId, FrameId};
+use pagegraph::analysis::registry::AnalysisRegistry;
+use pagegraph::analysis::trackers::TrackerCategories;
+use pagegraph::from_xml::read_from_reader;
+use pagegraph::graph::{EdgeId, FrameId, NodeId, PageGraph};
+use pagegraph::types::RequestType;
 
-use clap::{App, Arg, SubCommand};
+use config::Config;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use std::fs::File;
 use std::io::{BufReader, BufRead};
 
+mod ad_dom_share;
 mod adblock_rules;
+mod analysis;
+mod blocked;
+mod cname_cloaking;
+mod config;
+mod critical_path;
+mod csp;
+mod navigations;
+mod popups;
+mod privacy_report;
 mod request_id_info;
 mod downstream_requests;
+mod export;
+#[cfg(feature = "default-lists")]
+mod filter_lists;
+mod graph_source;
+mod histogram;
+mod requests;
+mod schema;
+mod script_cost;
+mod script_source;
+mod security_report;
+mod shields_comparison;
+mod stats;
+mod storage;
+mod third_party_costs;
+mod watch;
+
+/// Reads the graph at the given path or URL, merging in the graphs of any remote frames recorded
+/// alongside it. Up to `parallelism` frame graphs are read at once.
+fn read_with_remote_frames(graph_file: &str, parallelism: usize) -> PageGraph {
+    let mut graph = read_from_reader(graph_source::open(graph_file).unwrap_or_else(|e| panic!("Could not open {}: {}", graph_file, e)));
+
+    let frames = graph.all_remote_frame_ids().into_iter().filter_map(|remote_frame_id| {
+        let frame_path = graph_source::sibling(graph_file, &format!("page_graph_{}.0.graphml", remote_frame_id));
+        // We have to just ignore the remote frame's contents if we couldn't successfully record any.
+        graph_source::exists(&frame_path).then_some((remote_frame_id, frame_path))
+    }).collect::<Vec<_>>();
+
+    for batch in frames.chunks(parallelism.max(1)) {
+        let frame_graphs = std::thread::scope(|scope| {
+            let readers = batch.iter()
+                .map(|(_, frame_path)| scope.spawn(move || read_from_reader(graph_source::open(frame_path).unwrap_or_else(|e| panic!("Could not open {}: {}", frame_path, e)))))
+                .collect::<Vec<_>>();
+            readers.into_iter().map(|reader| reader.join().expect("Reading a remote frame graph panicked")).collect::<Vec<_>>()
+        });
+        for ((remote_frame_id, _), frame_graph) in batch.iter().zip(frame_graphs) {
+            graph.merge_frame(frame_graph, remote_frame_id);
+        }
+    }
+
+    let diagnostics = &graph.diagnostics;
+    if !diagnostics.is_empty() {
+        eprintln!(
+            "Warning: {} is malformed; left out {} nodes with duplicate ids and {} edges to missing nodes, and gave new ids to {} edges with duplicate ids",
+            graph_file, diagnostics.duplicate_nodes.len(), diagnostics.dangling_edges.len(), diagnostics.remapped_edges.len(),
+        );
+    }
+
+    graph
+}
+
+/// Reads the adblock rules from a newline-separated filter list file.
+fn read_filter_list(path: &str) -> Vec<String> {
+    let file = File::open(path).expect("Could not open filter list");
+    BufReader::new(file).lines()
+        .map(|l| l.expect("Could not parse line"))
+        .collect()
+}
+
+/// Reads a Disconnect-style tracker list from a `services.json` file.
+fn read_tracker_list(path: &str) -> TrackerCategories {
+    let file = File::open(path).expect("Could not open tracker list");
+    serde_json::from_reader(BufReader::new(file)).expect("Tracker list should be in the format of Disconnect's services.json")
+}
+
+/// Arguments for using the well-known filter lists from `filter_lists`, for subcommands that
+/// take adblock rules. They are always accepted, so that `--help` can say they need the
+/// `default-lists` feature.
+fn default_lists_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("default_lists")
+            .long("default-lists")
+            .help("Also use EasyList, EasyPrivacy, and uBlock Origin's filters, downloading them if they aren't cached (requires building pagegraph-cli with the default-lists feature)"),
+        Arg::with_name("pin")
+            .long("pin")
+            .help("Fetch a default list at a particular git revision, e.g. easylist=<commit>")
+            .takes_value(true)
+            .value_name("NAME=REVISION")
+            .multiple(true)
+            .number_of_values(1)
+            .requires("default_lists"),
+    ]
+}
+
+/// The rules of the well-known filter lists. Pins given on the command line take precedence over
+/// those in the configuration file.
+#[cfg(feature = "default-lists")]
+fn default_list_rules(matches: &ArgMatches, config: &Config) -> Result<Vec<String>, String> {
+    let pins = config.pins.iter().map(|pin| pin.as_str()).chain(matches.values_of("pin").into_iter().flatten());
+    filter_lists::parse_pins(pins)
+        .and_then(|pins| filter_lists::default_list_rules(&pins, config.cache_dir.as_deref()))
+        .map_err(|e| format!("could not use the default filter lists: {}", e))
+}
+
+#[cfg(not(feature = "default-lists"))]
+fn default_list_rules(_matches: &ArgMatches, _config: &Config) -> Result<Vec<String>, String> {
+    Err("the default filter lists were requested, but pagegraph-cli was built without the default-lists feature".to_string())
+}
+
+/// Collects the adblock rules given directly, from a filter list file, and from the default
+/// lists, for subcommands that take adblock rules. Lists from the configuration file are used as
+/// well. Panics if no rules are given at all.
+fn filter_rules_from(matches: &ArgMatches, config: &Config) -> Vec<String> {
+    let mut rules = vec![];
+    rules.extend(matches.value_of("filter_rule").map(|rule| rule.to_string()));
+    if let Some(path) = matches.value_of("path_to_filterlist") {
+        rules.extend(read_filter_list(path));
+    }
+    config.filter_lists.iter().for_each(|path| rules.extend(read_filter_list(path.to_str().expect("failed to convert filter list path to a string"))));
+    if matches.is_present("default_lists") || config.default_lists {
+        match default_list_rules(matches, config) {
+            Ok(default_rules) => rules.extend(default_rules),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if rules.is_empty() {
+        panic!("No adblock rules were given; use --rule, --list, or --default-lists, or set filter_lists in the configuration file");
+    }
+    rules
+}
 
 fn main() {
     let matches = App::new("pagegraph-rust CLI")
         .version("1.0")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("graph_file")
             .short("f")
             .value_name("FILE")
-            .help("Set the graph to query")
+            .help("Set the graph to query, as a path, an http(s):// URL, or an s3:// URL with the s3 feature, optionally gzipped")
             .takes_value(true)
             .required(true))
+        .arg(Arg::with_name("config")
+            .long("config")
+            .value_name("FILE")
+            .help("Read default options from this file, instead of pagegraph.toml in the working directory")
+            .takes_value(true))
         .subcommand(SubCommand::with_name("identify")
             .about("Check information about a particular node or edge id in the graph")
             .arg(Arg::with_name("id")
@@ -32,14 +175,28 @@ fn main() {
                 .help("Adblock rule to use, using ABP syntax")
                 .short("r")
                 .long("rule")
-                .takes_value(true)
-                .required_unless("path_to_filterlist"))
+                .takes_value(true))
             .arg(Arg::with_name("path_to_filterlist")
                 .short("l")
                 .long("list")
-                .required_unless("filter_rule")
                 .help("Set path to filterlist file (newline-separated adblock rules) to use")
-                .takes_value(true)))
+                .takes_value(true))
+            .args(&default_lists_args()))
+        .subcommand(SubCommand::with_name("ad_dom_share")
+            .about("Score the share of the final DOM, and of its known visible area, attributable to scripts and elements matching a filter list")
+            .arg(Arg::with_name("path_to_filterlist")
+                .short("l")
+                .long("list")
+                .help("Set path to filterlist file (newline-separated adblock rules) to use")
+                .takes_value(true))
+            .args(&default_lists_args()))
+        .subcommand(SubCommand::with_name("analysis")
+            .about("Run a registered analysis by name, printing its results as JSON, or list every registered analysis")
+            .arg(Arg::with_name("name")
+                .help("Name of the analysis to run")
+                .takes_value(true)
+                .value_name("NAME")
+                .required(false)))
         .subcommand(SubCommand::with_name("downstream_requests")
             .about("Find network requests initiated as a result of a given edge in the graph")
             .arg(Arg::with_name("requests")
@@ -53,6 +210,212 @@ fn main() {
                 .takes_value(true)
                 .value_name("ID")
                 .required(true)))
+        .subcommand(SubCommand::with_name("requests")
+            .about("List every network request made by the page")
+            .arg(Arg::with_name("websockets")
+                .help("List WebSocket connections and their activity instead")
+                .takes_value(false)
+                .short("w")
+                .long("websockets")
+                .required(false))
+            .arg(Arg::with_name("request_type")
+                .help("Only list requests of this type")
+                .short("t")
+                .long("type")
+                .takes_value(true)
+                .value_name("TYPE")
+                .possible_values(&["image", "script", "stylesheet", "xhr", "unknown"])
+                .required(false))
+            .arg(Arg::with_name("mime_type")
+                .help("Only list requests whose response MIME type matches, e.g. image/png or image/*")
+                .short("m")
+                .long("mime")
+                .takes_value(true)
+                .value_name("MIME")
+                .required(false))
+            .arg(Arg::with_name("third_party")
+                .help("Only list requests to third-party URLs")
+                .takes_value(false)
+                .long("third-party")
+                .required(false))
+            .arg(Arg::with_name("trackers")
+                .help("Disconnect-style tracker list (services.json) used to categorize third parties")
+                .long("trackers")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(false)))
+        .subcommand(SubCommand::with_name("histogram")
+            .about("Count requests, script executions, and DOM mutations over time, printing JSON to stdout and sparklines to stderr")
+            .arg(Arg::with_name("bucket_ms")
+                .help("Length of each bucket, in milliseconds")
+                .long("bucket-ms")
+                .takes_value(true)
+                .value_name("MS")
+                .default_value("100")))
+        .subcommand(SubCommand::with_name("stats")
+            .about("Print summary counts of the graph's contents, including request outcomes")
+            .arg(Arg::with_name("frames")
+                .help("Print statistics for each frame instead")
+                .takes_value(false)
+                .long("frames")
+                .required(false)))
+        .subcommand(SubCommand::with_name("storage")
+            .about("Print how scripts read and wrote each cookie and Web Storage key")
+            .arg(Arg::with_name("cookie_jar")
+                .help("Print the cookies left at the end of the page load instead, for each origin")
+                .takes_value(false)
+                .long("cookie-jar")
+                .required(false)))
+        .subcommand(SubCommand::with_name("third_party_costs")
+            .about("Report the bytes, requests, DOM nodes, and API calls attributable to each third party")
+            .arg(Arg::with_name("entities")
+                .help("JSON file mapping registrable domains to the name of the entity that owns them")
+                .short("e")
+                .long("entities")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(false))
+            .arg(Arg::with_name("trackers")
+                .help("Disconnect-style tracker list (services.json) used to categorize third parties")
+                .long("trackers")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(false)))
+        .subcommand(SubCommand::with_name("blocked")
+            .about("List every resource blocked by Brave Shields, and the rules that blocked them"))
+        .subcommand(SubCommand::with_name("navigations")
+            .about("List the server redirects, meta refreshes, and location changes that navigated the top-level page"))
+        .subcommand(SubCommand::with_name("popups")
+            .about("List every call to window.open, with the destination URL and the events the calling script was listening for"))
+        .subcommand(SubCommand::with_name("privacy_report")
+            .about("Report third parties, fingerprinting, storage usage, cookie syncing, and blocked resources")
+            .arg(Arg::with_name("html")
+                .help("Print a self-contained HTML page instead of JSON")
+                .long("html")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::with_name("trackers")
+                .help("Disconnect-style tracker list (services.json) used to categorize third parties")
+                .long("trackers")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(false)))
+        .subcommand(SubCommand::with_name("export")
+            .about("Export the graph for viewing, as a self-contained HTML page with an interactive viewer, or as a set of node and edge ids to highlight in other viewers")
+            .arg(Arg::with_name("format")
+                .help("Format to export; highlight prints the highlighted node and edge ids as JSON, or every remaining id if nothing is highlighted; numeric writes an edge list, a sparse adjacency matrix, and node and edge tables into the --out directory")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["html", "highlight", "numeric"])
+                .default_value("html"))
+            .arg(Arg::with_name("downstream")
+                .help("Highlight this edge and everything it caused")
+                .long("downstream")
+                .takes_value(true)
+                .value_name("EDGE_ID")
+                .required(false))
+            .arg(Arg::with_name("url")
+                .help("Highlight every node and edge recording a URL matching this glob")
+                .long("url")
+                .takes_value(true)
+                .value_name("GLOB")
+                .required(false))
+            .arg(Arg::with_name("from")
+                .help("Only export the nodes connected to this node, in either direction")
+                .long("from")
+                .takes_value(true)
+                .value_name("NODE_ID")
+                .required(false))
+            .arg(Arg::with_name("prune")
+                .help("Drop text nodes and attribute edges")
+                .long("prune")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::with_name("one_hot")
+                .help("With --format numeric, add one-hot encodings of node and edge kinds to the node and edge tables")
+                .long("one-hot")
+                .takes_value(false)
+                .required(false))
+            .arg(Arg::with_name("out")
+                .help("Write the export to this file instead of stdout, or to this directory for --format numeric")
+                .short("o")
+                .long("out")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(false)))
+        .subcommand(SubCommand::with_name("security_report")
+            .about("Report mixed content, dangerous sink usage by third-party scripts, and third-party data flowing into the page"))
+        .subcommand(SubCommand::with_name("cname_cloaking")
+            .about("List first-party requests whose hosts are CNAME aliases for domains in a tracker list")
+            .arg(Arg::with_name("trackers")
+                .help("Disconnect-style tracker list (services.json) of known tracker domains")
+                .long("trackers")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true))
+            .arg(Arg::with_name("cnames")
+                .help("JSON file mapping hosts to their CNAME chains, used instead of resolving hosts with dig")
+                .long("cnames")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(false)))
+        .subcommand(SubCommand::with_name("critical_path")
+            .about("Approximate the chain of render-blocking scripts, stylesheets, and fonts delaying DOMContentLoaded"))
+        .subcommand(SubCommand::with_name("csp")
+            .about("Print the minimal Content-Security-Policy that allows everything the page loaded")
+            .arg(Arg::with_name("policy")
+                .help("Instead, list every recorded load that this policy would block")
+                .short("p")
+                .long("policy")
+                .takes_value(true)
+                .value_name("POLICY")
+                .required(false)))
+        .subcommand(SubCommand::with_name("shields_comparison")
+            .about("Compare the graph, recorded with Brave Shields enabled, against a recording of the same URL without Shields")
+            .arg(Arg::with_name("shields_off_file")
+                .help("Graph of the same URL recorded with Shields disabled")
+                .takes_value(true)
+                .value_name("FILE")
+                .required(true)))
+        .subcommand(SubCommand::with_name("schema")
+            .about("Print the JSON Schema of a subcommand's JSON output, or list the subcommands with JSON output")
+            .arg(Arg::with_name("subcommand")
+                .help("Subcommand to describe the output of")
+                .takes_value(true)
+                .value_name("SUBCOMMAND")
+                .required(false))
+            .arg(Arg::with_name("analysis")
+                .help("For the analysis subcommand, the analysis to describe the results of")
+                .takes_value(true)
+                .value_name("NAME")
+                .required(false)))
+        .subcommand(SubCommand::with_name("script_cost")
+            .about("Rank scripts by the number and time spread of the API calls, DOM mutations, and requests they made")
+            .arg(Arg::with_name("node_id")
+                .help("Node id of a single script to report on")
+                .takes_value(true)
+                .value_name("ID")
+                .required(false)))
+        .subcommand(SubCommand::with_name("script_source")
+            .about("Dump the recorded source text of scripts, with a header describing each script")
+            .arg(Arg::with_name("node_id")
+                .help("Node id of the script to dump")
+                .takes_value(true)
+                .value_name("ID")
+                .required_unless("all"))
+            .arg(Arg::with_name("all")
+                .help("Dump every script in the graph")
+                .takes_value(false)
+                .short("a")
+                .long("all")
+                .conflicts_with("node_id"))
+            .arg(Arg::with_name("out_dir")
+                .help("Write each script to a separate file in this directory instead of stdout")
+                .short("o")
+                .long("out-dir")
+                .takes_value(true)
+                .value_name("DIR")
+                .required(false)))
         .subcommand(SubCommand::with_name("request_id_info")
             .about("Get all information from the graph associated with a particular Blink request id")
             .arg(Arg::with_name("request_id")
@@ -71,22 +434,60 @@ fn main() {
                 .takes_value(true)
                 .value_name("FRAME")
                 .required(false)))
+        .subcommand(SubCommand::with_name("watch")
+            .about("Run a subcommand on each graph written to a directory, printing a line of JSON per graph")
+            .arg(Arg::with_name("dir")
+                .help("Directory to watch for new graphs, such as a crawler's output directory")
+                .takes_value(true)
+                .value_name("DIR")
+                .required(true))
+            .arg(Arg::with_name("run")
+                .help("Subcommand to run on each graph")
+                .long("run")
+                .takes_value(true)
+                .value_name("SUBCOMMAND")
+                .required(true))
+            .arg(Arg::with_name("interval")
+                .help("How often to check the directory for new graphs, in milliseconds")
+                .long("interval")
+                .takes_value(true)
+                .value_name("MS")
+                .default_value("1000"))
+            .arg(Arg::with_name("existing")
+                .help("Also run on graphs already in the directory")
+                .long("existing"))
+            .arg(Arg::with_name("args")
+                .help("Arguments for the subcommand, after --")
+                .multiple(true)
+                .last(true)))
         .get_matches();
 
-    let graph_file = matches.value_of("graph_file").unwrap();
+    let config = Config::load(matches.value_of("config"));
 
-    let mut graph = read_from_file(&graph_file);
+    // Schemas don't depend on any particular graph.
+    if let Some(matches) = matches.subcommand_matches("schema") {
+        schema::main(matches.value_of("subcommand"), matches.value_of("analysis"), config.format);
+        return;
+    }
 
-    graph.all_remote_frame_ids().into_iter().for_each(|remote_frame_id| {
-        let mut frame_path = std::path::Path::new(&graph_file).to_path_buf();
-        frame_path.set_file_name(format!("page_graph_{}.0.graphml", remote_frame_id));
-        if !frame_path.exists() {
-            // We have to just ignore the remote frame's contents if we couldn't successfully record any.
-            return;
-        }
-        let frame_graph = read_from_file(frame_path.to_str().expect("failed to convert frame path to a string"));
-        graph.merge_frame(frame_graph, &remote_frame_id);
-    });
+    // Each graph in the directory is read by a separate run of the subcommand.
+    if let Some(matches) = matches.subcommand_matches("watch") {
+        let interval = matches.value_of("interval").unwrap().parse::<u64>().expect("Could not parse interval as a number");
+        let args = matches.values_of("args").map(|args| args.collect::<Vec<_>>()).unwrap_or_default();
+        watch::main(
+            std::path::Path::new(matches.value_of("dir").unwrap()),
+            matches.value_of("run").unwrap(),
+            &args,
+            matches.value_of("config"),
+            std::time::Duration::from_millis(interval),
+            matches.is_present("existing"),
+        );
+        return;
+    }
+
+    let graph_file = matches.value_of("graph_file").expect("A graph file must be given with -f");
+
+    let graph = read_with_remote_frames(graph_file, config.parallelism());
 
     if let Some(matches) = matches.subcommand_matches("identify") {
         let id = matches.value_of("id").unwrap().parse::<usize>().expect("Could not parse id as a number");
@@ -95,6 +496,7 @@ fn main() {
             println!("Node n{}", id);
             println!("Timestamp: {}", node.node_timestamp);
             println!("Type: {:?}", node.node_type);
+            println!("Summary: {}", node.describe(&graph));
 
             println!("");
             println!("Incoming edges");
@@ -115,6 +517,7 @@ fn main() {
             println!("Edge e{}", id);
             println!("Timestamp: {:?}", edge.edge_timestamp);
             println!("Type: {:?}", edge.edge_type);
+            println!("Summary: {}", edge.describe(&graph));
 
             println!("");
             println!("Source node");
@@ -133,31 +536,90 @@ fn main() {
             println!("No node or edge with id {} was found in this graph.", id);
         }
     } else if let Some(matches) = matches.subcommand_matches("adblock_rules") {
-        let rule = matches.value_of("filter_rule");
-        let filterlist = matches.value_of("path_to_filterlist");
-        let filter_rules = if let Some(rule) = rule {
-            vec![rule.to_string()]
-        } else {
-            // open file
-            let file = File::open(filterlist
-                .expect("At least one of path_to_filterlist or filter_rule must be defined")).unwrap();
-            let reader = BufReader::new(file);
-            let rules: Vec<_> = reader.lines()
-                .map(|l| l.expect("Could not parse line"))
-                .collect();
-            rules
-        };
-        adblock_rules::main(&graph, filter_rules);
+        let filter_rules = filter_rules_from(matches, &config);
+        adblock_rules::main(&graph, filter_rules, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("ad_dom_share") {
+        let filter_rules = filter_rules_from(matches, &config);
+        ad_dom_share::main(&graph, &filter_rules, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("analysis") {
+        analysis::main(&graph, &AnalysisRegistry::with_builtins_using(config.thresholds), matches.value_of("name"), config.format);
     } else if let Some(matches) = matches.subcommand_matches("downstream_requests") {
         use std::convert::TryFrom;
         let just_requests = matches.is_present("requests");
         let edge_id = EdgeId::try_from(matches.value_of("edge_id").unwrap()).expect("Provided edge id was invalid");
-        downstream_requests::main(&graph, edge_id, just_requests);
+        downstream_requests::main(&graph, edge_id, just_requests, config.format);
     } else if let Some(matches) = matches.subcommand_matches("request_id_info") {
         use std::convert::TryFrom;
         let request_id = matches.value_of("request_id").unwrap().parse::<usize>().expect("Request id should be parseable as a number");
         let just_source = matches.is_present("source");
         let frame_id: Option<FrameId> = matches.value_of("frame_id").map(|frame_id_str| FrameId::try_from(frame_id_str).expect("Frame id should be parseable"));
-        request_id_info::main(&graph, request_id, frame_id, just_source);
+        request_id_info::main(&graph, request_id, frame_id, just_source, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("requests") {
+        let filter = requests::RequestFilter {
+            request_type: matches.value_of("request_type").map(|request_type| [RequestType::Image, RequestType::Script, RequestType::CSS, RequestType::AJAX, RequestType::Unknown].iter()
+                .find(|known| known.as_str() == request_type)
+                .cloned()
+                .unwrap()),
+            mime_type: matches.value_of("mime_type").map(|mime_type| mime_type.to_string()),
+            third_party: matches.is_present("third_party"),
+        };
+        let trackers = matches.value_of("trackers").map(read_tracker_list);
+        requests::main(&graph, matches.is_present("websockets"), &filter, trackers.as_ref(), config.format);
+    } else if let Some(matches) = matches.subcommand_matches("histogram") {
+        let bucket_ms = matches.value_of("bucket_ms").unwrap().parse::<usize>().expect("Bucket length should be a number of milliseconds");
+        if bucket_ms == 0 {
+            panic!("Bucket length should be at least 1ms");
+        }
+        histogram::main(&graph, bucket_ms, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("stats") {
+        stats::main(&graph, matches.is_present("frames"), config.format);
+    } else if let Some(matches) = matches.subcommand_matches("storage") {
+        storage::main(&graph, matches.is_present("cookie_jar"), config.format);
+    } else if let Some(matches) = matches.subcommand_matches("third_party_costs") {
+        let trackers = matches.value_of("trackers").map(read_tracker_list);
+        // Without an entities file, domains are grouped by the entities in the tracker list
+        let entities = matches.value_of("entities").map(|path| {
+            let file = File::open(path).expect("Could not open entities file");
+            serde_json::from_reader(BufReader::new(file)).expect("Entities file should be a JSON object mapping domains to entity names")
+        }).or_else(|| trackers.as_ref().map(|trackers| trackers.entities())).unwrap_or_default();
+        third_party_costs::main(&graph, &entities, trackers.as_ref(), config.format);
+    } else if matches.subcommand_matches("blocked").is_some() {
+        blocked::main(&graph, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("script_cost") {
+        use std::convert::TryFrom;
+        let node_id = matches.value_of("node_id").map(|id| NodeId::try_from(id).expect("Provided node id was invalid"));
+        script_cost::main(&graph, node_id, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("script_source") {
+        use std::convert::TryFrom;
+        let node_id = matches.value_of("node_id").map(|id| NodeId::try_from(id).expect("Provided node id was invalid"));
+        script_source::main(&graph, node_id, matches.value_of("out_dir").map(std::path::Path::new));
+    } else if let Some(matches) = matches.subcommand_matches("cname_cloaking") {
+        let trackers = read_tracker_list(matches.value_of("trackers").unwrap());
+        let cnames = matches.value_of("cnames").map(|path| {
+            let file = File::open(path).expect("Could not open CNAMEs file");
+            serde_json::from_reader(BufReader::new(file)).expect("CNAMEs file should be a JSON object mapping hosts to lists of CNAME targets")
+        });
+        cname_cloaking::main(&graph, &trackers, cnames.as_ref(), config.format);
+    } else if matches.subcommand_matches("critical_path").is_some() {
+        critical_path::main(&graph, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("csp") {
+        csp::main(&graph, matches.value_of("policy"), config.format);
+    } else if matches.subcommand_matches("navigations").is_some() {
+        navigations::main(&graph, config.format);
+    } else if matches.subcommand_matches("popups").is_some() {
+        popups::main(&graph, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("privacy_report") {
+        let trackers = matches.value_of("trackers").map(read_tracker_list);
+        privacy_report::main(&graph, matches.is_present("html"), trackers.as_ref(), &config.thresholds, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("export") {
+        use std::convert::TryFrom;
+        let from = matches.value_of("from").map(|id| NodeId::try_from(id).expect("Provided node id was invalid"));
+        let downstream = matches.value_of("downstream").map(|id| EdgeId::try_from(id).expect("Provided edge id was invalid"));
+        export::main(graph, export::Format::new(matches.value_of("format").unwrap(), matches.is_present("one_hot"), config.format), from, matches.is_present("prune"), downstream, matches.value_of("url"), matches.value_of("out").map(std::path::Path::new));
+    } else if matches.subcommand_matches("security_report").is_some() {
+        security_report::main(&graph, config.format);
+    } else if let Some(matches) = matches.subcommand_matches("shields_comparison") {
+        let shields_off = read_with_remote_frames(matches.value_of("shields_off_file").unwrap(), config.parallelism());
+        shields_comparison::main(&shields_off, &graph, config.format);
     }
 }
@@ -0,0 +1,9 @@
+//! Prints out the sequence of redirects and navigations of the top-level page.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, output_format: OutputFormat) {
+    println!("{}", config::to_json(output_format, &graph.navigations()));
+}
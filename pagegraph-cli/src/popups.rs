@@ -0,0 +1,10 @@
+//! Prints out every call to `window.open` made during the page load, along with the events that
+//! may have triggered it.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, output_format: OutputFormat) {
+    println!("{}", config::to_json(output_format, &graph.popups()));
+}
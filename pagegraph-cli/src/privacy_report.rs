@@ -0,0 +1,21 @@
+//! Prints out a report of privacy-relevant page behavior: third parties, fingerprinting, storage
+//! usage, cookie syncing, and blocked resources.
+
+use pagegraph::analysis::Thresholds;
+use pagegraph::analysis::report;
+use pagegraph::analysis::trackers::TrackerCategories;
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, html: bool, trackers: Option<&TrackerCategories>, thresholds: &Thresholds, output_format: OutputFormat) {
+    let mut report = report::privacy_with(graph, thresholds);
+    if let Some(trackers) = trackers {
+        report.categorize(trackers);
+    }
+    if html {
+        print!("{}", report.to_html());
+    } else {
+        println!("{}", config::to_json(output_format, &report));
+    }
+}
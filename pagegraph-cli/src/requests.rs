@@ -0,0 +1,72 @@
+//! Prints out every network request made by the page, or every WebSocket connection.
+
+use pagegraph::analysis::trackers::{TrackerCategories, TrackerInfo};
+use pagegraph::filter;
+use pagegraph::graph::{EdgeId, HasFrameId, NodeId, PageGraph};
+use pagegraph::requests::RequestOutcome;
+use pagegraph::types::{EdgeKind, EdgeType, RequestType, Url};
+
+use crate::config::{self, OutputFormat};
+
+/// Restricts which requests are listed.
+pub struct RequestFilter {
+    pub request_type: Option<RequestType>,
+    /// A MIME type pattern, like `image/png` or `image/*`.
+    pub mime_type: Option<String>,
+    pub third_party: bool,
+}
+
+/// A single request, as listed in the output.
+#[derive(serde::Serialize, schemars::JsonSchema)]
+pub struct Request<'a> {
+    request_id: usize,
+    edge_id: EdgeId,
+    timestamp: Option<isize>,
+    request_type: &'a RequestType,
+    url: Option<&'a Url>,
+    initiator: NodeId,
+    outcome: Option<RequestOutcome>,
+    status_code: Option<u16>,
+    mime_type: Option<String>,
+    size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tracker: Option<&'a TrackerInfo>,
+}
+
+/// If `trackers` is given, each request is tagged with the tracker listing for its URL.
+pub fn main(graph: &PageGraph, websockets: bool, filter: &RequestFilter, trackers: Option<&TrackerCategories>, output_format: OutputFormat) {
+    if websockets {
+        println!("{}", config::to_json(output_format, &graph.websocket_connections()));
+        return;
+    }
+
+    let mut requests = graph.filter_edges(filter::edge_kind(EdgeKind::RequestStart)).filter_map(|edge| match &edge.edge_type {
+        EdgeType::RequestStart { request_id, request_type, .. } => {
+            let info = graph.request_in_frame(*request_id, edge.id.get_frame_id());
+            let response = info.as_ref().and_then(|info| info.response.as_ref());
+            let url = graph.target_node(edge).node_type.url();
+            if filter.request_type.as_ref().map(|filter_type| filter_type != request_type).unwrap_or(false) ||
+                filter.mime_type.as_ref().map(|pattern| !response.map(|response| response.mime_type_matches(pattern)).unwrap_or(false)).unwrap_or(false) ||
+                filter.third_party && url.and_then(|url| graph.is_third_party(url)) != Some(true) {
+                return None;
+            }
+            Some(Request {
+                request_id: *request_id,
+                edge_id: edge.id,
+                timestamp: edge.edge_timestamp,
+                request_type,
+                url,
+                initiator: edge.source,
+                status_code: response.and_then(|response| response.status_code),
+                mime_type: response.and_then(|response| response.mime_type.clone()),
+                size: info.as_ref().and_then(|info| info.size),
+                outcome: info.map(|info| info.outcome),
+                tracker: trackers.zip(url).and_then(|(trackers, url)| trackers.lookup_url(url)),
+            })
+        }
+        _ => None,
+    }).collect::<Vec<_>>();
+    requests.sort_by_key(|request| (request.timestamp, request.edge_id));
+
+    println!("{}", config::to_json(output_format, &requests));
+}
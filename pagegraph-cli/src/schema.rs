@@ -0,0 +1,109 @@
+//! Prints out the JSON Schema of a subcommand's output, or lists the subcommands with one.
+
+use std::collections::HashSet;
+
+use pagegraph::analysis::ad_dom_share::AdDomShare;
+use pagegraph::analysis::cname_cloaking::CloakedRequest;
+use pagegraph::analysis::critical_path::CriticalPath;
+use pagegraph::analysis::csp::CspViolation;
+use pagegraph::analysis::navigations::Navigation;
+use pagegraph::analysis::page_weight::ThirdPartyCost;
+use pagegraph::analysis::popups::Popup;
+use pagegraph::analysis::registry::AnalysisRegistry;
+use pagegraph::analysis::report::{PrivacyReport, SecurityReport};
+use pagegraph::analysis::script_cost::ScriptCost;
+use pagegraph::analysis::shields::{BlockedResource, ShieldsComparison};
+use pagegraph::analysis::storage::{Cookie, StorageKeyUsage};
+use pagegraph::analysis::websockets::WebSocketConnection;
+use pagegraph::export::Highlight;
+use pagegraph::graph::{DownstreamRequests, MatchedResource};
+use pagegraph::schema::{Schema, SchemaGenerator};
+use pagegraph::stats::{ActivityHistogram, FrameStats, GraphSummary};
+use schemars::schema::{SchemaObject, SubschemaValidation};
+
+use crate::config::{self, OutputFormat};
+use crate::{analysis, request_id_info, requests};
+
+/// Every subcommand with JSON output. `identify` and `script_source` only print text.
+const SUBCOMMANDS: &[&str] = &[
+    "ad_dom_share", "adblock_rules", "analysis", "blocked", "cname_cloaking", "critical_path", "csp",
+    "downstream_requests", "export", "histogram", "navigations", "popups", "privacy_report", "request_id_info",
+    "requests", "schema", "script_cost", "security_report", "shields_comparison", "stats", "storage", "third_party_costs",
+];
+
+/// Output that differs depending on the subcommand's arguments matches any of the alternatives.
+fn any_of(alternatives: Vec<Schema>) -> Schema {
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation { any_of: Some(alternatives), ..Default::default() })),
+        ..Default::default()
+    }.into()
+}
+
+/// The schema of the subcommand's output, with `analysis` naming an analysis for the `analysis`
+/// subcommand.
+fn output_schema(generator: &mut SchemaGenerator, subcommand: &str, analysis: Option<&str>) -> Schema {
+    match subcommand {
+        "ad_dom_share" => generator.subschema_for::<AdDomShare>(),
+        "adblock_rules" => generator.subschema_for::<Vec<MatchedResource>>(),
+        "analysis" => match analysis {
+            Some(name) => {
+                let registry = AnalysisRegistry::with_builtins();
+                let analysis = registry.get(name).unwrap_or_else(|| panic!("No analysis named {} is registered", name));
+                analysis.schema(generator).unwrap_or_else(|| panic!("The {} analysis has no schema", name))
+            }
+            None => generator.subschema_for::<Vec<analysis::AnalysisListing>>(),
+        },
+        "blocked" => generator.subschema_for::<Vec<BlockedResource>>(),
+        "cname_cloaking" => generator.subschema_for::<Vec<CloakedRequest>>(),
+        "critical_path" => generator.subschema_for::<CriticalPath>(),
+        // Only with --policy; the inferred policy is printed as text.
+        "csp" => generator.subschema_for::<Vec<CspViolation>>(),
+        "downstream_requests" => any_of(vec![
+            generator.subschema_for::<DownstreamRequests>(),
+            generator.subschema_for::<HashSet<usize>>(),
+        ]),
+        // Only with --format highlight.
+        "export" => generator.subschema_for::<Highlight>(),
+        "histogram" => generator.subschema_for::<ActivityHistogram>(),
+        "navigations" => generator.subschema_for::<Vec<Navigation>>(),
+        "popups" => generator.subschema_for::<Vec<Popup>>(),
+        // Only without --html.
+        "privacy_report" => generator.subschema_for::<PrivacyReport>(),
+        "request_id_info" => generator.subschema_for::<request_id_info::RequestInfo>(),
+        "requests" => any_of(vec![
+            generator.subschema_for::<Vec<requests::Request>>(),
+            generator.subschema_for::<Vec<WebSocketConnection>>(),
+        ]),
+        "schema" => any_of(vec![
+            generator.subschema_for::<Vec<String>>(),
+            Schema::new_ref("http://json-schema.org/draft-07/schema#".to_string()),
+        ]),
+        "script_cost" => any_of(vec![
+            generator.subschema_for::<ScriptCost>(),
+            generator.subschema_for::<Vec<ScriptCost>>(),
+        ]),
+        "security_report" => generator.subschema_for::<SecurityReport>(),
+        "shields_comparison" => generator.subschema_for::<ShieldsComparison>(),
+        "stats" => any_of(vec![
+            generator.subschema_for::<GraphSummary>(),
+            generator.subschema_for::<Vec<FrameStats>>(),
+        ]),
+        "storage" => any_of(vec![
+            generator.subschema_for::<Vec<StorageKeyUsage>>(),
+            generator.subschema_for::<Vec<Cookie>>(),
+        ]),
+        "third_party_costs" => generator.subschema_for::<Vec<ThirdPartyCost>>(),
+        _ => panic!("The {} subcommand has no JSON output; run without a subcommand to list those that do", subcommand),
+    }
+}
+
+pub fn main(subcommand: Option<&str>, analysis: Option<&str>, output_format: OutputFormat) {
+    match subcommand {
+        Some(subcommand) => {
+            let mut generator = pagegraph::schema::generator();
+            let schema = output_schema(&mut generator, subcommand, analysis);
+            println!("{}", serde_json::to_string_pretty(&pagegraph::schema::into_root_schema(generator, schema)).unwrap());
+        }
+        None => println!("{}", config::to_json(output_format, SUBCOMMANDS)),
+    }
+}
@@ -0,0 +1,13 @@
+//! Prints out the actions attributed to each script, as a proxy for their main-thread work,
+//! ranked from the most actions to the fewest.
+
+use pagegraph::graph::{NodeId, PageGraph};
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, node_id: Option<NodeId>, output_format: OutputFormat) {
+    match node_id {
+        Some(node_id) => println!("{}", config::to_json(output_format, &graph.script_cost(node_id))),
+        None => println!("{}", config::to_json(output_format, &graph.script_costs())),
+    }
+}
@@ -0,0 +1,55 @@
+//! Dumps the recorded source text of scripts, each preceded by a comment header describing where
+//! the script came from.
+
+use std::io::Write;
+use std::path::Path;
+
+use pagegraph::filter;
+use pagegraph::graph::{Node, NodeId, PageGraph};
+use pagegraph::types::{EdgeType, NodeKind, NodeType};
+
+fn header(graph: &PageGraph, node: &Node) -> String {
+    let (url, script_type, script_id) = match &node.node_type {
+        NodeType::Script { url, script_type, script_id, .. } => (url, script_type, script_id),
+        _ => panic!("Node {} is not a script", node.id),
+    };
+    let mut header = format!("// node: {}\n// script id: {}\n// type: {}\n// url: {}\n",
+        node.id,
+        script_id,
+        script_type.as_str(),
+        url.as_deref().unwrap_or("(inline)"));
+    if let Some(hash) = node.script_hash() {
+        header.push_str(&format!("// hash: {}\n", hash));
+    }
+    graph.incoming_edges(node)
+        .filter(|edge| matches!(edge.edge_type, EdgeType::Execute {} | EdgeType::ExecuteFromAttribute { .. }))
+        .for_each(|edge| header.push_str(&format!("// executed by: {} ({})\n", edge.source, graph.source_node(edge).label())));
+    header
+}
+
+pub fn main(graph: &PageGraph, node_id: Option<NodeId>, out_dir: Option<&Path>) {
+    let mut scripts = match node_id {
+        Some(node_id) => {
+            let node = graph.nodes.get(&node_id).unwrap_or_else(|| panic!("No node with id {} was found in this graph", node_id));
+            vec![node]
+        }
+        None => graph.filter_nodes(filter::node_kind(NodeKind::Script)).collect(),
+    };
+    scripts.sort_by_key(|node| node.id);
+
+    for node in scripts {
+        let source = match &node.node_type {
+            NodeType::Script { source, .. } => source,
+            _ => panic!("Node {} is not a script", node.id),
+        };
+        let contents = format!("{}\n{}\n", header(graph, node), source);
+        match out_dir {
+            Some(out_dir) => {
+                let path = out_dir.join(format!("{}.js", node.id.to_string().replace(':', "_")));
+                std::fs::write(&path, contents).unwrap_or_else(|e| panic!("Could not write {}: {}", path.display(), e));
+                println!("{}", path.display());
+            }
+            None => std::io::stdout().write_all(contents.as_bytes()).unwrap(),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Prints out a report of security-relevant page behavior: mixed content, dangerous sink usage,
+//! and flows of third-party data into the page.
+
+use pagegraph::analysis::report;
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, output_format: OutputFormat) {
+    println!("{}", config::to_json(output_format, &report::security(graph)));
+}
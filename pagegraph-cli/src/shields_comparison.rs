@@ -0,0 +1,10 @@
+//! Prints out a comparison of recordings of the same URL with and without Brave Shields.
+
+use pagegraph::analysis::shields::compare_shields;
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(shields_off: &PageGraph, shields_on: &PageGraph, output_format: OutputFormat) {
+    println!("{}", config::to_json(output_format, &compare_shields(shields_off, shields_on)));
+}
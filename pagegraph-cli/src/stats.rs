@@ -0,0 +1,14 @@
+//! Prints out summary counts of the graph's contents, either overall or for each frame.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, frames: bool, output_format: OutputFormat) {
+    if frames {
+        println!("{}", config::to_json(output_format, &graph.frame_stats()));
+        return;
+    }
+
+    println!("{}", config::to_json(output_format, &graph.summary()));
+}
@@ -0,0 +1,15 @@
+//! Prints out how scripts used cookies and Web Storage, or the cookies left at the end of the
+//! page load.
+
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+pub fn main(graph: &PageGraph, cookie_jar: bool, output_format: OutputFormat) {
+    if cookie_jar {
+        println!("{}", config::to_json(output_format, &graph.cookie_jar()));
+        return;
+    }
+
+    println!("{}", config::to_json(output_format, &graph.storage_usage()));
+}
@@ -0,0 +1,17 @@
+//! Prints out the cost of each third party on the page.
+
+use std::collections::HashMap;
+
+use pagegraph::analysis::trackers::TrackerCategories;
+use pagegraph::graph::PageGraph;
+
+use crate::config::{self, OutputFormat};
+
+/// If `trackers` is given, each third party is tagged with its tracker categories.
+pub fn main(graph: &PageGraph, entities: &HashMap<String, String>, trackers: Option<&TrackerCategories>, output_format: OutputFormat) {
+    let mut costs = graph.third_party_costs(entities);
+    if let Some(trackers) = trackers {
+        trackers.categorize(&mut costs);
+    }
+    println!("{}", config::to_json(output_format, &costs));
+}
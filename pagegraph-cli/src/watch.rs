@@ -0,0 +1,99 @@
+//! Runs a subcommand on each graph written to a directory, such as a crawler's output directory,
+//! printing one line of JSON per graph.
+//!
+//! There's no file notification library among the CLI's dependencies, so the directory is polled.
+//! A graph is considered fully written once its size and modification time are unchanged between
+//! two polls. Graphs of remote frames are skipped, since they're merged into the graph of the
+//! page that embedded them.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+
+/// How much of the start of a graph file is searched for its `desc` element.
+const DESC_SEARCH_LENGTH: u64 = 64 * 1024;
+
+#[derive(Serialize)]
+struct WatchResult<'a> {
+    file: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The size and modification time of a file, used to tell whether it's still being written.
+type FileState = (u64, SystemTime);
+
+fn graph_files(dir: &Path) -> HashMap<PathBuf, FileState> {
+    std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Could not read {}: {}", dir.display(), e))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().map(|extension| extension == "graphml").unwrap_or(false))
+        // Files can be removed between listing the directory and reading their metadata.
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((entry.path(), (metadata.len(), metadata.modified().ok()?)))
+        })
+        .collect()
+}
+
+/// Whether the graph was recorded for a remote frame, without parsing the whole file.
+fn is_remote_frame(path: &Path) -> bool {
+    let mut start = String::new();
+    match std::fs::File::open(path) {
+        Ok(file) => { let _ = file.take(DESC_SEARCH_LENGTH).read_to_string(&mut start); }
+        Err(_) => return false,
+    }
+    start.contains("<is_root>false</is_root>")
+}
+
+/// Runs this executable with the subcommand on a single graph.
+fn run_on(path: &Path, config_file: Option<&str>, subcommand: &str, args: &[&str]) -> Result<serde_json::Value, String> {
+    let exe = std::env::current_exe().expect("Could not find the path of the running executable");
+    let mut command = Command::new(exe);
+    command.arg("-f").arg(path);
+    if let Some(config_file) = config_file {
+        command.arg("--config").arg(config_file);
+    }
+    let output = command.arg(subcommand).args(args).output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    // Subcommands that print text, rather than JSON, have their output kept as a string.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(serde_json::from_str(&stdout).unwrap_or_else(|_| serde_json::Value::String(stdout.into_owned())))
+}
+
+/// Polls `dir` every `interval` forever. Graphs already in the directory are only analyzed if
+/// `existing` is set.
+pub fn main(dir: &Path, subcommand: &str, args: &[&str], config_file: Option<&str>, interval: Duration, existing: bool) {
+    let mut seen = if existing { HashSet::new() } else { graph_files(dir).into_keys().collect() };
+    let mut pending = HashMap::new();
+    loop {
+        for (path, state) in graph_files(dir) {
+            if seen.contains(&path) {
+                continue;
+            }
+            if pending.get(&path) != Some(&state) {
+                pending.insert(path, state);
+                continue;
+            }
+            pending.remove(&path);
+            seen.insert(path.clone());
+            if is_remote_frame(&path) {
+                continue;
+            }
+            let (output, error) = match run_on(&path, config_file, subcommand, args) {
+                Ok(output) => (Some(output), None),
+                Err(error) => (None, Some(error)),
+            };
+            println!("{}", serde_json::to_string(&WatchResult { file: &path, output, error }).unwrap());
+        }
+        std::thread::sleep(interval);
+    }
+}
@@ -8,14 +8,14 @@ fn main() {
     let graph_file = std::env::args().skip(1).next().expect("Provide a path to a `.graphml` file");
     let graph = read_from_file(&graph_file);
 
-    let html_elements = graph.filter_nodes(|node_type| {
-        match node_type {
+    let html_elements = graph.filter_nodes(|node| {
+        match node.node_type {
             NodeType::HtmlElement { .. } => true,
             _ => false,
         }
     });
 
-    let mut heavily_modified_elements: Vec<_> = html_elements.iter().filter_map(|node| {
+    let mut heavily_modified_elements: Vec<_> = html_elements.filter_map(|node| {
         let num_modifications = graph.all_html_element_modifications(node.id).len();
         if num_modifications >= 4 {
             Some((node.id, num_modifications))
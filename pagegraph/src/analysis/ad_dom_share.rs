@@ -0,0 +1,238 @@
+//! Measurement of how much of the final page is attributable to ads and trackers.
+//!
+//! Scripts and elements whose requests match a set of adblock filter rules are treated as ad
+//! actors, along with everything they cause downstream: elements created by an ad script, and
+//! scripts executed by an ad element. A node in the final DOM is attributable to ads if it, or
+//! any of its ancestors, including the owners of the frames it's in, is an ad actor.
+//!
+//! Rendered sizes aren't recorded, so visible area is only reconstructed from `width` and
+//! `height` attributes and inline styles given in pixels. Only the outermost sized elements are
+//! counted, so that nested elements don't contribute their area more than once.
+
+use std::collections::HashSet;
+
+use adblock::engine::Engine;
+
+use crate::graph::{Node, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// The share of the final DOM attributable to ad and tracker scripts.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AdDomShare {
+    pub url: String,
+    /// The number of elements and text nodes in the final DOM, including in frames.
+    pub dom_nodes: usize,
+    pub ad_dom_nodes: usize,
+    pub dom_share: f64,
+    /// The number of outermost elements in the final DOM with a known size.
+    pub sized_elements: usize,
+    /// The total area, in square pixels, of the outermost sized elements.
+    pub area: u64,
+    pub ad_area: u64,
+    /// The share of `area` attributable to ads, if any element had a known size.
+    pub area_share: Option<f64>,
+    /// Scripts attributed to ads, sorted by node id.
+    pub ad_scripts: Vec<NodeId>,
+    /// A single comparable score for the page, between 0 and 1. This is `area_share` where
+    /// sizes are known, and `dom_share` otherwise.
+    pub score: f64,
+}
+
+/// Parses a length in CSS pixels, like `300` or `250px`.
+fn pixels(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let value = value.strip_suffix("px").unwrap_or(value).trim();
+    value.parse::<f64>().ok().filter(|value| value.is_finite() && *value >= 0.0).map(|value| value.round() as u64)
+}
+
+/// Returns the value of the given property in an inline style declaration, like
+/// `width: 300px; height: 250px`.
+fn style_property<'a>(style: &'a str, property: &str) -> Option<&'a str> {
+    style.split(';')
+        .rev()
+        .filter_map(|declaration| declaration.split_once(':'))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case(property))
+        .map(|(_, value)| value.trim_end_matches("!important"))
+        .next()
+}
+
+fn share(part: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { part as f64 / total as f64 }
+}
+
+impl PageGraph {
+    /// Returns the width and height of the element in pixels, if both are known.
+    fn element_size(&self, element: &Node) -> Option<(u64, u64)> {
+        let style = self.latest_attribute(element, "style").map(|(_, style)| style);
+        let dimension = |property: &str| style.and_then(|style| style_property(style, property))
+            .or_else(|| self.latest_attribute(element, property).map(|(_, value)| value))
+            .and_then(pixels);
+        Some((dimension("width")?, dimension("height")?))
+    }
+
+    /// Returns every script and element whose requests match the given filter rules, and
+    /// everything they caused downstream.
+    fn ad_actors(&self, filter_rules: &[String]) -> HashSet<NodeId> {
+        let blocker = Engine::from_rules_debug(filter_rules, Default::default());
+        let matched_urls = self.nodes.values()
+            .filter_map(|node| match &node.node_type {
+                NodeType::Resource { url } => Some((node, url)),
+                _ => None,
+            })
+            .filter(|(node, url)| self.resource_request_types(&node.id).iter().any(|(request_type, _)| {
+                let result = blocker.check_network_urls(url, &self.desc.url, request_type);
+                result.matched && result.exception.is_none()
+            }))
+            .map(|(node, url)| (node.id, url))
+            .collect::<Vec<_>>();
+
+        let mut to_visit = self.nodes.values()
+            .filter(|node| match &node.node_type {
+                NodeType::Script { url: Some(url), .. } => matched_urls.iter().any(|(_, matched)| *matched == url),
+                NodeType::HtmlElement { .. } | NodeType::FrameOwner { .. } => self.outgoing_edges(node)
+                    .any(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }) && matched_urls.iter().any(|(resource, _)| *resource == edge.target)),
+                _ => false,
+            })
+            .map(|node| node.id)
+            .collect::<Vec<_>>();
+
+        let mut actors = HashSet::new();
+        while let Some(actor) = to_visit.pop() {
+            if !actors.insert(actor) {
+                continue;
+            }
+            let actor = self.nodes.get(&actor).unwrap();
+            to_visit.extend(self.outgoing_edges(actor)
+                .filter(|edge| matches!(edge.edge_type, EdgeType::CreateNode {} | EdgeType::Execute {} | EdgeType::ExecuteFromAttribute { .. }))
+                .map(|edge| edge.target));
+        }
+        actors
+    }
+
+    /// Computes the share of the final DOM, and of its visible area where element sizes are
+    /// known, that is attributable to scripts and elements matching the given adblock filter
+    /// rules.
+    pub fn ad_dom_share(&self, filter_rules: &[String]) -> AdDomShare {
+        let actors = self.ad_actors(filter_rules);
+        let tree = self.dom_tree();
+
+        let (mut dom_nodes, mut ad_dom_nodes, mut sized_elements) = (0, 0, 0);
+        let (mut area, mut ad_area) = (0u64, 0u64);
+        // Each entry is a node, whether it's attributable to ads, and whether it's inside a sized
+        // element.
+        let mut to_visit = self.top_level_documents().iter().map(|document| (document.id, false, false)).collect::<Vec<_>>();
+        while let Some((node_id, in_ad, in_sized)) = to_visit.pop() {
+            let node = self.nodes.get(&node_id).unwrap();
+            let is_ad = in_ad || actors.contains(&node_id);
+            let mut is_sized = in_sized;
+
+            match node.node_type {
+                NodeType::HtmlElement { .. } | NodeType::FrameOwner { .. } | NodeType::TextNode { .. } => {
+                    dom_nodes += 1;
+                    if is_ad {
+                        ad_dom_nodes += 1;
+                    }
+                    if let Some((width, height)) = self.element_size(node).filter(|_| !in_sized) {
+                        is_sized = true;
+                        sized_elements += 1;
+                        // Sizes are attacker-controlled, so they may be absurdly large.
+                        let element_area = width.saturating_mul(height);
+                        area = area.saturating_add(element_area);
+                        if is_ad {
+                            ad_area = ad_area.saturating_add(element_area);
+                        }
+                    }
+                }
+                _ => (),
+            }
+
+            let mut children = tree.children(node_id).to_vec();
+            children.extend(tree.shadow_root(node_id));
+            if let NodeType::FrameOwner { .. } = node.node_type {
+                children.extend(self.content_documents_of(node).iter().map(|document| document.id));
+            }
+            to_visit.extend(children.into_iter().rev().map(|child| (child, is_ad, is_sized)));
+        }
+
+        let mut ad_scripts = actors.iter()
+            .filter(|actor| matches!(self.nodes.get(actor).unwrap().node_type, NodeType::Script { .. }))
+            .copied()
+            .collect::<Vec<_>>();
+        ad_scripts.sort();
+
+        let dom_share = share(ad_dom_nodes as u64, dom_nodes as u64);
+        let area_share = if area > 0 { Some(share(ad_area, area)) } else { None };
+        AdDomShare {
+            url: self.desc.url.to_string(),
+            dom_nodes,
+            ad_dom_nodes,
+            dom_share,
+            sized_elements,
+            area,
+            ad_area,
+            area_share,
+            ad_scripts,
+            score: area_share.unwrap_or(dom_share),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_ad_dom_share() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+        let article = builder.add_element(parser, body, "div");
+        builder.set_attribute(parser, article, "style", "width: 600px; height: 300px");
+        builder.add_text(parser, article, "Hello");
+
+        let element = builder.add_element(parser, body, "script");
+        builder.add_request(element, "https://ads.net/ad.js", RequestType::Script);
+        let ad_script = builder.add_script(element, Some("https://ads.net/ad.js"), "");
+        let slot = builder.add_element(ad_script, body, "div");
+        builder.set_attribute(ad_script, slot, "width", "300");
+        builder.set_attribute(ad_script, slot, "height", "200px");
+        let image = builder.add_element(ad_script, slot, "img");
+        builder.set_attribute(ad_script, image, "width", "300");
+        builder.set_attribute(ad_script, image, "height", "200");
+        let graph = builder.build();
+
+        let share = graph.ad_dom_share(&["||ads.net^".to_string()]);
+        assert_eq!(share.ad_scripts, vec![ad_script]);
+        // The script element requested the ad script, so it's attributed along with the slot and
+        // image
+        assert_eq!((share.dom_nodes, share.ad_dom_nodes), (6, 3));
+        // The image is inside the sized slot, so it doesn't count separately
+        assert_eq!((share.sized_elements, share.area, share.ad_area), (2, 240000, 60000));
+        assert_eq!(share.area_share, Some(0.25));
+        assert_eq!(share.score, 0.25);
+    }
+
+    #[test]
+    fn test_oversized_elements() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+        let element = builder.add_element(parser, body, "script");
+        builder.add_request(element, "https://ads.net/ad.js", RequestType::Script);
+        let ad_script = builder.add_script(element, Some("https://ads.net/ad.js"), "");
+        for _ in 0..2 {
+            let slot = builder.add_element(ad_script, body, "div");
+            builder.set_attribute(ad_script, slot, "width", "1e20");
+            builder.set_attribute(ad_script, slot, "height", "1e20px");
+        }
+        let graph = builder.build();
+
+        let share = graph.ad_dom_share(&["||ads.net^".to_string()]);
+        assert_eq!((share.sized_elements, share.area, share.ad_area), (2, u64::MAX, u64::MAX));
+        assert_eq!(share.area_share, Some(1.0));
+    }
+}
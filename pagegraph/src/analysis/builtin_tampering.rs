@@ -0,0 +1,146 @@
+//! Detection of scripts that redefine properties of builtins and Web API prototypes.
+//!
+//! Plain assignments, like `window.fetch = wrapper`, aren't recorded by PageGraph, so tampering
+//! is found through recorded calls to the JS builtins that redefine properties or prototypes,
+//! like `Object.defineProperty(Navigator.prototype, "userAgent", ...)`. The redefined API is
+//! recovered from the call's arguments where possible, and matched against later Web API calls
+//! from other scripts, which would have received the tampered version.
+
+use crate::analysis::split_call_args;
+use crate::graph::{EdgeId, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// JS builtins that can redefine a property or replace an object's prototype.
+const TAMPERING_BUILTINS: [&str; 7] = [
+    "Object.defineProperty",
+    "Object.defineProperties",
+    "Object.setPrototypeOf",
+    "Reflect.defineProperty",
+    "Reflect.setPrototypeOf",
+    "Object.prototype.__defineGetter__",
+    "Object.prototype.__defineSetter__",
+];
+
+/// A single call that redefined part of a builtin or Web API.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BuiltinTampering {
+    pub script: NodeId,
+    pub script_url: Option<String>,
+    pub edge: EdgeId,
+    pub timestamp: Option<isize>,
+    /// The builtin used to redefine the API, like `Object.defineProperty`.
+    pub builtin: String,
+    /// The API that was redefined, like `Navigator.userAgent`, if it could be determined from
+    /// the call's arguments.
+    pub api: Option<String>,
+    pub args: Option<String>,
+    /// Other scripts that called the redefined API afterwards, sorted by node id.
+    pub later_callers: Vec<NodeId>,
+}
+
+/// Normalizes a serialized object, like `[object NavigatorPrototype]` or `Navigator.prototype`,
+/// to the interface name used in Web API method names, like `Navigator`.
+fn interface_name(object: &str) -> Option<String> {
+    let object = object.trim().trim_start_matches("[object ").trim_end_matches(']');
+    let object = object.trim_end_matches(".prototype").trim_end_matches("Prototype");
+    let object = object.rsplit('.').next()?;
+    if !object.is_empty() && object.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$') {
+        Some(object.to_string())
+    } else {
+        None
+    }
+}
+
+/// Returns the `Interface.property` name of the API redefined by a call with the given
+/// arguments, e.g. `["[object Navigator]", "userAgent", ...]`, if it can be determined.
+fn tampered_api(builtin: &str, args: &str) -> Option<String> {
+    let args = split_call_args(args);
+    if builtin.starts_with("Object.prototype.") {
+        // `__defineGetter__` is called on the object itself, so only the property is recorded.
+        return None;
+    }
+    let interface = interface_name(args.first()?)?;
+    match builtin {
+        "Object.setPrototypeOf" | "Reflect.setPrototypeOf" | "Object.defineProperties" => Some(interface),
+        _ => Some(format!("{}.{}", interface, args.get(1)?)),
+    }
+}
+
+impl PageGraph {
+    /// Returns every recorded call that redefined part of a builtin or Web API, in chronological
+    /// order.
+    pub fn builtin_tampering(&self) -> Vec<BuiltinTampering> {
+        let mut tampering = self.edges.values().filter_map(|edge| {
+            let (args, builtin) = match (&edge.edge_type, &self.target_node(edge).node_type) {
+                (EdgeType::JsCall { args, .. }, NodeType::JsBuiltin { method }) if TAMPERING_BUILTINS.contains(&method.as_str()) => (args, method),
+                _ => return None,
+            };
+            let script = self.source_node(edge);
+            let api = args.as_deref().and_then(|args| tampered_api(builtin, args));
+
+            let mut later_callers = match (&api, edge.edge_timestamp) {
+                (Some(api), Some(timestamp)) => self.edges_in_range(timestamp..)
+                    .filter(|call| call.source != script.id && matches!(call.edge_type, EdgeType::JsCall { .. }))
+                    .filter(|call| match &self.target_node(call).node_type {
+                        NodeType::WebApi { method } => method == api || method.starts_with(&format!("{}.", api)),
+                        _ => false,
+                    })
+                    .map(|call| call.source)
+                    .collect::<Vec<_>>(),
+                _ => vec![],
+            };
+            later_callers.sort();
+            later_callers.dedup();
+
+            Some(BuiltinTampering {
+                script: script.id,
+                script_url: script.node_type.url().map(|url| url.to_string()),
+                edge: edge.id,
+                timestamp: edge.edge_timestamp,
+                builtin: builtin.clone(),
+                api,
+                args: args.clone(),
+                later_callers,
+            })
+        }).collect::<Vec<_>>();
+        tampering.sort_by_key(|tampering| (tampering.timestamp, tampering.edge));
+        tampering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_builtin_tampering() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let first = builder.add_element(parser, root, "script");
+        let second = builder.add_element(parser, root, "script");
+        let tamperer = builder.add_script(first, Some("https://fp.net/spoof.js"), "");
+        let victim = builder.add_script(second, None, "");
+
+        builder.call_web_api(victim, "Navigator.userAgent", None);
+        let define_property = builder.add_node(NodeType::JsBuiltin { method: "Object.defineProperty".to_string() });
+        let call = builder.add_edge(tamperer, define_property, EdgeType::JsCall {
+            args: Some("[\"[object NavigatorPrototype]\", \"userAgent\", \"[object Object]\"]".to_string()),
+            script_position: 0,
+        });
+        builder.call_web_api(tamperer, "Navigator.userAgent", None);
+        builder.call_web_api(victim, "Navigator.userAgent", None);
+        builder.call_web_api(victim, "Navigator.platform", None);
+        let graph = builder.build();
+
+        let tampering = graph.builtin_tampering();
+        assert_eq!(tampering.len(), 1);
+        assert_eq!((tampering[0].edge, tampering[0].script), (call, tamperer));
+        assert_eq!(tampering[0].api.as_deref(), Some("Navigator.userAgent"));
+        assert_eq!(tampering[0].later_callers, vec![victim]);
+
+        assert_eq!(tampered_api("Object.setPrototypeOf", "Window.prototype, null").as_deref(), Some("Window"));
+    }
+}
@@ -0,0 +1,109 @@
+//! Detection of trackers cloaked behind first-party subdomains.
+//!
+//! A tracker can be served from a first-party subdomain, like `metrics.example.com`, whose DNS
+//! record is a CNAME pointing at the tracker's own domain. Such requests look first-party when
+//! comparing URLs, so each first-party host is resolved through a caller-provided
+//! [`CnameResolver`], and its CNAME chain is checked against a tracker list.
+
+use std::collections::HashMap;
+
+use super::trackers::{TrackerCategories, TrackerInfo};
+use crate::graph::PageGraph;
+
+/// Resolves the CNAME records of hosts. PageGraph doesn't record DNS resolution, so this has to
+/// be done separately, either live or from results captured during the crawl.
+pub trait CnameResolver {
+    /// Returns the CNAME targets `host` resolves through, in order, or nothing if it has no
+    /// CNAME record.
+    fn cname_chain(&self, host: &str) -> Vec<String>;
+}
+
+/// Precomputed CNAME chains, keyed by host.
+impl CnameResolver for HashMap<String, Vec<String>> {
+    fn cname_chain(&self, host: &str) -> Vec<String> {
+        self.get(host).cloned().unwrap_or_default()
+    }
+}
+
+/// A first-party request whose host is an alias for a known tracker domain.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CloakedRequest {
+    pub request_id: usize,
+    pub url: String,
+    pub host: String,
+    pub cname_chain: Vec<String>,
+    /// The first domain in the CNAME chain found in the tracker list.
+    pub tracker_domain: String,
+    pub tracker: TrackerInfo,
+}
+
+impl PageGraph {
+    /// Returns every first-party request whose host has a CNAME chain leading to a domain in
+    /// `trackers`, in request order. Hosts that are themselves in `trackers` aren't reported, as
+    /// they aren't cloaked. Each host is only resolved once.
+    pub fn cname_cloaked_requests<R: CnameResolver + ?Sized>(&self, resolver: &R, trackers: &TrackerCategories) -> Vec<CloakedRequest> {
+        let mut chains = HashMap::<String, Vec<String>>::new();
+        let mut cloaked = vec![];
+
+        for request in self.requests() {
+            let url = match request.url.as_ref().filter(|url| self.is_third_party(url) == Some(false)) {
+                Some(url) => url,
+                None => continue,
+            };
+            let host = match url.parsed().and_then(|url| url.host_str().map(|host| host.to_ascii_lowercase())) {
+                Some(host) => host,
+                None => continue,
+            };
+            if trackers.lookup_host(&host).is_some() {
+                continue;
+            }
+
+            let chain = chains.entry(host.clone()).or_insert_with(|| resolver.cname_chain(&host).into_iter()
+                .map(|target| target.trim_end_matches('.').to_ascii_lowercase())
+                .collect());
+            let listed = chain.iter().find_map(|target| trackers.lookup_host(target).map(|tracker| (target, tracker)));
+            if let Some((tracker_domain, tracker)) = listed {
+                cloaked.push(CloakedRequest {
+                    request_id: request.request_id,
+                    url: url.to_string(),
+                    host,
+                    cname_chain: chain.clone(),
+                    tracker_domain: tracker_domain.clone(),
+                    tracker: tracker.clone(),
+                });
+            }
+        }
+        cloaked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::trackers::TrackerCategory;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_cname_cloaked_requests() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let cloaked = builder.add_request(parser, "https://metrics.example.com/collect", RequestType::Image);
+        builder.add_request(parser, "https://cdn.example.com/app.js", RequestType::Script);
+        builder.add_request(parser, "https://tracker.net/pixel.gif", RequestType::Image);
+        let graph = builder.build();
+
+        let mut trackers = TrackerCategories::new();
+        trackers.insert("tracker.net", "Tracker", TrackerCategory::Analytics);
+        let mut resolver = HashMap::new();
+        resolver.insert("metrics.example.com".to_string(), vec!["example.eu1.tracker.net.".to_string()]);
+        resolver.insert("cdn.example.com".to_string(), vec!["example.cdnprovider.com.".to_string()]);
+
+        let requests = graph.cname_cloaked_requests(&resolver, &trackers);
+        assert_eq!(requests.len(), 1);
+        assert_eq!((requests[0].request_id, requests[0].host.as_str()), (cloaked, "metrics.example.com"));
+        assert_eq!(requests[0].tracker_domain, "example.eu1.tracker.net");
+        assert_eq!(requests[0].tracker.entity, "Tracker");
+    }
+}
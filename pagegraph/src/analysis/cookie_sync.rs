@@ -0,0 +1,107 @@
+//! Detection of identifiers stored in cookies or Web Storage being sent to third parties.
+//!
+//! Cookie syncing shares a tracker's identifier for the user with other trackers by including it
+//! in the URLs of requests to them. Any stored value that looks like an identifier, and appears
+//! in the URL of a third-party request, is reported as a possible sync.
+
+use crate::analysis::Thresholds;
+use crate::analysis::storage::StorageArea;
+use crate::graph::{NodeId, PageGraph};
+use crate::graph_algos::domain_of_url;
+
+/// A stored identifier that appeared in the URL of a third-party request.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CookieSync {
+    pub area: StorageArea,
+    pub key: String,
+    pub identifier: String,
+    /// Scripts that stored the identifier, sorted by node id.
+    pub writers: Vec<NodeId>,
+    pub request_id: usize,
+    pub url: String,
+    pub domain: Option<String>,
+}
+
+/// Returns the identifier in a stored value, if it looks like one. Cookie values may be recorded
+/// along with their name and attributes, like `uid=abc; path=/`, so those are stripped. Values
+/// shorter than `min_length` are ignored.
+fn identifier_of<'a>(key: &str, value: &'a str, min_length: usize) -> Option<&'a str> {
+    let value = value.split(';').next().unwrap_or(value).trim();
+    let value = value.strip_prefix(key).and_then(|value| value.strip_prefix('=')).unwrap_or(value);
+    Some(value).filter(|value| value.len() >= min_length)
+        .filter(|value| value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .filter(|value| value.chars().any(|c| c.is_ascii_digit()))
+}
+
+impl PageGraph {
+    /// Returns every stored identifier that appeared in the URL of a third-party request, in
+    /// request order.
+    pub fn cookie_syncs(&self) -> Vec<CookieSync> {
+        self.cookie_syncs_with(&Thresholds::default())
+    }
+
+    /// Like [`cookie_syncs`](PageGraph::cookie_syncs), with the minimum identifier length taken
+    /// from `thresholds`.
+    pub fn cookie_syncs_with(&self, thresholds: &Thresholds) -> Vec<CookieSync> {
+        let identifiers = self.storage_usage().into_iter()
+            .flat_map(|usage| usage.values.iter()
+                .filter_map(|value| identifier_of(&usage.key, value, thresholds.min_identifier_length).map(|identifier| identifier.to_string()))
+                .map(|identifier| (usage.area, usage.key.clone(), identifier, usage.writers.clone()))
+                .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let mut syncs = vec![];
+        for request in self.requests() {
+            let url = match request.url.as_ref().filter(|url| self.is_third_party(url) == Some(true)) {
+                Some(url) => url,
+                None => continue,
+            };
+            let query = url.parsed().map(|url| url.query_pairs().map(|(_, value)| value.into_owned()).collect::<Vec<_>>()).unwrap_or_default();
+            for (area, key, identifier, writers) in identifiers.iter() {
+                if url.contains(identifier.as_str()) || query.iter().any(|value| value.contains(identifier.as_str())) {
+                    syncs.push(CookieSync {
+                        area: *area,
+                        key: key.clone(),
+                        identifier: identifier.clone(),
+                        writers: writers.clone(),
+                        request_id: request.request_id,
+                        url: url.to_string(),
+                        domain: domain_of_url(url),
+                    });
+                }
+            }
+        }
+        syncs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::{EdgeType, NodeType, RequestType};
+
+    #[test]
+    fn test_cookie_syncs() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let tracker = builder.add_script(element, Some("https://tracker.net/t.js"), "");
+        let cookies = builder.add_node(NodeType::CookieJar {});
+        builder.add_edge(tracker, cookies, EdgeType::StorageSet { key: "uid".to_string(), value: Some("uid=a1b2c3d4e5; path=/".to_string()) });
+        builder.add_edge(tracker, cookies, EdgeType::StorageSet { key: "consent".to_string(), value: Some("accepted".to_string()) });
+        builder.add_request(tracker, "https://example.com/log?id=a1b2c3d4e5", RequestType::Image);
+        let sync = builder.add_request(tracker, "https://partner.com/sync?u=a1b2c3d4e5&consent=accepted", RequestType::Image);
+        let graph = builder.build();
+
+        let syncs = graph.cookie_syncs();
+        assert_eq!(syncs.len(), 1);
+        assert_eq!((syncs[0].request_id, syncs[0].identifier.as_str(), &syncs[0].writers), (sync, "a1b2c3d4e5", &vec![tracker]));
+        assert_eq!(syncs[0].domain.as_deref(), Some("partner.com"));
+
+        let strict = Thresholds { min_identifier_length: 16, ..Thresholds::default() };
+        assert!(graph.cookie_syncs_with(&strict).is_empty());
+    }
+}
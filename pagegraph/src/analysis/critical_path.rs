@@ -0,0 +1,193 @@
+//! Approximation of the critical request chain delaying `DOMContentLoaded`.
+//!
+//! PageGraph doesn't record when the parser was blocked, or what discovered a subresource, so
+//! dependencies between render-blocking requests are reconstructed from the requests started
+//! before [`DOMContentLoaded`](crate::lifecycle::Lifecycle::dom_content_loaded):
+//! - Scripts without `async` or `defer`, stylesheets, and fonts are considered render-blocking.
+//! - A request started by a script depends on the request that fetched that script.
+//! - A font depends on the most recently finished stylesheet, as fonts are only discovered once
+//!   the stylesheet using them has been parsed.
+//! - Any other blocking request depends on the most recently finished blocking script or
+//!   stylesheet, as the parser can't continue past a blocking script until it and any preceding
+//!   stylesheets have loaded.
+//!
+//! The critical path is then the dependency chain ending with the request that finished last.
+//! PageGraph doesn't record rendering either, so this is also used to approximate the chain
+//! delaying first render.
+
+use crate::graph::{NodeId, PageGraph};
+use crate::types::{NodeType, RequestType, ResourceType};
+
+/// The kinds of render-blocking requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum BlockingKind {
+    Script,
+    Stylesheet,
+    Font,
+}
+
+/// A single render-blocking request on the critical path. Timestamps are in milliseconds relative
+/// to the start of the page load.
+#[derive(Debug, Clone, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CriticalHop {
+    pub request_id: usize,
+    pub url: Option<String>,
+    pub kind: BlockingKind,
+    pub initiator: NodeId,
+    pub start: isize,
+    /// When the request completed or failed, or when it started if it never finished.
+    pub end: isize,
+    /// How long the request took, from `start` to `end`.
+    pub duration: isize,
+    /// How long after the previous hop ended, or after the page load started for the first hop,
+    /// this request started.
+    pub wait: isize,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CriticalPath {
+    pub dom_content_loaded: Option<isize>,
+    /// The blocking requests along the path, in order.
+    pub hops: Vec<CriticalHop>,
+    /// When the last hop ended.
+    pub end: Option<isize>,
+}
+
+impl PageGraph {
+    /// A script is only blocking if it was fetched by the parser, or by a `<script>` element
+    /// without `async` or `defer`.
+    fn is_blocking_script_request(&self, initiators: &[NodeId]) -> bool {
+        initiators.iter().all(|initiator| {
+            let initiator = self.nodes.get(initiator).unwrap();
+            match &initiator.node_type {
+                NodeType::Parser {} => true,
+                NodeType::HtmlElement { .. } => ["async", "defer"].iter().all(|key| self.latest_attribute(initiator, key).is_none()) &&
+                    self.latest_attribute(initiator, "type").map(|(_, value)| !value.eq_ignore_ascii_case("module")).unwrap_or(true),
+                _ => false,
+            }
+        })
+    }
+
+    /// Returns the approximate chain of render-blocking requests delaying `DOMContentLoaded` in
+    /// the root frame. The path is empty if no blocking requests were made before it.
+    pub fn critical_path(&self) -> CriticalPath {
+        let dom_content_loaded = self.lifecycle().dom_content_loaded;
+        let dcl = match dom_content_loaded {
+            Some(dcl) => dcl,
+            None => return CriticalPath { dom_content_loaded, hops: vec![], end: None },
+        };
+
+        let mut candidates = self.requests().into_iter()
+            .filter(|request| request.frame_id.is_none())
+            .filter_map(|request| {
+                let start = self.edges.get(&request.starts[0]).unwrap().edge_timestamp?;
+                if start > dcl {
+                    return None;
+                }
+                let kind = match (&request.resource_type, &request.request_type) {
+                    (Some(ResourceType::Css), _) | (None, RequestType::CSS) => BlockingKind::Stylesheet,
+                    (Some(ResourceType::Font), _) => BlockingKind::Font,
+                    (Some(ResourceType::Script), _) | (None, RequestType::Script) if self.is_blocking_script_request(&request.initiators) => BlockingKind::Script,
+                    _ => return None,
+                };
+                let end = request.completion.or(request.error)
+                    .and_then(|edge| self.edges.get(&edge).unwrap().edge_timestamp)
+                    .unwrap_or(start);
+                Some(CriticalHop {
+                    request_id: request.request_id,
+                    url: request.url.map(|url| url.to_string()),
+                    kind,
+                    initiator: request.initiators[0],
+                    start,
+                    end,
+                    duration: end - start,
+                    wait: 0,
+                })
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|hop| (hop.start, hop.request_id));
+
+        // The index of the request each candidate depends on, if any.
+        let parents = candidates.iter().map(|hop| {
+            let initiator_script_url = match &self.nodes.get(&hop.initiator).unwrap().node_type {
+                NodeType::Script { url, .. } => Some(url.as_deref()),
+                _ => None,
+            };
+            if let Some(url) = initiator_script_url {
+                // Inline scripts aren't fetched, so they don't add a hop.
+                return url.and_then(|url| candidates.iter().position(|other| other.kind == BlockingKind::Script && other.url.as_deref() == Some(url)));
+            }
+            candidates.iter().enumerate()
+                .filter(|(_, other)| other.end <= hop.start && other.request_id != hop.request_id)
+                .filter(|(_, other)| match hop.kind {
+                    BlockingKind::Font => other.kind == BlockingKind::Stylesheet,
+                    _ => other.kind != BlockingKind::Font,
+                })
+                .max_by_key(|(_, other)| (other.end, other.start))
+                .map(|(index, _)| index)
+        }).collect::<Vec<_>>();
+
+        let last = candidates.iter().enumerate().max_by_key(|(_, hop)| (hop.end, hop.start)).map(|(index, _)| index);
+        let mut path = vec![];
+        let mut current = last;
+        while let Some(index) = current {
+            // Guard against cycles from requests that both started and ended at the same time.
+            if path.contains(&index) {
+                break;
+            }
+            path.push(index);
+            current = parents[index];
+        }
+        path.reverse();
+
+        let mut previous_end = 0;
+        let hops = path.into_iter().map(|index| {
+            let mut hop = candidates[index].clone();
+            hop.wait = hop.start - previous_end;
+            previous_end = hop.end;
+            hop
+        }).collect::<Vec<_>>();
+        CriticalPath { dom_content_loaded, end: hops.last().map(|hop| hop.end), hops }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_critical_path() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let stylesheet = builder.add_request(parser, "https://example.com/style.css", RequestType::CSS);
+        let blocking = builder.add_element(parser, root, "script");
+        let asynchronous = builder.add_element(parser, root, "script");
+        builder.set_attribute(parser, asynchronous, "async", "");
+        let script = builder.add_request(blocking, "https://example.com/app.js", RequestType::Script);
+        builder.add_request(asynchronous, "https://ads.net/ads.js", RequestType::Script);
+        builder.advance_time(20);
+        builder.complete_request(stylesheet, ResourceType::Css, None);
+        let font = builder.add_request(parser, "https://example.com/font.woff2", RequestType::Unknown);
+        builder.advance_time(50);
+        builder.complete_request(script, ResourceType::Script, None);
+        let app = builder.add_script(blocking, Some("https://example.com/app.js"), "");
+        let data = builder.add_request(app, "https://example.com/data.css", RequestType::CSS);
+        builder.advance_time(100);
+        builder.complete_request(font, ResourceType::Font, None);
+        builder.complete_request(data, ResourceType::Css, None);
+        builder.add_text(parser, root, "Done");
+        let graph = builder.build();
+
+        let path = graph.critical_path();
+        let hops = path.hops.iter().map(|hop| (hop.request_id, hop.kind)).collect::<Vec<_>>();
+        assert_eq!(hops, vec![(script, BlockingKind::Script), (data, BlockingKind::Stylesheet)]);
+        assert_eq!(path.hops[1].wait, path.hops[1].start - path.hops[0].end);
+        assert_eq!(path.hops[0].wait, path.hops[0].start);
+        assert_eq!(path.end, Some(path.hops[1].end));
+    }
+}
@@ -0,0 +1,358 @@
+//! Inference and evaluation of Content Security Policies from recorded page behavior.
+//!
+//! Every subresource request, WebSocket connection, frame document, and inline or `eval`ed
+//! script is treated as a load governed by a CSP fetch directive. The directive is chosen from
+//! the response's resource type when it was recorded, and otherwise from the initiating element
+//! or the request type. Loads from the page's own origin are allowed by `'self'`, and others by
+//! the origin they were loaded from.
+//!
+//! Policies are evaluated with a subset of CSP's source matching rules: `'none'`, `*`, `'self'`,
+//! scheme sources, host sources with optional wildcards, ports, and paths, and
+//! `'unsafe-inline'` and `'unsafe-eval'` for inline and `eval`ed scripts. Nonces and hashes
+//! can't be checked against the graph, so they never allow a load.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::graph::{EdgeId, PageGraph};
+use crate::types::{EdgeType, NodeType, RequestType, ResourceType, Url};
+
+use super::origin_of_url;
+
+/// What a load governed by a CSP directive fetched or executed.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum CspLoadSource {
+    Url(Url),
+    InlineScript,
+    EvalScript,
+}
+
+/// A single load that a page's CSP would need to allow.
+#[derive(Debug, Clone, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CspLoad {
+    pub directive: &'static str,
+    pub source: CspLoadSource,
+    /// The edge that the load was recorded on.
+    pub edge: EdgeId,
+}
+
+/// The minimal policy that allows everything the page loaded.
+#[derive(Debug, Default, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct InferredCsp {
+    pub directives: BTreeMap<&'static str, BTreeSet<String>>,
+}
+
+impl std::fmt::Display for InferredCsp {
+    /// Formats the policy as the value of a `Content-Security-Policy` header.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let directives = self.directives.iter()
+            .map(|(directive, sources)| std::iter::once(*directive).chain(sources.iter().map(|source| &source[..])).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        write!(f, "{}", directives.join("; "))
+    }
+}
+
+/// A parsed Content Security Policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    directives: BTreeMap<String, Vec<String>>,
+}
+
+impl From<&str> for ContentSecurityPolicy {
+    fn from(policy: &str) -> Self {
+        let mut directives = BTreeMap::new();
+        policy.split(';').for_each(|directive| {
+            let mut tokens = directive.split_whitespace();
+            if let Some(name) = tokens.next() {
+                // Only the first occurrence of a directive takes effect.
+                directives.entry(name.to_ascii_lowercase()).or_insert_with(|| tokens.map(|token| token.to_string()).collect());
+            }
+        });
+        Self { directives }
+    }
+}
+
+/// A load that a policy would have blocked.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CspViolation {
+    /// The directive the load falls under.
+    pub directive: &'static str,
+    /// The directive that was actually applied, which may be `default-src` if the policy doesn't
+    /// have the load's own directive.
+    pub effective_directive: String,
+    pub source: CspLoadSource,
+    pub edge: EdgeId,
+}
+
+/// Returns the CSP directive governing requests for the given resource type.
+fn directive_for_resource_type(resource_type: &ResourceType) -> Option<&'static str> {
+    match resource_type {
+        ResourceType::Image | ResourceType::Svg => Some("img-src"),
+        ResourceType::Css => Some("style-src"),
+        ResourceType::Script => Some("script-src"),
+        ResourceType::Font => Some("font-src"),
+        ResourceType::Audio | ResourceType::Video | ResourceType::TextTrack => Some("media-src"),
+        ResourceType::Manifest => Some("manifest-src"),
+        ResourceType::Raw => Some("connect-src"),
+        _ => None,
+    }
+}
+
+/// Returns the CSP directive governing requests made by the given element.
+fn directive_for_element(tag_name: &str) -> Option<&'static str> {
+    match &tag_name.to_ascii_lowercase()[..] {
+        "iframe" | "frame" => Some("frame-src"),
+        "img" | "picture" => Some("img-src"),
+        "audio" | "video" | "source" | "track" => Some("media-src"),
+        "script" => Some("script-src"),
+        "object" | "embed" => Some("object-src"),
+        _ => None,
+    }
+}
+
+fn directive_for_request_type(request_type: &RequestType) -> &'static str {
+    match request_type {
+        RequestType::Image => "img-src",
+        RequestType::Script => "script-src",
+        RequestType::CSS => "style-src",
+        RequestType::AJAX => "connect-src",
+        RequestType::Unknown => "default-src",
+    }
+}
+
+/// Returns whether a single source expression allows the given URL, for a page with the given
+/// URL. `'self'` allows nothing on pages without a parseable URL.
+fn source_matches_url(source: &str, url: &url::Url, page: Option<&url::Url>) -> bool {
+    let source = source.to_ascii_lowercase();
+    let is_network_scheme = matches!(url.scheme(), "http" | "https" | "ws" | "wss");
+    match &source[..] {
+        "*" => return is_network_scheme,
+        "'self'" => return page.map(|page| url.origin() == page.origin()).unwrap_or(false),
+        _ if source.starts_with('\'') => return false,
+        _ => (),
+    }
+    // Scheme sources, like `https:`. Insecure schemes also allow their secure equivalents.
+    if let Some(scheme) = source.strip_suffix(':') {
+        return scheme == url.scheme() || (scheme == "http" && url.scheme() == "https") || (scheme == "ws" && url.scheme() == "wss");
+    }
+
+    // Host sources, like `https://*.example.com:443/path/`.
+    let (scheme, rest) = match source.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, &source[..]),
+    };
+    match scheme {
+        Some(scheme) if scheme != url.scheme() && !(scheme == "http" && url.scheme() == "https") => return false,
+        None if !is_network_scheme => return false,
+        _ => (),
+    }
+    let (host_and_port, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], Some(&rest[index..])),
+        None => (rest, None),
+    };
+    let (host, port) = match host_and_port.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (host_and_port, None),
+    };
+    let url_host = url.host_str().unwrap_or_default();
+    let host_matches = match host.strip_prefix("*.") {
+        Some(suffix) => url_host.ends_with(&format!(".{}", suffix)),
+        None => host == "*" || host == url_host,
+    };
+    let port_matches = match port {
+        Some("*") => true,
+        Some(port) => port.parse::<u16>().ok() == url.port_or_known_default(),
+        // Ports are only recorded in parsed URLs when they aren't the scheme's default.
+        None => url.port().is_none(),
+    };
+    let path_matches = match path {
+        Some(path) if path.ends_with('/') => url.path().starts_with(path),
+        Some(path) => url.path() == path,
+        None => true,
+    };
+    host_matches && port_matches && path_matches
+}
+
+impl ContentSecurityPolicy {
+    /// Returns the name and source list of the directive that applies to loads governed by the
+    /// given directive, falling back to `default-src`.
+    fn effective_directive(&self, directive: &str) -> Option<(&str, &[String])> {
+        self.directives.get_key_value(directive)
+            .or_else(|| self.directives.get_key_value("default-src"))
+            .map(|(name, sources)| (&name[..], &sources[..]))
+    }
+
+    fn allows(&self, sources: &[String], load: &CspLoadSource, page: Option<&url::Url>) -> bool {
+        let has_keyword = |keyword: &str| sources.iter().any(|source| source.eq_ignore_ascii_case(keyword));
+        match load {
+            // Nonces and hashes disable 'unsafe-inline'.
+            CspLoadSource::InlineScript => has_keyword("'unsafe-inline'") && !sources.iter().any(|source| {
+                let source = source.to_ascii_lowercase();
+                source.starts_with("'nonce-") || source.starts_with("'sha")
+            }),
+            CspLoadSource::EvalScript => has_keyword("'unsafe-eval'"),
+            CspLoadSource::Url(url) => match url.parsed() {
+                Some(url) => sources.iter().any(|source| source_matches_url(source, url, page)),
+                None => false,
+            },
+        }
+    }
+}
+
+impl PageGraph {
+    /// Returns every load that the page's CSP would govern, in no particular order.
+    pub fn csp_loads(&self) -> Vec<CspLoad> {
+        let mut loads = vec![];
+
+        self.requests().into_iter().for_each(|request| {
+            let url = match &request.url {
+                Some(url) => url.clone(),
+                None => return,
+            };
+            let from_element = request.initiators.iter()
+                .filter_map(|initiator| self.nodes.get(initiator).unwrap().node_type.tag_name())
+                .find_map(directive_for_element);
+            let directive = request.resource_type.as_ref().and_then(directive_for_resource_type)
+                .or(from_element)
+                .unwrap_or_else(|| directive_for_request_type(&request.request_type));
+            loads.push(CspLoad { directive, source: CspLoadSource::Url(url), edge: request.starts[0] });
+        });
+
+        self.websocket_connections().into_iter().for_each(|connection| if let Some(event) = connection.events.first() {
+            loads.push(CspLoad { directive: "connect-src", source: CspLoadSource::Url(connection.url), edge: event.edge });
+        });
+
+        self.edges.values().for_each(|edge| match (&edge.edge_type, &self.target_node(edge).node_type) {
+            (EdgeType::CrossDom {}, _) if matches!(self.source_node(edge).node_type, NodeType::FrameOwner { .. }) => {
+                let target = self.target_node(edge);
+                let documents = match target.node_type {
+                    NodeType::RemoteFrame { .. } => self.outgoing_edges(target)
+                        .filter(|edge| matches!(edge.edge_type, EdgeType::CrossDom {}))
+                        .map(|edge| self.target_node(edge))
+                        .filter(|node| matches!(node.node_type, NodeType::DomRoot { .. }))
+                        .collect(),
+                    NodeType::DomRoot { .. } => vec![target],
+                    _ => vec![],
+                };
+                documents.into_iter()
+                    .filter_map(|document| document.node_type.url())
+                    .filter(|url| url.parsed().map(|url| url.scheme() != "about").unwrap_or(false))
+                    .for_each(|url| loads.push(CspLoad { directive: "frame-src", source: CspLoadSource::Url(url.clone()), edge: edge.id }));
+            }
+            (EdgeType::Execute {}, NodeType::Script { url: None, .. }) => {
+                let source = match self.source_node(edge).node_type {
+                    NodeType::Script { .. } => CspLoadSource::EvalScript,
+                    _ => CspLoadSource::InlineScript,
+                };
+                loads.push(CspLoad { directive: "script-src", source, edge: edge.id });
+            }
+            (EdgeType::ExecuteFromAttribute { .. }, _) => {
+                loads.push(CspLoad { directive: "script-src", source: CspLoadSource::InlineScript, edge: edge.id });
+            }
+            _ => (),
+        });
+
+        loads
+    }
+
+    /// Derives the minimal policy that would allow every load the page made.
+    pub fn infer_csp(&self) -> InferredCsp {
+        let page_origin = origin_of_url(&self.desc.url);
+        let mut csp = InferredCsp::default();
+        self.csp_loads().into_iter().for_each(|load| {
+            let source = match &load.source {
+                CspLoadSource::InlineScript => "'unsafe-inline'".to_string(),
+                CspLoadSource::EvalScript => "'unsafe-eval'".to_string(),
+                CspLoadSource::Url(url) => match url.parsed() {
+                    Some(parsed) if matches!(parsed.scheme(), "data" | "blob" | "filesystem" | "mediastream") => format!("{}:", parsed.scheme()),
+                    Some(_) if origin_of_url(url) == page_origin => "'self'".to_string(),
+                    Some(_) => origin_of_url(url).unwrap(),
+                    None => return,
+                },
+            };
+            csp.directives.entry(load.directive).or_default().insert(source);
+        });
+        csp
+    }
+
+    /// Evaluates a policy against the page's recorded loads, returning every load the policy
+    /// would have blocked, in chronological order.
+    pub fn csp_violations(&self, policy: &ContentSecurityPolicy) -> Vec<CspViolation> {
+        let page = self.desc.url.parsed();
+        let mut violations = self.csp_loads().into_iter().filter_map(|load| {
+            let (effective_directive, sources) = policy.effective_directive(load.directive)?;
+            if policy.allows(sources, &load.source, page) {
+                return None;
+            }
+            Some(CspViolation {
+                directive: load.directive,
+                effective_directive: effective_directive.to_string(),
+                source: load.source,
+                edge: load.edge,
+            })
+        }).collect::<Vec<_>>();
+        violations.sort_by_key(|violation| (self.edges.get(&violation.edge).unwrap().edge_timestamp, violation.edge));
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    fn build() -> PageGraph {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let request = builder.add_request(element, "https://cdn.example.net/lib.js", RequestType::Script);
+        builder.complete_request(request, ResourceType::Script, None);
+        let script = builder.add_script(element, Some("https://cdn.example.net/lib.js"), "");
+        builder.add_script(script, None, "eval('1')");
+        let inline_element = builder.add_element(parser, root, "script");
+        builder.add_script(inline_element, None, "track()");
+        builder.add_request(script, "https://api.example.com/data", RequestType::AJAX);
+        let image = builder.add_element(parser, root, "img");
+        builder.add_request(image, "https://example.com/logo.png", RequestType::Image);
+        let iframe = builder.add_frame_owner(parser, root, "iframe");
+        builder.add_frame_document(iframe, "https://ads.example.org:8443/frame.html");
+        builder.build()
+    }
+
+    #[test]
+    fn test_infer_csp() {
+        let csp = build().infer_csp();
+        assert_eq!(csp.to_string(), "connect-src https://api.example.com; frame-src https://ads.example.org:8443; img-src 'self'; script-src 'unsafe-eval' 'unsafe-inline' https://cdn.example.net");
+    }
+
+    #[test]
+    fn test_csp_violations() {
+        let graph = build();
+        let policy = ContentSecurityPolicy::from("default-src 'self'; script-src *.example.net 'unsafe-inline' 'nonce-abc'; connect-src https://*.example.com/");
+
+        let violations = graph.csp_violations(&policy);
+        let blocked = violations.iter().map(|violation| (violation.directive, &violation.effective_directive[..], &violation.source)).collect::<Vec<_>>();
+        assert_eq!(blocked, vec![
+            ("script-src", "script-src", &CspLoadSource::EvalScript),
+            ("script-src", "script-src", &CspLoadSource::InlineScript),
+            ("frame-src", "default-src", &CspLoadSource::Url("https://ads.example.org:8443/frame.html".into())),
+        ]);
+
+        assert!(graph.csp_violations(&ContentSecurityPolicy::from("img-src 'none'")).iter().all(|violation| violation.directive == "img-src"));
+    }
+
+    #[test]
+    fn test_unparseable_page_url() {
+        let mut graph = build();
+        graph.desc.url = Url::from("");
+        // Without a page origin, 'self' allows nothing, but other sources still apply.
+        let violations = graph.csp_violations(&ContentSecurityPolicy::from("img-src 'self'; connect-src https://api.example.com"));
+        let blocked = violations.iter().map(|violation| &violation.source).collect::<Vec<_>>();
+        assert_eq!(blocked, vec![&CspLoadSource::Url("https://example.com/logo.png".into())]);
+        assert!(graph.infer_csp().directives.contains_key("img-src"));
+    }
+}
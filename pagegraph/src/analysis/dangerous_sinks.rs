@@ -0,0 +1,161 @@
+//! Detection of third-party scripts writing markup or script URLs into the page.
+//!
+//! `innerHTML`, `outerHTML`, `insertAdjacentHTML`, and `document.write` parse arbitrary markup
+//! into the DOM, and changing a `<script>` element's `src` loads arbitrary code, so these are
+//! where malvertising typically lands. Only calls to these APIs that PageGraph recorded as
+//! [`WebApi`](NodeType::WebApi) calls can be found.
+//!
+//! Web API calls don't record the element they were made on, so for the markup-parsing APIs the
+//! affected element is inferred from where the script inserted nodes before making its next call.
+
+use crate::graph::{Edge, EdgeId, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// The kind of dangerous sink that was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum DangerousSinkKind {
+    InnerHtml,
+    OuterHtml,
+    InsertAdjacentHtml,
+    DocumentWrite,
+    ScriptSrc,
+}
+
+/// Web API method name suffixes for each markup-parsing sink.
+const MARKUP_SINKS: [(&str, DangerousSinkKind); 5] = [
+    (".innerHTML", DangerousSinkKind::InnerHtml),
+    (".outerHTML", DangerousSinkKind::OuterHtml),
+    (".insertAdjacentHTML", DangerousSinkKind::InsertAdjacentHtml),
+    ("Document.write", DangerousSinkKind::DocumentWrite),
+    ("Document.writeln", DangerousSinkKind::DocumentWrite),
+];
+
+/// A single write to a dangerous sink by a third-party script.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DangerousSinkUse {
+    pub kind: DangerousSinkKind,
+    pub edge: EdgeId,
+    pub timestamp: Option<isize>,
+    pub script: NodeId,
+    pub script_url: String,
+    /// The elements that were written to. For `document.write`, this is the DOM root of the
+    /// script's document.
+    pub elements: Vec<NodeId>,
+    /// The markup or URL written, if it was recorded.
+    pub value: Option<String>,
+}
+
+fn markup_sink_of(method: &str) -> Option<DangerousSinkKind> {
+    MARKUP_SINKS.iter().find(|(suffix, _)| method.ends_with(suffix)).map(|(_, kind)| *kind)
+}
+
+impl PageGraph {
+    /// Returns the parents of every node the script inserted after the given call, up until its
+    /// next Web API call.
+    fn elements_written_after(&self, script: NodeId, call: &Edge) -> Vec<NodeId> {
+        let script = self.nodes.get(&script).unwrap();
+        let timestamp = match call.edge_timestamp {
+            Some(timestamp) => timestamp,
+            None => return vec![],
+        };
+        let mut elements = vec![];
+        for edge in self.outgoing_edges_in_range(script, timestamp..) {
+            match edge.edge_type {
+                EdgeType::JsCall { .. } if edge.id != call.id => break,
+                EdgeType::InsertNode { .. } => if let Some(parent) = self.dom_parent_at_insertion(edge) {
+                    if !elements.contains(&parent) {
+                        elements.push(parent);
+                    }
+                },
+                _ => (),
+            }
+        }
+        elements
+    }
+
+    /// Returns the node an [`InsertNode`](EdgeType::InsertNode) edge inserted its target into.
+    fn dom_parent_at_insertion(&self, edge: &Edge) -> Option<NodeId> {
+        let parent = match edge.edge_type {
+            EdgeType::InsertNode { parent, .. } => parent,
+            _ => return None,
+        };
+        self.nodes.values()
+            .find(|node| crate::graph::is_same_frame_context(node.id, edge.id) && node.node_type.html_node_id() == Some(parent))
+            .map(|node| node.id)
+    }
+
+    /// Returns every use of a dangerous sink by a script fetched from a third-party URL, in
+    /// chronological order.
+    pub fn dangerous_sink_uses(&self) -> Vec<DangerousSinkUse> {
+        let mut uses = self.edges.values().filter_map(|edge| {
+            let script = self.source_node(edge);
+            let script_url = match &script.node_type {
+                NodeType::Script { url: Some(url), .. } if self.is_third_party(url) == Some(true) => url,
+                _ => return None,
+            };
+            let (kind, elements, value) = match (&edge.edge_type, &self.target_node(edge).node_type) {
+                (EdgeType::JsCall { args, .. }, NodeType::WebApi { method }) => {
+                    let kind = markup_sink_of(method)?;
+                    let elements = if kind == DangerousSinkKind::DocumentWrite {
+                        self.document_of_actor(script).map(|document| vec![document.id]).unwrap_or_default()
+                    } else {
+                        self.elements_written_after(script.id, edge)
+                    };
+                    (kind, elements, args.clone())
+                }
+                (EdgeType::SetAttribute { key, value, .. }, NodeType::HtmlElement { tag_name, .. }) if key == "src" && tag_name.eq_ignore_ascii_case("script") => {
+                    (DangerousSinkKind::ScriptSrc, vec![edge.target], value.clone())
+                }
+                _ => return None,
+            };
+            Some(DangerousSinkUse {
+                kind,
+                edge: edge.id,
+                timestamp: edge.edge_timestamp,
+                script: script.id,
+                script_url: script_url.to_string(),
+                elements,
+                value,
+            })
+        }).collect::<Vec<_>>();
+        uses.sort_by_key(|sink_use| (sink_use.timestamp, sink_use.edge));
+        uses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_dangerous_sink_uses() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+        let slot = builder.add_element(parser, body, "div");
+        let first_party_element = builder.add_element(parser, body, "script");
+        let first_party = builder.add_script(first_party_element, Some("https://example.com/app.js"), "");
+        builder.call_web_api(first_party, "Element.innerHTML", Some("<b>hi</b>"));
+
+        let element = builder.add_element(parser, body, "script");
+        let ad_script = builder.add_script(element, Some("https://ads.net/ad.js"), "");
+        let inner_html = builder.call_web_api(ad_script, "Element.innerHTML", Some("<img src=x>"));
+        builder.add_element(ad_script, slot, "img");
+        let write = builder.call_web_api(ad_script, "Document.write", None);
+        let injected = builder.add_element(ad_script, body, "script");
+        let script_src = builder.set_attribute(ad_script, injected, "src", "https://evil.net/x.js");
+        builder.set_attribute(ad_script, slot, "src", "https://ads.net/x.png");
+        let graph = builder.build();
+
+        let uses = graph.dangerous_sink_uses();
+        assert_eq!(uses.iter().map(|sink_use| sink_use.edge).collect::<Vec<_>>(), vec![inner_html, write, script_src]);
+        assert_eq!((uses[0].kind, &uses[0].elements, uses[0].value.as_deref()), (DangerousSinkKind::InnerHtml, &vec![slot], Some("<img src=x>")));
+        assert_eq!((uses[1].kind, &uses[1].elements), (DangerousSinkKind::DocumentWrite, &vec![root]));
+        assert_eq!((uses[2].kind, &uses[2].elements), (DangerousSinkKind::ScriptSrc, &vec![injected]));
+        assert!(uses.iter().all(|sink_use| sink_use.script == ad_script));
+    }
+}
@@ -0,0 +1,122 @@
+//! Detection of resources that the page requested more than once.
+//!
+//! Requests are grouped by normalized URL: fragments are removed and query parameters are sorted,
+//! so that URLs which can only differ in formatting are grouped together. Beacon-style tracker
+//! calls usually differ in a cache-busting query parameter, so query strings can also be ignored
+//! entirely.
+
+use std::collections::BTreeMap;
+
+use crate::graph::{EdgeId, NodeId, PageGraph};
+use crate::requests::{RequestInfo, RequestOutcome};
+use crate::types::Url;
+
+/// A resource fetched multiple times.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DuplicateRequests {
+    pub normalized_url: String,
+    /// Every distinct URL requested within the group, sorted.
+    pub urls: Vec<String>,
+    pub count: usize,
+    /// The first [`RequestStart`](crate::types::EdgeType::RequestStart) edge of every request in
+    /// the group, in chronological order.
+    pub requests: Vec<EdgeId>,
+    pub initiators: Vec<NodeId>,
+    /// The total size of every response after the first.
+    pub wasted_bytes: usize,
+}
+
+/// Normalizes a URL for grouping, returning it unchanged if it can't be parsed.
+fn normalize_url(url: &Url, ignore_query: bool) -> String {
+    let mut parsed = match url.parsed() {
+        Some(parsed) => parsed.clone(),
+        None => return url.to_string(),
+    };
+    parsed.set_fragment(None);
+    if ignore_query {
+        parsed.set_query(None);
+    } else if parsed.query().is_some() {
+        let mut pairs = parsed.query_pairs().into_owned().collect::<Vec<_>>();
+        pairs.sort();
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    parsed.to_string()
+}
+
+impl PageGraph {
+    /// Returns every resource that was successfully requested more than once, from the most to
+    /// the least wasted bytes. Blocked requests are not included.
+    pub fn duplicate_requests(&self, ignore_query: bool) -> Vec<DuplicateRequests> {
+        let mut groups = BTreeMap::<String, Vec<RequestInfo>>::new();
+        self.requests().into_iter()
+            .filter(|request| !matches!(request.outcome, RequestOutcome::Blocked { .. }))
+            .for_each(|request| if let Some(url) = &request.url {
+                groups.entry(normalize_url(url, ignore_query)).or_default().push(request);
+            });
+
+        let mut duplicates = groups.into_iter()
+            .filter(|(_, requests)| requests.len() > 1)
+            .map(|(normalized_url, mut requests)| {
+                let first_start = |request: &RequestInfo| request.starts[0];
+                requests.sort_by_key(|request| (self.edges.get(&first_start(request)).unwrap().edge_timestamp, first_start(request)));
+
+                let mut urls = requests.iter().filter_map(|request| request.url.as_ref().map(|url| url.to_string())).collect::<Vec<_>>();
+                urls.sort();
+                urls.dedup();
+                let mut initiators = requests.iter().flat_map(|request| request.initiators.iter().copied()).collect::<Vec<_>>();
+                initiators.sort();
+                initiators.dedup();
+
+                DuplicateRequests {
+                    normalized_url,
+                    urls,
+                    count: requests.len(),
+                    wasted_bytes: requests.iter().skip(1).filter_map(|request| request.size).sum(),
+                    requests: requests.iter().map(first_start).collect(),
+                    initiators,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        duplicates.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.normalized_url.cmp(&b.normalized_url)));
+        duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::PageGraphBuilder;
+    use crate::types::{RequestType, ResourceType};
+
+    #[test]
+    fn test_duplicate_requests() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let first = builder.add_element(parser, root, "script");
+        let second = builder.add_element(parser, root, "script");
+        [
+            (first, "https://cdn.example.com/lib.js?v=1&min=1"),
+            (second, "https://cdn.example.com/lib.js?min=1&v=1#main"),
+            (first, "https://t.net/beacon?ts=1"),
+            (first, "https://t.net/beacon?ts=2"),
+            (first, "https://example.com/app.js"),
+        ].iter().for_each(|(initiator, url)| {
+            let request = builder.add_request(*initiator, url, RequestType::Script);
+            builder.complete_request(request, ResourceType::Script, Some(100));
+        });
+        let graph = builder.build();
+
+        let duplicates = graph.duplicate_requests(false);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].normalized_url, "https://cdn.example.com/lib.js?min=1&v=1");
+        assert_eq!(duplicates[0].count, 2);
+        assert_eq!(duplicates[0].initiators, vec![first, second]);
+        assert_eq!(duplicates[0].wasted_bytes, 100);
+
+        let duplicates = graph.duplicate_requests(true);
+        assert_eq!(duplicates.len(), 2);
+        assert!(duplicates.iter().any(|duplicate| duplicate.normalized_url == "https://t.net/beacon" && duplicate.urls.len() == 2));
+    }
+}
@@ -0,0 +1,95 @@
+//! Reads of `navigator`, `screen`, and related properties describing the browser environment.
+//!
+//! Unlike [fingerprinting](crate::analysis::fingerprinting) detection, this doesn't judge whether
+//! the reads are suspicious. It just counts how often each script read each property, for filter
+//! list authors who want to inspect the raw accesses themselves.
+
+use std::collections::BTreeMap;
+
+use crate::graph::{FrameId, HasFrameId, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// Web API properties, or interface prefixes ending in `.`, describing the browser environment.
+const ENVIRONMENT_PROPERTIES: [&str; 15] = [
+    "Navigator.",
+    "NavigatorUAData.",
+    "Screen.",
+    "ScreenOrientation.",
+    "NetworkInformation.",
+    "Window.devicePixelRatio",
+    "Window.innerWidth",
+    "Window.innerHeight",
+    "Window.outerWidth",
+    "Window.outerHeight",
+    "Window.screenX",
+    "Window.screenY",
+    "Window.screenLeft",
+    "Window.screenTop",
+    "Window.matchMedia",
+];
+
+fn is_environment_property(method: &str) -> bool {
+    ENVIRONMENT_PROPERTIES.iter()
+        .any(|property| if property.ends_with('.') { method.starts_with(property) } else { method == *property })
+}
+
+/// The reads of a single environment property by a single script.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct EnvironmentAccess {
+    pub script: NodeId,
+    pub script_url: Option<String>,
+    /// The frame the script ran in.
+    pub frame_id: FrameId,
+    pub property: String,
+    pub count: usize,
+}
+
+impl PageGraph {
+    /// Returns how many times each script read each environment property, sorted by script and
+    /// then by property.
+    pub fn environment_accesses(&self) -> Vec<EnvironmentAccess> {
+        let mut accesses = BTreeMap::<(NodeId, &str), EnvironmentAccess>::new();
+        for edge in self.edges.values() {
+            let method = match (&edge.edge_type, &self.target_node(edge).node_type) {
+                (EdgeType::JsCall { .. }, NodeType::WebApi { method }) if is_environment_property(method) => method,
+                _ => continue,
+            };
+            accesses.entry((edge.source, method.as_str())).or_insert_with(|| EnvironmentAccess {
+                script: edge.source,
+                script_url: self.source_node(edge).node_type.url().map(|url| url.to_string()),
+                frame_id: edge.source.get_frame_id().unwrap_or(self.desc.frame_id),
+                property: method.clone(),
+                count: 0,
+            }).count += 1;
+        }
+        accesses.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_environment_accesses() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, Some("https://cdn.net/lib.js"), "");
+        builder.call_web_api(script, "Screen.width", None);
+        builder.call_web_api(script, "Navigator.userAgent", None);
+        builder.call_web_api(script, "Navigator.userAgent", None);
+        builder.call_web_api(script, "Window.devicePixelRatio", None);
+        builder.call_web_api(script, "Window.open", None);
+        builder.call_web_api(script, "HTMLCanvasElement.toDataURL", None);
+        let graph = builder.build();
+
+        let accesses = graph.environment_accesses();
+        let properties = accesses.iter().map(|access| (access.property.as_str(), access.count)).collect::<Vec<_>>();
+        assert_eq!(properties, vec![("Navigator.userAgent", 2), ("Screen.width", 1), ("Window.devicePixelRatio", 1)]);
+        assert!(accesses.iter().all(|access| access.script == script && access.frame_id == graph.desc.frame_id));
+        assert_eq!(accesses[0].script_url.as_deref(), Some("https://cdn.net/lib.js"));
+    }
+}
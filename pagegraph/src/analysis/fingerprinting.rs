@@ -0,0 +1,153 @@
+//! Detection of scripts calling Web APIs commonly used for browser fingerprinting.
+//!
+//! Most of these APIs have legitimate uses, so a single call isn't conclusive. A script is only
+//! considered likely to be fingerprinting if it reads back rendered canvas, WebGL, or audio
+//! output, or if it queries several different categories of device properties.
+
+use std::collections::BTreeMap;
+
+use crate::analysis::Thresholds;
+use crate::graph::{NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum FingerprintingCategory {
+    Canvas,
+    WebGl,
+    Audio,
+    Fonts,
+    Navigator,
+    Screen,
+    MediaDevices,
+}
+
+impl FingerprintingCategory {
+    /// Whether this category reads back rendered output, which is rarely done for anything but
+    /// fingerprinting.
+    fn is_readback(&self) -> bool {
+        matches!(self, Self::Canvas | Self::WebGl | Self::Audio)
+    }
+}
+
+/// Web API methods, or interface prefixes ending in `.`, for each fingerprinting category.
+const FINGERPRINTING_APIS: [(&str, FingerprintingCategory); 17] = [
+    ("HTMLCanvasElement.toDataURL", FingerprintingCategory::Canvas),
+    ("HTMLCanvasElement.toBlob", FingerprintingCategory::Canvas),
+    ("CanvasRenderingContext2D.getImageData", FingerprintingCategory::Canvas),
+    ("OffscreenCanvas.convertToBlob", FingerprintingCategory::Canvas),
+    ("WebGLRenderingContext.getParameter", FingerprintingCategory::WebGl),
+    ("WebGLRenderingContext.getSupportedExtensions", FingerprintingCategory::WebGl),
+    ("WebGLRenderingContext.readPixels", FingerprintingCategory::WebGl),
+    ("WebGL2RenderingContext.getParameter", FingerprintingCategory::WebGl),
+    ("WebGL2RenderingContext.readPixels", FingerprintingCategory::WebGl),
+    ("OfflineAudioContext.startRendering", FingerprintingCategory::Audio),
+    ("AnalyserNode.getFloatFrequencyData", FingerprintingCategory::Audio),
+    ("AudioBuffer.getChannelData", FingerprintingCategory::Audio),
+    ("CanvasRenderingContext2D.measureText", FingerprintingCategory::Fonts),
+    ("FontFaceSet.check", FingerprintingCategory::Fonts),
+    ("Navigator.", FingerprintingCategory::Navigator),
+    ("Screen.", FingerprintingCategory::Screen),
+    ("MediaDevices.enumerateDevices", FingerprintingCategory::MediaDevices),
+];
+
+fn fingerprinting_category_of(method: &str) -> Option<FingerprintingCategory> {
+    FINGERPRINTING_APIS.iter()
+        .find(|(api, _)| if api.ends_with('.') { method.starts_with(api) } else { method == *api })
+        .map(|(_, category)| *category)
+}
+
+/// The fingerprinting-related API usage of a single script.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct FingerprintingScript {
+    pub script: NodeId,
+    pub script_url: Option<String>,
+    pub third_party: Option<bool>,
+    pub categories: Vec<FingerprintingCategory>,
+    /// The distinct fingerprinting-related methods called, sorted by name.
+    pub apis: Vec<String>,
+    pub calls: usize,
+    pub likely_fingerprinting: bool,
+}
+
+impl PageGraph {
+    /// Returns every script that called a fingerprinting-related Web API, sorted by node id.
+    pub fn fingerprinting_scripts(&self) -> Vec<FingerprintingScript> {
+        self.fingerprinting_scripts_with(&Thresholds::default())
+    }
+
+    /// Like [`fingerprinting_scripts`](PageGraph::fingerprinting_scripts), with the number of
+    /// categories that make a script likely to be fingerprinting taken from `thresholds`.
+    pub fn fingerprinting_scripts_with(&self, thresholds: &Thresholds) -> Vec<FingerprintingScript> {
+        let mut scripts = BTreeMap::<NodeId, FingerprintingScript>::new();
+        for edge in self.edges.values() {
+            let method = match (&edge.edge_type, &self.target_node(edge).node_type) {
+                (EdgeType::JsCall { .. }, NodeType::WebApi { method }) => method,
+                _ => continue,
+            };
+            let category = match fingerprinting_category_of(method) {
+                Some(category) => category,
+                None => continue,
+            };
+            let script = scripts.entry(edge.source).or_insert_with(|| {
+                let script_url = self.source_node(edge).node_type.url();
+                FingerprintingScript {
+                    script: edge.source,
+                    third_party: script_url.and_then(|url| self.is_third_party(url)),
+                    script_url: script_url.map(|url| url.to_string()),
+                    categories: vec![],
+                    apis: vec![],
+                    calls: 0,
+                    likely_fingerprinting: false,
+                }
+            });
+            if !script.categories.contains(&category) {
+                script.categories.push(category);
+            }
+            if !script.apis.contains(method) {
+                script.apis.push(method.clone());
+            }
+            script.calls += 1;
+        }
+
+        scripts.into_values().map(|mut script| {
+            script.categories.sort();
+            script.apis.sort();
+            script.likely_fingerprinting = script.categories.iter().any(|category| category.is_readback()) ||
+                script.categories.len() >= thresholds.likely_fingerprinting_categories;
+            script
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_fingerprinting_scripts() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let first = builder.add_element(parser, root, "script");
+        let second = builder.add_element(parser, root, "script");
+        let fingerprinter = builder.add_script(first, Some("https://fp.net/fp.js"), "");
+        let app = builder.add_script(second, None, "");
+        builder.call_web_api(fingerprinter, "Navigator.userAgent", None);
+        builder.call_web_api(fingerprinter, "HTMLCanvasElement.toDataURL", None);
+        builder.call_web_api(fingerprinter, "HTMLCanvasElement.toDataURL", None);
+        builder.call_web_api(app, "Navigator.userAgent", None);
+        builder.call_web_api(app, "Document.title", None);
+        let graph = builder.build();
+
+        let scripts = graph.fingerprinting_scripts();
+        assert_eq!(scripts.len(), 2);
+        assert_eq!((scripts[0].script, scripts[0].calls, scripts[0].third_party), (fingerprinter, 3, Some(true)));
+        assert_eq!(scripts[0].categories, vec![FingerprintingCategory::Canvas, FingerprintingCategory::Navigator]);
+        assert!(scripts[0].likely_fingerprinting);
+        assert_eq!((scripts[1].script, &scripts[1].apis), (app, &vec!["Navigator.userAgent".to_string()]));
+        assert!(!scripts[1].likely_fingerprinting);
+    }
+}
@@ -0,0 +1,102 @@
+//! Detection of iframes injected into the page by scripts, a common pattern for delivering ads.
+
+use crate::graph::{NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// An iframe that was created by a script rather than the HTML parser.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct InjectedIframe {
+    pub frame_owner: NodeId,
+    pub injecting_script: NodeId,
+    pub injecting_script_url: Option<String>,
+    /// The last `src` the iframe was given, or the URL of the last document loaded into it if
+    /// its `src` was never set.
+    pub src: Option<String>,
+    /// URLs of requests made from within the iframe's documents that are third-party to the page.
+    pub third_party_requests: Vec<String>,
+}
+
+impl PageGraph {
+    /// Returns every `<iframe>` that was created by a script, in order of creation.
+    pub fn injected_iframes(&self) -> Vec<InjectedIframe> {
+        let mut iframes = self.nodes.values()
+            .filter(|node| matches!(&node.node_type, NodeType::FrameOwner { tag_name, .. } if tag_name.eq_ignore_ascii_case("iframe")))
+            .filter_map(|frame_owner| {
+                let creation = self.incoming_edges(frame_owner)
+                    .filter(|edge| matches!(edge.edge_type, EdgeType::CreateNode {}))
+                    .min_by_key(|edge| edge.edge_timestamp)?;
+                let script = self.source_node(creation);
+                let injecting_script_url = match &script.node_type {
+                    NodeType::Script { url, .. } => url.as_ref().map(|url| url.to_string()),
+                    _ => return None,
+                };
+
+                let documents = self.content_documents_of(frame_owner);
+
+                let src = self.incoming_edges(frame_owner)
+                    .filter(|edge| matches!(&edge.edge_type, EdgeType::SetAttribute { key, .. } if key == "src"))
+                    .max_by_key(|edge| edge.edge_timestamp)
+                    .and_then(|edge| edge.edge_type.value().map(|value| value.to_string()))
+                    .or_else(|| documents.iter()
+                        .max_by_key(|document| document.node_timestamp)
+                        .and_then(|document| document.node_type.url().map(|url| url.to_string())));
+
+                let mut third_party_requests = documents.iter()
+                    .flat_map(|document| self.requests_from_document(document))
+                    .filter_map(|request| self.target_node(request).node_type.url())
+                    .filter(|url| self.is_third_party(url) == Some(true))
+                    .map(|url| url.to_string())
+                    .collect::<Vec<_>>();
+                third_party_requests.sort();
+                third_party_requests.dedup();
+
+                Some((creation.edge_timestamp, InjectedIframe {
+                    frame_owner: frame_owner.id,
+                    injecting_script: script.id,
+                    injecting_script_url,
+                    src,
+                    third_party_requests,
+                }))
+            })
+            .collect::<Vec<_>>();
+
+        iframes.sort_by_key(|(timestamp, iframe)| (*timestamp, iframe.frame_owner));
+        iframes.into_iter().map(|(_, iframe)| iframe).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_injected_iframes() {
+        let mut builder = PageGraphBuilder::new("https://news.example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+
+        // An iframe from the page's own markup
+        builder.add_frame_owner(parser, root, "iframe");
+
+        // An iframe injected by an ad script
+        let script_element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(script_element, Some("https://ads.example.net/ad.js"), "inject()");
+        let iframe = builder.add_frame_owner(script, root, "iframe");
+        builder.set_attribute(script, iframe, "src", "about:blank");
+        let document = builder.add_frame_document(iframe, "about:blank");
+        let img = builder.add_element(script, document, "img");
+        builder.add_request(img, "https://ads.example.net/creative.png", RequestType::Image);
+        builder.add_request(img, "https://static.example.com/logo.png", RequestType::Image);
+        let graph = builder.build();
+
+        let iframes = graph.injected_iframes();
+        assert_eq!(iframes.len(), 1);
+        assert_eq!(iframes[0].frame_owner, iframe);
+        assert_eq!(iframes[0].injecting_script, script);
+        assert_eq!(iframes[0].injecting_script_url.as_deref(), Some("https://ads.example.net/ad.js"));
+        assert_eq!(iframes[0].src.as_deref(), Some("about:blank"));
+        assert_eq!(iframes[0].third_party_requests, vec!["https://ads.example.net/creative.png".to_string()]);
+    }
+}
@@ -0,0 +1,110 @@
+//! Detection of insecure subresource requests made by a secure page.
+//!
+//! Mixed content is categorized as in the [Mixed Content] specification: images, audio, and
+//! video are passive content, which browsers may still load, while everything else, including
+//! scripts, stylesheets, frames, and fetches, is active content, which browsers block.
+//!
+//! [Mixed Content]: https://www.w3.org/TR/mixed-content/
+
+use crate::graph::{NodeId, PageGraph};
+use crate::requests::RequestOutcome;
+use crate::types::{EdgeType, NodeType, RequestType, Url};
+
+/// Elements whose requests are passive mixed content.
+const PASSIVE_ELEMENTS: [&str; 5] = ["img", "audio", "video", "source", "track"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum MixedContentKind {
+    Active,
+    Passive,
+}
+
+/// An `http:` request made by an `https:` page.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MixedContentRequest {
+    pub request_id: usize,
+    /// The first insecure URL requested, which may have been reached through a redirect.
+    pub url: String,
+    pub kind: MixedContentKind,
+    pub request_type: RequestType,
+    pub initiators: Vec<NodeId>,
+    /// The scripts responsible for the request, either because they made it themselves or
+    /// because they created the element that made it.
+    pub scripts: Vec<NodeId>,
+    pub outcome: RequestOutcome,
+}
+
+fn is_insecure(url: &Url) -> bool {
+    url.parsed().map(|url| url.scheme() == "http" || url.scheme() == "ws").unwrap_or(false)
+}
+
+impl PageGraph {
+    /// Returns every insecure request made by the page, in request order. Returns nothing if the
+    /// page itself was not loaded over `https:`.
+    pub fn mixed_content(&self) -> Vec<MixedContentRequest> {
+        if self.desc.url.parsed().map(|url| url.scheme() != "https").unwrap_or(true) {
+            return vec![];
+        }
+
+        self.requests().into_iter().filter_map(|request| {
+            let url = request.redirects.iter().chain(request.url.iter()).find(|url| is_insecure(url))?.to_string();
+            let passive = request.request_type == RequestType::Image || request.initiators.iter().any(|initiator| {
+                self.nodes.get(initiator).unwrap().node_type.tag_name().map(|tag| PASSIVE_ELEMENTS.contains(&tag.to_ascii_lowercase().as_str())).unwrap_or(false)
+            });
+
+            let mut scripts = request.initiators.iter().flat_map(|initiator| {
+                let initiator = self.nodes.get(initiator).unwrap();
+                match initiator.node_type {
+                    NodeType::Script { .. } => vec![initiator.id],
+                    _ => self.incoming_edges(initiator)
+                        .filter(|edge| matches!(edge.edge_type, EdgeType::CreateNode {}))
+                        .map(|edge| self.source_node(edge))
+                        .filter(|creator| matches!(creator.node_type, NodeType::Script { .. }))
+                        .map(|creator| creator.id)
+                        .collect(),
+                }
+            }).collect::<Vec<_>>();
+            scripts.sort();
+            scripts.dedup();
+
+            Some(MixedContentRequest {
+                request_id: request.request_id,
+                url,
+                kind: if passive { MixedContentKind::Passive } else { MixedContentKind::Active },
+                request_type: request.request_type,
+                initiators: request.initiators,
+                scripts,
+                outcome: request.outcome,
+            })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_mixed_content() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let script_element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(script_element, None, "");
+        let image = builder.add_element(script, root, "img");
+        let passive = builder.add_request(image, "http://example.com/a.png", RequestType::Image);
+        let active = builder.add_request(script, "http://api.example.com/data", RequestType::AJAX);
+        builder.add_request(script, "https://api.example.com/data", RequestType::AJAX);
+        let graph = builder.build();
+
+        let mixed = graph.mixed_content();
+        assert_eq!(mixed.len(), 2);
+        assert_eq!((mixed[0].request_id, mixed[0].kind), (passive, MixedContentKind::Passive));
+        assert_eq!(mixed[0].scripts, vec![script]);
+        assert_eq!((mixed[1].request_id, mixed[1].kind), (active, MixedContentKind::Active));
+        assert_eq!(mixed[1].url, "http://api.example.com/data");
+    }
+}
@@ -0,0 +1,129 @@
+//! Higher-level analyses of page behavior, built on top of the core graph queries.
+
+pub mod ad_dom_share;
+pub mod builtin_tampering;
+pub mod cname_cloaking;
+pub mod cookie_sync;
+pub mod critical_path;
+pub mod csp;
+pub mod dangerous_sinks;
+pub mod duplicate_requests;
+pub mod environment_access;
+pub mod fingerprinting;
+pub mod injected_iframes;
+pub mod mixed_content;
+pub mod navigations;
+pub mod page_weight;
+pub mod popups;
+pub mod post_message;
+pub mod registry;
+pub mod report;
+pub mod script_cost;
+pub mod script_hashes;
+pub mod shields;
+pub mod storage;
+pub mod taint;
+pub mod trackers;
+pub mod websockets;
+pub mod workers;
+
+use std::collections::HashMap;
+
+use crate::graph::{Edge, FrameId, HasFrameId, PageGraph};
+use crate::types::{EdgeType, Url};
+
+/// Cutoffs used by analyses to decide whether behavior is worth reporting. The defaults avoid
+/// most false positives on typical pages, but stricter or looser values can suit other corpora.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Thresholds {
+    /// Scripts querying at least this many
+    /// [fingerprinting categories](fingerprinting::FingerprintingCategory) are considered likely
+    /// to be fingerprinting.
+    pub likely_fingerprinting_categories: usize,
+    /// Stored values shorter than this are too likely to appear in URLs by chance to be reported
+    /// as [cookie syncs](cookie_sync).
+    pub min_identifier_length: usize,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            likely_fingerprinting_categories: 3,
+            min_identifier_length: 8,
+        }
+    }
+}
+
+/// Returns the serialized origin of the given URL, e.g. `https://example.com:8080`. Opaque
+/// origins, like those of `data:` or `about:blank` URLs, are serialized as `null`.
+pub(crate) fn origin_of_url(url: &Url) -> Option<String> {
+    url.parsed().map(|url| url.origin().ascii_serialization())
+}
+
+/// Splits the recorded arguments of a JS call, like `["https://example.com", "_blank"]`, into
+/// individual arguments with any surrounding quotes removed. Commas inside quoted arguments are
+/// not treated as separators.
+pub(crate) fn split_call_args(args: &str) -> Vec<String> {
+    let args = args.trim();
+    let args = args.strip_prefix('[').and_then(|args| args.strip_suffix(']')).unwrap_or(args);
+
+    let mut split = vec![];
+    let mut current = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+    for c in args.chars() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if q == c => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, ',') => {
+                split.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => (),
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() || !split.is_empty() {
+        split.push(current);
+    }
+
+    split.into_iter().map(|arg| {
+        let arg = arg.trim();
+        let unquoted = ['"', '\''].iter().find_map(|q| arg.strip_prefix(*q).and_then(|arg| arg.strip_suffix(*q)));
+        unquoted.unwrap_or(arg).to_string()
+    }).collect()
+}
+
+/// The times at which event listeners were removed, indexed by frame and listener id, since
+/// listener ids are only unique within the frame they were registered in.
+pub(crate) struct ListenerRemovals(HashMap<(Option<FrameId>, usize), Vec<Option<isize>>>);
+
+impl ListenerRemovals {
+    pub(crate) fn new(graph: &PageGraph) -> Self {
+        let mut removals = HashMap::<_, Vec<_>>::new();
+        for edge in graph.edges.values() {
+            if let EdgeType::RemoveEventListener { event_listener_id, .. } = edge.edge_type {
+                removals.entry((edge.id.get_frame_id(), event_listener_id)).or_default().push(edge.edge_timestamp);
+            }
+        }
+        Self(removals)
+    }
+
+    /// Whether the listener added by `registration`, an
+    /// [`AddEventListener`](EdgeType::AddEventListener) edge, had been removed by `time`. Removals
+    /// are assumed to have happened in time if either timestamp is missing.
+    pub(crate) fn removed_by(&self, registration: &Edge, time: Option<isize>) -> bool {
+        let event_listener_id = match registration.edge_type {
+            EdgeType::AddEventListener { event_listener_id, .. } => event_listener_id,
+            _ => return false,
+        };
+        self.0.get(&(registration.id.get_frame_id(), event_listener_id))
+            .map(|removals| removals.iter().any(|removed_at| match (removed_at, time) {
+                (Some(removed_at), Some(time)) => *removed_at <= time,
+                _ => true,
+            }))
+            .unwrap_or(false)
+    }
+}
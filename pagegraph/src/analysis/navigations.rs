@@ -0,0 +1,224 @@
+//! Reconstruction of the redirects and navigations that moved the top-level page away from the
+//! URL it was loaded from.
+//!
+//! Three mechanisms are recognized:
+//!
+//! - server redirects, from recorded redirects of requests for a top-level document, or from the
+//!   top-level document having a different URL than the one the graph was recorded from,
+//! - `<meta http-equiv="refresh">` elements in a top-level document, and
+//! - scripts in a top-level document setting `location`, or calling `location.assign` or
+//!   `location.replace`.
+//!
+//! A navigation usually ends the recording, so the last navigation in the sequence may not have
+//! been completed.
+
+use crate::analysis::split_call_args;
+use crate::graph::{Edge, EdgeId, Node, NodeId, PageGraph, HasFrameId};
+use crate::types::{EdgeType, NodeType, Url};
+
+/// Web API methods that navigate the document when called with a URL.
+const LOCATION_SETTERS: [&str; 5] = [
+    "Location.href",
+    "Location.assign",
+    "Location.replace",
+    "Window.location",
+    "Document.location",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum NavigationMechanism {
+    ServerRedirect,
+    MetaRefresh,
+    LocationSet,
+}
+
+/// A single step in the sequence of top-level navigations.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Navigation {
+    pub mechanism: NavigationMechanism,
+    pub timestamp: Option<isize>,
+    /// The edge recording the navigation, if there is one.
+    pub edge: Option<EdgeId>,
+    /// The request initiator, `<meta>` element, or script responsible for the navigation.
+    pub initiator: Option<NodeId>,
+    pub from: Option<String>,
+    pub destination: String,
+    /// The number of seconds a meta refresh waits before navigating.
+    pub delay: Option<u32>,
+}
+
+/// Parses the `content` attribute of a refresh `<meta>` element, like `5; url=/next`, returning
+/// the delay and the URL, if one is given.
+fn parse_refresh(content: &str) -> Option<(u32, Option<&str>)> {
+    let (delay, rest) = content.split_once([';', ',']).unwrap_or((content, ""));
+    let delay = delay.trim().split('.').next()?.parse().ok()?;
+    let rest = rest.trim();
+    let url = if rest.len() >= 4 && rest.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("url")) {
+        rest[3..].trim_start().strip_prefix('=').map(|url| url.trim().trim_matches(|c| c == '"' || c == '\''))
+    } else {
+        Some(rest).filter(|rest| !rest.is_empty())
+    };
+    Some((delay, url))
+}
+
+fn resolve(base: Option<&Url>, url: &str) -> String {
+    base.and_then(Url::parsed)
+        .and_then(|base| base.join(url).ok())
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| url.to_string())
+}
+
+impl PageGraph {
+    /// Returns the DOM roots of documents loaded directly in the top-level frame.
+    pub(crate) fn top_level_documents(&self) -> Vec<&Node> {
+        let mut documents = self.nodes.values()
+            .filter(|node| node.id.get_frame_id().is_none() && matches!(node.node_type, NodeType::DomRoot { .. }))
+            .filter(|node| !self.incoming_edges(node).any(|edge| matches!(edge.edge_type, EdgeType::CrossDom {})))
+            .collect::<Vec<_>>();
+        documents.sort_by_key(|document| document.id);
+        documents
+    }
+
+    /// Returns the most recent value set for the given attribute of an element.
+    pub(crate) fn latest_attribute<'a>(&'a self, element: &Node, key: &str) -> Option<(&'a Edge, &'a str)> {
+        self.incoming_edges(element)
+            .filter_map(|edge| match &edge.edge_type {
+                EdgeType::SetAttribute { key: set_key, value: Some(value), .. } if set_key.eq_ignore_ascii_case(key) => Some((edge, value.as_str())),
+                _ => None,
+            })
+            .max_by_key(|(edge, _)| (edge.edge_timestamp, edge.id))
+    }
+
+    /// Returns the sequence of redirects and navigations of the top-level page, in
+    /// chronological order.
+    pub fn navigations(&self) -> Vec<Navigation> {
+        let documents = self.top_level_documents();
+        let document_urls = documents.iter().filter_map(|document| document.node_type.url()).collect::<Vec<_>>();
+        let mut navigations = vec![];
+
+        // Server redirects
+        for request in self.requests() {
+            if !request.url.as_ref().map(|url| document_urls.contains(&url)).unwrap_or(false) {
+                continue;
+            }
+            let hops = request.redirects.iter().chain(request.url.iter()).collect::<Vec<_>>();
+            for (i, hop) in hops.windows(2).enumerate() {
+                let edge = request.starts.get(i + 1).map(|edge| self.edges.get(edge).unwrap());
+                navigations.push(Navigation {
+                    mechanism: NavigationMechanism::ServerRedirect,
+                    timestamp: edge.and_then(|edge| edge.edge_timestamp),
+                    edge: edge.map(|edge| edge.id),
+                    initiator: request.initiators.first().copied(),
+                    from: Some(hop[0].to_string()),
+                    destination: hop[1].to_string(),
+                    delay: None,
+                });
+            }
+        }
+        if let Some(url) = documents.first().and_then(|document| document.node_type.url()) {
+            let recorded = navigations.iter().any(|navigation| *url == navigation.destination);
+            if *url != self.desc.url && !recorded {
+                navigations.push(Navigation {
+                    mechanism: NavigationMechanism::ServerRedirect,
+                    timestamp: None,
+                    edge: None,
+                    initiator: None,
+                    from: Some(self.desc.url.to_string()),
+                    destination: url.to_string(),
+                    delay: None,
+                });
+            }
+        }
+
+        let candidates = self.nodes.values().filter(|node| match &node.node_type {
+            NodeType::HtmlElement { tag_name, .. } => tag_name.eq_ignore_ascii_case("meta"),
+            NodeType::Script { .. } => true,
+            _ => false,
+        });
+        for node in candidates {
+            let document = match self.document_of_actor(node) {
+                Some(document) if documents.iter().any(|top_level| top_level.id == document.id) => document,
+                _ => continue,
+            };
+            let document_url = document.node_type.url();
+
+            match &node.node_type {
+                NodeType::HtmlElement { .. } => {
+                    let is_refresh = self.latest_attribute(node, "http-equiv")
+                        .map(|(_, value)| value.trim().eq_ignore_ascii_case("refresh"))
+                        .unwrap_or(false);
+                    let (edge, content) = match self.latest_attribute(node, "content") {
+                        Some(content) if is_refresh => content,
+                        _ => continue,
+                    };
+                    if let Some((delay, url)) = parse_refresh(content) {
+                        navigations.push(Navigation {
+                            mechanism: NavigationMechanism::MetaRefresh,
+                            timestamp: edge.edge_timestamp,
+                            edge: Some(edge.id),
+                            initiator: Some(node.id),
+                            from: document_url.map(|url| url.to_string()),
+                            destination: url.map(|url| resolve(document_url, url)).or_else(|| document_url.map(|url| url.to_string())).unwrap_or_default(),
+                            delay: Some(delay),
+                        });
+                    }
+                }
+                NodeType::Script { .. } => for edge in self.outgoing_edges(node) {
+                    let args = match (&edge.edge_type, &self.target_node(edge).node_type) {
+                        (EdgeType::JsCall { args: Some(args), .. }, NodeType::WebApi { method }) if LOCATION_SETTERS.contains(&method.as_str()) => args,
+                        _ => continue,
+                    };
+                    if let Some(url) = split_call_args(args).into_iter().next().filter(|url| !url.is_empty()) {
+                        navigations.push(Navigation {
+                            mechanism: NavigationMechanism::LocationSet,
+                            timestamp: edge.edge_timestamp,
+                            edge: Some(edge.id),
+                            initiator: Some(node.id),
+                            from: document_url.map(|url| url.to_string()),
+                            destination: resolve(document_url, &url),
+                            delay: None,
+                        });
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        navigations.sort_by_key(|navigation| (navigation.timestamp, navigation.mechanism, navigation.edge));
+        navigations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_navigations() {
+        let mut builder = PageGraphBuilder::new("https://example.com/start");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let meta = builder.add_element(parser, root, "meta");
+        builder.set_attribute(parser, meta, "http-equiv", "Refresh");
+        let content = builder.set_attribute(parser, meta, "content", "5; URL='/later'");
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, Some("https://example.com/app.js"), "");
+        builder.call_web_api(script, "Location.href", None);
+        let location_set = builder.call_web_api(script, "Location.replace", Some("[\"https://scam.net/win\"]"));
+        let graph = builder.build();
+
+        let navigations = graph.navigations();
+        assert_eq!(navigations.len(), 2);
+        assert_eq!((navigations[0].mechanism, navigations[0].edge, navigations[0].initiator), (NavigationMechanism::MetaRefresh, Some(content), Some(meta)));
+        assert_eq!((navigations[0].destination.as_str(), navigations[0].delay), ("https://example.com/later", Some(5)));
+        assert_eq!((navigations[1].mechanism, navigations[1].edge, navigations[1].initiator), (NavigationMechanism::LocationSet, Some(location_set), Some(script)));
+        assert_eq!(navigations[1].destination, "https://scam.net/win");
+
+        assert_eq!(parse_refresh("0"), Some((0, None)));
+        assert_eq!(parse_refresh("1,https://example.com/"), Some((1, Some("https://example.com/"))));
+        assert_eq!(parse_refresh("0; ééx"), Some((0, Some("ééx"))));
+    }
+}
@@ -0,0 +1,215 @@
+//! Attribution of the bytes downloaded by the page to the domains and entities that served them.
+//!
+//! Sizes come from the response sizes recorded on request completion and error edges. Some
+//! responses, like streamed fetches, video, or audio, cannot be sized; these are counted
+//! separately rather than being treated as empty.
+//!
+//! [`third_party_costs`](PageGraph::third_party_costs) extends this with the work done by each
+//! third party's scripts, attributing scripts to the domain they were fetched from. Inline
+//! scripts are not attributed to any third party.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::trackers::TrackerCategory;
+use crate::graph::PageGraph;
+use crate::graph_algos::domain_of_url;
+use crate::requests::RequestOutcome;
+use crate::types::{EdgeType, NodeType};
+
+/// The bytes downloaded from a single domain or entity.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ByteAttribution {
+    /// The domain or entity name, or `None` for requests to URLs without a registrable domain.
+    pub name: Option<String>,
+    /// The total size of every sized response.
+    pub bytes: usize,
+    pub requests: usize,
+    /// The number of requests whose response size could not be determined.
+    pub unsized_requests: usize,
+}
+
+impl ByteAttribution {
+    fn add(&mut self, size: Option<usize>) {
+        self.requests += 1;
+        match size {
+            Some(size) => self.bytes += size,
+            None => self.unsized_requests += 1,
+        }
+    }
+}
+
+/// Sorts attributions from the most to the fewest bytes.
+fn sorted(totals: BTreeMap<Option<String>, ByteAttribution>) -> Vec<ByteAttribution> {
+    let mut totals = totals.into_iter().map(|(name, totals)| ByteAttribution { name, ..totals }).collect::<Vec<_>>();
+    totals.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    totals
+}
+
+/// The cost of a single third party on the page.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ThirdPartyCost {
+    /// The entity name, or the registrable domain for domains without a known entity.
+    pub name: String,
+    pub domains: BTreeSet<String>,
+    pub bytes: usize,
+    pub requests: usize,
+    pub unsized_requests: usize,
+    /// Scripts fetched from the third party.
+    pub scripts: usize,
+    /// DOM nodes created by the third party's scripts.
+    pub dom_nodes_created: usize,
+    /// Web API and JS builtin calls made by the third party's scripts.
+    pub api_calls: usize,
+    /// Tracker categories of the third party's domains. These are only known once filled in
+    /// from a tracker list by [`TrackerCategories::categorize`](super::trackers::TrackerCategories::categorize).
+    pub categories: Vec<TrackerCategory>,
+}
+
+impl PageGraph {
+    /// Returns the size and registrable domain of every request that wasn't blocked.
+    pub(crate) fn sized_requests(&self) -> Vec<(Option<String>, Option<usize>)> {
+        self.requests().into_iter()
+            .filter(|request| !matches!(request.outcome, RequestOutcome::Blocked { .. }))
+            .map(|request| {
+                let size = request.completion.or(request.error)
+                    .and_then(|edge| self.edges.get(&edge))
+                    .and_then(|edge| edge.edge_type.response_size());
+                (request.url.as_ref().and_then(domain_of_url), size)
+            })
+            .collect()
+    }
+
+    /// Returns the bytes downloaded from each registrable domain, from most to fewest bytes.
+    pub fn bytes_by_domain(&self) -> Vec<ByteAttribution> {
+        let mut totals = BTreeMap::<_, ByteAttribution>::new();
+        self.sized_requests().into_iter().for_each(|(domain, size)| totals.entry(domain).or_default().add(size));
+        sorted(totals)
+    }
+
+    /// Returns the bytes downloaded from each entity, from most to fewest bytes. `entities` maps
+    /// registrable domains to the name of the entity that owns them; domains that aren't in the
+    /// map are treated as their own entity.
+    pub fn bytes_by_entity(&self, entities: &HashMap<String, String>) -> Vec<ByteAttribution> {
+        let mut totals = BTreeMap::<_, ByteAttribution>::new();
+        self.sized_requests().into_iter().for_each(|(domain, size)| {
+            let entity = domain.map(|domain| entities.get(&domain).cloned().unwrap_or(domain));
+            totals.entry(entity).or_default().add(size);
+        });
+        sorted(totals)
+    }
+
+    /// Returns the cost of every third party on the page, from most to fewest bytes. `entities`
+    /// maps registrable domains to the name of the entity that owns them, as for
+    /// [`bytes_by_entity`](PageGraph::bytes_by_entity).
+    pub fn third_party_costs(&self, entities: &HashMap<String, String>) -> Vec<ThirdPartyCost> {
+        let first_party = domain_of_url(&self.desc.url);
+        let mut costs = BTreeMap::<String, ThirdPartyCost>::new();
+        // Returns the entity name and domain for third-party domains.
+        let third_party = |domain: Option<String>| {
+            let domain = domain.filter(|domain| Some(domain) != first_party.as_ref())?;
+            let name = entities.get(&domain).cloned().unwrap_or_else(|| domain.clone());
+            Some((name, domain))
+        };
+        fn cost_of(costs: &mut BTreeMap<String, ThirdPartyCost>, (name, domain): (String, String)) -> &mut ThirdPartyCost {
+            let cost = costs.entry(name.clone()).or_insert_with(|| ThirdPartyCost { name, ..Default::default() });
+            cost.domains.insert(domain);
+            cost
+        }
+
+        self.sized_requests().into_iter().for_each(|(domain, size)| if let Some(third_party) = third_party(domain) {
+            let cost = cost_of(&mut costs, third_party);
+            cost.requests += 1;
+            match size {
+                Some(size) => cost.bytes += size,
+                None => cost.unsized_requests += 1,
+            }
+        });
+
+        self.nodes.values().for_each(|node| {
+            let domain = match &node.node_type {
+                NodeType::Script { url: Some(url), .. } => domain_of_url(url),
+                _ => return,
+            };
+            if let Some(third_party) = third_party(domain) {
+                let cost = cost_of(&mut costs, third_party);
+                cost.scripts += 1;
+                self.outgoing_edges(node).for_each(|edge| match edge.edge_type {
+                    EdgeType::CreateNode {} => cost.dom_nodes_created += 1,
+                    EdgeType::JsCall { .. } => cost.api_calls += 1,
+                    _ => (),
+                });
+            }
+        });
+
+        let mut costs = costs.into_values().collect::<Vec<_>>();
+        costs.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+        costs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::{RequestType, ResourceType};
+
+    #[test]
+    fn test_byte_attribution() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "img");
+        [
+            ("https://example.com/logo.png", Some(1000)),
+            ("https://ads.doubleclick.net/ad.png", Some(5000)),
+            ("https://www.googletagmanager.com/gtm.js", Some(2000)),
+            ("https://www.googletagmanager.com/stream", None),
+        ].iter().for_each(|(url, size)| {
+            let request = builder.add_request(element, url, RequestType::Image);
+            builder.complete_request(request, ResourceType::Image, *size);
+        });
+        let graph = builder.build();
+
+        let by_domain = graph.bytes_by_domain();
+        assert_eq!(by_domain.iter().map(|totals| (totals.name.as_deref().unwrap(), totals.bytes)).collect::<Vec<_>>(),
+            vec![("doubleclick.net", 5000), ("googletagmanager.com", 2000), ("example.com", 1000)]);
+        assert_eq!(by_domain[1].unsized_requests, 1);
+
+        let entities = [("doubleclick.net", "Google"), ("googletagmanager.com", "Google")].iter()
+            .map(|(domain, entity)| (domain.to_string(), entity.to_string()))
+            .collect();
+        let by_entity = graph.bytes_by_entity(&entities);
+        assert_eq!(by_entity[0], ByteAttribution { name: Some("Google".to_string()), bytes: 7000, requests: 3, unsized_requests: 1 });
+        assert_eq!(by_entity[1].name.as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn test_third_party_costs() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let request = builder.add_request(element, "https://www.googletagmanager.com/gtm.js", RequestType::Script);
+        builder.complete_request(request, ResourceType::Script, Some(2000));
+        let script = builder.add_script(element, Some("https://www.googletagmanager.com/gtm.js"), "");
+        builder.add_element(script, root, "iframe");
+        builder.call_web_api(script, "Document.cookie", None);
+        let pixel = builder.add_request(script, "https://ads.doubleclick.net/p.gif", RequestType::Image);
+        builder.complete_request(pixel, ResourceType::Image, Some(43));
+        let first_party = builder.add_request(parser, "https://example.com/style.css", RequestType::CSS);
+        builder.complete_request(first_party, ResourceType::Css, Some(500));
+        let graph = builder.build();
+
+        let entities = [("doubleclick.net", "Google"), ("googletagmanager.com", "Google")].iter()
+            .map(|(domain, entity)| (domain.to_string(), entity.to_string()))
+            .collect();
+        let costs = graph.third_party_costs(&entities);
+        assert_eq!(costs.len(), 1);
+        assert_eq!(costs[0].name, "Google");
+        assert_eq!(costs[0].domains.len(), 2);
+        assert_eq!((costs[0].bytes, costs[0].requests, costs[0].scripts), (2043, 2, 1));
+        assert_eq!((costs[0].dom_nodes_created, costs[0].api_calls), (1, 1));
+    }
+}
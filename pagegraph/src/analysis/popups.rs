@@ -0,0 +1,154 @@
+//! Detection of scripts opening popups and new windows through `window.open`.
+//!
+//! PageGraph doesn't record which event a script was handling when it made a call, so the
+//! triggering events of a popup are approximated as the events the calling script was listening
+//! for when it called `window.open`, as registered through `addEventListener` or recorded as
+//! existing listeners. A popup opened from a `click` listener on the whole document is a common
+//! pattern for forced navigation by ad scripts.
+
+use crate::analysis::{split_call_args, ListenerRemovals};
+use crate::graph::{Edge, EdgeId, Node, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType, Url};
+
+/// Web API method names for `window.open`.
+const WINDOW_OPEN_METHODS: [&str; 2] = ["Window.open", "window.open"];
+
+/// An event listener, defined in the script that opened a popup, which may have triggered it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TriggeringEvent {
+    pub event: String,
+    pub event_listener_id: usize,
+    /// The node the listener was attached to.
+    pub target: NodeId,
+}
+
+/// A single call to `window.open`.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Popup {
+    pub edge: EdgeId,
+    pub timestamp: Option<isize>,
+    pub script: NodeId,
+    pub script_url: Option<String>,
+    pub document: Option<NodeId>,
+    /// The URL opened, resolved against the URL of the opening document. This is `about:blank`
+    /// if no URL was passed, and `None` if the call's arguments weren't recorded.
+    pub url: Option<String>,
+    /// Whether `url` is third-party to the top-level page.
+    pub third_party: Option<bool>,
+    /// The name of the window the URL was opened in, like `_blank`.
+    pub target: Option<String>,
+    pub features: Option<String>,
+    pub triggering_events: Vec<TriggeringEvent>,
+}
+
+impl PageGraph {
+    /// Returns the event listeners defined in `script` that were registered, and not yet
+    /// removed, at the time of `call`.
+    fn listeners_active_during(&self, script: &Node, call: &Edge, removals: &ListenerRemovals) -> Vec<TriggeringEvent> {
+        let script_id = match script.node_type.script_id() {
+            Some(script_id) => script_id,
+            None => return vec![],
+        };
+        let before_call = |edge: &Edge| match (edge.edge_timestamp, call.edge_timestamp) {
+            (Some(timestamp), Some(call_timestamp)) => timestamp <= call_timestamp,
+            _ => true,
+        };
+
+        let mut events = vec![];
+        for edge in self.edges.values() {
+            let event = match &edge.edge_type {
+                EdgeType::AddEventListener { key, event_listener_id, script_id: listener_script_id }
+                    if *listener_script_id == script_id && crate::graph::is_same_frame_context(edge.id, script.id) && before_call(edge) => {
+                    if removals.removed_by(edge, call.edge_timestamp) {
+                        continue;
+                    }
+                    TriggeringEvent { event: key.clone(), event_listener_id: *event_listener_id, target: edge.target }
+                }
+                EdgeType::EventListener { key, event_listener_id } if edge.target == script.id => {
+                    TriggeringEvent { event: key.clone(), event_listener_id: *event_listener_id, target: edge.source }
+                }
+                _ => continue,
+            };
+            if !events.iter().any(|other: &TriggeringEvent| other.event_listener_id == event.event_listener_id) {
+                events.push(event);
+            }
+        }
+        events.sort_by_key(|event| event.event_listener_id);
+        events
+    }
+
+    /// Returns every call to `window.open` in chronological order.
+    pub fn popups(&self) -> Vec<Popup> {
+        let removals = ListenerRemovals::new(self);
+        let mut popups = self.edges.values().filter_map(|edge| {
+            let args = match (&edge.edge_type, &self.target_node(edge).node_type) {
+                (EdgeType::JsCall { args, .. }, NodeType::WebApi { method }) if WINDOW_OPEN_METHODS.contains(&method.as_str()) => args,
+                _ => return None,
+            };
+            let script = self.source_node(edge);
+            let document = self.document_of_actor(script);
+            let args = args.as_deref().map(split_call_args);
+
+            let url = args.as_ref().map(|args| match args.first().map(|url| url.trim()) {
+                None | Some("") | Some("undefined") | Some("null") => Url::from("about:blank"),
+                Some(url) => {
+                    let base = document.and_then(|document| document.node_type.url()).unwrap_or(&self.desc.url);
+                    Url::from(base.parsed().and_then(|base| base.join(url).ok()).map(|url| url.to_string()).unwrap_or_else(|| url.to_string()))
+                }
+            });
+            let arg = |index: usize| args.as_ref().and_then(|args| args.get(index)).filter(|arg| !arg.is_empty()).cloned();
+
+            Some(Popup {
+                edge: edge.id,
+                timestamp: edge.edge_timestamp,
+                script: script.id,
+                script_url: script.node_type.url().map(|url| url.to_string()),
+                document: document.map(|document| document.id),
+                third_party: url.as_ref().and_then(|url| self.is_third_party(url)),
+                url: url.map(|url| url.to_string()),
+                target: arg(1),
+                features: arg(2),
+                triggering_events: self.listeners_active_during(script, edge, &removals),
+            })
+        }).collect::<Vec<_>>();
+        popups.sort_by_key(|popup| (popup.timestamp, popup.edge));
+        popups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_popups() {
+        let mut builder = PageGraphBuilder::new("https://example.com/article");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, Some("https://ads.net/pop.js"), "");
+        let click = builder.add_event_listener(script, root, "click", script);
+        builder.add_event_listener(script, root, "load", script);
+        let removed = builder.add_event_listener(script, root, "scroll", script);
+        // The builder numbers scripts from 1
+        builder.add_edge(script, root, EdgeType::RemoveEventListener { key: "scroll".to_string(), event_listener_id: removed, script_id: 1 });
+
+        let open = builder.call_web_api(script, "Window.open", Some("[\"/landing?a=1,2\", \"_blank\", \"\"]"));
+        builder.call_web_api(script, "Window.open", Some("[]"));
+        // Other APIs named `open` don't open windows.
+        builder.call_web_api(script, "open", Some("[\"GET\", \"/data\"]"));
+        let graph = builder.build();
+
+        let popups = graph.popups();
+        assert_eq!(popups.len(), 2);
+        assert_eq!((popups[0].edge, popups[0].script), (open, script));
+        assert_eq!(popups[0].url.as_deref(), Some("https://example.com/landing?a=1,2"));
+        assert_eq!((popups[0].third_party, popups[0].target.as_deref(), popups[0].features.as_deref()), (Some(false), Some("_blank"), None));
+        assert_eq!(popups[0].triggering_events.iter().map(|event| (event.event.as_str(), event.target)).collect::<Vec<_>>(), vec![("click", root), ("load", root)]);
+        assert_eq!(popups[0].triggering_events[0].event_listener_id, click);
+        assert_eq!(popups[1].url.as_deref(), Some("about:blank"));
+    }
+}
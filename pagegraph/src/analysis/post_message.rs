@@ -0,0 +1,217 @@
+//! Analysis of cross-frame messaging via `postMessage`, which is commonly used to pass
+//! identifiers between first- and third-party frames.
+//!
+//! PageGraph records calls to `postMessage` and the registration of `message` event listeners,
+//! but not which window a message was delivered to. The receivers of each message are therefore
+//! approximated as every `message` listener in another document that was registered, and not yet
+//! removed, at the time the message was sent.
+
+use std::collections::BTreeMap;
+
+use crate::analysis::{origin_of_url, ListenerRemovals};
+use crate::graph::{Edge, EdgeId, Node, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// A `message` event listener that could have received a message.
+#[derive(Debug, Clone, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MessageListener {
+    pub event_listener_id: usize,
+    /// The script that registered the listener.
+    pub registering_script: NodeId,
+    /// The script containing the listener function, if it is present in the graph.
+    pub listener_script: Option<NodeId>,
+    pub document: Option<NodeId>,
+    pub origin: Option<String>,
+}
+
+/// A single call to `postMessage`.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PostMessage {
+    pub edge: EdgeId,
+    pub timestamp: Option<isize>,
+    pub sender_script: NodeId,
+    pub sender_document: Option<NodeId>,
+    pub sender_origin: Option<String>,
+    pub args: Option<String>,
+    /// Listeners in other documents that could have received this message.
+    pub receivers: Vec<MessageListener>,
+}
+
+/// The number of messages sent from one origin to another.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MessageFlow {
+    pub sender_origin: Option<String>,
+    pub receiver_origin: Option<String>,
+    pub messages: usize,
+}
+
+fn is_post_message(method: &str) -> bool {
+    method == "postMessage" || method.ends_with(".postMessage")
+}
+
+impl PageGraph {
+    fn document_origin(&self, document: Option<&Node>) -> Option<String> {
+        document.and_then(|document| document.node_type.url()).and_then(origin_of_url)
+    }
+
+    /// Returns every `message` event listener registration.
+    fn message_listeners(&self) -> Vec<(&Edge, MessageListener)> {
+        self.edges.values()
+            .filter_map(|edge| match &edge.edge_type {
+                EdgeType::AddEventListener { key, event_listener_id, script_id } if key == "message" => Some((edge, *event_listener_id, *script_id)),
+                _ => None,
+            })
+            .map(|(edge, event_listener_id, script_id)| {
+                let document = self.document_of_actor(self.target_node(edge));
+                let listener = MessageListener {
+                    event_listener_id,
+                    registering_script: edge.source,
+                    listener_script: self.nodes.values()
+                        .find(|node| node.node_type.script_id() == Some(script_id) && crate::graph::is_same_frame_context(node.id, edge.id))
+                        .map(|node| node.id),
+                    document: document.map(|document| document.id),
+                    origin: self.document_origin(document),
+                };
+                (edge, listener)
+            })
+            .collect()
+    }
+
+    /// Returns every call to `postMessage` in chronological order, along with the listeners that
+    /// could have received each message.
+    pub fn post_messages(&self) -> Vec<PostMessage> {
+        let listeners = self.message_listeners();
+        let removals = ListenerRemovals::new(self);
+
+        let mut messages = self.edges.values()
+            .filter(|edge| matches!(edge.edge_type, EdgeType::JsCall { .. }))
+            .filter(|edge| matches!(&self.target_node(edge).node_type, NodeType::WebApi { method } if is_post_message(method)))
+            .map(|edge| {
+                let sender = self.source_node(edge);
+                let sender_document = self.document_of_actor(sender);
+                let receivers = listeners.iter()
+                    .filter(|(_, listener)| listener.document.is_none() || listener.document != sender_document.map(|document| document.id))
+                    .filter(|(registration, _)| match edge.edge_timestamp {
+                        Some(sent_at) => registration.edge_timestamp.map(|t| t <= sent_at).unwrap_or(true) &&
+                            !removals.removed_by(registration, Some(sent_at)),
+                        None => true,
+                    })
+                    .map(|(_, listener)| listener.clone())
+                    .collect();
+
+                PostMessage {
+                    edge: edge.id,
+                    timestamp: edge.edge_timestamp,
+                    sender_script: sender.id,
+                    sender_document: sender_document.map(|document| document.id),
+                    sender_origin: self.document_origin(sender_document),
+                    args: match &edge.edge_type {
+                        EdgeType::JsCall { args, .. } => args.clone(),
+                        _ => unreachable!(),
+                    },
+                    receivers,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        messages.sort_by_key(|message| (message.timestamp, message.edge));
+        messages
+    }
+
+    /// Aggregates every `postMessage` call into counts of messages sent between each pair of
+    /// origins, sorted by origin.
+    pub fn message_flows(&self) -> Vec<MessageFlow> {
+        let mut flows = BTreeMap::new();
+        self.post_messages().into_iter().for_each(|message| {
+            let sender_origin = message.sender_origin;
+            let mut receiver_origins = message.receivers.into_iter().map(|receiver| receiver.origin).collect::<Vec<_>>();
+            receiver_origins.sort();
+            receiver_origins.dedup();
+            receiver_origins.into_iter().for_each(|receiver_origin| {
+                *flows.entry((sender_origin.clone(), receiver_origin)).or_insert(0) += 1;
+            });
+        });
+        flows.into_iter()
+            .map(|((sender_origin, receiver_origin), messages)| MessageFlow { sender_origin, receiver_origin, messages })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::graph::FrameId;
+
+    #[test]
+    fn test_post_message_flows() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+
+        let iframe = builder.add_frame_owner(parser, root, "iframe");
+        let frame_document = builder.add_frame_document(iframe, "https://ads.example.net/frame.html");
+        let frame_script_element = builder.add_element(parser, frame_document, "script");
+        let frame_script = builder.add_script(frame_script_element, Some("https://ads.example.net/sync.js"), "onmessage = sync");
+        builder.add_event_listener(frame_script, frame_document, "message", frame_script);
+
+        let script_element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(script_element, None, "frames[0].postMessage(uid, '*')");
+        // Listeners in the sender's own document don't receive its messages.
+        builder.add_event_listener(script, root, "message", script);
+        let message = builder.call_web_api(script, "Window.postMessage", Some("[\"uid=1234\",\"*\"]"));
+        let graph = builder.build();
+
+        let messages = graph.post_messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].edge, message);
+        assert_eq!(messages[0].sender_script, script);
+        assert_eq!(messages[0].sender_origin.as_deref(), Some("https://example.com"));
+        assert_eq!(messages[0].receivers.len(), 1);
+        assert_eq!(messages[0].receivers[0].listener_script, Some(frame_script));
+        assert_eq!(messages[0].receivers[0].document, Some(frame_document));
+
+        assert_eq!(graph.message_flows(), vec![MessageFlow {
+            sender_origin: Some("https://example.com".to_string()),
+            receiver_origin: Some("https://ads.example.net".to_string()),
+            messages: 1,
+        }]);
+    }
+
+    #[test]
+    fn test_listener_removals_are_frame_scoped() {
+        let remote_frame_id = FrameId::try_from("000000000000000000000000000000AD").unwrap();
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let iframe = builder.add_frame_owner(parser, root, "iframe");
+        let frame_document = builder.add_frame_document(iframe, "https://ads.example.net/frame.html");
+        let frame_script_element = builder.add_element(parser, frame_document, "script");
+        let frame_script = builder.add_script(frame_script_element, None, "");
+        let listener = builder.add_event_listener(frame_script, frame_document, "message", frame_script);
+        let remote_frame = builder.add_node(NodeType::RemoteFrame { frame_id: remote_frame_id });
+        builder.add_edge(iframe, remote_frame, EdgeType::CrossDom {});
+        builder.advance_time(100);
+        let script_element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(script_element, None, "");
+        builder.call_web_api(script, "Window.postMessage", Some("[\"uid=1234\",\"*\"]"));
+        let mut graph = builder.build();
+
+        // A listener with the same id is removed in another frame, before the message is sent.
+        let mut frame = PageGraphBuilder::new("https://other.net/").frame_id(remote_frame_id).is_root(false);
+        let frame_parser = frame.parser();
+        let frame_root = frame.dom_root();
+        let element = frame.add_element(frame_parser, frame_root, "script");
+        let other_script = frame.add_script(element, None, "");
+        frame.add_edge(other_script, frame_root, EdgeType::RemoveEventListener { key: "message".to_string(), event_listener_id: listener, script_id: 1 });
+        graph.merge_frame(frame.build(), &remote_frame_id);
+
+        let messages = graph.post_messages();
+        assert_eq!(messages[0].receivers.iter().map(|receiver| receiver.listener_script).collect::<Vec<_>>(), vec![Some(frame_script)]);
+    }
+}
@@ -0,0 +1,232 @@
+//! A registry of analyses that can be discovered and run by name.
+//!
+//! Front-ends like `pagegraph-cli` don't need to know about every analysis ahead of time. Each
+//! analysis implements [`Analysis`], producing its results as JSON, and is added to an
+//! [`AnalysisRegistry`]. Crates building on this one can register their own analyses alongside
+//! the [built-in ones](AnalysisRegistry::with_builtins) without modifying this crate.
+
+use crate::analysis::Thresholds;
+use crate::graph::PageGraph;
+#[cfg(feature = "schemars")]
+use crate::schema::{Schema, SchemaGenerator};
+
+/// The lazily built indexes of a [`PageGraph`] that an analysis relies on. The registry builds
+/// these before running an analysis, so that their cost isn't attributed to the first analysis
+/// to use them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphIndex {
+    /// Timestamp-sorted nodes and edges, used by temporal queries.
+    Time,
+    /// Edges grouped by request id, used by [`requests`](PageGraph::requests).
+    Requests,
+    /// Parent and child relations of DOM nodes.
+    Dom,
+    /// Inferred [lifecycle](crate::lifecycle) event timings.
+    Lifecycle,
+}
+
+impl PageGraph {
+    /// Builds the given index, if it hasn't been built already.
+    pub fn build_index(&self, index: GraphIndex) {
+        match index {
+            GraphIndex::Time => { self.time_index(); }
+            GraphIndex::Requests => { self.request_index(); }
+            GraphIndex::Dom => { self.dom_tree(); }
+            GraphIndex::Lifecycle => { self.lifecycle(); }
+        }
+    }
+}
+
+/// An analysis that can be registered in an [`AnalysisRegistry`].
+pub trait Analysis {
+    /// A unique name for the analysis, in `snake_case`, used to select it.
+    fn name(&self) -> &str;
+
+    /// A one-line description of the analysis.
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// The indexes the analysis relies on.
+    fn required_indexes(&self) -> &[GraphIndex] {
+        &[]
+    }
+
+    /// The schema of the analysis results, if known. Named types it uses are defined in
+    /// `generator`.
+    #[cfg(feature = "schemars")]
+    fn schema(&self, _generator: &mut SchemaGenerator) -> Option<Schema> {
+        None
+    }
+
+    fn run(&self, graph: &PageGraph) -> serde_json::Value;
+}
+
+/// Results of a built-in analysis, which also have a schema with the `schemars` feature.
+#[cfg(feature = "schemars")]
+trait AnalysisResults: serde::Serialize + schemars::JsonSchema {}
+#[cfg(feature = "schemars")]
+impl<T: serde::Serialize + schemars::JsonSchema> AnalysisResults for T {}
+#[cfg(not(feature = "schemars"))]
+trait AnalysisResults: serde::Serialize {}
+#[cfg(not(feature = "schemars"))]
+impl<T: serde::Serialize> AnalysisResults for T {}
+
+/// An analysis backed by a function returning serializable results.
+struct FnAnalysis<T: AnalysisResults> {
+    name: &'static str,
+    description: &'static str,
+    indexes: &'static [GraphIndex],
+    analyze: Box<dyn Fn(&PageGraph) -> T>,
+}
+
+impl<T: AnalysisResults> Analysis for FnAnalysis<T> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn required_indexes(&self) -> &[GraphIndex] {
+        self.indexes
+    }
+
+    #[cfg(feature = "schemars")]
+    fn schema(&self, generator: &mut SchemaGenerator) -> Option<Schema> {
+        Some(generator.subschema_for::<T>())
+    }
+
+    fn run(&self, graph: &PageGraph) -> serde_json::Value {
+        serde_json::to_value((self.analyze)(graph)).expect("Analysis results should always be serializable as JSON")
+    }
+}
+
+/// A set of analyses, looked up by name.
+#[derive(Default)]
+pub struct AnalysisRegistry {
+    analyses: Vec<Box<dyn Analysis>>,
+}
+
+impl AnalysisRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry of every built-in analysis that doesn't take any parameters.
+    pub fn with_builtins() -> Self {
+        Self::with_builtins_using(Thresholds::default())
+    }
+
+    /// Like [`with_builtins`](AnalysisRegistry::with_builtins), with analyses that report
+    /// behavior past some cutoff using the given thresholds.
+    pub fn with_builtins_using(thresholds: Thresholds) -> Self {
+        let mut registry = Self::new();
+        macro_rules! builtin {
+            ($name:literal, $description:literal, $indexes:expr, $analyze:expr) => {
+                registry.register(Box::new(FnAnalysis { name: $name, description: $description, indexes: $indexes, analyze: Box::new($analyze) }));
+            };
+        }
+        builtin!("blocked_resources", "Resources blocked by Brave Shields, and the rules that blocked them", &[], PageGraph::blocked_resources);
+        builtin!("builtin_tampering", "Scripts overriding or wrapping built-in JavaScript functions", &[GraphIndex::Time], PageGraph::builtin_tampering);
+        builtin!("cookie_jar", "Cookies set by scripts and remaining at the end of the page load", &[GraphIndex::Time], PageGraph::cookie_jar);
+        builtin!("cookie_syncs", "Identifiers from cookies or storage sent to third parties", &[GraphIndex::Requests], move |graph: &PageGraph| graph.cookie_syncs_with(&thresholds));
+        builtin!("critical_path", "The chain of render-blocking requests delaying DOMContentLoaded", &[GraphIndex::Requests, GraphIndex::Lifecycle], PageGraph::critical_path);
+        builtin!("dangerous_sinks", "Third-party scripts writing markup or script URLs into the page", &[GraphIndex::Time], PageGraph::dangerous_sink_uses);
+        builtin!("environment_access", "Reads of navigator, screen, and other browser environment properties by each script", &[], PageGraph::environment_accesses);
+        builtin!("fingerprinting", "Scripts calling Web APIs commonly used for fingerprinting", &[], move |graph: &PageGraph| graph.fingerprinting_scripts_with(&thresholds));
+        builtin!("frame_stats", "Counts of the activity within each frame", &[], PageGraph::frame_stats);
+        builtin!("injected_iframes", "Iframes injected into the page by scripts", &[], PageGraph::injected_iframes);
+        builtin!("message_flows", "Counts of postMessage calls between each pair of origins", &[], PageGraph::message_flows);
+        builtin!("mixed_content", "Insecure subresource requests made by a secure page", &[GraphIndex::Requests], PageGraph::mixed_content);
+        builtin!("navigations", "Redirects and navigations of the top-level page", &[GraphIndex::Requests], PageGraph::navigations);
+        builtin!("popups", "Calls to window.open", &[], PageGraph::popups);
+        builtin!("requests", "Every network request made by the page", &[GraphIndex::Requests], PageGraph::requests);
+        builtin!("script_costs", "Scripts ranked by the actions attributed to them", &[], PageGraph::script_costs);
+        builtin!("storage_usage", "How scripts use cookies and Web Storage", &[GraphIndex::Time], PageGraph::storage_usage);
+        builtin!("summary", "Summary statistics over the whole graph", &[GraphIndex::Requests], PageGraph::summary);
+        builtin!("taint_flows", "Data from third-party responses flowing into the first-party DOM and storage", &[GraphIndex::Time], PageGraph::taint_flows);
+        builtin!("websockets", "WebSocket connections opened by the page", &[], PageGraph::websocket_connections);
+        builtin!("workers", "Web workers, shared workers, and service workers", &[], PageGraph::workers);
+        registry
+    }
+
+    /// Adds an analysis to the registry. Panics if an analysis with the same name is already
+    /// registered.
+    pub fn register(&mut self, analysis: Box<dyn Analysis>) {
+        if self.get(analysis.name()).is_some() {
+            panic!("An analysis named {} is already registered", analysis.name());
+        }
+        self.analyses.push(analysis);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Analysis> {
+        self.analyses.iter().find(|analysis| analysis.name() == name).map(|analysis| analysis.as_ref())
+    }
+
+    /// Returns every registered analysis, sorted by name.
+    pub fn analyses(&self) -> Vec<&dyn Analysis> {
+        let mut analyses = self.analyses.iter().map(|analysis| analysis.as_ref()).collect::<Vec<_>>();
+        analyses.sort_by_key(|analysis| analysis.name());
+        analyses
+    }
+
+    /// Runs the named analysis over the graph, after building the indexes it requires. Returns
+    /// `None` if no analysis with that name is registered.
+    pub fn run(&self, name: &str, graph: &PageGraph) -> Option<serde_json::Value> {
+        let analysis = self.get(name)?;
+        analysis.required_indexes().iter().for_each(|index| graph.build_index(*index));
+        Some(analysis.run(graph))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    struct NodeCount;
+
+    impl Analysis for NodeCount {
+        fn name(&self) -> &str {
+            "node_count"
+        }
+
+        fn required_indexes(&self) -> &[GraphIndex] {
+            &[GraphIndex::Time]
+        }
+
+        fn run(&self, graph: &PageGraph) -> serde_json::Value {
+            graph.nodes.len().into()
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        builder.add_element(parser, root, "div");
+        let graph = builder.build();
+
+        let mut registry = AnalysisRegistry::with_builtins();
+        registry.register(Box::new(NodeCount));
+        assert_eq!(registry.run("node_count", &graph), Some(serde_json::json!(3)));
+        assert_eq!(registry.run("popups", &graph), Some(serde_json::json!([])));
+        assert!(registry.run("missing", &graph).is_none());
+
+        let names = registry.analyses().iter().map(|analysis| analysis.name()).collect::<Vec<_>>();
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(names.contains(&"critical_path"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate_registration() {
+        let mut registry = AnalysisRegistry::new();
+        registry.register(Box::new(NodeCount));
+        registry.register(Box::new(NodeCount));
+    }
+}
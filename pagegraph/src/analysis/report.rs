@@ -0,0 +1,189 @@
+//! Reports that combine several analyses into a single structured artifact.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::graph::PageGraph;
+
+use super::Thresholds;
+use super::builtin_tampering::BuiltinTampering;
+use super::cookie_sync::CookieSync;
+use super::dangerous_sinks::DangerousSinkUse;
+use super::fingerprinting::FingerprintingScript;
+use super::mixed_content::MixedContentRequest;
+use super::page_weight::ThirdPartyCost;
+use super::shields::BlockedResource;
+use super::storage::StorageKeyUsage;
+use super::taint::TaintFlow;
+use super::trackers::TrackerCategories;
+
+/// Security-relevant behavior of a page.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SecurityReport {
+    pub url: String,
+    pub mixed_content: Vec<MixedContentRequest>,
+    pub dangerous_sinks: Vec<DangerousSinkUse>,
+    pub taint_flows: Vec<TaintFlow>,
+}
+
+/// Runs every security analysis over the graph.
+pub fn security(graph: &PageGraph) -> SecurityReport {
+    SecurityReport {
+        url: graph.desc.url.to_string(),
+        mixed_content: graph.mixed_content(),
+        dangerous_sinks: graph.dangerous_sink_uses(),
+        taint_flows: graph.taint_flows(),
+    }
+}
+
+/// Privacy-relevant behavior of a page.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PrivacyReport {
+    pub url: String,
+    pub third_parties: Vec<ThirdPartyCost>,
+    pub fingerprinting: Vec<FingerprintingScript>,
+    pub builtin_tampering: Vec<BuiltinTampering>,
+    pub storage: Vec<StorageKeyUsage>,
+    pub cookie_syncs: Vec<CookieSync>,
+    pub blocked: Vec<BlockedResource>,
+}
+
+/// Runs every privacy analysis over the graph. Third parties are grouped by registrable domain.
+pub fn privacy(graph: &PageGraph) -> PrivacyReport {
+    privacy_with(graph, &Thresholds::default())
+}
+
+/// Like [`privacy`], with the cutoffs used by each analysis taken from `thresholds`.
+pub fn privacy_with(graph: &PageGraph, thresholds: &Thresholds) -> PrivacyReport {
+    PrivacyReport {
+        url: graph.desc.url.to_string(),
+        third_parties: graph.third_party_costs(&HashMap::new()),
+        fingerprinting: graph.fingerprinting_scripts_with(thresholds),
+        builtin_tampering: graph.builtin_tampering(),
+        storage: graph.storage_usage(),
+        cookie_syncs: graph.cookie_syncs_with(thresholds),
+        blocked: graph.blocked_resources(),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Appends a titled table to `html`, or a note if there are no rows.
+fn write_table(html: &mut String, title: &str, headers: &[&str], rows: Vec<Vec<String>>) {
+    writeln!(html, "<h2>{} ({})</h2>", escape_html(title), rows.len()).unwrap();
+    if rows.is_empty() {
+        html.push_str("<p class=\"none\">None recorded.</p>\n");
+        return;
+    }
+    html.push_str("<table>\n<tr>");
+    headers.iter().for_each(|header| write!(html, "<th>{}</th>", escape_html(header)).unwrap());
+    html.push_str("</tr>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        row.iter().for_each(|cell| write!(html, "<td>{}</td>", escape_html(cell)).unwrap());
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+}
+
+fn or_dash(value: Option<&str>) -> String {
+    value.unwrap_or("-").to_string()
+}
+
+impl PrivacyReport {
+    /// Fills in the tracker categories of each third party from a tracker list.
+    pub fn categorize(&mut self, trackers: &TrackerCategories) {
+        trackers.categorize(&mut self.third_parties);
+    }
+
+    /// Renders the report as a self-contained HTML page, with no external stylesheets or
+    /// scripts.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        write!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Privacy report for {}</title>\n", escape_html(&self.url)).unwrap();
+        html.push_str("<style>\nbody { font-family: sans-serif; margin: 2em; }\ntable { border-collapse: collapse; margin-bottom: 1em; }\nth, td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; vertical-align: top; word-break: break-all; }\nth { background: #eee; }\n.none { color: #888; }\n</style>\n</head>\n<body>\n");
+        writeln!(html, "<h1>Privacy report for {}</h1>", escape_html(&self.url)).unwrap();
+
+        write!(html, "<ul>\n<li>{} third parties</li>\n<li>{} likely fingerprinting scripts</li>\n<li>{} possible cookie syncs</li>\n<li>{} blocked resources</li>\n</ul>\n",
+            self.third_parties.len(),
+            self.fingerprinting.iter().filter(|script| script.likely_fingerprinting).count(),
+            self.cookie_syncs.len(),
+            self.blocked.len()).unwrap();
+
+        write_table(&mut html, "Third parties", &["Name", "Domains", "Categories", "Requests", "Bytes", "Scripts", "API calls"], self.third_parties.iter().map(|cost| vec![
+            cost.name.clone(),
+            cost.domains.iter().cloned().collect::<Vec<_>>().join(", "),
+            cost.categories.iter().map(|category| format!("{:?}", category)).collect::<Vec<_>>().join(", "),
+            cost.requests.to_string(),
+            cost.bytes.to_string(),
+            cost.scripts.to_string(),
+            cost.api_calls.to_string(),
+        ]).collect());
+        write_table(&mut html, "Fingerprinting", &["Script", "URL", "Categories", "APIs", "Calls", "Likely fingerprinting"], self.fingerprinting.iter().map(|script| vec![
+            script.script.to_string(),
+            or_dash(script.script_url.as_deref()),
+            script.categories.iter().map(|category| format!("{:?}", category)).collect::<Vec<_>>().join(", "),
+            script.apis.join(", "),
+            script.calls.to_string(),
+            if script.likely_fingerprinting { "yes" } else { "no" }.to_string(),
+        ]).collect());
+        write_table(&mut html, "Builtin tampering", &["Script", "URL", "Builtin", "API", "Later callers"], self.builtin_tampering.iter().map(|tampering| vec![
+            tampering.script.to_string(),
+            or_dash(tampering.script_url.as_deref()),
+            tampering.builtin.clone(),
+            or_dash(tampering.api.as_deref()),
+            tampering.later_callers.len().to_string(),
+        ]).collect());
+        write_table(&mut html, "Storage", &["Area", "Key", "Writers", "Readers", "Values", "Third-party access"], self.storage.iter().map(|usage| vec![
+            format!("{:?}", usage.area),
+            usage.key.clone(),
+            usage.writers.len().to_string(),
+            usage.readers.len().to_string(),
+            usage.values.len().to_string(),
+            if usage.third_party_access { "yes" } else { "no" }.to_string(),
+        ]).collect());
+        write_table(&mut html, "Cookie syncs", &["Area", "Key", "Identifier", "Domain", "URL"], self.cookie_syncs.iter().map(|sync| vec![
+            format!("{:?}", sync.area),
+            sync.key.clone(),
+            sync.identifier.clone(),
+            or_dash(sync.domain.as_deref()),
+            sync.url.clone(),
+        ]).collect());
+        write_table(&mut html, "Blocked resources", &["URL", "Shield", "Rule"], self.blocked.iter().map(|blocked| vec![
+            or_dash(blocked.url.as_deref()),
+            blocked.shield.map(|shield| format!("{:?}", shield)).unwrap_or_else(|| "-".to_string()),
+            or_dash(blocked.rule.as_deref()),
+        ]).collect());
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_privacy_report_html() {
+        let mut builder = PageGraphBuilder::new("https://example.com/?q=<b>");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, Some("https://fp.net/fp.js"), "");
+        builder.call_web_api(script, "HTMLCanvasElement.toDataURL", None);
+        let graph = builder.build();
+
+        let report = privacy(&graph);
+        assert_eq!(report.fingerprinting.len(), 1);
+        let html = report.to_html();
+        assert!(html.contains("<title>Privacy report for https://example.com/?q=&lt;b&gt;</title>"));
+        assert!(html.contains("<td>https://fp.net/fp.js</td>"));
+        assert!(html.contains("<h2>Cookie syncs (0)</h2>"));
+    }
+}
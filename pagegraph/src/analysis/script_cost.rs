@@ -0,0 +1,123 @@
+//! Estimation of the main-thread work done by each script, from the actions attributed to it.
+//!
+//! PageGraph doesn't record how long scripts ran, but every Web API call, DOM mutation, and
+//! request made by a script is recorded as an edge from that script. The number of these edges,
+//! and how widely their timestamps are spread over the page load, are used as a proxy for the
+//! script's burden on the main thread.
+
+use std::collections::BTreeSet;
+
+use crate::filter;
+use crate::graph::{NodeId, PageGraph};
+use crate::types::{EdgeKind, NodeKind, NodeType};
+
+/// The actions attributed to a single script.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ScriptCost {
+    pub script: NodeId,
+    pub script_url: Option<String>,
+    pub third_party: Option<bool>,
+    /// Every action the script performed, including those not in any of the categories below.
+    pub actions: usize,
+    pub api_calls: usize,
+    pub dom_mutations: usize,
+    pub requests: usize,
+    pub storage_accesses: usize,
+    /// Timestamp of the script's first action, in milliseconds relative to the start of the page
+    /// load.
+    pub first_action: Option<isize>,
+    /// Timestamp of the script's last action.
+    pub last_action: Option<isize>,
+    /// The time from the first action to the last.
+    pub active_span_ms: isize,
+    /// The number of distinct milliseconds in which the script performed any action. Scripts
+    /// active over many separate moments, like polling timers, have a high count even with few
+    /// actions in total.
+    pub active_ms: usize,
+}
+
+impl PageGraph {
+    /// Returns the actions attributed to the given script node. Panics if the node isn't a
+    /// script.
+    pub fn script_cost(&self, script: NodeId) -> ScriptCost {
+        let node = self.nodes.get(&script).unwrap_or_else(|| panic!("Node {} could not be found in the graph", script));
+        let script_url = match &node.node_type {
+            NodeType::Script { url, .. } => url.clone(),
+            _ => panic!("Node {} is not a script", script),
+        };
+
+        let mut cost = ScriptCost {
+            script,
+            third_party: script_url.as_ref().and_then(|url| self.is_third_party(url)),
+            script_url: script_url.map(|url| url.to_string()),
+            actions: 0,
+            api_calls: 0,
+            dom_mutations: 0,
+            requests: 0,
+            storage_accesses: 0,
+            first_action: None,
+            last_action: None,
+            active_span_ms: 0,
+            active_ms: 0,
+        };
+        let mut timestamps = BTreeSet::new();
+        for edge in self.outgoing_edges(node) {
+            cost.actions += 1;
+            match edge.edge_type.kind() {
+                EdgeKind::JsCall => cost.api_calls += 1,
+                EdgeKind::CreateNode | EdgeKind::InsertNode | EdgeKind::RemoveNode | EdgeKind::DeleteNode |
+                EdgeKind::SetAttribute | EdgeKind::DeleteAttribute | EdgeKind::TextChange | EdgeKind::AttachShadow => cost.dom_mutations += 1,
+                EdgeKind::RequestStart => cost.requests += 1,
+                EdgeKind::StorageSet | EdgeKind::ReadStorageCall | EdgeKind::DeleteStorage | EdgeKind::ClearStorage => cost.storage_accesses += 1,
+                _ => (),
+            }
+            timestamps.extend(edge.edge_timestamp);
+        }
+        cost.first_action = timestamps.iter().next().copied();
+        cost.last_action = timestamps.iter().next_back().copied();
+        cost.active_span_ms = cost.last_action.zip(cost.first_action).map(|(last, first)| last - first).unwrap_or(0);
+        cost.active_ms = timestamps.len();
+        cost
+    }
+
+    /// Returns the costs of every script in the graph, from the most actions to the fewest.
+    pub fn script_costs(&self) -> Vec<ScriptCost> {
+        let mut costs = self.filter_nodes(filter::node_kind(NodeKind::Script))
+            .map(|node| self.script_cost(node.id))
+            .collect::<Vec<_>>();
+        costs.sort_by_key(|cost| (std::cmp::Reverse(cost.actions), std::cmp::Reverse(cost.active_ms), cost.script));
+        costs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_script_costs() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let first = builder.add_element(parser, root, "script");
+        let second = builder.add_element(parser, root, "script");
+        let tracker = builder.add_script(first, Some("https://tracker.net/t.js"), "");
+        let app = builder.add_script(second, None, "");
+        builder.call_web_api(tracker, "Navigator.userAgent", None);
+        builder.advance_time(1000);
+        builder.call_web_api(tracker, "Navigator.userAgent", None);
+        builder.add_request(tracker, "https://tracker.net/beacon", RequestType::AJAX);
+        builder.add_element(app, root, "div");
+        let graph = builder.build();
+
+        let costs = graph.script_costs();
+        assert_eq!(costs.iter().map(|cost| cost.script).collect::<Vec<_>>(), vec![tracker, app]);
+        assert_eq!((costs[0].actions, costs[0].api_calls, costs[0].requests, costs[0].active_ms), (3, 2, 1, 3));
+        assert!(costs[0].active_span_ms > 1000);
+        assert_eq!(costs[0].third_party, Some(true));
+        // Creating and inserting the element
+        assert_eq!((costs[1].actions, costs[1].dom_mutations, costs[1].active_span_ms), (2, 2, 1));
+    }
+}
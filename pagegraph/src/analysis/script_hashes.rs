@@ -0,0 +1,159 @@
+//! Identification of scripts by the hash of their source text.
+//!
+//! The same script is often served from several URLs, whether from different CDNs, with
+//! cache-busting query parameters, or inlined into the page. Hashing the recorded source lets
+//! these be recognized as a single artifact, both within a graph and across a corpus of graphs.
+//! Scripts without recorded source text are not hashed.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::graph::{Node, NodeId, PageGraph};
+use crate::types::NodeType;
+
+/// A hash of a script's source text.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct ScriptHash(u64);
+
+impl ScriptHash {
+    pub fn of_source(source: &str) -> Self {
+        Self(seahash::hash(source.as_bytes()))
+    }
+}
+
+impl std::fmt::Display for ScriptHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl serde::Serialize for ScriptHash {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for ScriptHash {
+    fn schema_name() -> String {
+        "ScriptHash".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, SchemaObject, StringValidation};
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation { pattern: Some("^[0-9a-f]{16}$".to_string()), ..Default::default() })),
+            ..Default::default()
+        }.into()
+    }
+}
+
+impl Node {
+    /// The hash of this node's source text, for [`Script`](NodeType::Script) nodes with recorded
+    /// source.
+    pub fn script_hash(&self) -> Option<ScriptHash> {
+        match &self.node_type {
+            NodeType::Script { source, .. } if !source.is_empty() => Some(ScriptHash::of_source(source)),
+            _ => None,
+        }
+    }
+}
+
+impl PageGraph {
+    /// Groups every script with recorded source text by its hash. Scripts within each group are
+    /// sorted by node id.
+    pub fn scripts_by_hash(&self) -> BTreeMap<ScriptHash, Vec<NodeId>> {
+        let mut scripts = BTreeMap::<_, Vec<_>>::new();
+        self.nodes.values().for_each(|node| if let Some(hash) = node.script_hash() {
+            scripts.entry(hash).or_default().push(node.id);
+        });
+        scripts.values_mut().for_each(|nodes| nodes.sort());
+        scripts
+    }
+}
+
+/// Every occurrence of a single script across a corpus.
+#[derive(Debug, Default, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CorpusScript {
+    /// The URLs the script was fetched from. Inline occurrences have no URL.
+    pub urls: BTreeSet<String>,
+    /// The top-level URLs of the pages that executed the script.
+    pub pages: BTreeSet<String>,
+    /// Whether the script was executed inline on any page.
+    pub inline: bool,
+    /// The total number of script nodes with this hash.
+    pub occurrences: usize,
+}
+
+/// An index of scripts by hash across many graphs.
+#[derive(Debug, Default)]
+pub struct ScriptCorpus {
+    scripts: HashMap<ScriptHash, CorpusScript>,
+}
+
+impl ScriptCorpus {
+    /// Adds every hashed script in the graph to the corpus.
+    pub fn add_graph(&mut self, graph: &PageGraph) {
+        graph.nodes.values().for_each(|node| if let Some(hash) = node.script_hash() {
+            let script = self.scripts.entry(hash).or_default();
+            match node.node_type.url() {
+                Some(url) => { script.urls.insert(url.to_string()); }
+                None => script.inline = true,
+            }
+            script.pages.insert(graph.desc.url.to_string());
+            script.occurrences += 1;
+        });
+    }
+
+    pub fn get(&self, hash: ScriptHash) -> Option<&CorpusScript> {
+        self.scripts.get(&hash)
+    }
+
+    /// Returns every script executed on at least `min_pages` pages, from the most to the fewest
+    /// pages.
+    pub fn shared_scripts(&self, min_pages: usize) -> Vec<(ScriptHash, &CorpusScript)> {
+        let mut shared = self.scripts.iter()
+            .filter(|(_, script)| script.pages.len() >= min_pages)
+            .map(|(hash, script)| (*hash, script))
+            .collect::<Vec<_>>();
+        shared.sort_by(|(a_hash, a), (b_hash, b)| b.pages.len().cmp(&a.pages.len()).then_with(|| a_hash.cmp(b_hash)));
+        shared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_scripts_by_hash() {
+        let build = |url: &str| {
+            let mut builder = PageGraphBuilder::new(url);
+            let parser = builder.parser();
+            let root = builder.dom_root();
+            let first = builder.add_element(parser, root, "script");
+            let second = builder.add_element(parser, root, "script");
+            let third = builder.add_element(parser, root, "script");
+            let cdn = builder.add_script(first, Some("https://cdn-a.net/t.js"), "track()");
+            let mirror = builder.add_script(second, Some("https://cdn-b.net/t.js?v=2"), "track()");
+            builder.add_script(third, None, "");
+            (builder.build(), cdn, mirror)
+        };
+        let (graph, cdn, mirror) = build("https://example.com/");
+
+        let scripts = graph.scripts_by_hash();
+        assert_eq!(scripts.len(), 1);
+        let mut expected = vec![cdn, mirror];
+        expected.sort();
+        assert_eq!(scripts.get(&ScriptHash::of_source("track()")), Some(&expected));
+
+        let mut corpus = ScriptCorpus::default();
+        corpus.add_graph(&graph);
+        corpus.add_graph(&build("https://example.org/").0);
+        let shared = corpus.shared_scripts(2);
+        assert_eq!(shared.len(), 1);
+        assert_eq!((shared[0].1.urls.len(), shared[0].1.occurrences, shared[0].1.inline), (2, 4, false));
+    }
+}
@@ -0,0 +1,327 @@
+//! Analysis of the resources that Brave Shields blocked during the recorded page load.
+//!
+//! Blocks are recorded as [`ResourceBlock`](EdgeType::ResourceBlock) edges from the node
+//! responsible for the block to the blocked resource. That is either a shield node, or a filter
+//! node which is itself connected to a shield node by [`Filter`](EdgeType::Filter) or
+//! [`Shield`](EdgeType::Shield) edges.
+//!
+//! The effect of Shields on a page can also be measured by comparing two recordings of the same
+//! URL, one with Shields enabled and one without, using [`compare_shields`].
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::graph::{EdgeId, NodeId, PageGraph};
+use crate::graph_algos::domain_of_url;
+use crate::types::{EdgeType, NodeKind, NodeType, Url};
+
+/// Activity that would have happened if a blocked resource had been loaded.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreventedActivity {
+    /// A `<script>` element requested the blocked resource, and never executed a script.
+    ScriptExecution { element: NodeId },
+    /// A frame element requested the blocked resource, and never loaded a document.
+    FrameLoad { element: NodeId },
+}
+
+/// A resource blocked by Brave Shields.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BlockedResource {
+    pub resource: NodeId,
+    pub url: Option<Url>,
+    /// The [`ResourceBlock`](EdgeType::ResourceBlock) edge recording the block.
+    pub block_edge: EdgeId,
+    pub timestamp: Option<isize>,
+    /// The shield responsible for the block, if it could be determined.
+    pub shield: Option<NodeKind>,
+    /// The filter list rule responsible for the block, if one was recorded.
+    pub rule: Option<String>,
+    /// Requests started for the blocked resource.
+    pub requests: Vec<EdgeId>,
+    /// The nodes that started requests for the blocked resource.
+    pub initiators: Vec<NodeId>,
+    pub prevented: Vec<PreventedActivity>,
+}
+
+fn is_shield(kind: NodeKind) -> bool {
+    matches!(kind, NodeKind::AdsShield | NodeKind::TrackersShield | NodeKind::JavascriptShield | NodeKind::FingerprintingShield | NodeKind::FingerprintingV2Shield)
+}
+
+impl PageGraph {
+    /// Finds the shield that the given blocking node belongs to, by walking back along `Filter`
+    /// and `Shield` edges.
+    pub(crate) fn shield_of_blocker(&self, blocker: NodeId) -> Option<NodeKind> {
+        let mut to_check = vec![blocker];
+        let mut checked = HashSet::new();
+        while let Some(node) = to_check.pop() {
+            if !checked.insert(node) {
+                continue;
+            }
+            let node = self.nodes.get(&node).unwrap();
+            if is_shield(node.node_type.kind()) {
+                return Some(node.node_type.kind());
+            }
+            self.incoming_edges(node)
+                .filter(|edge| matches!(edge.edge_type, EdgeType::Filter {} | EdgeType::Shield {}))
+                .for_each(|edge| to_check.push(edge.source));
+        }
+        None
+    }
+
+    fn prevented_activity_of(&self, initiator: NodeId) -> Option<PreventedActivity> {
+        let initiator = self.nodes.get(&initiator).unwrap();
+        let tag_name = initiator.node_type.tag_name()?.to_ascii_lowercase();
+        let has_outgoing = |edge_kind: fn(&EdgeType) -> bool| self.outgoing_edges(initiator).any(|edge| edge_kind(&edge.edge_type));
+        match &tag_name[..] {
+            "script" if !has_outgoing(|edge_type| matches!(edge_type, EdgeType::Execute {})) => Some(PreventedActivity::ScriptExecution { element: initiator.id }),
+            "iframe" | "frame" if !has_outgoing(|edge_type| matches!(edge_type, EdgeType::CrossDom {})) => Some(PreventedActivity::FrameLoad { element: initiator.id }),
+            _ => None,
+        }
+    }
+
+    /// Returns every resource blocked by Brave Shields, in the order the blocks occurred.
+    pub fn blocked_resources(&self) -> Vec<BlockedResource> {
+        let mut blocked = self.edges.values()
+            .filter(|edge| matches!(edge.edge_type, EdgeType::ResourceBlock {}))
+            .map(|edge| {
+                let resource = self.target_node(edge);
+                let rule = match &self.source_node(edge).node_type {
+                    NodeType::AdFilter { rule } => Some(rule.clone()),
+                    _ => None,
+                };
+
+                let mut requests = self.incoming_edges(resource)
+                    .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+                    .collect::<Vec<_>>();
+                requests.sort_by_key(|edge| (edge.edge_timestamp, edge.id));
+                let mut initiators = requests.iter().map(|edge| edge.source).collect::<Vec<_>>();
+                initiators.sort();
+                initiators.dedup();
+                let prevented = initiators.iter().filter_map(|initiator| self.prevented_activity_of(*initiator)).collect();
+
+                BlockedResource {
+                    resource: resource.id,
+                    url: resource.node_type.url().cloned(),
+                    block_edge: edge.id,
+                    timestamp: edge.edge_timestamp,
+                    shield: self.shield_of_blocker(edge.source),
+                    rule,
+                    requests: requests.into_iter().map(|edge| edge.id).collect(),
+                    initiators,
+                    prevented,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        blocked.sort_by_key(|blocked| (blocked.timestamp, blocked.block_edge));
+        blocked
+    }
+}
+
+/// The number of some kind of item in each of the two compared recordings.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ItemCounts {
+    pub shields_off: usize,
+    pub shields_on: usize,
+    /// The number of items from the recording without Shields that have no counterpart in the
+    /// recording with Shields.
+    pub removed: usize,
+}
+
+impl ItemCounts {
+    fn of<K: Eq + Hash>(shields_off: impl Iterator<Item = K>, shields_on: impl Iterator<Item = K>) -> Self {
+        let mut remaining = HashMap::new();
+        let mut counts = Self { shields_off: 0, shields_on: 0, removed: 0 };
+        shields_on.for_each(|key| {
+            counts.shields_on += 1;
+            *remaining.entry(key).or_insert(0usize) += 1;
+        });
+        shields_off.for_each(|key| {
+            counts.shields_off += 1;
+            match remaining.get_mut(&key) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => counts.removed += 1,
+            }
+        });
+        counts
+    }
+}
+
+/// A comparison of two recordings of the same URL, one with Shields enabled and one without.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ShieldsComparison {
+    pub requests: ItemCounts,
+    pub scripts: ItemCounts,
+    pub storage_writes: ItemCounts,
+    pub dom_nodes: ItemCounts,
+    /// URLs requested without Shields, but never requested with Shields, sorted and deduplicated.
+    pub removed_request_urls: Vec<String>,
+    /// Resources blocked in the recording with Shields.
+    pub blocked: Vec<BlockedResource>,
+    /// Requests that were not blocked in the recording with Shields, despite Shields blocking
+    /// other resources from the same domain.
+    pub leaked_requests: Vec<EdgeId>,
+    /// Scripts that were executed in the recording with Shields, despite Shields blocking
+    /// resources from the same domain.
+    pub leaked_scripts: Vec<NodeId>,
+}
+
+impl PageGraph {
+    fn is_blocked(&self, resource: NodeId) -> bool {
+        self.incoming_edges(self.nodes.get(&resource).unwrap()).any(|edge| matches!(edge.edge_type, EdgeType::ResourceBlock {}))
+    }
+
+    /// URLs of every request that was not blocked.
+    fn requested_urls(&self) -> impl Iterator<Item = &Url> {
+        self.edges.values()
+            .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+            .filter(move |edge| !self.is_blocked(edge.target))
+            .filter_map(move |edge| self.target_node(edge).node_type.url())
+    }
+
+    /// Scripts are identified by URL, or by their source text for inline scripts.
+    fn script_keys(&self) -> impl Iterator<Item = &str> {
+        self.nodes.values().filter_map(|node| match &node.node_type {
+            NodeType::Script { url: Some(url), .. } => Some(&url[..]),
+            NodeType::Script { source, .. } => Some(&source[..]),
+            _ => None,
+        })
+    }
+
+    fn storage_write_keys(&self) -> impl Iterator<Item = (NodeKind, &str)> {
+        self.edges.values().filter_map(move |edge| match &edge.edge_type {
+            EdgeType::StorageSet { key, .. } => Some((self.target_node(edge).node_type.kind(), &key[..])),
+            _ => None,
+        })
+    }
+
+    fn dom_node_keys(&self) -> impl Iterator<Item = Option<&str>> {
+        self.nodes.values()
+            .filter(|node| matches!(node.node_type.kind(), NodeKind::HtmlElement | NodeKind::TextNode))
+            .map(|node| node.node_type.tag_name())
+    }
+}
+
+/// Compares two recordings of the same URL, one made without Shields and one with Shields
+/// enabled.
+pub fn compare_shields(shields_off: &PageGraph, shields_on: &PageGraph) -> ShieldsComparison {
+    let on_urls = shields_on.requested_urls().collect::<HashSet<_>>();
+    let mut removed_request_urls = shields_off.requested_urls()
+        .filter(|url| !on_urls.contains(url))
+        .map(|url| url.to_string())
+        .collect::<Vec<_>>();
+    removed_request_urls.sort();
+    removed_request_urls.dedup();
+
+    let blocked = shields_on.blocked_resources();
+    let blocked_domains = blocked.iter()
+        .filter_map(|blocked| blocked.url.as_ref().and_then(domain_of_url))
+        .collect::<HashSet<_>>();
+    let is_blocked_domain = |url: &Url| domain_of_url(url).map(|domain| blocked_domains.contains(&domain)).unwrap_or(false);
+
+    let mut leaked_requests = shields_on.edges.values()
+        .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+        .filter(|edge| !shields_on.is_blocked(edge.target))
+        .filter(|edge| shields_on.target_node(edge).node_type.url().map(is_blocked_domain).unwrap_or(false))
+        .map(|edge| edge.id)
+        .collect::<Vec<_>>();
+    leaked_requests.sort();
+    let mut leaked_scripts = shields_on.nodes.values()
+        .filter(|node| matches!(&node.node_type, NodeType::Script { url: Some(url), .. } if is_blocked_domain(url)))
+        .map(|node| node.id)
+        .collect::<Vec<_>>();
+    leaked_scripts.sort();
+
+    ShieldsComparison {
+        requests: ItemCounts::of(shields_off.requested_urls(), shields_on.requested_urls()),
+        scripts: ItemCounts::of(shields_off.script_keys(), shields_on.script_keys()),
+        storage_writes: ItemCounts::of(shields_off.storage_write_keys(), shields_on.storage_write_keys()),
+        dom_nodes: ItemCounts::of(shields_off.dom_node_keys(), shields_on.dom_node_keys()),
+        removed_request_urls,
+        blocked,
+        leaked_requests,
+        leaked_scripts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_blocked_resources() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let shields = builder.add_node(NodeType::BraveShields {});
+        let ads_shield = builder.add_node(NodeType::AdsShield {});
+        builder.add_edge(shields, ads_shield, EdgeType::Shield {});
+        let filter = builder.add_node(NodeType::AdFilter { rule: "||ads.net^".to_string() });
+        builder.add_edge(ads_shield, filter, EdgeType::Filter {});
+
+        let element = builder.add_element(parser, root, "script");
+        let request = builder.add_request(element, "https://ads.net/ad.js", RequestType::Script);
+        let resource = builder.resource_of(request);
+        let block = builder.add_edge(filter, resource, EdgeType::ResourceBlock {});
+        builder.fail_request(request);
+
+        let loaded = builder.add_element(parser, root, "script");
+        builder.add_script(loaded, Some("https://example.com/app.js"), "");
+        let graph = builder.build();
+
+        let blocked = graph.blocked_resources();
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].resource, resource);
+        assert_eq!(blocked[0].url.as_deref(), Some("https://ads.net/ad.js"));
+        assert_eq!(blocked[0].block_edge, block);
+        assert_eq!(blocked[0].shield, Some(NodeKind::AdsShield));
+        assert_eq!(blocked[0].rule.as_deref(), Some("||ads.net^"));
+        assert_eq!(blocked[0].initiators, vec![element]);
+        assert_eq!(blocked[0].prevented, vec![PreventedActivity::ScriptExecution { element }]);
+    }
+
+    #[test]
+    fn test_compare_shields() {
+        let record = |shields: bool| {
+            let mut builder = PageGraphBuilder::new("https://example.com/");
+            let parser = builder.parser();
+            let root = builder.dom_root();
+            let element = builder.add_element(parser, root, "script");
+            let request = builder.add_request(element, "https://ads.net/ad.js", RequestType::Script);
+            if shields {
+                let ads_shield = builder.add_node(NodeType::AdsShield {});
+                let resource = builder.resource_of(request);
+                builder.add_edge(ads_shield, resource, EdgeType::ResourceBlock {});
+                builder.fail_request(request);
+            } else {
+                let script = builder.add_script(element, Some("https://ads.net/ad.js"), "");
+                let cookie_jar = builder.add_node(NodeType::CookieJar {});
+                builder.add_edge(script, cookie_jar, EdgeType::StorageSet { key: "uid".to_string(), value: None });
+                builder.add_element(script, root, "div");
+            }
+            let image = builder.add_element(parser, root, "img");
+            builder.add_request(image, "https://ads.net/pixel.gif", RequestType::Image);
+            builder.build()
+        };
+        let shields_off = record(false);
+        let shields_on = record(true);
+
+        let comparison = compare_shields(&shields_off, &shields_on);
+        assert_eq!(comparison.requests, ItemCounts { shields_off: 2, shields_on: 1, removed: 1 });
+        assert_eq!(comparison.scripts, ItemCounts { shields_off: 1, shields_on: 0, removed: 1 });
+        assert_eq!(comparison.storage_writes, ItemCounts { shields_off: 1, shields_on: 0, removed: 1 });
+        assert_eq!(comparison.dom_nodes.removed, 1);
+        assert_eq!(comparison.removed_request_urls, vec!["https://ads.net/ad.js".to_string()]);
+        assert_eq!(comparison.blocked.len(), 1);
+        assert_eq!(comparison.leaked_requests.len(), 1);
+        assert_eq!(shields_on.target_node(shields_on.edges.get(&comparison.leaked_requests[0]).unwrap()).node_type.url().map(|url| url.as_str()), Some("https://ads.net/pixel.gif"));
+        assert!(comparison.leaked_scripts.is_empty());
+    }
+}
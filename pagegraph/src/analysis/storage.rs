@@ -0,0 +1,272 @@
+//! Summaries of how scripts use cookies and Web Storage, and the cookies left at the end of the
+//! page load.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::analysis::origin_of_url;
+use crate::graph::{HasFrameId, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum StorageArea {
+    Cookies,
+    LocalStorage,
+    SessionStorage,
+}
+
+impl StorageArea {
+    pub(crate) fn of(node_type: &NodeType) -> Option<Self> {
+        match node_type {
+            NodeType::CookieJar {} => Some(Self::Cookies),
+            NodeType::LocalStorage {} => Some(Self::LocalStorage),
+            NodeType::SessionStorage {} => Some(Self::SessionStorage),
+            _ => None,
+        }
+    }
+}
+
+/// Every recorded use of a single key in a storage area.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct StorageKeyUsage {
+    pub area: StorageArea,
+    /// The storage area node, which is separate for each frame.
+    pub storage: NodeId,
+    pub key: String,
+    /// Scripts that wrote to or deleted the key, sorted by node id.
+    pub writers: Vec<NodeId>,
+    /// Scripts that read the key, sorted by node id.
+    pub readers: Vec<NodeId>,
+    /// Every distinct value written to the key, in the order they were written.
+    pub values: Vec<String>,
+    /// Whether any script accessing the key was fetched from a third-party URL.
+    pub third_party_access: bool,
+}
+
+/// A cookie set by a script and not deleted by the end of the page load.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Cookie {
+    /// The origin of the frame the cookie was set from.
+    pub origin: Option<String>,
+    pub name: String,
+    pub value: String,
+    /// The `Domain` attribute, if one was given.
+    pub domain: Option<String>,
+    /// The `Path` attribute, if one was given.
+    pub path: Option<String>,
+    /// The script that last set the cookie.
+    pub setter: NodeId,
+    pub setter_url: Option<String>,
+    /// When the cookie was last set, in milliseconds relative to the start of the page load.
+    pub set_time: Option<isize>,
+}
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// Parses a cookie date, like `Thu, 01 Jan 1970 00:00:00 GMT`, into milliseconds since the Unix
+/// epoch.
+fn parse_cookie_date(date: &str) -> Option<i64> {
+    let tokens = date.split([' ', ',', '-', ':']).filter(|token| !token.is_empty()).collect::<Vec<_>>();
+    let month = tokens.iter().find_map(|token| MONTHS.iter().position(|month| token.to_ascii_lowercase().starts_with(month)))? as i64 + 1;
+    let numbers = tokens.iter().filter_map(|token| token.parse::<i64>().ok()).collect::<Vec<_>>();
+    let (day, year, hours, minutes, seconds) = match numbers[..] {
+        [day, year, hours, minutes, seconds, ..] => (day, year, hours, minutes, seconds),
+        [day, year, ..] => (day, year, 0, 0, 0),
+        _ => return None,
+    };
+    let year = if year < 100 { year + if year < 70 { 2000 } else { 1900 } } else { year };
+    // Days since the epoch in the proleptic Gregorian calendar.
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(((days * 24 + hours) * 60 + minutes) * 60_000 + seconds * 1000)
+}
+
+/// The parsed assignment to `document.cookie` recorded in a [`StorageSet`](EdgeType::StorageSet)
+/// edge: the value, and the `Domain` and `Path` attributes. `None` if the assignment deletes the
+/// cookie, by having already expired at `now`, in milliseconds since the Unix epoch.
+fn parse_cookie_assignment(key: &str, assignment: &str, now: i64) -> Option<(String, Option<String>, Option<String>)> {
+    let mut parts = assignment.split(';').map(|part| part.trim());
+    let first = parts.next().unwrap_or("");
+    // Values may be recorded with or without the cookie's name.
+    let value = first.strip_prefix(key).and_then(|value| value.strip_prefix('=')).unwrap_or(first).to_string();
+    let (mut domain, mut path) = (None, None);
+    for part in parts {
+        let (name, attribute) = part.split_once('=').unwrap_or((part, ""));
+        match name.trim().to_ascii_lowercase().as_str() {
+            "domain" => domain = Some(attribute.trim().to_string()),
+            "path" => path = Some(attribute.trim().to_string()),
+            "max-age" if attribute.trim().parse::<i64>().map(|max_age| max_age <= 0).unwrap_or(false) => return None,
+            "expires" if parse_cookie_date(attribute).map(|expires| expires <= now).unwrap_or(false) => return None,
+            _ => (),
+        }
+    }
+    Some((value, domain, path))
+}
+
+impl PageGraph {
+    /// Returns the cookies set by scripts that remain at the end of the page load, after applying
+    /// every assignment to and deletion from `document.cookie` in order. Sorted by origin, name,
+    /// domain, and path, which together identify a cookie.
+    ///
+    /// Only cookies set by scripts are recorded, so cookies set via HTTP headers are missing.
+    pub fn cookie_jar(&self) -> Vec<Cookie> {
+        let frame_urls = self.frame_stats().into_iter().map(|frame| (frame.frame_id, frame.url)).collect::<HashMap<_, _>>();
+        let mut jar = BTreeMap::<(Option<String>, String, Option<String>, Option<String>), Cookie>::new();
+
+        for edge in self.edges_chronological() {
+            let jar_node = self.target_node(edge);
+            if !matches!(jar_node.node_type, NodeType::CookieJar {}) {
+                continue;
+            }
+            let frame_id = jar_node.id.get_frame_id().unwrap_or(self.desc.frame_id);
+            let origin = frame_urls.get(&frame_id).cloned().flatten()
+                .or_else(|| jar_node.id.get_frame_id().is_none().then(|| self.desc.url.clone()))
+                .and_then(|url| origin_of_url(&url));
+            match &edge.edge_type {
+                EdgeType::StorageSet { key, value } => {
+                    let now = self.desc.time.start as i64 + edge.edge_timestamp.unwrap_or(0) as i64;
+                    match parse_cookie_assignment(key, value.as_deref().unwrap_or(""), now) {
+                        Some((value, domain, path)) => {
+                            let setter = self.source_node(edge);
+                            jar.insert((origin.clone(), key.clone(), domain.clone(), path.clone()), Cookie {
+                                origin,
+                                name: key.clone(),
+                                value,
+                                domain,
+                                path,
+                                setter: setter.id,
+                                setter_url: setter.node_type.url().map(|url| url.to_string()),
+                                set_time: edge.edge_timestamp,
+                            });
+                        }
+                        // Expiring a cookie deletes it for any domain and path it was set with.
+                        None => jar.retain(|(cookie_origin, name, _, _), _| cookie_origin != &origin || name != key),
+                    }
+                }
+                EdgeType::DeleteStorage { key } => jar.retain(|(cookie_origin, name, _, _), _| cookie_origin != &origin || name != key),
+                EdgeType::ClearStorage { .. } => jar.retain(|(cookie_origin, _, _, _), _| cookie_origin != &origin),
+                _ => (),
+            }
+        }
+
+        jar.into_values().collect()
+    }
+
+    /// Returns the usage of every key written to or read from cookies, `localStorage`, or
+    /// `sessionStorage`, sorted by storage area and key.
+    pub fn storage_usage(&self) -> Vec<StorageKeyUsage> {
+        let mut usage = BTreeMap::<(StorageArea, NodeId, String), StorageKeyUsage>::new();
+
+        for edge in self.edges_in_range(..).chain(self.edges.values().filter(|edge| edge.edge_timestamp.is_none())) {
+            let (storage, script, key, value, is_write) = match &edge.edge_type {
+                EdgeType::StorageSet { key, value } => (edge.target, edge.source, key, value.as_ref(), true),
+                EdgeType::DeleteStorage { key } => (edge.target, edge.source, key, None, true),
+                EdgeType::ReadStorageCall { key } => (edge.target, edge.source, key, None, false),
+                EdgeType::StorageReadResult { key, .. } => (edge.source, edge.target, key, None, false),
+                _ => continue,
+            };
+            let area = match StorageArea::of(&self.nodes.get(&storage).unwrap().node_type) {
+                Some(area) => area,
+                None => continue,
+            };
+            let is_third_party = self.nodes.get(&script).unwrap().node_type.url()
+                .and_then(|url| self.is_third_party(url))
+                .unwrap_or(false);
+
+            let key_usage = usage.entry((area, storage, key.clone())).or_insert_with(|| StorageKeyUsage {
+                area,
+                storage,
+                key: key.clone(),
+                writers: vec![],
+                readers: vec![],
+                values: vec![],
+                third_party_access: false,
+            });
+            if is_write {
+                key_usage.writers.push(script);
+            } else {
+                key_usage.readers.push(script);
+            }
+            if let Some(value) = value.filter(|value| !key_usage.values.contains(value)) {
+                key_usage.values.push(value.clone());
+            }
+            key_usage.third_party_access |= is_third_party;
+        }
+
+        usage.into_values().map(|mut key_usage| {
+            key_usage.writers.sort();
+            key_usage.writers.dedup();
+            key_usage.readers.sort();
+            key_usage.readers.dedup();
+            key_usage
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_storage_usage() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let first = builder.add_element(parser, root, "script");
+        let second = builder.add_element(parser, root, "script");
+        let tracker = builder.add_script(first, Some("https://tracker.net/t.js"), "");
+        let app = builder.add_script(second, None, "");
+        let cookies = builder.add_node(NodeType::CookieJar {});
+        let local_storage = builder.add_node(NodeType::LocalStorage {});
+
+        let set = |key: &str, value: &str| EdgeType::StorageSet { key: key.to_string(), value: Some(value.to_string()) };
+        builder.add_edge(tracker, cookies, set("uid", "abc"));
+        builder.add_edge(tracker, cookies, set("uid", "abc"));
+        builder.add_edge(app, local_storage, set("theme", "dark"));
+        builder.add_edge(local_storage, app, EdgeType::StorageReadResult { key: "theme".to_string(), value: Some("dark".to_string()) });
+        let graph = builder.build();
+
+        let usage = graph.storage_usage();
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[0].area, usage[0].key.as_str(), &usage[0].writers, &usage[0].values), (StorageArea::Cookies, "uid", &vec![tracker], &vec!["abc".to_string()]));
+        assert!(usage[0].third_party_access);
+        assert_eq!((usage[1].area, &usage[1].writers, &usage[1].readers), (StorageArea::LocalStorage, &vec![app], &vec![app]));
+        assert!(!usage[1].third_party_access);
+    }
+
+    #[test]
+    fn test_cookie_jar() {
+        let mut builder = PageGraphBuilder::new("https://example.com/page.html");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, Some("https://example.com/app.js"), "");
+        let cookies = builder.add_node(NodeType::CookieJar {});
+
+        let set = |key: &str, value: &str| EdgeType::StorageSet { key: key.to_string(), value: Some(value.to_string()) };
+        builder.add_edge(script, cookies, set("uid", "uid=first; path=/"));
+        builder.advance_time(10);
+        builder.add_edge(script, cookies, set("uid", "uid=second; path=/; domain=example.com"));
+        builder.add_edge(script, cookies, set("session", "session=abc"));
+        builder.add_edge(script, cookies, set("consent", "yes"));
+        builder.advance_time(10);
+        builder.add_edge(script, cookies, set("session", "session=; expires=Thu, 01 Jan 1970 00:00:00 GMT"));
+        builder.add_edge(script, cookies, EdgeType::DeleteStorage { key: "consent".to_string() });
+        let graph = builder.build();
+
+        let jar = graph.cookie_jar();
+        let cookies = jar.iter().map(|cookie| (cookie.name.as_str(), cookie.value.as_str(), cookie.domain.as_deref())).collect::<Vec<_>>();
+        assert_eq!(cookies, vec![("uid", "first", None), ("uid", "second", Some("example.com"))]);
+        assert!(jar.iter().all(|cookie| cookie.origin.as_deref() == Some("https://example.com") && cookie.setter == script));
+        assert!(jar[1].set_time > jar[0].set_time);
+
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), Some(1_445_412_480_000));
+    }
+}
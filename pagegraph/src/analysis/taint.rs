@@ -0,0 +1,186 @@
+//! Tracking of data from third-party network responses into the first-party DOM and storage.
+//!
+//! PageGraph doesn't record values as they flow through scripts, so taint is tracked at the
+//! granularity of graph items: once an element or script receives a third-party response, every
+//! later action it takes is treated as carrying that response's data. Taint propagates forwards
+//! in time, through
+//!
+//! - elements that receive a third-party response, like a `<script src>` element,
+//! - scripts executed by a tainted element or script,
+//! - elements created by a tainted script, and
+//! - scripts that read a storage key after a tainted script wrote to it.
+//!
+//! A flow is reported whenever a tainted script modifies the DOM of a first-party document, or
+//! writes to storage on behalf of one. Only timestamped edges are considered.
+
+use std::collections::HashMap;
+
+use crate::graph::{Edge, EdgeId, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType, Url};
+
+/// The kind of write through which tainted data reached the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum TaintSink {
+    DomWrite,
+    StorageWrite,
+}
+
+/// A path from a third-party response into the first-party DOM or storage.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TaintFlow {
+    /// The edge through which the third-party data entered the page. This is normally the
+    /// [`RequestComplete`](EdgeType::RequestComplete) edge for the response, or the
+    /// [`Execute`](EdgeType::Execute) edge of a third-party script whose response wasn't recorded.
+    pub source: EdgeId,
+    pub source_url: String,
+    /// The elements and scripts the taint passed through, from the first to receive the response
+    /// to the script that performed the write.
+    pub path: Vec<NodeId>,
+    pub sink: EdgeId,
+    pub sink_kind: TaintSink,
+    /// The DOM node or storage area that was written to.
+    pub target: NodeId,
+}
+
+#[derive(Clone)]
+struct Taint {
+    source: EdgeId,
+    source_url: String,
+    path: Vec<NodeId>,
+}
+
+impl Taint {
+    fn through(&self, node: NodeId) -> Self {
+        let mut taint = self.clone();
+        if taint.path.last() != Some(&node) {
+            taint.path.push(node);
+        }
+        taint
+    }
+}
+
+impl PageGraph {
+    /// Returns whether the given DOM node belongs to a first-party document. Documents without a
+    /// URL, like `about:blank` frames, are treated as belonging to their embedding page.
+    fn in_first_party_document(&self, node: NodeId) -> bool {
+        let node = self.nodes.get(&node).unwrap();
+        match self.document_of_actor(node) {
+            Some(document) => document.node_type.url().and_then(|url| self.is_third_party(url)) != Some(true),
+            None => false,
+        }
+    }
+
+    fn third_party_url_of(&self, node: NodeId) -> Option<&Url> {
+        self.nodes.get(&node).unwrap().node_type.url().filter(|url| self.is_third_party(url) == Some(true))
+    }
+
+    /// Follows data from third-party responses forwards through the graph, returning every flow
+    /// into the first-party DOM or storage in chronological order of the write.
+    pub fn taint_flows(&self) -> Vec<TaintFlow> {
+        let mut tainted = HashMap::<NodeId, Taint>::new();
+        let mut tainted_storage = HashMap::<(NodeId, String), Taint>::new();
+        let mut flows = vec![];
+
+        let report = |flows: &mut Vec<TaintFlow>, taint: &Taint, edge: &Edge, sink_kind| flows.push(TaintFlow {
+            source: taint.source,
+            source_url: taint.source_url.clone(),
+            path: taint.path.clone(),
+            sink: edge.id,
+            sink_kind,
+            target: edge.target,
+        });
+
+        for edge in self.edges_in_range(..) {
+            match &edge.edge_type {
+                EdgeType::RequestComplete { .. } => if let Some(url) = self.third_party_url_of(edge.source) {
+                    tainted.entry(edge.target).or_insert_with(|| Taint { source: edge.id, source_url: url.to_string(), path: vec![edge.target] });
+                },
+                EdgeType::Execute {} | EdgeType::ExecuteFromAttribute { .. } => {
+                    let taint = match (tainted.get(&edge.source), self.third_party_url_of(edge.target)) {
+                        (Some(taint), _) => taint.through(edge.target),
+                        (None, Some(url)) => Taint { source: edge.id, source_url: url.to_string(), path: vec![edge.target] },
+                        (None, None) => continue,
+                    };
+                    tainted.entry(edge.target).or_insert(taint);
+                }
+                EdgeType::CreateNode {} => if let Some(taint) = tainted.get(&edge.source) {
+                    let taint = taint.through(edge.target);
+                    tainted.entry(edge.target).or_insert(taint);
+                },
+                EdgeType::InsertNode { .. } | EdgeType::RemoveNode { .. } | EdgeType::SetAttribute { .. } | EdgeType::DeleteAttribute { .. } | EdgeType::TextChange {} => {
+                    let is_script = matches!(self.source_node(edge).node_type, NodeType::Script { .. });
+                    if let Some(taint) = tainted.get(&edge.source).filter(|_| is_script) {
+                        if self.in_first_party_document(edge.target) {
+                            report(&mut flows, taint, edge, TaintSink::DomWrite);
+                        }
+                    }
+                }
+                EdgeType::StorageSet { key, .. } => if let Some(taint) = tainted.get(&edge.source) {
+                    let source = self.source_node(edge);
+                    if self.document_of_actor(source).map(|document| self.in_first_party_document(document.id)).unwrap_or(false) {
+                        report(&mut flows, taint, edge, TaintSink::StorageWrite);
+                    }
+                    tainted_storage.insert((edge.target, key.clone()), taint.clone());
+                },
+                EdgeType::StorageReadResult { key, .. } => if let Some(taint) = tainted_storage.get(&(edge.source, key.clone())) {
+                    let taint = taint.through(edge.target);
+                    tainted.entry(edge.target).or_insert(taint);
+                },
+                _ => (),
+            }
+        }
+
+        flows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::{RequestType, ResourceType};
+
+    #[test]
+    fn test_taint_flows() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+
+        // A third-party script injects a script, which rewrites a first-party element
+        let element = builder.add_element(parser, body, "script");
+        let request = builder.add_request(element, "https://ads.net/loader.js", RequestType::Script);
+        let response = builder.complete_request(request, ResourceType::Script, Some(100));
+        builder.advance_time(10);
+        let loader = builder.add_script(element, Some("https://ads.net/loader.js"), "");
+        let injected_element = builder.add_element(loader, body, "script");
+        let injected = builder.add_script(injected_element, None, "");
+        let heading = builder.add_element(parser, body, "h1");
+        let write = builder.set_attribute(injected, heading, "class", "ad");
+        let storage = builder.add_node(NodeType::LocalStorage {});
+        let storage_write = builder.add_edge(injected, storage, EdgeType::StorageSet { key: "seen".to_string(), value: None });
+
+        // A first-party script that reads the tainted key afterwards is tainted too
+        builder.advance_time(10);
+        let first_party_element = builder.add_element(parser, body, "script");
+        let first_party = builder.add_script(first_party_element, Some("https://example.com/app.js"), "");
+        builder.add_element(first_party, body, "div");
+        builder.advance_time(10);
+        builder.add_edge(storage, first_party, EdgeType::StorageReadResult { key: "seen".to_string(), value: None });
+        builder.advance_time(10);
+        let late_write = builder.set_attribute(first_party, heading, "title", "x");
+        let graph = builder.build();
+
+        let flows = graph.taint_flows();
+        assert!(flows.iter().all(|flow| flow.source == response && flow.source_url == "https://ads.net/loader.js"));
+        // Inserting the injected script is itself a write to the first-party DOM
+        assert_eq!((flows[0].target, flows[0].sink_kind), (injected_element, TaintSink::DomWrite));
+        assert_eq!(flows[0].path, vec![element, loader]);
+        assert_eq!(flows[1..].iter().map(|flow| flow.sink).collect::<Vec<_>>(), vec![write, storage_write, late_write]);
+        assert_eq!(flows[1].path, vec![element, loader, injected_element, injected]);
+        assert_eq!(flows[2].sink_kind, TaintSink::StorageWrite);
+        assert_eq!(flows[3].path, vec![element, loader, injected_element, injected, first_party]);
+    }
+}
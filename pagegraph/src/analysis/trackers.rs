@@ -0,0 +1,172 @@
+//! Categorization of third parties using tracker lists in the format of Disconnect's
+//! [`services.json`](https://github.com/disconnectme/disconnect-tracking-protection).
+//!
+//! A Disconnect list maps each category to the entities in it, and each entity to the domains it
+//! owns. Hosts are matched against the list by walking up their domain suffixes, so that listing
+//! `tracker.com` also covers `cdn.tracker.com`.
+
+use std::collections::HashMap;
+
+use super::page_weight::ThirdPartyCost;
+use crate::types::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum TrackerCategory {
+    Advertising,
+    Analytics,
+    Content,
+    Cryptomining,
+    Fingerprinting,
+    Social,
+    Other,
+}
+
+impl TrackerCategory {
+    /// Maps a Disconnect category name to a category. Disconnect splits fingerprinting into
+    /// `FingerprintingInvasive` and `FingerprintingGeneral`, which are combined here.
+    pub fn from_disconnect(name: &str) -> Self {
+        match name {
+            "Advertising" => Self::Advertising,
+            "Analytics" => Self::Analytics,
+            "Content" => Self::Content,
+            "Cryptomining" => Self::Cryptomining,
+            "Fingerprinting" | "FingerprintingInvasive" | "FingerprintingGeneral" => Self::Fingerprinting,
+            "Social" => Self::Social,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The entity owning a tracker domain, and the categories it's listed under.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TrackerInfo {
+    pub entity: String,
+    /// Sorted, without duplicates.
+    pub categories: Vec<TrackerCategory>,
+}
+
+/// A tracker list, mapping domains to the entity that owns them and their categories.
+#[derive(Debug, Default)]
+pub struct TrackerCategories {
+    domains: HashMap<String, TrackerInfo>,
+}
+
+impl TrackerCategories {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lists `domain`, and its subdomains, as belonging to `entity` and being in `category`.
+    pub fn insert(&mut self, domain: &str, entity: &str, category: TrackerCategory) {
+        let info = self.domains.entry(domain.to_ascii_lowercase()).or_insert_with(|| TrackerInfo {
+            entity: entity.to_string(),
+            categories: vec![],
+        });
+        if let Err(index) = info.categories.binary_search(&category) {
+            info.categories.insert(index, category);
+        }
+    }
+
+    /// Returns the listing for the given host, or its closest listed parent domain.
+    pub fn lookup_host(&self, host: &str) -> Option<&TrackerInfo> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        std::iter::successors(Some(host.as_str()), |host| host.split_once('.').map(|(_, parent)| parent))
+            .find_map(|host| self.domains.get(host))
+    }
+
+    /// Returns the listing for the host of the given URL.
+    pub fn lookup_url(&self, url: &Url) -> Option<&TrackerInfo> {
+        self.lookup_host(url.parsed()?.host_str()?)
+    }
+
+    /// Returns a map from every listed domain to its entity, for use with
+    /// [`third_party_costs`](crate::graph::PageGraph::third_party_costs).
+    pub fn entities(&self) -> HashMap<String, String> {
+        self.domains.iter().map(|(domain, info)| (domain.clone(), info.entity.clone())).collect()
+    }
+
+    /// Fills in the categories of each third party, from every category any of its domains is
+    /// listed under.
+    pub fn categorize(&self, costs: &mut [ThirdPartyCost]) {
+        for cost in costs {
+            let mut categories = cost.domains.iter()
+                .filter_map(|domain| self.lookup_host(domain))
+                .flat_map(|info| info.categories.iter().copied())
+                .collect::<Vec<_>>();
+            categories.sort();
+            categories.dedup();
+            cost.categories = categories;
+        }
+    }
+}
+
+/// Maps each entity's name to its properties.
+type DisconnectEntities = HashMap<String, HashMap<String, DisconnectValue>>;
+
+#[derive(serde::Deserialize)]
+struct DisconnectList {
+    categories: HashMap<String, Vec<DisconnectEntities>>,
+}
+
+/// Entities map their homepage to their domains, but can also have other properties, like
+/// `"dnt": "eff"`, which are ignored.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum DisconnectValue {
+    Domains(Vec<String>),
+    Other(serde::de::IgnoredAny),
+}
+
+impl<'de> serde::Deserialize<'de> for TrackerCategories {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let list = DisconnectList::deserialize(deserializer)?;
+        let mut trackers = Self::new();
+        for (category, entities) in list.categories {
+            let category = TrackerCategory::from_disconnect(&category);
+            for (entity, properties) in entities.iter().flatten() {
+                for value in properties.values() {
+                    if let DisconnectValue::Domains(domains) = value {
+                        domains.iter().for_each(|domain| trackers.insert(domain, entity, category));
+                    }
+                }
+            }
+        }
+        Ok(trackers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disconnect_list() {
+        let list = r#"{
+            "license": "...",
+            "categories": {
+                "Advertising": [
+                    { "AdCo": { "https://adco.com/": ["adco.com", "adco-cdn.net"] } }
+                ],
+                "FingerprintingInvasive": [
+                    { "AdCo": { "https://adco.com/": ["adco.com"], "dnt": "eff" } }
+                ],
+                "Social": [
+                    { "Friends": { "https://friends.example/": ["friends.example"] } }
+                ]
+            }
+        }"#;
+        let trackers: TrackerCategories = serde_json::from_str(list).unwrap();
+
+        let info = trackers.lookup_url(&Url::from("https://pixel.ADCO.com/p.gif")).unwrap();
+        assert_eq!(info.entity, "AdCo");
+        assert_eq!(info.categories, vec![TrackerCategory::Advertising, TrackerCategory::Fingerprinting]);
+        assert!(trackers.lookup_host("notadco.com").is_none());
+
+        let mut costs = vec![ThirdPartyCost { name: "AdCo".to_string(), domains: vec!["adco-cdn.net".to_string()].into_iter().collect(), ..Default::default() }];
+        trackers.categorize(&mut costs);
+        assert_eq!(costs[0].categories, vec![TrackerCategory::Advertising]);
+        assert_eq!(trackers.entities().get("friends.example").map(|entity| entity.as_str()), Some("Friends"));
+    }
+}
@@ -0,0 +1,211 @@
+//! Analysis of WebSocket connections, which carry traffic that isn't visible to analyses based on
+//! matching ordinary resource requests.
+//!
+//! Connections are found from requests to `ws:` and `wss:` URLs, and from calls to the
+//! `WebSocket` constructor. Other Web API calls on `WebSocket` objects are classified by method
+//! name and attributed to the most recent connection opened by the same script.
+
+use std::sync::OnceLock;
+
+use crate::analysis::origin_of_url;
+use crate::graph::{Edge, EdgeId, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType, Url};
+
+/// A kind of activity on a WebSocket connection.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum WebSocketActivity {
+    Open,
+    Send,
+    Receive,
+    Close,
+    /// Any other call on a `WebSocket` object, with the name of the method.
+    Other(String),
+}
+
+impl WebSocketActivity {
+    /// Classifies a Web API method name, returning `None` if it is unrelated to WebSockets.
+    fn from_method(method: &str) -> Option<Self> {
+        let (interface, member) = match method.split_once('.') {
+            Some((interface, member)) => (interface, Some(member)),
+            None => (method, None),
+        };
+        if interface != "WebSocket" {
+            return None;
+        }
+        Some(match member {
+            None | Some("constructor") => Self::Open,
+            Some("send") => Self::Send,
+            Some("close") => Self::Close,
+            Some(member) if member.contains("message") => Self::Receive,
+            Some(member) => Self::Other(member.to_string()),
+        })
+    }
+}
+
+/// A single recorded interaction with a WebSocket connection.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct WebSocketEvent {
+    pub activity: WebSocketActivity,
+    pub edge: EdgeId,
+    pub timestamp: Option<isize>,
+    /// Serialized data sent, or arguments passed, if any were recorded.
+    pub data: Option<String>,
+}
+
+/// A WebSocket connection opened by the page.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct WebSocketConnection {
+    pub url: Url,
+    pub destination_origin: Option<String>,
+    pub third_party: Option<bool>,
+    /// The script that opened the connection, if it was opened by a script.
+    pub opening_script: Option<NodeId>,
+    pub opened_at: Option<isize>,
+    /// Every recorded interaction with the connection, in chronological order, including the
+    /// initial handshake request and the call that opened it.
+    pub events: Vec<WebSocketEvent>,
+}
+
+fn is_websocket_url(url: &Url) -> bool {
+    url.parsed().map(|url| url.scheme() == "ws" || url.scheme() == "wss").unwrap_or(false)
+}
+
+/// Extracts the first WebSocket URL from a serialized argument list.
+fn websocket_url_in_args(args: &str) -> Option<Url> {
+    static PATTERN: OnceLock<regex::Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| regex::Regex::new(r#"(?i)\bwss?://[^\s"',\]\)]+"#).unwrap());
+    pattern.find(args).map(|url| Url::from(url.as_str()))
+}
+
+impl PageGraph {
+    /// Returns every WebSocket connection opened by the page, in the order they were opened.
+    pub fn websocket_connections(&self) -> Vec<WebSocketConnection> {
+        let mut connections: Vec<WebSocketConnection> = vec![];
+
+        let new_connection = |url: Url, opening_script: Option<NodeId>, edge: &Edge, event: WebSocketEvent| WebSocketConnection {
+            destination_origin: origin_of_url(&url),
+            third_party: self.is_third_party(&url),
+            url,
+            opening_script,
+            opened_at: edge.edge_timestamp,
+            events: vec![event],
+        };
+
+        // Handshake requests
+        self.edges_chronological()
+            .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+            .filter(|edge| self.target_node(edge).node_type.url().map(is_websocket_url).unwrap_or(false))
+            .for_each(|edge| {
+                let initiator = self.source_node(edge);
+                let opening_script = if let NodeType::Script { .. } = initiator.node_type { Some(initiator.id) } else { None };
+                let url = self.target_node(edge).node_type.url().unwrap().clone();
+                connections.push(new_connection(url, opening_script, edge, WebSocketEvent {
+                    activity: WebSocketActivity::Open,
+                    edge: edge.id,
+                    timestamp: edge.edge_timestamp,
+                    data: None,
+                }));
+            });
+
+        // Web API calls, which are attributed to connections in chronological order
+        self.edges_chronological()
+            .filter_map(|edge| match (&edge.edge_type, &self.target_node(edge).node_type) {
+                (EdgeType::JsCall { args, .. }, NodeType::WebApi { method }) => WebSocketActivity::from_method(method).map(|activity| (edge, activity, args.clone())),
+                _ => None,
+            })
+            .for_each(|(edge, activity, data)| {
+                let script = edge.source;
+                let event = WebSocketEvent { activity, edge: edge.id, timestamp: edge.edge_timestamp, data };
+
+                if event.activity == WebSocketActivity::Open {
+                    let url = event.data.as_deref().and_then(websocket_url_in_args);
+                    // Prefer attaching the constructor call to the matching handshake request.
+                    let handshake = connections.iter_mut().find(|connection|
+                        connection.opening_script == Some(script) &&
+                        Some(&connection.url) == url.as_ref() &&
+                        !connection.events.iter().any(|event| matches!(self.edges.get(&event.edge).map(|edge| &edge.edge_type), Some(EdgeType::JsCall { .. })))
+                    );
+                    match (handshake, url) {
+                        (Some(connection), _) => {
+                            connection.opened_at = connection.opened_at.into_iter().chain(edge.edge_timestamp).min();
+                            connection.events.push(event);
+                        }
+                        (None, Some(url)) => connections.push(new_connection(url, Some(script), edge, event)),
+                        // A constructor call without a recognizable URL can't be matched to anything.
+                        (None, None) => (),
+                    }
+                } else {
+                    let connection = connections.iter_mut()
+                        .filter(|connection| connection.opening_script == Some(script))
+                        .filter(|connection| connection.opened_at <= edge.edge_timestamp)
+                        .max_by_key(|connection| connection.opened_at);
+                    if let Some(connection) = connection {
+                        connection.events.push(event);
+                    }
+                }
+            });
+
+        connections.iter_mut().for_each(|connection| connection.events.sort_by_key(|event| (event.timestamp, event.edge)));
+        connections.sort_by_key(|connection| connection.opened_at);
+        connections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_websocket_connections() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, Some("https://tracker.net/ws.js"), "new WebSocket(...)");
+        builder.call_web_api(script, "WebSocket", Some("[\"wss://collect.tracker.net/s\"]"));
+        builder.add_request(script, "wss://collect.tracker.net/s", RequestType::Unknown);
+        builder.call_web_api(script, "WebSocket.send", Some("[\"uid=1234\"]"));
+        builder.call_web_api(script, "WebSocket.close", None);
+        builder.call_web_api(script, "Document.cookie", None);
+        let graph = builder.build();
+
+        let connections = graph.websocket_connections();
+        assert_eq!(connections.len(), 1);
+        let connection = &connections[0];
+        assert_eq!(connection.url, "wss://collect.tracker.net/s");
+        assert_eq!(connection.destination_origin.as_deref(), Some("wss://collect.tracker.net"));
+        assert_eq!(connection.third_party, Some(true));
+        assert_eq!(connection.opening_script, Some(script));
+        assert_eq!(connection.events.iter().map(|event| event.activity.clone()).collect::<Vec<_>>(),
+            vec![WebSocketActivity::Open, WebSocketActivity::Open, WebSocketActivity::Send, WebSocketActivity::Close]);
+        assert_eq!(connection.events[2].data.as_deref(), Some("[\"uid=1234\"]"));
+    }
+
+    #[test]
+    fn test_untimestamped_constructor_call() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, None, "");
+        builder.add_request(script, "wss://example.com/live", RequestType::Unknown);
+        let constructor_call = builder.call_web_api(script, "WebSocket", Some("[\"wss://example.com/live\"]"));
+        let mut graph = builder.build();
+        graph.edges.get_mut(&constructor_call).unwrap().edge_timestamp = None;
+        let handshake_time = graph.edges.values()
+            .find(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+            .and_then(|edge| edge.edge_timestamp);
+
+        let connections = graph.websocket_connections();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].events.len(), 2);
+        assert!(handshake_time.is_some());
+        assert_eq!(connections[0].opened_at, handshake_time);
+    }
+}
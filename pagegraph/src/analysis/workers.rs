@@ -0,0 +1,211 @@
+//! Analysis of web workers, shared workers, and service workers, whose activity happens off of
+//! the main thread and outside of any document.
+//!
+//! Workers are found from [`Worker`](NodeType::Worker) nodes where the graph records them.
+//! Otherwise, they are inferred from calls to the `Worker` and `SharedWorker` constructors and to
+//! `ServiceWorkerContainer.register`, by matching the requested script URL against the graph's
+//! script nodes.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use crate::graph::{EdgeId, Node, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeKind, NodeType, WorkerType};
+
+/// A worker started by the page.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct WorkerInfo {
+    pub worker_type: WorkerType,
+    pub url: Option<String>,
+    /// The worker's node, if the graph records one.
+    pub worker_node: Option<NodeId>,
+    /// The script that started the worker.
+    pub spawning_script: Option<NodeId>,
+    /// Every script executed within the worker, including the top-level worker script.
+    pub scripts: Vec<NodeId>,
+    /// Requests started from within the worker.
+    pub requests: Vec<EdgeId>,
+    /// Reads and writes of cookies or storage performed from within the worker.
+    pub storage_accesses: Vec<EdgeId>,
+}
+
+fn worker_type_of_constructor(method: &str) -> Option<WorkerType> {
+    match method {
+        "Worker" | "Worker.constructor" => Some(WorkerType::Dedicated),
+        "SharedWorker" | "SharedWorker.constructor" => Some(WorkerType::Shared),
+        "ServiceWorkerContainer.register" => Some(WorkerType::Service),
+        _ => None,
+    }
+}
+
+/// Extracts the first string from a serialized argument list.
+fn first_string_arg(args: &str) -> Option<String> {
+    static PATTERN: OnceLock<regex::Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| regex::Regex::new(r#""((?:[^"\\]|\\.)*)""#).unwrap());
+    pattern.captures(args).map(|captures| captures[1].replace("\\/", "/"))
+}
+
+impl PageGraph {
+    /// Returns the worker that the given script was executed within, if any.
+    pub fn worker_of<'a>(&'a self, script: &Node) -> Option<&'a Node> {
+        let mut to_check = vec![script.id];
+        let mut checked = HashSet::new();
+        while let Some(script) = to_check.pop() {
+            if !checked.insert(script) {
+                continue;
+            }
+            for edge in self.incoming_edges(self.nodes.get(&script).unwrap()) {
+                if !matches!(edge.edge_type, EdgeType::Execute {}) {
+                    continue;
+                }
+                let executor = self.source_node(edge);
+                match executor.node_type {
+                    NodeType::Worker { .. } => return Some(executor),
+                    NodeType::Script { .. } => to_check.push(executor.id),
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the given scripts, along with every script they executed.
+    fn scripts_executed_from(&self, mut to_check: Vec<NodeId>) -> Vec<NodeId> {
+        let mut scripts = HashSet::new();
+        while let Some(script) = to_check.pop() {
+            if scripts.insert(script) {
+                self.outgoing_edges(self.nodes.get(&script).unwrap())
+                    .filter(|edge| matches!(edge.edge_type, EdgeType::Execute {}))
+                    .for_each(|edge| to_check.push(edge.target));
+            }
+        }
+        let mut scripts = scripts.into_iter().collect::<Vec<_>>();
+        scripts.sort();
+        scripts
+    }
+
+    fn worker_info(&self, worker_type: WorkerType, url: Option<String>, worker_node: Option<NodeId>, spawning_script: Option<NodeId>, top_level_scripts: Vec<NodeId>) -> WorkerInfo {
+        let scripts = self.scripts_executed_from(top_level_scripts);
+        let actors = scripts.iter().chain(worker_node.iter()).map(|id| self.nodes.get(id).unwrap()).collect::<Vec<_>>();
+
+        let mut requests = actors.iter()
+            .flat_map(|actor| self.outgoing_edges(actor))
+            .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+            .map(|edge| edge.id)
+            .collect::<Vec<_>>();
+        requests.sort();
+
+        let mut storage_accesses = actors.iter()
+            .flat_map(|actor| self.outgoing_edges(actor))
+            .filter(|edge| matches!(self.target_node(edge).node_type.kind(), NodeKind::CookieJar | NodeKind::LocalStorage | NodeKind::SessionStorage | NodeKind::Storage))
+            .map(|edge| edge.id)
+            .collect::<Vec<_>>();
+        storage_accesses.sort();
+
+        WorkerInfo { worker_type, url, worker_node, spawning_script, scripts, requests, storage_accesses }
+    }
+
+    /// Returns every worker started by the page.
+    pub fn workers(&self) -> Vec<WorkerInfo> {
+        let mut workers = self.nodes.values()
+            .filter_map(|node| match &node.node_type {
+                NodeType::Worker { worker_type, url } => Some((node, *worker_type, url.as_ref().map(|url| url.to_string()))),
+                _ => None,
+            })
+            .map(|(node, worker_type, url)| {
+                let spawning_script = self.incoming_edges(node)
+                    .filter(|edge| matches!(self.source_node(edge).node_type, NodeType::Script { .. }))
+                    .min_by_key(|edge| edge.edge_timestamp)
+                    .map(|edge| edge.source);
+                let top_level_scripts = self.outgoing_edges(node)
+                    .filter(|edge| matches!(edge.edge_type, EdgeType::Execute {}))
+                    .map(|edge| edge.target)
+                    .collect();
+                (node.node_timestamp, self.worker_info(worker_type, url, Some(node.id), spawning_script, top_level_scripts))
+            })
+            .collect::<Vec<_>>();
+
+        // Workers started from script, for graphs that don't record worker nodes
+        let base_url = self.desc.url.parsed();
+        self.edges.values()
+            .filter(|edge| matches!(edge.edge_type, EdgeType::JsCall { .. }))
+            .filter_map(|edge| {
+                let worker_type = self.target_node(edge).node_type.method().and_then(worker_type_of_constructor)?;
+                let url = match &edge.edge_type {
+                    EdgeType::JsCall { args: Some(args), .. } => first_string_arg(args)?,
+                    _ => return None,
+                };
+                let resolved_url = base_url.and_then(|base| base.join(&url).ok()).map(|url| url.to_string()).unwrap_or(url);
+                Some((edge, worker_type, resolved_url))
+            })
+            .filter(|(_, _, url)| !workers.iter().any(|(_, worker)| worker.url.as_ref() == Some(url) && worker.worker_node.is_some()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .for_each(|(edge, worker_type, url)| {
+                let top_level_scripts = self.nodes.values()
+                    .filter(|node| matches!(&node.node_type, NodeType::Script { url: Some(script_url), .. } if *script_url == url))
+                    // Scripts executed from a document are not worker scripts
+                    .filter(|node| !self.incoming_edges(node).any(|edge| matches!(self.source_node(edge).node_type.kind(), NodeKind::HtmlElement | NodeKind::DomRoot)))
+                    .map(|node| node.id)
+                    .collect();
+                let timestamp = edge.edge_timestamp.unwrap_or_default();
+                workers.push((timestamp, self.worker_info(worker_type, Some(url), None, Some(edge.source), top_level_scripts)));
+            });
+
+        workers.sort_by_key(|(timestamp, worker)| (*timestamp, worker.worker_node, worker.url.clone()));
+        workers.into_iter().map(|(_, worker)| worker).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_workers() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, None, "new Worker('/w.js')");
+
+        // A worker recorded with its own node
+        builder.call_web_api(script, "Worker", Some("[\"/w.js\"]"));
+        let worker = builder.add_node(NodeType::Worker { worker_type: WorkerType::Dedicated, url: Some("https://example.com/w.js".into()) });
+        builder.add_edge(script, worker, EdgeType::CreateNode {});
+        let worker_script = builder.add_script(worker, Some("https://example.com/w.js"), "importScripts('https://t.net/i.js')");
+        let imported = builder.add_script(worker_script, Some("https://t.net/i.js"), "fetch('https://t.net/c')");
+        builder.add_request(imported, "https://t.net/c", RequestType::AJAX);
+
+        // A service worker inferred from its registration
+        builder.call_web_api(script, "ServiceWorkerContainer.register", Some("[\"/sw.js\"]"));
+        let service_worker_script = builder.add_node(NodeType::Script {
+            url: Some("https://example.com/sw.js".into()),
+            script_type: crate::types::ScriptType::Classic,
+            script_id: 100,
+            source: String::new(),
+        });
+        let graph = builder.build();
+
+        let workers = graph.workers();
+        assert_eq!(workers.len(), 2);
+
+        assert_eq!(workers[0].worker_type, WorkerType::Dedicated);
+        assert_eq!(workers[0].worker_node, Some(worker));
+        assert_eq!(workers[0].spawning_script, Some(script));
+        assert_eq!(workers[0].scripts, vec![worker_script, imported]);
+        assert_eq!(workers[0].requests.len(), 1);
+
+        assert_eq!(workers[1].worker_type, WorkerType::Service);
+        assert_eq!(workers[1].url.as_deref(), Some("https://example.com/sw.js"));
+        assert_eq!(workers[1].worker_node, None);
+        assert_eq!(workers[1].scripts, vec![service_worker_script]);
+
+        let imported = graph.nodes.get(&imported).unwrap();
+        assert_eq!(graph.worker_of(imported).map(|node| node.id), Some(worker));
+        assert!(graph.document_of_actor(imported).is_none());
+    }
+}
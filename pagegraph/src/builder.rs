@@ -0,0 +1,420 @@
+//! Programmatic construction of synthetic PageGraphs, e.g. for tests or tooling that doesn't have
+//! access to a real browser dump.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use petgraph::graphmap::DiGraphMap;
+
+use crate::graph::{Edge, EdgeId, FrameId, Node, NodeId, PageGraph, PageGraphDescriptor, PageGraphTime};
+use crate::types::{EdgeType, HtmlElementId, NodeKind, NodeType, RequestType, ResourceType, ResponseMetadata, ScriptId, ScriptType, Url};
+
+/// Incrementally builds a valid [`PageGraph`].
+///
+/// Node, edge, HTML node, script, and request ids are assigned automatically. Every item added
+/// to the graph is timestamped with the builder's current time, which advances by one
+/// millisecond per item, or further via [`advance_time`](Self::advance_time).
+///
+/// A new builder already contains a [`Parser`](NodeType::Parser) node and a
+/// [`DomRoot`](NodeType::DomRoot) node for the page's document.
+pub struct PageGraphBuilder {
+    url: String,
+    frame_id: FrameId,
+    is_root: bool,
+    start_time: u64,
+
+    nodes: HashMap<NodeId, Node>,
+    edges: HashMap<EdgeId, Edge>,
+    graph: DiGraphMap<NodeId, Vec<EdgeId>>,
+
+    next_node_id: usize,
+    next_edge_id: usize,
+    next_html_node_id: HtmlElementId,
+    next_script_id: ScriptId,
+    next_request_id: usize,
+    next_event_listener_id: usize,
+    now: isize,
+
+    parser: NodeId,
+    dom_root: NodeId,
+    /// Maps request ids to the ids of their initiator and their resource node.
+    requests: HashMap<usize, (NodeId, NodeId)>,
+    /// Maps the Blink ids of DOM nodes to the Blink ids of their current children, in order.
+    dom_children: HashMap<HtmlElementId, Vec<HtmlElementId>>,
+}
+
+impl PageGraphBuilder {
+    /// Creates a builder for a graph recorded from the given page URL.
+    pub fn new(url: &str) -> Self {
+        let mut builder = Self {
+            url: url.to_string(),
+            frame_id: FrameId::try_from("00000000000000000000000000000001").unwrap(),
+            is_root: true,
+            start_time: 0,
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+            graph: DiGraphMap::new(),
+            next_node_id: 0,
+            next_edge_id: 0,
+            next_html_node_id: 1,
+            next_script_id: 1,
+            next_request_id: 1,
+            next_event_listener_id: 1,
+            now: 0,
+            parser: NodeId::from(0),
+            dom_root: NodeId::from(0),
+            requests: HashMap::new(),
+            dom_children: HashMap::new(),
+        };
+
+        builder.parser = builder.add_node(NodeType::Parser {});
+        let node_id = builder.new_html_node_id();
+        builder.dom_root = builder.add_node(NodeType::DomRoot {
+            url: Some(url.into()),
+            tag_name: "#document".to_string(),
+            is_deleted: false,
+            node_id,
+        });
+        builder.add_edge(builder.parser, builder.dom_root, EdgeType::CreateNode {});
+
+        builder
+    }
+
+    /// Sets the frame id recorded in the graph's descriptor.
+    pub fn frame_id(mut self, frame_id: FrameId) -> Self {
+        self.frame_id = frame_id;
+        self
+    }
+
+    /// Sets whether the graph is recorded from the top-level frame, rather than from a remote
+    /// frame that can be merged into another graph with [`PageGraph::merge_frame`].
+    pub fn is_root(mut self, is_root: bool) -> Self {
+        self.is_root = is_root;
+        self
+    }
+
+    /// Sets the wall-clock start time recorded in the graph's descriptor, in milliseconds since
+    /// the Unix epoch.
+    pub fn start_time(mut self, start_time: u64) -> Self {
+        self.start_time = start_time;
+        self
+    }
+
+    /// The id of the page's [`Parser`](NodeType::Parser) node.
+    pub fn parser(&self) -> NodeId {
+        self.parser
+    }
+
+    /// The id of the page's top-level [`DomRoot`](NodeType::DomRoot) node.
+    pub fn dom_root(&self) -> NodeId {
+        self.dom_root
+    }
+
+    /// The timestamp that will be assigned to the next item added to the graph.
+    pub fn now(&self) -> isize {
+        self.now
+    }
+
+    /// Moves the builder's clock forward by the given number of milliseconds.
+    pub fn advance_time(&mut self, ms: isize) -> &mut Self {
+        self.now += ms;
+        self
+    }
+
+    /// Returns a fresh Blink node id, for use in new DOM nodes.
+    pub fn new_html_node_id(&mut self) -> HtmlElementId {
+        let id = self.next_html_node_id;
+        self.next_html_node_id += 1;
+        id
+    }
+
+    fn tick(&mut self) -> isize {
+        let now = self.now;
+        self.now += 1;
+        now
+    }
+
+    fn html_node_id_of(&self, node: NodeId) -> HtmlElementId {
+        self.nodes.get(&node)
+            .and_then(|node| node.node_type.html_node_id())
+            .unwrap_or_else(|| panic!("{} is not a DOM node", node))
+    }
+
+    /// Adds a node of any type to the graph, without any edges.
+    pub fn add_node(&mut self, node_type: NodeType) -> NodeId {
+        let id = NodeId::from(self.next_node_id);
+        self.next_node_id += 1;
+        let node_timestamp = self.tick();
+        self.graph.add_node(id);
+        self.nodes.insert(id, Node { id, node_timestamp, node_type });
+        id
+    }
+
+    /// Adds an edge of any type between two existing nodes.
+    pub fn add_edge(&mut self, source: NodeId, target: NodeId, edge_type: EdgeType) -> EdgeId {
+        assert!(self.nodes.contains_key(&source), "source node {} does not exist", source);
+        assert!(self.nodes.contains_key(&target), "target node {} does not exist", target);
+        let id = EdgeId::from(self.next_edge_id);
+        self.next_edge_id += 1;
+        let edge_timestamp = Some(self.tick());
+        match self.graph.edge_weight_mut(source, target) {
+            Some(edges) => edges.push(id),
+            None => { self.graph.add_edge(source, target, vec![id]); },
+        }
+        self.edges.insert(id, Edge { id, edge_timestamp, edge_type, source, target });
+        id
+    }
+
+    /// Adds an HTML element created by `creator` (usually the parser or a script) and inserted
+    /// into the DOM below `parent`.
+    pub fn add_element(&mut self, creator: NodeId, parent: NodeId, tag_name: &str) -> NodeId {
+        let node_id = self.new_html_node_id();
+        let element = self.add_node(NodeType::HtmlElement {
+            tag_name: tag_name.to_string(),
+            is_deleted: false,
+            node_id,
+        });
+        self.add_edge(creator, element, EdgeType::CreateNode {});
+        self.insert_node(creator, element, parent);
+        element
+    }
+
+    /// Adds a text node created by `creator` and inserted into the DOM below `parent`.
+    pub fn add_text(&mut self, creator: NodeId, parent: NodeId, text: &str) -> NodeId {
+        let node_id = self.new_html_node_id();
+        let text_node = self.add_node(NodeType::TextNode {
+            text: Some(text.to_string()),
+            is_deleted: false,
+            node_id,
+        });
+        self.add_edge(creator, text_node, EdgeType::CreateNode {});
+        self.insert_node(creator, text_node, parent);
+        text_node
+    }
+
+    /// Adds a frame owner element (e.g. an `<iframe>`) created by `creator` and inserted into the
+    /// DOM below `parent`.
+    pub fn add_frame_owner(&mut self, creator: NodeId, parent: NodeId, tag_name: &str) -> NodeId {
+        let node_id = self.new_html_node_id();
+        let frame_owner = self.add_node(NodeType::FrameOwner {
+            tag_name: tag_name.to_string(),
+            is_deleted: false,
+            node_id,
+        });
+        self.add_edge(creator, frame_owner, EdgeType::CreateNode {});
+        self.insert_node(creator, frame_owner, parent);
+        frame_owner
+    }
+
+    /// Adds a document loaded from `url` into a frame owner, in the same local frame context as
+    /// the rest of the graph. Returns the new document's [`DomRoot`](NodeType::DomRoot) node,
+    /// which elements in the frame can be inserted into.
+    pub fn add_frame_document(&mut self, frame_owner: NodeId, url: &str) -> NodeId {
+        let node_id = self.new_html_node_id();
+        let dom_root = self.add_node(NodeType::DomRoot {
+            url: Some(url.into()),
+            tag_name: "#document".to_string(),
+            is_deleted: false,
+            node_id,
+        });
+        self.add_edge(frame_owner, dom_root, EdgeType::CrossDom {});
+        dom_root
+    }
+
+    /// Records `actor` attaching a new shadow root to the `host` element, returning the shadow
+    /// root's node.
+    pub fn attach_shadow(&mut self, actor: NodeId, host: NodeId, mode: &str) -> NodeId {
+        let host = self.html_node_id_of(host);
+        let node_id = self.new_html_node_id();
+        let shadow_root = self.add_node(NodeType::ShadowRoot { is_deleted: false, node_id });
+        self.add_edge(actor, shadow_root, EdgeType::AttachShadow { host, mode: Some(mode.to_string()) });
+        shadow_root
+    }
+
+    fn detach(&mut self, node: HtmlElementId) {
+        self.dom_children.values_mut().for_each(|children| children.retain(|child| *child != node));
+    }
+
+    /// Records `actor` removing the existing DOM node `node` from its parent.
+    pub fn remove_node(&mut self, actor: NodeId, node: NodeId) -> EdgeId {
+        self.detach(self.html_node_id_of(node));
+        self.add_edge(actor, node, EdgeType::RemoveNode { script_position: None })
+    }
+
+    /// Records `actor` inserting the existing DOM node `node` below `parent`, after any of its
+    /// existing children.
+    pub fn insert_node(&mut self, actor: NodeId, node: NodeId, parent: NodeId) -> EdgeId {
+        let node_id = self.html_node_id_of(node);
+        let parent = self.html_node_id_of(parent);
+        self.detach(node_id);
+        let children = self.dom_children.entry(parent).or_default();
+        let before = children.last().copied();
+        children.push(node_id);
+        self.add_edge(actor, node, EdgeType::InsertNode { parent, before, script_position: None })
+    }
+
+    /// Records `actor` setting an attribute on an HTML element.
+    pub fn set_attribute(&mut self, actor: NodeId, element: NodeId, key: &str, value: &str) -> EdgeId {
+        self.add_edge(actor, element, EdgeType::SetAttribute {
+            key: key.to_string(),
+            value: Some(value.to_string()),
+            is_style: false,
+            script_position: None,
+        })
+    }
+
+    /// Records `actor` deleting an attribute from an HTML element.
+    pub fn delete_attribute(&mut self, actor: NodeId, element: NodeId, key: &str) -> EdgeId {
+        self.add_edge(actor, element, EdgeType::DeleteAttribute {
+            key: key.to_string(),
+            is_style: false,
+            script_position: None,
+        })
+    }
+
+    /// Adds a classic script executed by `executor` (usually a `<script>` element). Scripts with
+    /// no `url` are considered inline.
+    pub fn add_script(&mut self, executor: NodeId, url: Option<&str>, source: &str) -> NodeId {
+        let script_id = self.next_script_id;
+        self.next_script_id += 1;
+        let script = self.add_node(NodeType::Script {
+            url: url.map(Url::from),
+            script_type: ScriptType::Classic,
+            script_id,
+            source: source.to_string(),
+        });
+        self.add_edge(executor, script, EdgeType::Execute {});
+        script
+    }
+
+    /// Records `script` calling the given Web API method, returning the new
+    /// [`JsCall`](EdgeType::JsCall) edge.
+    ///
+    /// A single [`WebApi`](NodeType::WebApi) node is shared by all calls to the same method.
+    pub fn call_web_api(&mut self, script: NodeId, method: &str, args: Option<&str>) -> EdgeId {
+        let web_api = self.nodes.values()
+            .find(|node| node.node_type.kind() == NodeKind::WebApi && node.node_type.method() == Some(method))
+            .map(|node| node.id);
+        let web_api = web_api.unwrap_or_else(|| self.add_node(NodeType::WebApi { method: method.to_string() }));
+        self.add_edge(script, web_api, EdgeType::JsCall {
+            args: args.map(|args| args.to_string()),
+            script_position: 0,
+        })
+    }
+
+    /// Records `script` adding an event listener for `key` events on `target`, with the listener
+    /// function defined in `listener`. Returns the new event listener id.
+    pub fn add_event_listener(&mut self, script: NodeId, target: NodeId, key: &str, listener: NodeId) -> usize {
+        let script_id = self.nodes.get(&listener)
+            .and_then(|node| node.node_type.script_id())
+            .unwrap_or_else(|| panic!("{} is not a script", listener));
+        let event_listener_id = self.next_event_listener_id;
+        self.next_event_listener_id += 1;
+        self.add_edge(script, target, EdgeType::AddEventListener {
+            key: key.to_string(),
+            event_listener_id,
+            script_id,
+        });
+        event_listener_id
+    }
+
+    /// Records `initiator` starting a request for `url`, returning the new request id.
+    ///
+    /// A single [`Resource`](NodeType::Resource) node is shared by all requests to the same URL.
+    pub fn add_request(&mut self, initiator: NodeId, url: &str, request_type: RequestType) -> usize {
+        let resource = self.nodes.values()
+            .find(|node| matches!(&node.node_type, NodeType::Resource { url: resource_url } if resource_url == url))
+            .map(|node| node.id);
+        let resource = resource.unwrap_or_else(|| self.add_node(NodeType::Resource { url: url.into() }));
+
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        self.add_edge(initiator, resource, EdgeType::RequestStart {
+            request_type,
+            status: "started".to_string(),
+            request_id,
+        });
+        self.requests.insert(request_id, (initiator, resource));
+        request_id
+    }
+
+    /// The id of the [`Resource`](NodeType::Resource) node requested by the given request.
+    pub fn resource_of(&self, request_id: usize) -> NodeId {
+        self.requests.get(&request_id).expect("unknown request id").1
+    }
+
+    /// Records the successful completion of a previously started request.
+    pub fn complete_request(&mut self, request_id: usize, resource_type: ResourceType, size: Option<usize>) -> EdgeId {
+        let (initiator, resource) = *self.requests.get(&request_id).expect("unknown request id");
+        self.add_edge(resource, initiator, EdgeType::RequestComplete {
+            resource_type,
+            status: "complete".to_string(),
+            value: None,
+            response_hash: None,
+            request_id,
+            headers: ResponseMetadata::default(),
+            size,
+        })
+    }
+
+    /// Records the failure of a previously started request.
+    pub fn fail_request(&mut self, request_id: usize) -> EdgeId {
+        let (initiator, resource) = *self.requests.get(&request_id).expect("unknown request id");
+        self.add_edge(resource, initiator, EdgeType::RequestError {
+            status: "error".to_string(),
+            request_id,
+            value: None,
+            headers: ResponseMetadata::default(),
+            size: None,
+        })
+    }
+
+    /// Finishes building the graph.
+    pub fn build(self) -> PageGraph {
+        let desc = PageGraphDescriptor {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            about: "Synthetic graph built with pagegraph::builder::PageGraphBuilder".to_string(),
+            url: self.url.into(),
+            is_root: self.is_root,
+            frame_id: self.frame_id,
+            time: PageGraphTime {
+                start: self.start_time,
+                end: self.start_time + self.now.max(0) as u64,
+            },
+        };
+        PageGraph::new(desc, self.edges, self.nodes, self.graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_simple_page() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+        let script_element = builder.add_element(parser, body, "script");
+        let script = builder.add_script(script_element, Some("https://cdn.tracker.net/t.js"), "track()");
+        builder.advance_time(100);
+        let request = builder.add_request(script, "https://cdn.tracker.net/pixel.gif", RequestType::Image);
+        builder.complete_request(request, ResourceType::Image, Some(43));
+        let resource = builder.resource_of(request);
+        let graph = builder.build();
+
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.edges.len(), 8);
+        assert_eq!(graph.desc.time.end, 114);
+
+        let body = graph.nodes.get(&body).unwrap();
+        assert_eq!(graph.dom_root_for_html_node(body).map(|node| node.id), Some(root));
+
+        let script = graph.nodes.get(&script).unwrap();
+        assert_eq!(script.describe(&graph), "script https://cdn.tracker.net/t.js (3rd party) executed by <script> element at 9ms");
+
+        let resource = graph.nodes.get(&resource).unwrap();
+        assert_eq!(graph.resource_request_types(&resource.id), vec![("image".to_string(), Some(43))]);
+    }
+}
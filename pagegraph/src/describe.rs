@@ -0,0 +1,162 @@
+//! Human-readable, single-line summaries of graph items.
+
+use crate::graph::{Edge, Node, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// Maximum number of characters of recorded text or values to include in a summary.
+const MAX_EXCERPT_LEN: usize = 40;
+
+fn excerpt(text: &str) -> String {
+    let mut chars = text.chars();
+    let mut excerpt: String = chars.by_ref().take(MAX_EXCERPT_LEN).collect();
+    if chars.next().is_some() {
+        excerpt.push('…');
+    }
+    format!("{:?}", excerpt)
+}
+
+impl Node {
+    /// A short name for this node, suitable for referring to it from within another summary.
+    pub fn label(&self) -> String {
+        match &self.node_type {
+            NodeType::Resource { url } => format!("resource {}", url),
+            NodeType::WebApi { method } => format!("web API {}", method),
+            NodeType::JsBuiltin { method } => format!("JS builtin {}", method),
+            NodeType::HtmlElement { tag_name, .. } => format!("<{}> element", tag_name),
+            NodeType::TextNode { .. } => "text node".to_string(),
+            NodeType::DomRoot { url: Some(url), .. } => format!("DOM root {}", url),
+            NodeType::DomRoot { url: None, .. } => "DOM root".to_string(),
+            NodeType::FrameOwner { tag_name, .. } => format!("<{}> frame owner", tag_name),
+            NodeType::ShadowRoot { .. } => "shadow root".to_string(),
+            NodeType::LocalStorage {} => "local storage".to_string(),
+            NodeType::SessionStorage {} => "session storage".to_string(),
+            NodeType::CookieJar {} => "cookie jar".to_string(),
+            NodeType::Script { url: Some(url), .. } => format!("script {}", url),
+            NodeType::Script { script_id, .. } => format!("inline script {}", script_id),
+            NodeType::Parser {} => "parser".to_string(),
+            NodeType::Binding { binding, .. } => format!("binding {}", binding),
+            NodeType::BindingEvent { binding_event } => format!("binding event {}", binding_event),
+            NodeType::RemoteFrame { frame_id } => format!("remote frame {}", frame_id),
+            NodeType::AdFilter { rule } => format!("ad filter {}", rule),
+            NodeType::TrackerFilter => "tracker filter".to_string(),
+            NodeType::FingerprintingFilter => "fingerprinting filter".to_string(),
+            NodeType::Storage {} => "storage".to_string(),
+            NodeType::BraveShields {} => "Brave Shields".to_string(),
+            NodeType::AdsShield {} => "ads shield".to_string(),
+            NodeType::TrackersShield {} => "trackers shield".to_string(),
+            NodeType::JavascriptShield {} => "javascript shield".to_string(),
+            NodeType::FingerprintingShield {} => "fingerprinting shield".to_string(),
+            NodeType::FingerprintingV2Shield {} => "fingerprintingV2 shield".to_string(),
+            NodeType::Extensions {} => "extensions".to_string(),
+            NodeType::Worker { worker_type, url: Some(url) } => format!("{} {}", worker_type.as_str(), url),
+            NodeType::Worker { worker_type, url: None } => worker_type.as_str().to_string(),
+            NodeType::Unknown { kind, .. } => format!("unknown {}", kind),
+        }
+    }
+
+    /// A one-line, human-readable description of this node, e.g.
+    /// `script https://x.com/a.js (3rd party) executed by <script> element at 812ms`.
+    pub fn describe(&self, graph: &PageGraph) -> String {
+        let mut description = self.label();
+
+        match &self.node_type {
+            NodeType::TextNode { text: Some(text), .. } => {
+                description.push(' ');
+                description.push_str(&excerpt(text));
+            }
+            NodeType::Script { source, url: None, .. } => {
+                description.push(' ');
+                description.push_str(&excerpt(source));
+            }
+            _ => (),
+        }
+
+        if let Some(third_party) = self.node_type.url().and_then(|url| graph.is_third_party(url)) {
+            description.push_str(if third_party { " (3rd party)" } else { " (1st party)" });
+        }
+
+        if self.node_type.is_deleted() == Some(true) {
+            description.push_str(" (deleted)");
+        }
+
+        // Mention the actor responsible for bringing this node into existence, if there is one.
+        let origin = match &self.node_type {
+            NodeType::Script { .. } => Some(("executed", graph.incoming_edges(self)
+                .filter(|edge| matches!(edge.edge_type, EdgeType::Execute {} | EdgeType::ExecuteFromAttribute { .. }))
+                .collect::<Vec<_>>())),
+            NodeType::HtmlElement { .. } | NodeType::TextNode { .. } | NodeType::FrameOwner { .. } | NodeType::DomRoot { .. } | NodeType::ShadowRoot { .. } => Some(("created", graph.incoming_edges(self)
+                .filter(|edge| matches!(edge.edge_type, EdgeType::CreateNode {}))
+                .collect::<Vec<_>>())),
+            NodeType::Resource { .. } => Some(("requested", graph.incoming_edges(self)
+                .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+                .collect::<Vec<_>>())),
+            _ => None,
+        };
+
+        if let Some((verb, mut edges)) = origin {
+            edges.sort_by_key(|edge| edge.edge_timestamp);
+            if let Some(first) = edges.first() {
+                description.push_str(&format!(" {} by {}", verb, graph.source_node(first).label()));
+                if edges.len() > 1 {
+                    description.push_str(&format!(" (+{} more)", edges.len() - 1));
+                }
+            }
+        }
+
+        description.push_str(&format!(" at {}ms", self.node_timestamp));
+
+        description
+    }
+}
+
+impl Edge {
+    /// A one-line, human-readable description of this edge, e.g.
+    /// `<script> element executed script https://x.com/a.js at 812ms`.
+    pub fn describe(&self, graph: &PageGraph) -> String {
+        let action = match &self.edge_type {
+            EdgeType::CrossDom {} => "contains frame".to_string(),
+            EdgeType::TextChange {} => "changed text of".to_string(),
+            EdgeType::RemoveNode { .. } => "removed".to_string(),
+            EdgeType::DeleteNode {} => "deleted".to_string(),
+            EdgeType::InsertNode { parent, .. } => format!("inserted (below node {})", parent),
+            EdgeType::CreateNode {} => "created".to_string(),
+            EdgeType::JsResult { value: Some(value) } => format!("returned {} to", excerpt(value)),
+            EdgeType::JsResult { value: None } => "returned to".to_string(),
+            EdgeType::JsCall { args: Some(args), .. } => format!("called with {}", excerpt(args)),
+            EdgeType::JsCall { args: None, .. } => "called".to_string(),
+            EdgeType::RequestComplete { request_id, .. } => format!("completed request {} for", request_id),
+            EdgeType::RequestError { request_id, .. } => format!("failed request {} for", request_id),
+            EdgeType::RequestStart { request_id, request_type, .. } => format!("started {} request {} for", request_type.as_str(), request_id),
+            EdgeType::RequestResponse => "received response for".to_string(),
+            EdgeType::AddEventListener { key, .. } => format!("added `{}` listener to", key),
+            EdgeType::RemoveEventListener { key, .. } => format!("removed `{}` listener from", key),
+            EdgeType::EventListener { key, .. } => format!("dispatches `{}` events to", key),
+            EdgeType::StorageSet { key, value } => format!("set `{}` = {} in", key, value.as_deref().map(excerpt).unwrap_or_default()),
+            EdgeType::StorageReadResult { key, value } => format!("returned `{}` = {} to", key, value.as_deref().map(excerpt).unwrap_or_default()),
+            EdgeType::DeleteStorage { key } => format!("deleted `{}` from", key),
+            EdgeType::ReadStorageCall { key } => format!("read `{}` from", key),
+            EdgeType::ClearStorage { .. } => "cleared".to_string(),
+            EdgeType::ExecuteFromAttribute { attr_name } => format!("executed (from `{}` attribute)", attr_name),
+            EdgeType::Execute {} => "executed".to_string(),
+            EdgeType::SetAttribute { key, value, .. } => format!("set `{}` = {} on", key, value.as_deref().map(excerpt).unwrap_or_default()),
+            EdgeType::DeleteAttribute { key, .. } => format!("deleted `{}` from", key),
+            EdgeType::Binding {} => "bound".to_string(),
+            EdgeType::BindingEvent { .. } => "triggered binding event on".to_string(),
+            EdgeType::Filter {} => "filtered".to_string(),
+            EdgeType::Structure {} => "contains".to_string(),
+            EdgeType::Shield {} => "shields".to_string(),
+            EdgeType::ResourceBlock {} => "blocked".to_string(),
+            EdgeType::StorageBucket {} => "has storage bucket".to_string(),
+            EdgeType::AttachShadow { host, mode: Some(mode) } => format!("attached (to node {}, {})", host, mode),
+            EdgeType::AttachShadow { host, mode: None } => format!("attached (to node {})", host),
+            EdgeType::Unknown { kind, .. } => format!("performed unknown action `{}` on", kind),
+        };
+
+        let mut description = format!("{} {} {}", graph.source_node(self).label(), action, graph.target_node(self).label());
+        if let Some(timestamp) = self.edge_timestamp {
+            description.push_str(&format!(" at {}ms", timestamp));
+        }
+
+        description
+    }
+}
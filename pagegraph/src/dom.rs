@@ -0,0 +1,452 @@
+//! Reconstruction of the final state of the page's DOM trees, by replaying every recorded
+//! insertion and removal of DOM nodes in chronological order.
+//!
+//! Shadow trees are kept separate from the light DOM tree of their host element. They can either
+//! be traversed as nested trees, via [`DomTree::shadow_root`], or as part of the flat tree that is
+//! actually rendered, via [`DomTree::flattened_children`].
+//!
+//! [`PageGraph::dom_parent`], [`PageGraph::dom_children`], and [`PageGraph::dom_ancestors`] walk
+//! the final light DOM trees directly from graph nodes, reconstructing them on first use.
+//!
+//! [`PageGraph::dom_select`] finds the elements matching a simple CSS [`Selector`] against their
+//! final attributes, optionally piercing shadow boundaries.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use crate::graph::{FrameId, HasFrameId, Node, NodeId, PageGraph};
+use crate::types::{EdgeType, HtmlElementId, NodeType};
+
+/// The final structure of every DOM tree in the graph.
+#[derive(Debug, Default)]
+pub struct DomTree {
+    roots: Vec<NodeId>,
+    parents: HashMap<NodeId, NodeId>,
+    children: HashMap<NodeId, Vec<NodeId>>,
+    shadow_roots: HashMap<NodeId, NodeId>,
+    hosts: HashMap<NodeId, NodeId>,
+    /// Maps `<slot>` elements in shadow trees to the host element whose children they render.
+    slot_hosts: HashMap<NodeId, NodeId>,
+    /// The final attributes of each element, with lowercase names. Style properties are not
+    /// included.
+    attributes: HashMap<NodeId, HashMap<String, String>>,
+}
+
+impl DomTree {
+    fn detach(&mut self, node: NodeId) {
+        if let Some(parent) = self.parents.remove(&node) {
+            self.children.entry(parent).or_default().retain(|child| *child != node);
+        }
+    }
+
+    /// The DOM root of every document in the graph.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// The parent of the given node in its light DOM tree. The top-level nodes of a shadow tree
+    /// have the shadow root as their parent.
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.parents.get(&node).copied()
+    }
+
+    /// The children of the given node in its light DOM tree, in document order.
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        self.children.get(&node).map(|children| &children[..]).unwrap_or(&[])
+    }
+
+    /// The shadow root attached to the given host element, if any.
+    pub fn shadow_root(&self, host: NodeId) -> Option<NodeId> {
+        self.shadow_roots.get(&host).copied()
+    }
+
+    /// The host element of the given shadow root.
+    pub fn host(&self, shadow_root: NodeId) -> Option<NodeId> {
+        self.hosts.get(&shadow_root).copied()
+    }
+
+    /// The children of the given node in the flat tree, where shadow hosts render their shadow
+    /// tree in place of their light DOM children, and `<slot>`s render the light DOM children of
+    /// their host.
+    pub fn flattened_children(&self, node: NodeId) -> Vec<NodeId> {
+        if let Some(shadow_root) = self.shadow_root(node) {
+            return self.children(shadow_root).to_vec();
+        }
+        if let Some(host) = self.slot_hosts.get(&node) {
+            let assigned = self.children(*host);
+            if !assigned.is_empty() {
+                return assigned.to_vec();
+            }
+        }
+        self.children(node).to_vec()
+    }
+
+    /// The final value of the given attribute of an element, if it is set.
+    pub fn attribute(&self, node: NodeId, name: &str) -> Option<&str> {
+        self.attributes.get(&node)
+            .and_then(|attributes| attributes.get(&name.to_ascii_lowercase()))
+            .map(|value| value.as_str())
+    }
+
+    /// Every descendant of the given node, in document order. If `pierce_shadow` is set, the
+    /// shadow tree of each host is included, starting with its shadow root, just before the
+    /// host's light DOM children.
+    pub fn descendants(&self, node: NodeId, pierce_shadow: bool) -> Vec<NodeId> {
+        let mut descendants = vec![];
+        let mut to_visit = vec![];
+        let push_children = |node: NodeId, to_visit: &mut Vec<NodeId>| {
+            to_visit.extend(self.children(node).iter().rev());
+            if pierce_shadow {
+                to_visit.extend(self.shadow_root(node));
+            }
+        };
+        push_children(node, &mut to_visit);
+        while let Some(node) = to_visit.pop() {
+            descendants.push(node);
+            push_children(node, &mut to_visit);
+        }
+        descendants
+    }
+}
+
+impl PageGraph {
+    /// Reconstructs the final structure of every DOM tree in the graph.
+    pub fn reconstruct_dom(&self) -> DomTree {
+        let mut tree = DomTree::default();
+
+        let dom_nodes: HashMap<(Option<FrameId>, HtmlElementId), NodeId> = self.nodes.values()
+            .filter_map(|node| node.node_type.html_node_id().map(|html_id| ((node.id.get_frame_id(), html_id), node.id)))
+            .collect();
+        let lookup = |frame_id: Option<FrameId>, html_id: HtmlElementId| dom_nodes.get(&(frame_id, html_id)).copied();
+
+        tree.roots = self.nodes.values()
+            .filter(|node| matches!(node.node_type, NodeType::DomRoot { .. }))
+            .map(|node| node.id)
+            .collect();
+        tree.roots.sort();
+
+        for edge in self.edges_in_range(..) {
+            let frame_id = edge.id.get_frame_id();
+            match edge.edge_type {
+                EdgeType::InsertNode { parent, before, .. } => {
+                    let parent = match lookup(frame_id, parent) {
+                        Some(parent) => parent,
+                        None => continue,
+                    };
+                    tree.detach(edge.target);
+                    let siblings = tree.children.entry(parent).or_default();
+                    let index = before
+                        .and_then(|before| lookup(frame_id, before))
+                        .and_then(|before| siblings.iter().position(|sibling| *sibling == before))
+                        .map(|position| position + 1)
+                        .unwrap_or(0);
+                    siblings.insert(index, edge.target);
+                    tree.parents.insert(edge.target, parent);
+                }
+                EdgeType::RemoveNode { .. } => tree.detach(edge.target),
+                EdgeType::SetAttribute { ref key, ref value, is_style: false, .. } => {
+                    tree.attributes.entry(edge.target).or_default()
+                        .insert(key.to_ascii_lowercase(), value.clone().unwrap_or_default());
+                }
+                EdgeType::DeleteAttribute { ref key, is_style: false, .. } => {
+                    if let Some(attributes) = tree.attributes.get_mut(&edge.target) {
+                        attributes.remove(&key.to_ascii_lowercase());
+                    }
+                }
+                EdgeType::AttachShadow { host, .. } => {
+                    if let Some(host) = lookup(frame_id, host) {
+                        tree.shadow_roots.insert(host, edge.target);
+                        tree.hosts.insert(edge.target, host);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // Slots are only meaningful within the host's own shadow tree, not in nested shadow trees.
+        let mut slot_hosts = HashMap::new();
+        tree.hosts.iter().for_each(|(shadow_root, host)| {
+            let mut to_visit = tree.children(*shadow_root).to_vec();
+            let mut visited = HashSet::new();
+            while let Some(node) = to_visit.pop() {
+                if !visited.insert(node) {
+                    continue;
+                }
+                if self.nodes.get(&node).and_then(|node| node.node_type.tag_name()).map(|tag| tag.eq_ignore_ascii_case("slot")).unwrap_or(false) {
+                    slot_hosts.insert(node, *host);
+                }
+                to_visit.extend(tree.children(node));
+            }
+        });
+        tree.slot_hosts = slot_hosts;
+
+        tree
+    }
+
+    pub(crate) fn dom_tree(&self) -> &DomTree {
+        self.dom_tree.get_or_init(|| self.reconstruct_dom())
+    }
+
+    /// The parent of the given node in the final state of its light DOM tree, if it is attached.
+    pub fn dom_parent<'a>(&'a self, node: &Node) -> Option<&'a Node> {
+        self.dom_tree().parent(node.id).map(|parent| self.nodes.get(&parent).unwrap())
+    }
+
+    /// The children of the given node in the final state of its light DOM tree, in document order.
+    pub fn dom_children<'a>(&'a self, node: &Node) -> impl Iterator<Item=&'a Node> {
+        self.dom_tree().children(node.id).iter().map(move |child| self.nodes.get(child).unwrap())
+    }
+
+    /// Every ancestor of the given node in the final state of its light DOM tree, from its parent
+    /// up to the DOM root or shadow root at the top of the tree.
+    pub fn dom_ancestors<'a>(&'a self, node: &Node) -> impl Iterator<Item=&'a Node> {
+        let tree = self.dom_tree();
+        std::iter::successors(tree.parent(node.id), move |node| tree.parent(*node))
+            .map(move |ancestor| self.nodes.get(&ancestor).unwrap())
+    }
+
+    /// Every descendant of the given node matching the selector, in document order, going by the
+    /// final state of the DOM. If `pierce_shadow` is set, elements in shadow trees are matched as
+    /// well.
+    pub fn dom_select<'a>(&'a self, node: &Node, selector: &Selector, pierce_shadow: bool) -> Vec<&'a Node> {
+        let tree = self.dom_tree();
+        tree.descendants(node.id, pierce_shadow).into_iter()
+            .map(|descendant| self.nodes.get(&descendant).unwrap())
+            .filter(|descendant| selector.matches(tree, descendant))
+            .collect()
+    }
+}
+
+/// A list of compound CSS selectors, such as `div.ad, iframe[src]`, made up of type (`div`),
+/// universal (`*`), id (`#top`), class (`.ad`), and attribute (`[src]`, `[name=value]`)
+/// selectors. Combinators and pseudo-classes are not supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector(Vec<Vec<SimpleSelector>>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SimpleSelector {
+    Type(String),
+    Universal,
+    Id(String),
+    Class(String),
+    Attribute { name: String, value: Option<String> },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseSelectorError {
+    /// The selector, or one of the selectors in the list, is empty.
+    Empty,
+    /// The selector contains a character that isn't supported at that position, such as a
+    /// combinator or pseudo-class.
+    Unexpected(char),
+    /// An attribute selector is missing its closing `]` or quote.
+    Unterminated,
+}
+
+/// Whether the character can appear in identifiers, such as tag names and classes.
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+fn parse_ident(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, ParseSelectorError> {
+    let mut ident = String::new();
+    while let Some(c) = chars.peek().copied().filter(|c| is_ident_char(*c)) {
+        ident.push(c);
+        chars.next();
+    }
+    match chars.peek() {
+        _ if !ident.is_empty() => Ok(ident),
+        Some(c) => Err(ParseSelectorError::Unexpected(*c)),
+        None => Err(ParseSelectorError::Empty),
+    }
+}
+
+fn parse_attribute(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<SimpleSelector, ParseSelectorError> {
+    let name = parse_ident(chars)?.to_ascii_lowercase();
+    let value = match chars.next() {
+        Some(']') => return Ok(SimpleSelector::Attribute { name, value: None }),
+        Some('=') => match chars.peek().copied() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                chars.next();
+                let value: String = chars.by_ref().take_while(|c| *c != quote).collect();
+                value
+            }
+            _ => parse_ident(chars)?,
+        },
+        Some(c) => return Err(ParseSelectorError::Unexpected(c)),
+        None => return Err(ParseSelectorError::Unterminated),
+    };
+    match chars.next() {
+        Some(']') => Ok(SimpleSelector::Attribute { name, value: Some(value) }),
+        Some(c) => Err(ParseSelectorError::Unexpected(c)),
+        None => Err(ParseSelectorError::Unterminated),
+    }
+}
+
+fn parse_compound(selector: &str) -> Result<Vec<SimpleSelector>, ParseSelectorError> {
+    let mut chars = selector.trim().chars().peekable();
+    let mut compound = vec![];
+    while let Some(c) = chars.peek().copied() {
+        let simple = match c {
+            '*' if compound.is_empty() => {
+                chars.next();
+                SimpleSelector::Universal
+            }
+            '#' => {
+                chars.next();
+                SimpleSelector::Id(parse_ident(&mut chars)?)
+            }
+            '.' => {
+                chars.next();
+                SimpleSelector::Class(parse_ident(&mut chars)?)
+            }
+            '[' => {
+                chars.next();
+                parse_attribute(&mut chars)?
+            }
+            c if is_ident_char(c) && compound.is_empty() => SimpleSelector::Type(parse_ident(&mut chars)?),
+            c => return Err(ParseSelectorError::Unexpected(c)),
+        };
+        compound.push(simple);
+    }
+    if compound.is_empty() {
+        return Err(ParseSelectorError::Empty);
+    }
+    Ok(compound)
+}
+
+impl TryFrom<&str> for Selector {
+    type Error = ParseSelectorError;
+
+    fn try_from(v: &str) -> Result<Self, Self::Error> {
+        v.split(',').map(parse_compound).collect::<Result<_, _>>().map(Self)
+    }
+}
+
+impl Selector {
+    /// Whether the node is an element matching the selector, going by its final attributes.
+    pub fn matches(&self, tree: &DomTree, node: &Node) -> bool {
+        let tag_name = match &node.node_type {
+            NodeType::HtmlElement { tag_name, .. } | NodeType::FrameOwner { tag_name, .. } => tag_name,
+            _ => return false,
+        };
+        self.0.iter().any(|compound| compound.iter().all(|simple| match simple {
+            SimpleSelector::Type(tag) => tag.eq_ignore_ascii_case(tag_name),
+            SimpleSelector::Universal => true,
+            SimpleSelector::Id(id) => tree.attribute(node.id, "id") == Some(id),
+            SimpleSelector::Class(class) => tree.attribute(node.id, "class")
+                .map(|classes| classes.split_ascii_whitespace().any(|c| c == class))
+                .unwrap_or(false),
+            SimpleSelector::Attribute { name, value } => match (tree.attribute(node.id, name), value) {
+                (Some(actual), Some(value)) => actual == value,
+                (actual, None) => actual.is_some(),
+                (None, Some(_)) => false,
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::{ParseSelectorError, Selector};
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_reconstruct_dom_with_shadow_tree() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+        let widget = builder.add_element(parser, body, "ad-widget");
+        let light = builder.add_text(parser, widget, "Sponsored");
+        let removed = builder.add_element(parser, body, "p");
+        let footer = builder.add_element(parser, body, "footer");
+        builder.remove_node(parser, removed);
+
+        let element = builder.add_element(parser, body, "script");
+        let script = builder.add_script(element, None, "attachShadow()");
+        let shadow_root = builder.attach_shadow(script, widget, "closed");
+        let frame = builder.add_element(script, shadow_root, "div");
+        let slot = builder.add_element(script, frame, "slot");
+        let graph = builder.build();
+
+        let tree = graph.reconstruct_dom();
+        assert_eq!(tree.roots(), &[root]);
+        assert_eq!(tree.children(body), &[widget, footer, element]);
+        assert_eq!(tree.parent(removed), None);
+        assert_eq!(tree.children(widget), &[light]);
+        assert_eq!(tree.shadow_root(widget), Some(shadow_root));
+        assert_eq!(tree.host(shadow_root), Some(widget));
+
+        assert_eq!(tree.flattened_children(widget), vec![frame]);
+        assert_eq!(tree.flattened_children(slot), vec![light]);
+
+        assert_eq!(tree.descendants(body, false), vec![widget, light, footer, element]);
+        assert_eq!(tree.descendants(body, true), vec![widget, shadow_root, frame, slot, light, footer, element]);
+    }
+
+    #[test]
+    fn test_dom_traversal() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+        let div = builder.add_element(parser, body, "div");
+        let text = builder.add_text(parser, div, "Hello");
+        let moved = builder.add_element(parser, body, "span");
+        builder.set_attribute(parser, moved, "class", "ad");
+        builder.insert_node(parser, moved, div);
+        let graph = builder.build();
+        let ids = |nodes: Vec<&crate::graph::Node>| nodes.into_iter().map(|node| node.id).collect::<Vec<_>>();
+        let node = |id| graph.nodes.get(&id).unwrap();
+
+        assert_eq!(graph.dom_parent(node(text)).map(|parent| parent.id), Some(div));
+        assert_eq!(graph.dom_parent(node(root)).map(|parent| parent.id), None);
+        assert_eq!(ids(graph.dom_children(node(body)).collect()), vec![div]);
+        assert_eq!(ids(graph.dom_children(node(div)).collect()), vec![text, moved]);
+        assert_eq!(ids(graph.dom_ancestors(node(moved)).collect()), vec![div, body, root]);
+    }
+
+    #[test]
+    fn test_dom_select() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let body = builder.add_element(parser, root, "body");
+        let banner = builder.add_element(parser, body, "div");
+        builder.set_attribute(parser, banner, "class", "banner ad");
+        builder.set_attribute(parser, banner, "id", "top");
+        let image = builder.add_element(parser, banner, "IMG");
+        builder.set_attribute(parser, image, "data-ad", "1");
+        builder.set_attribute(parser, image, "alt", "Buy now");
+        let widget = builder.add_element(parser, body, "ad-widget");
+        let element = builder.add_element(parser, body, "script");
+        let script = builder.add_script(element, None, "attachShadow()");
+        let shadow_root = builder.attach_shadow(script, widget, "open");
+        let hidden = builder.add_element(script, shadow_root, "div");
+        builder.set_attribute(script, hidden, "class", "ad");
+        builder.set_attribute(script, banner, "data-ad", "");
+        builder.delete_attribute(script, image, "data-ad");
+        let graph = builder.build();
+        let node = |id| graph.nodes.get(&id).unwrap();
+        let select = |selector: &str, pierce_shadow| graph.dom_select(node(root), &Selector::try_from(selector).unwrap(), pierce_shadow)
+            .into_iter().map(|node| node.id).collect::<Vec<_>>();
+
+        assert_eq!(select(".ad", false), vec![banner]);
+        assert_eq!(select(".ad", true), vec![banner, hidden]);
+        assert_eq!(select("div#top.banner", false), vec![banner]);
+        assert_eq!(select("img", false), vec![image]);
+        assert_eq!(select("[data-ad]", false), vec![banner]);
+        assert_eq!(select("[alt='Buy now'], ad-widget", false), vec![image, widget]);
+        assert_eq!(select("*[alt=Buy]", false), vec![]);
+        assert_eq!(select("span", true), vec![]);
+
+        assert_eq!(Selector::try_from("div>p"), Err(ParseSelectorError::Unexpected('>')));
+        assert_eq!(Selector::try_from("div p"), Err(ParseSelectorError::Unexpected(' ')));
+        assert_eq!(Selector::try_from("a:hover"), Err(ParseSelectorError::Unexpected(':')));
+        assert_eq!(Selector::try_from("div,"), Err(ParseSelectorError::Empty));
+        assert_eq!(Selector::try_from("[src"), Err(ParseSelectorError::Unterminated));
+    }
+}
@@ -0,0 +1,394 @@
+//! Export of graphs into formats for other tools.
+//!
+//! [`html`] produces a single self-contained page embedding the graph and a small viewer, with a
+//! force-directed layout, filters by node kind, an inspector for the selected node, and a
+//! timeline slider that hides everything recorded after the selected time. The viewer doesn't
+//! load anything from the network, so the page can be shared and opened as a plain file. Large
+//! graphs should be [pruned](crate::prune) first, as the layout runs in the browser.
+//!
+//! A [`Highlight`] is a set of nodes and edges, like the effects of an edge or everything
+//! matching a URL pattern. It serializes to a JSON object with `nodes` and `edges` arrays of
+//! GraphML ids (`n12`, `e34`, with a `:FRAME_ID` suffix for remote frames), which viewers can load
+//! to highlight those parts of the original GraphML file. Highlights are also shown by the viewer
+//! embedded by [`html`].
+//!
+//! For graph machine learning and network analysis, [`edge_list`] and [`adjacency_matrix`] give
+//! the structure of the graph numerically, with nodes numbered by [`NodeRows`]. [`node_table`] and
+//! [`edge_table`] map those numbers back to GraphML ids and kinds, optionally with one-hot
+//! encodings of the kinds to use as features.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::graph::{Edge, EdgeId, NodeId, PageGraph};
+use crate::types::{EdgeKind, NodeKind};
+use crate::url_search::{UrlLocation, UrlPattern};
+
+/// The viewer's styles and script, embedded into every exported page.
+const VIEWER_STYLE: &str = include_str!("export/viewer.css");
+const VIEWER_SCRIPT: &str = include_str!("export/viewer.js");
+
+/// A set of nodes and edges to highlight in a viewer.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    pub nodes: BTreeSet<NodeId>,
+    pub edges: BTreeSet<EdgeId>,
+}
+
+impl Highlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlights every node and edge in the graph, e.g. one reduced with
+    /// [`reachable_from`](PageGraph::reachable_from).
+    pub fn of(graph: &PageGraph) -> Self {
+        Self {
+            nodes: graph.nodes.keys().copied().collect(),
+            edges: graph.edges.keys().copied().collect(),
+        }
+    }
+
+    /// Highlights an edge and everything it caused, as found by
+    /// [`all_downstream_effects_of`](PageGraph::all_downstream_effects_of).
+    pub fn downstream_of(graph: &PageGraph, edge: &Edge) -> Self {
+        let mut highlight = Self::new();
+        highlight.insert_edge(edge);
+        graph.all_downstream_effects_of(edge).into_iter().for_each(|edge| highlight.insert_edge(edge));
+        highlight
+    }
+
+    /// Highlights every node and edge where a URL matching the pattern was recorded, as found by
+    /// [`find_urls`](PageGraph::find_urls).
+    pub fn url_matches(graph: &PageGraph, pattern: &UrlPattern) -> Self {
+        let mut highlight = Self::new();
+        for hit in graph.find_urls(pattern) {
+            match hit.location {
+                UrlLocation::Resource(node) | UrlLocation::ScriptSrc(node) | UrlLocation::FrameUrl(node) | UrlLocation::Stylesheet(node) => {
+                    highlight.nodes.insert(node);
+                }
+                UrlLocation::Attribute(edge) => highlight.insert_edge(graph.edges.get(&edge).unwrap()),
+            }
+        }
+        highlight
+    }
+
+    /// Highlights an edge along with its source and target, so that it's connected to the rest of
+    /// the highlight.
+    pub fn insert_edge(&mut self, edge: &Edge) {
+        self.edges.insert(edge.id);
+        self.nodes.insert(edge.source);
+        self.nodes.insert(edge.target);
+    }
+
+    /// Adds everything highlighted by `other`.
+    pub fn extend(&mut self, other: Highlight) {
+        self.nodes.extend(other.nodes);
+        self.edges.extend(other.edges);
+    }
+}
+
+impl serde::Serialize for Highlight {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut highlight = serializer.serialize_struct("Highlight", 2)?;
+        highlight.serialize_field("nodes", &self.nodes.iter().map(|node| node.to_string()).collect::<Vec<_>>())?;
+        highlight.serialize_field("edges", &self.edges.iter().map(|edge| edge.to_string()).collect::<Vec<_>>())?;
+        highlight.end()
+    }
+}
+
+/// Highlights list GraphML ids, like `n12` or `e34:FRAME_ID`.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Highlight {
+    fn schema_name() -> String {
+        "Highlight".to_string()
+    }
+
+    fn json_schema(generator: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        #[derive(schemars::JsonSchema)]
+        #[allow(dead_code)]
+        struct Highlight {
+            nodes: Vec<String>,
+            edges: Vec<String>,
+        }
+        Highlight::json_schema(generator)
+    }
+}
+
+/// Serializes a string as a JSON string literal. `<` is escaped as well, so the result can be
+/// embedded in a `<script>` element without closing it.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '<' => json.push_str("\\u003c"),
+            c if (c as u32) < 0x20 || c == '\u{2028}' || c == '\u{2029}' => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn json_timestamp(timestamp: Option<isize>) -> String {
+    timestamp.map(|timestamp| timestamp.to_string()).unwrap_or_else(|| "null".to_string())
+}
+
+/// Serializes the nodes and edges of the graph, sorted by id, in the format the viewer reads.
+fn viewer_json(graph: &PageGraph, highlight: Option<&Highlight>) -> String {
+    let mut nodes = graph.nodes.values().collect::<Vec<_>>();
+    nodes.sort_by_key(|node| node.id);
+    let mut edges = graph.edges.values().collect::<Vec<_>>();
+    edges.sort_by_key(|edge| edge.id);
+
+    let mut json = String::new();
+    write!(json, "{{\"url\":{},\"nodes\":[", json_string(&graph.desc.url)).unwrap();
+    for (i, node) in nodes.iter().enumerate() {
+        write!(json, "{}{{\"id\":{},\"kind\":{},\"label\":{},\"description\":{},\"timestamp\":{},\"highlighted\":{}}}",
+            if i == 0 { "" } else { "," },
+            json_string(&node.id.to_string()),
+            json_string(&format!("{:?}", node.node_type.kind())),
+            json_string(&node.label()),
+            json_string(&node.describe(graph)),
+            json_timestamp(Some(node.node_timestamp)),
+            highlight.map(|highlight| highlight.nodes.contains(&node.id)).unwrap_or(false)).unwrap();
+    }
+    json.push_str("],\"edges\":[");
+    for (i, edge) in edges.iter().enumerate() {
+        write!(json, "{}{{\"id\":{},\"source\":{},\"target\":{},\"kind\":{},\"description\":{},\"timestamp\":{},\"highlighted\":{}}}",
+            if i == 0 { "" } else { "," },
+            json_string(&edge.id.to_string()),
+            json_string(&edge.source.to_string()),
+            json_string(&edge.target.to_string()),
+            json_string(&format!("{:?}", edge.edge_type.kind())),
+            json_string(&edge.describe(graph)),
+            json_timestamp(edge.edge_timestamp),
+            highlight.map(|highlight| highlight.edges.contains(&edge.id)).unwrap_or(false)).unwrap();
+    }
+    json.push_str("]}");
+    json
+}
+
+/// Renders the graph as a self-contained HTML page with an interactive viewer, optionally
+/// emphasizing a highlighted part of it.
+pub fn html(graph: &PageGraph, highlight: Option<&Highlight>) -> String {
+    let title = format!("PageGraph of {}", graph.desc.url)
+        .replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    format!(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n",
+        "<style>\n{style}</style>\n</head>\n<body>\n",
+        "<div id=\"sidebar\">\n<h1>{title}</h1>\n",
+        "<label>Time <input id=\"timeline\" type=\"range\"> <span id=\"time\"></span></label>\n",
+        "<h2>Node kinds</h2>\n<div id=\"kinds\"></div>\n",
+        "<h2>Inspector</h2>\n<div id=\"inspector\">Click a node to inspect it.</div>\n",
+        "</div>\n<canvas id=\"graph\"></canvas>\n",
+        "<script id=\"graph-data\" type=\"application/json\">{data}</script>\n",
+        "<script>\n{script}</script>\n</body>\n</html>\n"),
+        title = title,
+        style = VIEWER_STYLE,
+        data = viewer_json(graph, highlight),
+        script = VIEWER_SCRIPT)
+}
+
+/// Consecutive numbers from 0 for the nodes of a graph, in order of node id, used as the rows and
+/// columns of numeric exports.
+#[derive(Debug, Clone)]
+pub struct NodeRows {
+    ids: Vec<NodeId>,
+    rows: HashMap<NodeId, usize>,
+}
+
+impl NodeRows {
+    pub fn of(graph: &PageGraph) -> Self {
+        let mut ids = graph.nodes.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        let rows = ids.iter().enumerate().map(|(row, id)| (*id, row)).collect();
+        Self { ids, rows }
+    }
+
+    /// The row of a node, or `None` if it isn't in the graph.
+    pub fn row(&self, id: NodeId) -> Option<usize> {
+        self.rows.get(&id).copied()
+    }
+
+    /// The node at a row.
+    pub fn id(&self, row: usize) -> Option<NodeId> {
+        self.ids.get(row).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+fn sorted_edges(graph: &PageGraph) -> Vec<&Edge> {
+    let mut edges = graph.edges.values().collect::<Vec<_>>();
+    edges.sort_by_key(|edge| edge.id);
+    edges
+}
+
+/// Writes a CSV header naming a column for each kind, like `is_Script`.
+fn write_one_hot_header<K: std::fmt::Debug>(csv: &mut String, kinds: &[K]) {
+    kinds.iter().for_each(|kind| write!(csv, ",is_{:?}", kind).unwrap());
+}
+
+fn write_one_hot<K: PartialEq>(csv: &mut String, kinds: &[K], kind: K) {
+    kinds.iter().for_each(|other| csv.push_str(if *other == kind { ",1" } else { ",0" }));
+}
+
+/// Lists each edge as a line of `SOURCE TARGET` node rows, in order of edge id, so that parallel
+/// edges appear once each. This is the format read by NetworkX's `read_edgelist`, igraph's
+/// `Graph.Read_Edgelist`, and `numpy.loadtxt`, whose transposed result is a PyTorch Geometric
+/// `edge_index`.
+pub fn edge_list(graph: &PageGraph, rows: &NodeRows) -> String {
+    let mut list = String::new();
+    for edge in sorted_edges(graph) {
+        writeln!(list, "{} {}", rows.row(edge.source).unwrap(), rows.row(edge.target).unwrap()).unwrap();
+    }
+    list
+}
+
+/// Renders the sparse adjacency matrix in Matrix Market coordinate format, as read by
+/// `scipy.io.mmread`. Each entry is the number of edges from the row's node to the column's node.
+/// The format numbers rows and columns from 1, so the node at row `r` of `rows` is at `r + 1`.
+pub fn adjacency_matrix(graph: &PageGraph, rows: &NodeRows) -> String {
+    let mut entries = BTreeMap::<(usize, usize), usize>::new();
+    for edge in graph.edges.values() {
+        *entries.entry((rows.row(edge.source).unwrap(), rows.row(edge.target).unwrap())).or_default() += 1;
+    }
+    let mut matrix = String::from("%%MatrixMarket matrix coordinate integer general\n");
+    writeln!(matrix, "{} {} {}", rows.len(), rows.len(), entries.len()).unwrap();
+    for ((source, target), count) in entries {
+        writeln!(matrix, "{} {} {}", source + 1, target + 1, count).unwrap();
+    }
+    matrix
+}
+
+/// Renders a CSV table of each node's row, GraphML id, and kind, with a one-hot column for every
+/// [`NodeKind`] if `one_hot` is set.
+pub fn node_table(graph: &PageGraph, rows: &NodeRows, one_hot: bool) -> String {
+    let mut csv = String::from("row,id,kind");
+    if one_hot {
+        write_one_hot_header(&mut csv, &NodeKind::ALL);
+    }
+    csv.push('\n');
+    for (row, id) in rows.ids.iter().enumerate() {
+        let kind = graph.nodes.get(id).unwrap().node_type.kind();
+        write!(csv, "{},{},{:?}", row, id, kind).unwrap();
+        if one_hot {
+            write_one_hot(&mut csv, &NodeKind::ALL, kind);
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Renders a CSV table of each edge's line in the [`edge_list`], GraphML id, timestamp, and kind,
+/// with a one-hot column for every [`EdgeKind`] if `one_hot` is set.
+pub fn edge_table(graph: &PageGraph, one_hot: bool) -> String {
+    let mut csv = String::from("line,id,timestamp,kind");
+    if one_hot {
+        write_one_hot_header(&mut csv, &EdgeKind::ALL);
+    }
+    csv.push('\n');
+    for (line, edge) in sorted_edges(graph).into_iter().enumerate() {
+        let kind = edge.edge_type.kind();
+        let timestamp = edge.edge_timestamp.map(|timestamp| timestamp.to_string()).unwrap_or_default();
+        write!(csv, "{},{},{},{:?}", line, edge.id, timestamp, kind).unwrap();
+        if one_hot {
+            write_one_hot(&mut csv, &EdgeKind::ALL, kind);
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_html_export() {
+        let mut builder = PageGraphBuilder::new("https://example.com/?a=<b>&c");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        builder.add_script(element, None, "</script><script>alert(1)");
+        let graph = builder.build();
+
+        let html = html(&graph, None);
+        assert!(html.contains("<title>PageGraph of https://example.com/?a=&lt;b&gt;&amp;c</title>"));
+        // Recorded text can't close the data element
+        assert_eq!(html.matches("</script>").count(), 2);
+        assert!(html.contains("\"kind\":\"Script\""));
+        assert!(!html.contains("\"highlighted\":true"));
+        assert_eq!(json_string("a\"\\\n\u{1}"), "\"a\\\"\\\\\\n\\u0001\"");
+    }
+
+    #[test]
+    fn test_highlight() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "img");
+        let set_src = builder.set_attribute(parser, element, "src", "https://ads.net/banner.png");
+        builder.add_request(parser, "https://example.com/app.js", RequestType::Script);
+        let graph = builder.build();
+
+        let highlight = Highlight::url_matches(&graph, &UrlPattern::glob("https://ads.net/*"));
+        assert_eq!(highlight.edges.iter().copied().collect::<Vec<_>>(), vec![set_src]);
+        assert_eq!(highlight.nodes.iter().copied().collect::<Vec<_>>(), vec![parser, element]);
+        assert_eq!(serde_json::to_string(&highlight).unwrap(),
+            format!("{{\"nodes\":[\"{}\",\"{}\"],\"edges\":[\"{}\"]}}", parser, element, set_src));
+
+        let html = html(&graph, Some(&highlight));
+        assert_eq!(html.matches("\"highlighted\":true").count(), 3);
+    }
+
+    #[test]
+    fn test_numeric_export() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "img");
+        builder.set_attribute(parser, element, "src", "https://example.com/a.png");
+        let graph = builder.build();
+        let rows = NodeRows::of(&graph);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows.id(rows.row(element).unwrap()), Some(element));
+
+        let list = edge_list(&graph, &rows);
+        assert_eq!(list.lines().count(), graph.edges.len());
+        let parser_to_element = format!("{} {}", rows.row(parser).unwrap(), rows.row(element).unwrap());
+        assert!(list.lines().filter(|line| *line == parser_to_element).count() > 1);
+
+        let matrix = adjacency_matrix(&graph, &rows);
+        let mut lines = matrix.lines().skip(1);
+        let size = lines.next().unwrap().split(' ').map(|n| n.parse::<usize>().unwrap()).collect::<Vec<_>>();
+        assert_eq!(&size[..2], &[3, 3]);
+        assert_eq!(lines.map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap()).sum::<usize>(), graph.edges.len());
+
+        let nodes = node_table(&graph, &rows, true);
+        let header = nodes.lines().next().unwrap();
+        assert_eq!(header.split(',').count(), 3 + NodeKind::ALL.len());
+        let element_line = nodes.lines().find(|line| line.contains(",HtmlElement,")).unwrap();
+        assert_eq!(element_line.split(',').skip(3).filter(|value| *value == "1").count(), 1);
+
+        let edges = edge_table(&graph, false);
+        assert_eq!(edges.lines().count(), graph.edges.len() + 1);
+        assert!(edges.contains(",SetAttribute"));
+    }
+}
@@ -0,0 +1,227 @@
+"use strict";
+(function () {
+  const data = JSON.parse(document.getElementById("graph-data").textContent);
+  const canvas = document.getElementById("graph");
+  const context = canvas.getContext("2d");
+  const timeline = document.getElementById("timeline");
+  const timeLabel = document.getElementById("time");
+  const inspector = document.getElementById("inspector");
+
+  const nodes = data.nodes;
+  const byId = new Map(nodes.map(node => [node.id, node]));
+  const edges = data.edges.filter(edge => byId.has(edge.source) && byId.has(edge.target));
+  for (const edge of edges) {
+    edge.from = byId.get(edge.source);
+    edge.to = byId.get(edge.target);
+  }
+
+  // Start from a circle, so the layout is deterministic.
+  nodes.forEach((node, i) => {
+    const angle = i * 2.399963;
+    const radius = 10 * Math.sqrt(i + 1);
+    node.x = radius * Math.cos(angle);
+    node.y = radius * Math.sin(angle);
+    node.vx = 0;
+    node.vy = 0;
+  });
+
+  const kinds = [...new Set(nodes.map(node => node.kind))].sort();
+  const colors = new Map(kinds.map((kind, i) => [kind, "hsl(" + Math.round(i * 360 / kinds.length) + ", 70%, 50%)"]));
+  const hiddenKinds = new Set();
+  const kindsElement = document.getElementById("kinds");
+  for (const kind of kinds) {
+    const label = document.createElement("label");
+    const checkbox = document.createElement("input");
+    checkbox.type = "checkbox";
+    checkbox.checked = true;
+    checkbox.addEventListener("change", () => {
+      checkbox.checked ? hiddenKinds.delete(kind) : hiddenKinds.add(kind);
+      draw();
+    });
+    const swatch = document.createElement("span");
+    swatch.className = "swatch";
+    swatch.style.background = colors.get(kind);
+    const count = nodes.filter(node => node.kind === kind).length;
+    label.append(checkbox, swatch, kind + " (" + count + ")");
+    kindsElement.append(label);
+  }
+
+  // Items without a timestamp are shown at every time.
+  const timestamps = nodes.concat(edges).map(item => item.timestamp).filter(timestamp => timestamp !== null);
+  const minTime = timestamps.length ? Math.min(...timestamps) : 0;
+  const maxTime = timestamps.length ? Math.max(...timestamps) : 0;
+  timeline.min = minTime;
+  timeline.max = maxTime;
+  timeline.value = maxTime;
+  timeline.addEventListener("input", draw);
+
+  const isRecorded = item => item.timestamp === null || item.timestamp <= Number(timeline.value);
+  const isNodeVisible = node => isRecorded(node) && !hiddenKinds.has(node.kind);
+  const isEdgeVisible = edge => isRecorded(edge) && isNodeVisible(edge.from) && isNodeVisible(edge.to);
+
+  let scale = 1, offsetX = 0, offsetY = 0, selected = null;
+
+  function resize() {
+    canvas.width = canvas.clientWidth * devicePixelRatio;
+    canvas.height = canvas.clientHeight * devicePixelRatio;
+    draw();
+  }
+
+  function draw() {
+    timeLabel.textContent = (Number(timeline.value) - minTime) + " / " + (maxTime - minTime);
+    context.setTransform(1, 0, 0, 1, 0, 0);
+    context.clearRect(0, 0, canvas.width, canvas.height);
+    context.setTransform(scale * devicePixelRatio, 0, 0, scale * devicePixelRatio,
+      canvas.width / 2 + offsetX * devicePixelRatio, canvas.height / 2 + offsetY * devicePixelRatio);
+    context.lineWidth = 1 / scale;
+    for (const edge of edges) {
+      if (!isEdgeVisible(edge)) continue;
+      const touchesSelected = selected && (edge.from === selected || edge.to === selected);
+      context.strokeStyle = touchesSelected ? "rgba(0, 0, 0, 0.8)" :
+        edge.highlighted ? "rgba(230, 120, 0, 0.9)" : "rgba(0, 0, 0, 0.15)";
+      context.beginPath();
+      context.moveTo(edge.from.x, edge.from.y);
+      context.lineTo(edge.to.x, edge.to.y);
+      context.stroke();
+    }
+    for (const node of nodes) {
+      if (!isNodeVisible(node)) continue;
+      context.fillStyle = colors.get(node.kind);
+      context.beginPath();
+      context.arc(node.x, node.y, node === selected ? 7 : 4, 0, 2 * Math.PI);
+      context.fill();
+      if (node.highlighted) {
+        context.strokeStyle = "rgb(230, 120, 0)";
+        context.lineWidth = 2 / scale;
+        context.stroke();
+        context.lineWidth = 1 / scale;
+      }
+    }
+  }
+
+  // A simple force layout: springs along edges, repulsion between nodes, and gravity towards the
+  // center. Repulsion is only computed against a sample of nodes in large graphs.
+  let iterations = 0;
+  function step() {
+    const sample = Math.min(nodes.length, 200);
+    for (const node of nodes) {
+      for (let i = 0; i < sample; i++) {
+        const other = nodes[sample === nodes.length ? i : Math.floor(Math.random() * nodes.length)];
+        if (other === node) continue;
+        const dx = node.x - other.x, dy = node.y - other.y;
+        const distance = Math.max(Math.sqrt(dx * dx + dy * dy), 1);
+        const force = 200 * (nodes.length / sample) / (distance * distance);
+        node.vx += dx / distance * force;
+        node.vy += dy / distance * force;
+      }
+      node.vx -= node.x * 0.002;
+      node.vy -= node.y * 0.002;
+    }
+    for (const edge of edges) {
+      const dx = edge.to.x - edge.from.x, dy = edge.to.y - edge.from.y;
+      const distance = Math.max(Math.sqrt(dx * dx + dy * dy), 1);
+      const force = (distance - 30) * 0.02;
+      edge.from.vx += dx / distance * force;
+      edge.from.vy += dy / distance * force;
+      edge.to.vx -= dx / distance * force;
+      edge.to.vy -= dy / distance * force;
+    }
+    const cooling = Math.max(0.05, 1 - iterations / 300);
+    for (const node of nodes) {
+      node.vx = Math.max(-20, Math.min(20, node.vx)) * 0.6;
+      node.vy = Math.max(-20, Math.min(20, node.vy)) * 0.6;
+      node.x += node.vx * cooling;
+      node.y += node.vy * cooling;
+    }
+    draw();
+    if (++iterations < 400) requestAnimationFrame(step);
+  }
+
+  function text(content, tag) {
+    const element = document.createElement(tag || "span");
+    element.textContent = content;
+    return element;
+  }
+
+  function edgeList(title, list, otherEnd) {
+    const container = document.createElement("div");
+    container.append(text(title + " (" + list.length + ")", "strong"));
+    const items = document.createElement("ul");
+    for (const edge of list) {
+      const item = text(edge.id + " " + edge.kind + " ", "li");
+      const link = text(otherEnd(edge).id + " " + otherEnd(edge).label, "a");
+      link.addEventListener("click", () => select(otherEnd(edge)));
+      item.append(link);
+      item.title = edge.description;
+      items.append(item);
+    }
+    container.append(items);
+    return container;
+  }
+
+  function select(node) {
+    selected = node;
+    inspector.replaceChildren();
+    if (node) {
+      inspector.append(
+        text(node.id + " " + node.kind + (node.highlighted ? " (highlighted)" : ""), "strong"),
+        text(node.description, "pre"),
+        text("Timestamp: " + (node.timestamp === null ? "-" : node.timestamp - minTime), "div"),
+        edgeList("Incoming", edges.filter(edge => edge.to === node), edge => edge.from),
+        edgeList("Outgoing", edges.filter(edge => edge.from === node), edge => edge.to));
+    } else {
+      inspector.textContent = "Click a node to inspect it.";
+    }
+    draw();
+  }
+
+  function toGraph(event) {
+    const rect = canvas.getBoundingClientRect();
+    return {
+      x: (event.clientX - rect.left - rect.width / 2 - offsetX) / scale,
+      y: (event.clientY - rect.top - rect.height / 2 - offsetY) / scale,
+    };
+  }
+
+  let drag = null;
+  canvas.addEventListener("mousedown", event => {
+    drag = { x: event.clientX, y: event.clientY, offsetX, offsetY, moved: false };
+  });
+  window.addEventListener("mousemove", event => {
+    if (!drag) return;
+    if (Math.abs(event.clientX - drag.x) + Math.abs(event.clientY - drag.y) > 3) drag.moved = true;
+    offsetX = drag.offsetX + event.clientX - drag.x;
+    offsetY = drag.offsetY + event.clientY - drag.y;
+    draw();
+  });
+  window.addEventListener("mouseup", event => {
+    if (drag && !drag.moved) {
+      const point = toGraph(event);
+      let closest = null, closestDistance = 10 / scale;
+      for (const node of nodes) {
+        if (!isNodeVisible(node)) continue;
+        const distance = Math.hypot(node.x - point.x, node.y - point.y);
+        if (distance < closestDistance) {
+          closest = node;
+          closestDistance = distance;
+        }
+      }
+      select(closest);
+    }
+    drag = null;
+  });
+  canvas.addEventListener("wheel", event => {
+    event.preventDefault();
+    const factor = Math.exp(-event.deltaY * 0.001);
+    const rect = canvas.getBoundingClientRect();
+    const x = event.clientX - rect.left - rect.width / 2, y = event.clientY - rect.top - rect.height / 2;
+    offsetX = x - (x - offsetX) * factor;
+    offsetY = y - (y - offsetY) * factor;
+    scale *= factor;
+    draw();
+  }, { passive: false });
+
+  window.addEventListener("resize", resize);
+  resize();
+  step();
+})();
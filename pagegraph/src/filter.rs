@@ -0,0 +1,160 @@
+//! Filtering of the nodes and edges in a graph.
+//!
+//! [`filter_nodes`](PageGraph::filter_nodes) and [`filter_edges`](PageGraph::filter_edges) lazily
+//! yield the items matching a predicate. The functions in this module build common predicates,
+//! which can be combined in a closure:
+//!
+//! ```
+//! use pagegraph::builder::PageGraphBuilder;
+//! use pagegraph::filter::{self, GraphItem};
+//! use pagegraph::types::{EdgeKind, RequestType};
+//!
+//! let mut builder = PageGraphBuilder::new("https://example.com/");
+//! let parser = builder.parser();
+//! builder.add_request(parser, "https://example.com/app.js", RequestType::Script);
+//! builder.advance_time(1000);
+//! builder.add_request(parser, "https://example.com/late.js", RequestType::Script);
+//! let graph = builder.build();
+//!
+//! // Every request started in the root frame during the first second of the page load
+//! let is_request = filter::edge_kind(EdgeKind::RequestStart);
+//! let in_first_second = filter::in_time_range(0..1000);
+//! let early_requests = graph.filter_edges(|edge| is_request(edge) && in_first_second(edge) && edge.frame_id().is_none());
+//! assert_eq!(early_requests.count(), 1);
+//! ```
+//!
+//! For expensive predicates over large graphs, [`par_filter_nodes`](PageGraph::par_filter_nodes)
+//! and [`par_filter_edges`](PageGraph::par_filter_edges) evaluate the predicate on several
+//! threads instead.
+
+use std::ops::RangeBounds;
+
+use crate::graph::{Edge, FrameId, HasFrameId, Node, PageGraph};
+use crate::types::{EdgeKind, NodeKind};
+use crate::url_search::UrlPattern;
+
+/// Properties shared by nodes and edges, for predicates that apply to both.
+pub trait GraphItem {
+    /// The frame the item was merged from, or `None` for the root frame's graph.
+    fn frame_id(&self) -> Option<FrameId>;
+    /// Milliseconds relative to the start of the page load.
+    fn timestamp(&self) -> Option<isize>;
+}
+
+impl GraphItem for Node {
+    fn frame_id(&self) -> Option<FrameId> {
+        self.id.get_frame_id()
+    }
+
+    fn timestamp(&self) -> Option<isize> {
+        Some(self.node_timestamp)
+    }
+}
+
+impl GraphItem for Edge {
+    fn frame_id(&self) -> Option<FrameId> {
+        self.id.get_frame_id()
+    }
+
+    fn timestamp(&self) -> Option<isize> {
+        self.edge_timestamp
+    }
+}
+
+/// Matches nodes of the given kind.
+pub fn node_kind(kind: NodeKind) -> impl Fn(&Node) -> bool + Sync {
+    move |node| node.node_type.kind() == kind
+}
+
+/// Matches edges of the given kind.
+pub fn edge_kind(kind: EdgeKind) -> impl Fn(&Edge) -> bool + Sync {
+    move |edge| edge.edge_type.kind() == kind
+}
+
+/// Matches items merged from the given frame, or from the root frame's graph for `None`.
+pub fn in_frame<T: GraphItem>(frame_id: Option<FrameId>) -> impl Fn(&T) -> bool + Sync {
+    move |item| item.frame_id() == frame_id
+}
+
+/// Matches items with a timestamp within the given range. Edges without timestamps never match.
+pub fn in_time_range<T: GraphItem, R: RangeBounds<isize> + Sync>(range: R) -> impl Fn(&T) -> bool + Sync {
+    move |item| item.timestamp().map(|timestamp| range.contains(&timestamp)).unwrap_or(false)
+}
+
+/// Matches nodes with a URL, like resources, remote scripts, and documents, matching the pattern.
+pub fn url_matches(pattern: &UrlPattern) -> impl Fn(&Node) -> bool + Sync + '_ {
+    move |node| node.node_type.url().map(|url| pattern.is_match(url)).unwrap_or(false)
+}
+
+/// Evaluates `f` over `items` on as many threads as are available, keeping the order of `items`.
+fn par_filter<T: Sync, F: Fn(&T) -> bool + Sync>(items: Vec<&T>, f: F) -> Vec<&T> {
+    let threads = std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+    let chunk_size = ((items.len() + threads - 1) / threads).max(1);
+    let f = &f;
+    std::thread::scope(|scope| {
+        let workers = items.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().copied().filter(|item| f(item)).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        workers.into_iter().flat_map(|worker| worker.join().expect("Filter predicate panicked")).collect()
+    })
+}
+
+impl PageGraph {
+    /// Returns the nodes matching the predicate, in no particular order.
+    pub fn filter_nodes<F: Fn(&Node) -> bool>(&self, f: F) -> impl Iterator<Item=&Node> {
+        self.nodes.values().filter(move |node| f(node))
+    }
+
+    /// Returns the edges matching the predicate, in no particular order.
+    pub fn filter_edges<F: Fn(&Edge) -> bool>(&self, f: F) -> impl Iterator<Item=&Edge> {
+        self.edges.values().filter(move |edge| f(edge))
+    }
+
+    /// Like [`filter_nodes`](PageGraph::filter_nodes), but evaluates the predicate on several
+    /// threads, and collects the matching nodes.
+    pub fn par_filter_nodes<F: Fn(&Node) -> bool + Sync>(&self, f: F) -> Vec<&Node> {
+        par_filter(self.nodes.values().collect(), f)
+    }
+
+    /// Like [`filter_edges`](PageGraph::filter_edges), but evaluates the predicate on several
+    /// threads, and collects the matching edges.
+    pub fn par_filter_edges<F: Fn(&Edge) -> bool + Sync>(&self, f: F) -> Vec<&Edge> {
+        par_filter(self.edges.values().collect(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_filters() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        builder.add_element(parser, root, "img");
+        builder.advance_time(1000);
+        builder.add_request(parser, "https://tracker.net/pixel.gif", RequestType::Image);
+        builder.add_request(parser, "https://example.com/app.js", RequestType::Script);
+        let graph = builder.build();
+
+        let late = in_time_range(1000..);
+        let is_request = edge_kind(EdgeKind::RequestStart);
+        assert_eq!(graph.filter_edges(|edge| is_request(edge) && late(edge)).count(), 2);
+        assert_eq!(graph.filter_edges(in_time_range(..1000)).filter(|edge| is_request(edge)).count(), 0);
+
+        let pattern = UrlPattern::glob("https://tracker.net/*");
+        let trackers = graph.filter_nodes(url_matches(&pattern)).collect::<Vec<_>>();
+        assert_eq!(trackers.len(), 1);
+        assert_eq!(trackers[0].node_type.url().map(|url| url.as_str()), Some("https://tracker.net/pixel.gif"));
+
+        let mut elements = graph.par_filter_nodes(|node| node_kind(NodeKind::HtmlElement)(node) && in_frame(None)(node));
+        elements.sort_by_key(|node| node.id);
+        let mut expected = graph.filter_nodes(node_kind(NodeKind::HtmlElement)).collect::<Vec<_>>();
+        expected.sort_by_key(|node| node.id);
+        assert_eq!(elements.len(), 1);
+        assert_eq!(elements.iter().map(|node| node.id).collect::<Vec<_>>(), expected.iter().map(|node| node.id).collect::<Vec<_>>());
+    }
+}
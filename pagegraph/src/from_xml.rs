@@ -1,6 +1,7 @@
 use std::fs::File;
 use std::io::BufReader;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::convert::TryFrom;
 
 use xml::reader::{ EventReader, XmlEvent };
@@ -10,10 +11,13 @@ use crate::{ graph, types };
 
 /// Reads a PageGraph from a GraphML-formatted file.
 pub fn read_from_file(file: &str) -> graph::PageGraph {
-    let file = File::open(file).unwrap();
-    let file = BufReader::new(file);
+    read_from_reader(File::open(file).unwrap())
+}
 
-    let mut parser = EventReader::new(file);
+/// Reads a PageGraph from a stream of GraphML, such as a download or the output of a
+/// decompressor. The stream is parsed as it's read, rather than being read into memory first.
+pub fn read_from_reader<R: std::io::Read>(reader: R) -> graph::PageGraph {
+    let mut parser = EventReader::new(BufReader::new(reader));
 
     if let Ok(XmlEvent::StartDocument { .. }) = parser.next() {
         return parse_xml_document(&mut parser);
@@ -98,7 +102,7 @@ fn build_desc<R: std::io::Read>(
     graph::PageGraphDescriptor {
         version: version.unwrap(),
         about: about.unwrap(),
-        url: url.unwrap(),
+        url: url.unwrap().into(),
         is_root: is_root.unwrap().parse::<bool>().unwrap(),
         frame_id: graph::FrameId::try_from(frame_id.unwrap().as_str()).unwrap(),
         time: time.unwrap(),
@@ -273,9 +277,10 @@ fn build_key<R: std::io::Read>(
 fn build_graph<R: std::io::Read>(parser: &mut EventReader<R>, key: &KeyModel, desc: graph::PageGraphDescriptor) -> graph::PageGraph {
     const STR_REP: &'static str = "graph";
 
-    let mut edges = HashMap::new();
+    let mut edges = vec![];
     let mut nodes = HashMap::new();
     let mut graph = DiGraphMap::<graph::NodeId, Vec<graph::EdgeId>>::new();
+    let mut diagnostics = graph::ParseDiagnostics::default();
 
     while let Ok(e) = parser.next() {
         match e {
@@ -283,18 +288,16 @@ fn build_graph<R: std::io::Read>(parser: &mut EventReader<R>, key: &KeyModel, de
                 match &name.local_name[..] {
                     "node" => {
                         let node = build_node(parser, attributes, &key.node_items);
-                        graph.add_node(node.id);
-                        nodes.insert(node.id, node);
-                    }
-                    "edge" => {
-                        let edge = build_edge(parser, attributes, &key.edge_items);
-                        if let Some(concurrent_edges) = graph.edge_weight_mut(edge.source, edge.target) {
-                            concurrent_edges.push(edge.id);
-                        } else {
-                            graph.add_edge(edge.source, edge.target, vec![edge.id]);
+                        match nodes.entry(node.id) {
+                            Entry::Occupied(_) => diagnostics.duplicate_nodes.push(node),
+                            Entry::Vacant(entry) => {
+                                graph.add_node(node.id);
+                                entry.insert(node);
+                            }
                         }
-                        edges.insert(edge.id, edge);
                     }
+                    // Edges are only added once every node is known, since they could appear first.
+                    "edge" => edges.push(build_edge(parser, attributes, &key.edge_items)),
                     _ => println!("Unhandled local name in {}: {}", STR_REP, name.local_name),
                 }
             }
@@ -308,7 +311,37 @@ fn build_graph<R: std::io::Read>(parser: &mut EventReader<R>, key: &KeyModel, de
         }
     }
 
-    graph::PageGraph::new(desc, edges, nodes, graph)
+    let mut duplicate_edges = vec![];
+    let mut graph = graph::PageGraph::new(desc, HashMap::new(), nodes, graph);
+    for edge in edges {
+        if !graph.nodes.contains_key(&edge.source) || !graph.nodes.contains_key(&edge.target) {
+            diagnostics.dangling_edges.push(edge);
+        } else if graph.edges.contains_key(&edge.id) {
+            duplicate_edges.push(edge);
+        } else {
+            insert_edge(&mut graph, edge);
+        }
+    }
+    // New ids can only be assigned once every recorded id is taken, to avoid colliding with them.
+    for mut edge in duplicate_edges {
+        let new_id = graph.new_edge_id();
+        diagnostics.remapped_edges.push((edge.id, new_id));
+        edge.id = new_id;
+        insert_edge(&mut graph, edge);
+    }
+    graph.diagnostics = diagnostics;
+
+    graph.time_index();
+    graph
+}
+
+fn insert_edge(graph: &mut graph::PageGraph, edge: graph::Edge) {
+    if let Some(concurrent_edges) = graph.graph.edge_weight_mut(edge.source, edge.target) {
+        concurrent_edges.push(edge.id);
+    } else {
+        graph.graph.add_edge(edge.source, edge.target, vec![edge.id]);
+    }
+    graph.edges.insert(edge.id, edge);
 }
 
 fn build_edge<R: std::io::Read>(
@@ -553,6 +586,18 @@ macro_rules! drain_string_from {
             .expect(&format!("attribute `{}` was not present", $attr))
     };
 }
+/// Parse the attribute string as a URL, if present, keeping it as recorded if it can't be parsed
+macro_rules! drain_opt_url_from {
+    ( $attrs:ident, $key:ident, $attr:expr ) => {
+        drain_opt_string_from!($attrs, $key, $attr).map(types::Url::from)
+    };
+}
+/// Panic if the attribute string does not exist in the map, and parse it as a URL otherwise
+macro_rules! drain_url_from {
+    ( $attrs:ident, $key:ident, $attr:expr ) => {
+        types::Url::from(drain_string_from!($attrs, $key, $attr))
+    };
+}
 /// Panic if the attribute string cannot be parsed as a boolean value
 macro_rules! drain_bool_from {
     ( $attrs:ident, $key:ident, $attr:expr ) => {
@@ -583,6 +628,28 @@ macro_rules! drain_usize_from {
         }
     };
 }
+/// Interpret the attribute string as a size in bytes, where `-1` marks a response that couldn't
+/// be sized. Panic if the attribute string is otherwise not an unsigned numeric value.
+macro_rules! drain_size_from {
+    ( $attrs:ident, $key:ident, $attr:expr ) => {
+        match &drain_string_from!($attrs, $key, $attr)[..] {
+            "-1" => None,
+            size => Some(size.parse::<usize>().expect(&format!("could not parse attribute `{}` as a size", $attr))),
+        }
+    };
+}
+
+/// Drains every remaining attribute from `attrs`, keyed by its human-readable name from the key
+/// rather than its GraphML key id.
+fn drain_named_attrs(attrs: &mut HashMap<String, String>, key: &HashMap<String, KeyItem>) -> HashMap<String, String> {
+    attrs.drain().map(|(id, value)| {
+        let name = key.iter()
+            .find(|(_, item)| item.id == id)
+            .map(|(name, _)| name.to_owned())
+            .unwrap_or(id);
+        (name, value)
+    }).collect()
+}
 
 /// Allows building this type from a type string and a set of associated attributes, each of which
 /// correspond to intelligible string representations through a key.
@@ -600,6 +667,12 @@ impl KeyedAttrs for types::NodeType {
         macro_rules! drain_string {
             ( $attr:expr ) => { drain_string_from!(attrs, key, $attr) }
         }
+        macro_rules! drain_opt_url {
+            ( $attr:expr ) => { drain_opt_url_from!(attrs, key, $attr) }
+        }
+        macro_rules! drain_url {
+            ( $attr:expr ) => { drain_url_from!(attrs, key, $attr) }
+        }
         macro_rules! drain_bool {
             ( $attr:expr ) => { drain_bool_from!(attrs, key, $attr) }
         }
@@ -609,11 +682,23 @@ impl KeyedAttrs for types::NodeType {
 
         match type_str {
             "extensions" => Self::Extensions {},
+            "dedicated worker" | "worker" => Self::Worker {
+                worker_type: types::WorkerType::Dedicated,
+                url: drain_opt_url!("url"),
+            },
+            "shared worker" => Self::Worker {
+                worker_type: types::WorkerType::Shared,
+                url: drain_opt_url!("url"),
+            },
+            "service worker" => Self::Worker {
+                worker_type: types::WorkerType::Service,
+                url: drain_opt_url!("url"),
+            },
             "remote frame" => Self::RemoteFrame {
                 frame_id: graph::FrameId::try_from(&drain_string!("frame id") as &str).unwrap()
             },
             "resource" => Self::Resource {
-                url: drain_string!("url")
+                url: drain_url!("url")
             },
             "ad filter" => Self::AdFilter {
                 rule: drain_string!("rule")
@@ -637,11 +722,15 @@ impl KeyedAttrs for types::NodeType {
                 node_id: drain_usize!("node id"),
             },
             "DOM root" => Self::DomRoot {
-                url: drain_opt_string!("url"),
+                url: drain_opt_url!("url"),
                 tag_name: drain_string!("tag name"),
                 is_deleted: drain_bool!("is deleted"),
                 node_id: drain_usize!("node id"),
             },
+            "shadow root" => Self::ShadowRoot {
+                is_deleted: drain_bool!("is deleted"),
+                node_id: drain_usize!("node id"),
+            },
             "frame owner" => Self::FrameOwner {
                 tag_name: drain_string!("tag name"),
                 is_deleted: drain_bool!("is deleted"),
@@ -652,14 +741,14 @@ impl KeyedAttrs for types::NodeType {
             "session storage" => Self::SessionStorage {},
             "cookie jar" => Self::CookieJar {},
             "script" => Self::Script {
-                url: drain_opt_string!("url"),
-                script_type: drain_string!("script type"),
+                url: drain_opt_url!("url"),
+                script_type: types::ScriptType::from(&drain_string!("script type")[..]),
                 script_id: drain_usize!("script id"),
                 source: drain_string!("source"),
             },
             "parser" => Self::Parser {},
             "Brave Shields" => Self::BraveShields {},
-            "shieldsAds shield" => Self::AdsShield {},
+            "shieldsAds shield" | "ads shield" => Self::AdsShield {},
             "trackers shield" => Self::TrackersShield {},
             "javascript shield" => Self::JavascriptShield {},
             "fingerprinting shield" => Self::FingerprintingShield {},
@@ -671,7 +760,10 @@ impl KeyedAttrs for types::NodeType {
             "binding event" => Self::BindingEvent {
                 binding_event: drain_string!("binding event"),
             },
-            _ => panic!("Unknown node type `{}`", type_str),
+            _ => Self::Unknown {
+                kind: type_str.to_string(),
+                attributes: drain_named_attrs(attrs, key),
+            },
         }
     }
 }
@@ -693,6 +785,9 @@ impl KeyedAttrs for types::EdgeType {
         macro_rules! drain_usize {
             ( $attr:expr ) => { drain_usize_from!(attrs, key, $attr) }
         }
+        macro_rules! drain_size {
+            ( $attr:expr ) => { drain_size_from!(attrs, key, $attr) }
+        }
 
         match type_str {
             "filter" => Self::Filter {},
@@ -701,13 +796,20 @@ impl KeyedAttrs for types::EdgeType {
             "resource block" => Self::ResourceBlock {},
             "shield" => Self::Shield {},
             "text change" => Self::TextChange {},
-            "remove node" => Self::RemoveNode {},
+            "remove node" => Self::RemoveNode {
+                script_position: drain_opt_usize!("script position"),
+            },
             "delete node" => Self::DeleteNode {},
             "insert node" => Self::InsertNode {
                 parent: drain_usize!("parent"),
                 before: drain_opt_usize!("before"),
+                script_position: drain_opt_usize!("script position"),
             },
             "create node" => Self::CreateNode {},
+            "attach shadow" => Self::AttachShadow {
+                host: drain_usize!("host"),
+                mode: drain_opt_string!("mode"),
+            },
             "js result" => Self::JsResult {
                 value: drain_opt_string!("value"),
             },
@@ -716,20 +818,20 @@ impl KeyedAttrs for types::EdgeType {
                 script_position: drain_usize!("script position"),
             },
             "request complete" => Self::RequestComplete {
-                resource_type: drain_string!("resource type"),
+                resource_type: types::ResourceType::from(&drain_string!("resource type")[..]),
                 status: drain_string!("status"),
                 value: drain_opt_string!("value"),
                 response_hash: drain_opt_string!("response hash"),
                 request_id: drain_usize!("request id"),
-                headers: drain_string!("headers"),
-                size: drain_string!("size"),
+                headers: types::ResponseMetadata::from(&drain_string!("headers")[..]),
+                size: drain_size!("size"),
             },
             "request error" => Self::RequestError {
                 status: drain_string!("status"),
                 request_id: drain_usize!("request id"),
                 value: drain_opt_string!("value"),
-                headers: drain_string!("headers"),
-                size: drain_string!("size"),
+                headers: types::ResponseMetadata::from(&drain_string!("headers")[..]),
+                size: drain_size!("size"),
             },
             "request start" => Self::RequestStart {
                 request_type: crate::types::RequestType::from(&drain_string!("resource type")[..]),
@@ -777,16 +879,201 @@ impl KeyedAttrs for types::EdgeType {
                 key: drain_string!("key"),
                 value: drain_opt_string!("value"),
                 is_style: drain_bool!("is style"),
+                script_position: drain_opt_usize!("script position"),
             },
             "delete attribute" => Self::DeleteAttribute {
                 key: drain_string!("key"),
                 is_style: drain_bool!("is style"),
+                script_position: drain_opt_usize!("script position"),
             },
             "binding" => Self::Binding {},
             "binding event" => Self::BindingEvent {
                 script_position: drain_usize!("script position"),
             },
-            _ => panic!("Unknown edge type `{}`", type_str),
+            _ => Self::Unknown {
+                kind: type_str.to_string(),
+                attributes: drain_named_attrs(attrs, key),
+            },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(graphml: &str) -> graph::PageGraph {
+        read_from_reader(graphml.as_bytes())
+    }
+
+    #[test]
+    fn test_unknown_types_are_preserved() {
+        let graph = parse_str(r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <desc>
+    <version>0.7.0</version>
+    <about>test</about>
+    <url>https://example.com/</url>
+    <is_root>true</is_root>
+    <frame_id>0000000000000000000000000000000A</frame_id>
+    <time><start>1000</start><end>2000</end></time>
+  </desc>
+  <key id="d0" for="node" attr.name="id" attr.type="int"/>
+  <key id="d1" for="node" attr.name="node type" attr.type="string"/>
+  <key id="d2" for="node" attr.name="timestamp" attr.type="int"/>
+  <key id="d3" for="node" attr.name="flavor" attr.type="string"/>
+  <key id="d4" for="edge" attr.name="id" attr.type="int"/>
+  <key id="d5" for="edge" attr.name="edge type" attr.type="string"/>
+  <key id="d6" for="edge" attr.name="timestamp" attr.type="int"/>
+  <key id="d7" for="edge" attr.name="intensity" attr.type="int"/>
+  <graph id="G" edgedefault="directed">
+    <node id="n1"><data key="d0">1</data><data key="d1">parser</data><data key="d2">0</data></node>
+    <node id="n2"><data key="d0">2</data><data key="d1">quantum widget</data><data key="d2">5</data><data key="d3">strange</data></node>
+    <edge id="e3" source="n1" target="n2"><data key="d4">3</data><data key="d5">entangle</data><data key="d6">7</data><data key="d7">11</data></edge>
+  </graph>
+</graphml>"#);
+
+        let node = graph.nodes.get(&graph::NodeId::from(2)).unwrap();
+        match &node.node_type {
+            types::NodeType::Unknown { kind, attributes } => {
+                assert_eq!(kind, "quantum widget");
+                assert_eq!(attributes.get("flavor").map(String::as_str), Some("strange"));
+                assert_eq!(attributes.len(), 1);
+            }
+            o => panic!("unexpected node type {:?}", o),
+        }
+
+        let edge = graph.edges.get(&graph::EdgeId::from(3)).unwrap();
+        match &edge.edge_type {
+            types::EdgeType::Unknown { kind, attributes } => {
+                assert_eq!(kind, "entangle");
+                assert_eq!(attributes.get("intensity").map(String::as_str), Some("11"));
+                assert_eq!(attributes.len(), 1);
+            }
+            o => panic!("unexpected edge type {:?}", o),
+        }
+    }
+
+    #[test]
+    fn test_malformed_ids() {
+        let graph = parse_str(r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <desc>
+    <version>0.7.0</version>
+    <about>test</about>
+    <url>https://example.com/</url>
+    <is_root>true</is_root>
+    <frame_id>0000000000000000000000000000000A</frame_id>
+    <time><start>1000</start><end>2000</end></time>
+  </desc>
+  <key id="d0" for="node" attr.name="id" attr.type="int"/>
+  <key id="d1" for="node" attr.name="node type" attr.type="string"/>
+  <key id="d2" for="node" attr.name="timestamp" attr.type="int"/>
+  <key id="d4" for="edge" attr.name="id" attr.type="int"/>
+  <key id="d5" for="edge" attr.name="edge type" attr.type="string"/>
+  <key id="d6" for="edge" attr.name="timestamp" attr.type="int"/>
+  <graph id="G" edgedefault="directed">
+    <edge id="e3" source="n1" target="n2"><data key="d4">3</data><data key="d5">structure</data><data key="d6">1</data></edge>
+    <node id="n1"><data key="d0">1</data><data key="d1">parser</data><data key="d2">0</data></node>
+    <node id="n2"><data key="d0">2</data><data key="d1">parser</data><data key="d2">0</data></node>
+    <node id="n2"><data key="d0">2</data><data key="d1">quantum widget</data><data key="d2">0</data></node>
+    <edge id="e3" source="n2" target="n1"><data key="d4">3</data><data key="d5">structure</data><data key="d6">2</data></edge>
+    <edge id="e4" source="n1" target="n9"><data key="d4">4</data><data key="d5">structure</data><data key="d6">3</data></edge>
+  </graph>
+</graphml>"#);
+
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.diagnostics.duplicate_nodes.len(), 1);
+        assert!(matches!(graph.nodes.get(&graph::NodeId::from(2)).unwrap().node_type, types::NodeType::Parser {}));
+
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges.get(&graph::EdgeId::from(3)).unwrap().source, graph::NodeId::from(1));
+        let (recorded_id, new_id) = graph.diagnostics.remapped_edges[0];
+        assert_eq!(recorded_id, graph::EdgeId::from(3));
+        assert_eq!(graph.edges.get(&new_id).unwrap().source, graph::NodeId::from(2));
+
+        assert_eq!(graph.diagnostics.dangling_edges.iter().map(|edge| edge.id).collect::<Vec<_>>(), vec![graph::EdgeId::from(4)]);
+        assert!(!graph.graph.contains_node(graph::NodeId::from(9)));
+        assert_eq!(graph.graph.all_edges().map(|(_, _, edge_ids)| edge_ids.len()).sum::<usize>(), graph.edges.len());
+    }
+
+    /// A graph with a script node and a completed request, with the given attribute values.
+    fn typed_attributes_graph(script_type: &str, resource_type: &str, size: &str) -> String {
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <desc>
+    <version>0.7.0</version>
+    <about>test</about>
+    <url>https://example.com/</url>
+    <is_root>true</is_root>
+    <frame_id>0000000000000000000000000000000A</frame_id>
+    <time><start>1000</start><end>2000</end></time>
+  </desc>
+  <key id="d0" for="node" attr.name="id" attr.type="int"/>
+  <key id="d1" for="node" attr.name="node type" attr.type="string"/>
+  <key id="d2" for="node" attr.name="timestamp" attr.type="int"/>
+  <key id="d3" for="node" attr.name="url" attr.type="string"/>
+  <key id="d4" for="node" attr.name="script type" attr.type="string"/>
+  <key id="d5" for="node" attr.name="script id" attr.type="int"/>
+  <key id="d6" for="node" attr.name="source" attr.type="string"/>
+  <key id="d7" for="edge" attr.name="id" attr.type="int"/>
+  <key id="d8" for="edge" attr.name="edge type" attr.type="string"/>
+  <key id="d9" for="edge" attr.name="timestamp" attr.type="int"/>
+  <key id="d10" for="edge" attr.name="resource type" attr.type="string"/>
+  <key id="d11" for="edge" attr.name="status" attr.type="string"/>
+  <key id="d12" for="edge" attr.name="value" attr.type="string"/>
+  <key id="d13" for="edge" attr.name="response hash" attr.type="string"/>
+  <key id="d14" for="edge" attr.name="request id" attr.type="int"/>
+  <key id="d15" for="edge" attr.name="headers" attr.type="string"/>
+  <key id="d16" for="edge" attr.name="size" attr.type="string"/>
+  <graph id="G" edgedefault="directed">
+    <node id="n1"><data key="d0">1</data><data key="d1">script</data><data key="d2">0</data><data key="d4">{}</data><data key="d5">5</data><data key="d6"></data></node>
+    <node id="n2"><data key="d0">2</data><data key="d1">resource</data><data key="d2">0</data><data key="d3">https://EXAMPLE.com/a.vtt</data></node>
+    <edge id="e3" source="n2" target="n1"><data key="d7">3</data><data key="d8">request complete</data><data key="d9">1</data><data key="d10">{}</data><data key="d11">complete</data><data key="d14">8</data><data key="d15">HTTP/1.1 200 OK
+Content-Type: text/vtt</data><data key="d16">{}</data></edge>
+  </graph>
+</graphml>"#, script_type, resource_type, size)
+    }
+
+    #[test]
+    fn test_typed_attributes() {
+        let graph = parse_str(&typed_attributes_graph("module", "text track", "512"));
+        match &graph.nodes.get(&graph::NodeId::from(1)).unwrap().node_type {
+            types::NodeType::Script { script_type, script_id, .. } => {
+                assert_eq!(*script_type, types::ScriptType::Module);
+                assert_eq!(*script_id, 5);
+            }
+            o => panic!("unexpected node type {:?}", o),
+        }
+        let edge_type = &graph.edges.get(&graph::EdgeId::from(3)).unwrap().edge_type;
+        match edge_type {
+            types::EdgeType::RequestComplete { resource_type, size, headers, .. } => {
+                assert_eq!(*resource_type, types::ResourceType::TextTrack);
+                assert_eq!(*size, Some(512));
+                assert_eq!(headers.status_code, Some(200));
+                assert_eq!(headers.mime_type.as_deref(), Some("text/vtt"));
+            }
+            o => panic!("unexpected edge type {:?}", o),
+        }
+        // Typed values serialize to the strings PageGraph recorded.
+        let json = serde_json::to_value(edge_type).unwrap();
+        assert_eq!(json["RequestComplete"]["resource_type"], "text track");
+        assert_eq!(json["RequestComplete"]["headers"], "HTTP/1.1 200 OK\nContent-Type: text/vtt");
+
+        let url = graph.nodes.get(&graph::NodeId::from(2)).unwrap().node_type.url().unwrap();
+        assert_eq!(url.as_str(), "https://EXAMPLE.com/a.vtt");
+        assert_eq!(url.parsed().and_then(|url| url.host_str()), Some("example.com"));
+
+        let graph = parse_str(&typed_attributes_graph("importmap", "beacon", "-1"));
+        assert!(matches!(&graph.nodes.get(&graph::NodeId::from(1)).unwrap().node_type,
+            types::NodeType::Script { script_type: types::ScriptType::Other(other), .. } if other == "importmap"));
+        assert!(matches!(&graph.edges.get(&graph::EdgeId::from(3)).unwrap().edge_type,
+            types::EdgeType::RequestComplete { resource_type: types::ResourceType::Other(other), size: None, .. } if other == "beacon"));
+    }
+
+    #[test]
+    #[should_panic(expected = "could not parse attribute `size` as a size")]
+    fn test_malformed_size() {
+        parse_str(&typed_attributes_graph("classic", "image", "12kb"));
+    }
+}
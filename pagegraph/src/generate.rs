@@ -0,0 +1,217 @@
+//! Seeded generation of random, structurally valid PageGraphs, for property testing and fuzzing
+//! of graph analyses.
+//!
+//! Generated graphs uphold the invariants of graphs recorded by the browser:
+//! - every DOM node is created by the parser or a script, and inserted below an existing node
+//! - every script is executed by a `<script>` element or by another script
+//! - every request is started by a DOM element or a script, and either completes or fails
+//! - timestamps strictly increase in the order items were added
+//!
+//! With the `arbitrary` feature enabled, `PageGraph` also implements
+//! [`arbitrary::Arbitrary`](https://docs.rs/arbitrary).
+
+use crate::builder::PageGraphBuilder;
+use crate::graph::{NodeId, PageGraph};
+use crate::types::{RequestType, ResourceType};
+
+const TAG_NAMES: [&str; 6] = ["div", "span", "p", "a", "ul", "section"];
+
+/// Parameters controlling the shape of generated graphs.
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    /// URL of the generated page.
+    pub page_url: String,
+    /// Domains that scripts and resources may be loaded from, in addition to the page's own.
+    pub third_party_domains: Vec<String>,
+    /// Number of actions (element insertions, script executions, requests, ...) to generate.
+    pub actions: usize,
+    /// Fraction of requests that fail rather than complete, between 0 and 1.
+    pub failure_rate: f64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            page_url: "https://example.com/".to_string(),
+            third_party_domains: vec![
+                "cdn.example-cdn.net".to_string(),
+                "ads.example-ads.com".to_string(),
+                "metrics.example-analytics.io".to_string(),
+            ],
+            actions: 50,
+            failure_rate: 0.1,
+        }
+    }
+}
+
+/// A small, fast, deterministic pseudorandom number generator (SplitMix64).
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly picks an index in `0..n`. `n` must be nonzero.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        let sample = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        sample < p
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+}
+
+/// Generates a random graph from the given seed using the default configuration.
+pub fn random_graph(seed: u64) -> PageGraph {
+    random_graph_with(seed, &GeneratorConfig::default())
+}
+
+/// Generates a random graph from the given seed. The same seed and configuration always produce
+/// the same graph.
+pub fn random_graph_with(seed: u64, config: &GeneratorConfig) -> PageGraph {
+    let mut rng = Rng(seed);
+    let mut builder = PageGraphBuilder::new(&config.page_url);
+
+    let page_origin = url::Url::parse(&config.page_url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_else(|| "example.com".to_string());
+    let mut hosts = vec![page_origin];
+    hosts.extend(config.third_party_domains.iter().cloned());
+
+    let parser = builder.parser();
+    let root = builder.dom_root();
+    let body = builder.add_element(parser, root, "body");
+
+    let mut containers: Vec<NodeId> = vec![body];
+    let mut scripts: Vec<NodeId> = vec![];
+    let mut next_path = 0;
+
+    let mut random_url = |rng: &mut Rng, extension: &str| {
+        next_path += 1;
+        format!("https://{}/{}.{}", hosts[rng.below(hosts.len())], next_path, extension)
+    };
+
+    for _ in 0..config.actions {
+        // Actions after the first script also have a chance of being performed by a script
+        // rather than the parser.
+        let actor = if !scripts.is_empty() && rng.chance(0.5) { rng.pick(&scripts) } else { parser };
+
+        match rng.below(6) {
+            0 | 1 => {
+                let parent = rng.pick(&containers);
+                let element = builder.add_element(actor, parent, rng.pick(&TAG_NAMES));
+                containers.push(element);
+            }
+            2 => {
+                let parent = rng.pick(&containers);
+                builder.add_text(actor, parent, "lorem ipsum");
+            }
+            3 => {
+                // Add a `<script>` element, which either executes inline code or fetches a
+                // remote script.
+                let parent = rng.pick(&containers);
+                let element = builder.add_element(actor, parent, "script");
+                let script = if rng.chance(0.5) {
+                    builder.add_script(element, None, "void 0;")
+                } else {
+                    let url = random_url(&mut rng, "js");
+                    builder.set_attribute(actor, element, "src", &url);
+                    let request_id = builder.add_request(element, &url, RequestType::Script);
+                    builder.advance_time(rng.below(20) as isize);
+                    builder.complete_request(request_id, ResourceType::Script, Some(rng.below(100_000)));
+                    builder.add_script(element, Some(&url), "void 0;")
+                };
+                scripts.push(script);
+            }
+            4 => {
+                // Add an image, which fetches its `src`.
+                let parent = rng.pick(&containers);
+                let element = builder.add_element(actor, parent, "img");
+                let url = random_url(&mut rng, "png");
+                builder.set_attribute(actor, element, "src", &url);
+                let request_id = builder.add_request(element, &url, RequestType::Image);
+                builder.advance_time(rng.below(20) as isize);
+                if rng.chance(config.failure_rate) {
+                    builder.fail_request(request_id);
+                } else {
+                    builder.complete_request(request_id, ResourceType::Image, Some(rng.below(500_000)));
+                }
+            }
+            _ => {
+                // Make a background request from a script, if there are any.
+                if let Some(&script) = scripts.get(rng.below(scripts.len().max(1))) {
+                    let url = random_url(&mut rng, "json");
+                    let request_id = builder.add_request(script, &url, RequestType::AJAX);
+                    builder.advance_time(rng.below(50) as isize);
+                    if rng.chance(config.failure_rate) {
+                        builder.fail_request(request_id);
+                    } else {
+                        builder.complete_request(request_id, ResourceType::Raw, Some(rng.below(10_000)));
+                    }
+                }
+            }
+        }
+
+        builder.advance_time(rng.below(10) as isize);
+    }
+
+    builder.build()
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for PageGraph {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let seed = u.arbitrary::<u64>()?;
+        let config = GeneratorConfig {
+            actions: u.int_in_range(0..=200)?,
+            failure_rate: u.int_in_range(0..=100u8)? as f64 / 100.,
+            ..GeneratorConfig::default()
+        };
+        Ok(random_graph_with(seed, &config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{EdgeType, NodeType};
+
+    #[test]
+    fn test_generation_is_deterministic() {
+        let a = random_graph(7);
+        let b = random_graph(7);
+        assert_eq!(a.nodes.len(), b.nodes.len());
+        assert_eq!(a.edges.len(), b.edges.len());
+        a.nodes.iter().for_each(|(id, node)| assert_eq!(b.nodes.get(id).map(|node| &node.node_type), Some(&node.node_type)));
+    }
+
+    #[test]
+    fn test_generated_graphs_are_valid() {
+        for seed in 0..32 {
+            let graph = random_graph(seed);
+
+            graph.nodes.values()
+                .filter(|node| matches!(node.node_type, NodeType::HtmlElement { .. } | NodeType::TextNode { .. }))
+                .for_each(|node| assert_eq!(graph.dom_root_for_html_node(node).map(|root| root.id), Some(NodeId::from(1))));
+
+            graph.edges.values()
+                .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+                .for_each(|edge| assert_eq!(graph.direct_downstream_effects_of(edge).len(), 1));
+
+            graph.edges.values()
+                .filter(|edge| matches!(edge.edge_type, EdgeType::Execute {}))
+                .for_each(|edge| { graph.all_downstream_effects_of(edge); });
+        }
+    }
+}
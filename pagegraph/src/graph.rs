@@ -3,24 +3,45 @@ use std::convert::TryFrom;
 
 use petgraph::graphmap::DiGraphMap;
 
-use crate::types::{NodeType, EdgeType, RequestType};
+use crate::types::{NodeType, EdgeType, RequestType, Url};
 
-#[derive(Debug)]
+pub use crate::graph_algos::MatchedResource;
+
+#[derive(Debug, Clone)]
 pub struct PageGraphDescriptor {
     pub version: String,
     pub about: String,
-    pub url: String,
+    pub url: Url,
     pub is_root: bool,
     pub frame_id: FrameId,
     pub time: PageGraphTime,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PageGraphTime {
     pub start: u64,
     pub end: u64,
 }
 
+/// Malformed items found while parsing a graph, which were left out of it or changed so that the
+/// rest of the graph stays consistent.
+#[derive(Debug, Default, Clone)]
+pub struct ParseDiagnostics {
+    /// Nodes with the same id as an earlier node. The earlier node is kept in the graph.
+    pub duplicate_nodes: Vec<Node>,
+    /// Edges that reused the id of an earlier edge, as pairs of the recorded id and the id they
+    /// were given in the graph instead.
+    pub remapped_edges: Vec<(EdgeId, EdgeId)>,
+    /// Edges whose source or target node isn't in the graph, which are left out of it.
+    pub dangling_edges: Vec<Edge>,
+}
+
+impl ParseDiagnostics {
+    pub fn is_empty(&self) -> bool {
+        self.duplicate_nodes.is_empty() && self.remapped_edges.is_empty() && self.dangling_edges.is_empty()
+    }
+}
+
 /// The main PageGraph data structure.
 #[derive(Debug)]
 pub struct PageGraph {
@@ -28,8 +49,15 @@ pub struct PageGraph {
     pub edges: HashMap<EdgeId, Edge>,
     pub nodes: HashMap<NodeId, Node>,
     pub graph: DiGraphMap<NodeId, Vec<EdgeId>>,
+    /// Items that couldn't be parsed into the graph as recorded, including those of merged frames.
+    pub diagnostics: ParseDiagnostics,
 
     next_edge_id: std::cell::RefCell<usize>,
+    /// Built on first use, and cleared whenever the graph is modified through its own methods.
+    pub(crate) time_index: std::cell::OnceCell<crate::time_index::TimeIndex>,
+    pub(crate) lifecycle: std::cell::OnceCell<crate::lifecycle::Lifecycle>,
+    pub(crate) request_index: std::cell::OnceCell<crate::requests::RequestIndex>,
+    pub(crate) dom_tree: std::cell::OnceCell<crate::dom::DomTree>,
 }
 
 impl PageGraph {
@@ -39,7 +67,12 @@ impl PageGraph {
             edges,
             nodes,
             graph,
+            diagnostics: ParseDiagnostics::default(),
             next_edge_id: std::cell::RefCell::new(usize::MAX),
+            time_index: std::cell::OnceCell::new(),
+            lifecycle: std::cell::OnceCell::new(),
+            request_index: std::cell::OnceCell::new(),
+            dom_tree: std::cell::OnceCell::new(),
         }
     }
 
@@ -89,9 +122,29 @@ impl PageGraph {
             self.nodes.get(&node_id).unwrap()
         })
     }
+
+    /// Returns every edge from `source` to `target`, in the order they were recorded.
+    pub fn edge_groups_between<'a>(&'a self, source: &Node, target: &Node) -> Vec<&'a Edge> {
+        self.graph.edge_weight(source.id, target.id)
+            .map(|edge_ids| edge_ids.iter().map(|edge_id| self.edges.get(edge_id).unwrap()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Like [`outgoing_neighbors`](PageGraph::outgoing_neighbors), but yields each neighbor
+    /// together with every edge from `node` to that neighbor.
+    pub fn outgoing_edge_groups<'a>(&'a self, node: &'a Node) -> impl Iterator<Item=(&'a Node, Vec<&'a Edge>)> {
+        self.outgoing_neighbors(node).map(move |neighbor| (neighbor, self.edge_groups_between(node, neighbor)))
+    }
+
+    /// Like [`incoming_neighbors`](PageGraph::incoming_neighbors), but yields each neighbor
+    /// together with every edge from that neighbor to `node`.
+    pub fn incoming_edge_groups<'a>(&'a self, node: &'a Node) -> impl Iterator<Item=(&'a Node, Vec<&'a Edge>)> {
+        self.incoming_neighbors(node).map(move |neighbor| (neighbor, self.edge_groups_between(neighbor, node)))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 struct GraphItemId {
     id: usize,
     frame_id: Option<FrameId>,
@@ -142,6 +195,7 @@ pub fn is_same_frame_context<A: HasFrameId, B: HasFrameId>(a: A, b: B) -> bool {
 
 /// An identifier used to reference a node.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct NodeId(GraphItemId);
 
 impl From<usize> for NodeId {
@@ -186,6 +240,7 @@ impl TryFrom<&str> for NodeId {
 
 /// Downstream requests tree
 #[derive(serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DownstreamRequests {
     pub request_id: usize,
     pub url: String,
@@ -204,6 +259,7 @@ pub struct Node {
 
 /// An identifier used to reference an edge.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct EdgeId(GraphItemId);
 
 impl From<usize> for EdgeId {
@@ -276,6 +332,7 @@ impl PartialEq for Edge {
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FrameId(u128);
 
 impl TryFrom<&str> for FrameId {
@@ -411,3 +468,30 @@ mod id_parsing_tests {
         test_str("n99999:0123456789ABCDEF0123456789ABCDEF");
     }
 }
+
+#[cfg(test)]
+mod traversal_tests {
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_edge_groups() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "div");
+        let first = builder.set_attribute(parser, element, "class", "a");
+        let second = builder.set_attribute(parser, element, "class", "b");
+        let graph = builder.build();
+        let parser = graph.nodes.get(&parser).unwrap();
+        let element = graph.nodes.get(&element).unwrap();
+
+        let between = graph.edge_groups_between(parser, element);
+        assert_eq!(between.iter().filter(|edge| edge.id == first || edge.id == second).count(), 2);
+        assert!(graph.edge_groups_between(element, parser).is_empty());
+
+        let (neighbor, edges) = graph.incoming_edge_groups(element).find(|(neighbor, _)| neighbor.id == parser.id).unwrap();
+        assert_eq!(neighbor.id, parser.id);
+        assert_eq!(edges, between);
+        assert!(graph.outgoing_edge_groups(parser).any(|(neighbor, edges)| neighbor.id == element.id && edges.len() == between.len()));
+    }
+}
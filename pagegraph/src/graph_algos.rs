@@ -1,5 +1,6 @@
 use crate::graph::{PageGraph, Edge, EdgeId, Node, NodeId, FrameId, DownstreamRequests};
-use crate::types::{EdgeType, NodeType};
+use crate::types::{EdgeType, NodeKind, NodeType, RequestType, ResourceType, ScriptType, Url};
+use crate::url_search::{ReferenceKind, UrlReference};
 
 use addr::parse_domain_name;
 use petgraph::Direction;
@@ -8,14 +9,17 @@ use adblock::engine::Engine;
 const CAN_HAVE_SRC: [&str; 9] = ["audio", "embed", "iframe", "img", "input", "script", "source", "track", "video"];
 
 #[derive(serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MatchedResource {
     url: String,
     node_id: String,
     request_types: Vec<String>,
     requests: Vec<MatchedRequest>,
+    references: Vec<MatchedReference>,
 }
 
 #[derive(serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 struct MatchedRequest {
     request_id: usize,
     edge_id: String,
@@ -23,6 +27,16 @@ struct MatchedRequest {
     exception_filter: Option<String>
 }
 
+#[derive(serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+struct MatchedReference {
+    node_id: String,
+    edge_id: Option<String>,
+    kind: ReferenceKind,
+    blocking_filter: Option<String>,
+    exception_filter: Option<String>,
+}
+
 impl PageGraph {
     pub fn all_remote_frame_ids(&self) -> Vec<FrameId> {
         self.nodes.iter().filter_map(|(_node_id, node)|
@@ -40,9 +54,13 @@ impl PageGraph {
     pub fn merge_frame(&mut self, frame_graph: PageGraph, frame_id: &FrameId) {
         assert!(self.desc.is_root);
         assert!(!frame_graph.desc.is_root);
+        self.time_index.take();
+        self.lifecycle.take();
+        self.request_index.take();
+        self.dom_tree.take();
 
         // Find the single `remote frame` node with the specified `frame_id`
-        let matching_remote_frames = self.filter_nodes(|n| matches!(n, NodeType::RemoteFrame { frame_id: node_frame_id } if node_frame_id == frame_id));
+        let matching_remote_frames = self.filter_nodes(|n| matches!(&n.node_type, NodeType::RemoteFrame { frame_id: node_frame_id } if node_frame_id == frame_id)).collect::<Vec<_>>();
         assert!(matching_remote_frames.len() == 1);
         let remote_frame = matching_remote_frames[0].id.clone();
 
@@ -121,18 +139,21 @@ impl PageGraph {
             }).collect::<Vec<_>>();
             self.graph.add_edge(new_from_node_id, new_to_node_id, new_edge_ids);
         });
-    }
 
-    pub fn filter_edges<F: Fn(&EdgeType) -> bool>(&self, f: F) -> Vec<&Edge> {
-        self.edges.values().filter(|edge| {
-            f(&edge.edge_type)
-        }).collect()
-    }
-
-    pub fn filter_nodes<F: Fn(&NodeType) -> bool>(&self, f: F) -> Vec<&Node> {
-        self.nodes.values().filter(|node| {
-            f(&node.node_type)
-        }).collect()
+        // Keep the frame's diagnostics, with ids namespaced in the same way
+        let diagnostics = frame_graph.diagnostics;
+        self.diagnostics.duplicate_nodes.extend(diagnostics.duplicate_nodes.into_iter().map(|mut node| {
+            node.id = node.id.copy_for_frame_id(frame_id);
+            node
+        }));
+        self.diagnostics.remapped_edges.extend(diagnostics.remapped_edges.into_iter()
+            .map(|(recorded_id, new_id)| (recorded_id.copy_for_frame_id(frame_id), new_id.copy_for_frame_id(frame_id))));
+        self.diagnostics.dangling_edges.extend(diagnostics.dangling_edges.into_iter().map(|mut edge| {
+            edge.id = edge.id.copy_for_frame_id(frame_id);
+            edge.source = edge.source.copy_for_frame_id(frame_id);
+            edge.target = edge.target.copy_for_frame_id(frame_id);
+            edge
+        }));
     }
 
     pub fn dom_root_for_html_node<'a>(&'a self, node: &'a Node) -> Option<&'a Node> {
@@ -145,7 +166,7 @@ impl PageGraph {
                     let parent_node = {
                         let mut parent_nodes = self.nodes.values().filter(|parent_node|
                             crate::graph::is_same_frame_context(node.id, parent_node.id) &&
-                            matches!(parent_node.node_type, NodeType::HtmlElement { node_id, .. } | NodeType::DomRoot { node_id, .. } | NodeType::FrameOwner { node_id, .. } if node_id == parent_id)
+                            matches!(parent_node.node_type, NodeType::HtmlElement { node_id, .. } | NodeType::DomRoot { node_id, .. } | NodeType::FrameOwner { node_id, .. } | NodeType::ShadowRoot { node_id, .. } if node_id == parent_id)
                         );
                         let parent_node = parent_nodes.next().expect(&format!("No HTML parent node with id {} found for {:?}", parent_id, node));
                         assert!(parent_nodes.next().is_none(), "Multiple HTML parent nodes with id {} found", parent_id);
@@ -193,6 +214,15 @@ impl PageGraph {
                     _ => panic!("HTML element never inserted, but created by {:?}, which is not a script", creator),
                 }
             }
+            NodeType::ShadowRoot { .. } => {
+                // Shadow roots belong to the same document as their host element.
+                let host_id = self.incoming_edges(node).find_map(|edge| match edge.edge_type {
+                    EdgeType::AttachShadow { host, .. } => Some(host),
+                    _ => None,
+                })?;
+                let host = self.nodes.values().find(|host| crate::graph::is_same_frame_context(node.id, host.id) && host.node_type.html_node_id() == Some(host_id) && !matches!(host.node_type, NodeType::TextNode { .. }))?;
+                self.dom_root_for_html_node(host)
+            }
             _ => panic!("Supplied node was not an HTML element"),
         }
     }
@@ -240,7 +270,7 @@ impl PageGraph {
                 let source = self.source_node(edge);
                 match &source.node_type {
                     NodeType::HtmlElement { tag_name, .. } if tag_name == "script" => Some(self.dom_root_for_html_node(source).expect("could not find DOM root for script executor element")),
-                    NodeType::Script { script_type, .. } if script_type == "module" => {
+                    NodeType::Script { script_type: ScriptType::Module, .. } => {
                         // There's some complicated cases with module scripts that can sometimes
                         // lead to infinite loops, so just find the local context URL and use that.
                         // TODO improve
@@ -268,6 +298,8 @@ impl PageGraph {
                     }
                     // Unclear why, but DOM roots sometimes execute scripts as well.
                     NodeType::DomRoot { .. } => Some(source),
+                    // Workers run separately from any document.
+                    NodeType::Worker { .. } => None,
                     _ => panic!("Script was executed by {:?} (something other than a script HTML element or another script)", &source.node_type),
                 }
             }
@@ -401,6 +433,57 @@ impl PageGraph {
         return self.desc.url.to_string();
     }
 
+    /// Returns whether the given URL is third-party relative to the page the graph was recorded
+    /// from, by comparing registrable domains. Returns `None` if either URL has no registrable
+    /// domain, e.g. for `data:` URLs or bare IP addresses.
+    pub fn is_third_party(&self, url: &Url) -> Option<bool> {
+        let root_domain = domain_of_url(&self.desc.url)?;
+        let domain = domain_of_url(url)?;
+        Some(root_domain != domain)
+    }
+
+    /// Returns the DOM roots of every document loaded into the given frame owner element,
+    /// including documents from remote frames if they have been merged into this graph.
+    pub fn content_documents_of<'a>(&'a self, frame_owner: &Node) -> Vec<&'a Node> {
+        self.outgoing_edges(frame_owner)
+            .filter(|edge| matches!(edge.edge_type, EdgeType::CrossDom {}))
+            .map(|edge| self.target_node(edge))
+            .flat_map(|target| match target.node_type {
+                NodeType::RemoteFrame { .. } => self.outgoing_edges(target)
+                    .filter(|edge| matches!(edge.edge_type, EdgeType::CrossDom {}))
+                    .map(|edge| self.target_node(edge))
+                    .filter(|node| matches!(node.node_type, NodeType::DomRoot { .. }))
+                    .collect(),
+                NodeType::DomRoot { .. } => vec![target],
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    /// Returns the DOM root of the document that the given element or script acts on behalf of.
+    /// Returns `None` for scripts running in workers, and for other kinds of actors, like the
+    /// parser, which can't be attributed to a particular document.
+    pub fn document_of_actor<'a>(&'a self, actor: &'a Node) -> Option<&'a Node> {
+        match actor.node_type {
+            NodeType::DomRoot { .. } | NodeType::HtmlElement { .. } | NodeType::TextNode { .. } | NodeType::FrameOwner { .. } | NodeType::ShadowRoot { .. } => self.dom_root_for_html_node(actor),
+            // Workers run separately from any document.
+            NodeType::Script { .. } if self.worker_of(actor).is_some() => None,
+            NodeType::Script { .. } => self.incoming_edges(actor)
+                .find(|edge| matches!(edge.edge_type, EdgeType::Execute {}))
+                .and_then(|edge| self.dom_root_for_edge(edge)),
+            _ => None,
+        }
+    }
+
+    /// Returns every request started by an element or script belonging to the document with the
+    /// given DOM root, not including requests from documents in any nested frames.
+    pub fn requests_from_document<'a>(&'a self, dom_root: &Node) -> Vec<&'a Edge> {
+        self.edges.values()
+            .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+            .filter(|edge| self.document_of_actor(self.source_node(edge)).map(|document| document.id) == Some(dom_root.id))
+            .collect()
+    }
+
     /// Get every request type and associated resource size for a given resource.
     ///
     /// Some requests, like streamed fetches, video, or audio cannot be properly sized, so their
@@ -423,7 +506,7 @@ impl PageGraph {
                             .iter()
                             .filter_map(|(_, Edge { edge_type, .. })| if let EdgeType::RequestComplete { size, request_id: id, .. } = edge_type {
                                     if id == request_id {
-                                        Some(size.parse::<usize>().ok())
+                                        Some(*size)
                                     } else {
                                         None
                                     }
@@ -449,45 +532,90 @@ impl PageGraph {
         }
     }
 
+    /// Get every Resource node requested with the given request type, sorted by node id. This
+    /// matches the request types reported by [`resource_request_types`](PageGraph::resource_request_types).
+    pub fn resources_of_type(&self, request_type: RequestType) -> Vec<&Node> {
+        self.edges.values()
+            .filter(|edge| matches!(&edge.edge_type, EdgeType::RequestStart { request_type: edge_request_type, .. } if *edge_request_type == request_type))
+            .map(|edge| edge.target)
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|node_id| self.nodes.get(&node_id).unwrap())
+            .filter(|node| matches!(node.node_type, NodeType::Resource { .. }))
+            .collect()
+    }
+
+    /// Get every Resource node with a response whose MIME type matches the given pattern, like
+    /// `image/png` or `image/*`, sorted by node id. See [`ResponseMetadata::mime_type_matches`](crate::types::ResponseMetadata::mime_type_matches).
+    pub fn resources_with_mime_type(&self, pattern: &str) -> Vec<&Node> {
+        self.requests().into_iter()
+            .filter(|request| request.response.as_ref().map(|response| response.mime_type_matches(pattern)).unwrap_or(false))
+            .map(|request| request.resource)
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|node_id| self.nodes.get(&node_id).unwrap())
+            .collect()
+    }
+
     /// Get a collection of all Resource nodes whose requests match a set of adblock filter patterns.
+    ///
+    /// URLs referenced through `srcset` candidates or CSS `url()`s, as found by
+    /// [`url_references`](PageGraph::url_references), are matched too, and listed with the
+    /// element or stylesheet referencing them. Those without a Resource node of their own are
+    /// reported with the `node_id` of the first element or stylesheet referencing them, and no
+    /// requests.
     pub fn resources_matching_filters(&self, graph: &PageGraph, patterns: Vec<String>) -> Vec<MatchedResource> {
-        let source_url = self.root_url();
-
         let mut matching_resources : Vec<MatchedResource> = vec![];
 
-        let source_url = url::Url::parse(&source_url).expect("Could not parse source URL");
+        let source_url = self.desc.url.parsed().expect("Could not parse source URL");
         let source_hostname = source_url.host_str().expect(&format!("Source URL has no host, {:?}", source_url));
         let source_domain = get_domain(source_hostname);
         let blocker = Engine::from_rules_debug(&patterns, Default::default());
 
+        let check = |url: &str, request_type: &str| {
+            let request_url = url::Url::parse(url).ok()?;
+            let request_url_hostname = request_url.host_str()?;
+            let request_url_domain = get_domain(request_url_hostname);
+            let third_party = if source_domain.is_empty() {
+                None
+            } else {
+                Some(source_domain != request_url_domain)
+            };
+            let blocker_result = blocker
+                .check_network_urls_with_hostnames_subset(url,
+                                                          request_url_hostname,
+                                                          source_hostname,
+                                                          request_type,
+                                                          third_party,
+                                                          false,
+                                                          true);
+            if blocker_result.matched || blocker_result.exception.is_some() {
+                Some(blocker_result)
+            } else {
+                None
+            }
+        };
+
+        let mut references_by_url = std::collections::BTreeMap::<&str, Vec<&UrlReference>>::new();
+        let references = self.url_references();
+        references.iter().for_each(|reference| references_by_url.entry(&reference.url).or_default().push(reference));
+        let matching_references = |references: &[&UrlReference]| references.iter().filter_map(|reference| {
+            let blocker_result = check(&reference.url, reference.request_type())?;
+            Some(MatchedReference {
+                node_id: format!("{}", reference.owner),
+                edge_id: reference.edge.map(|edge_id| format!("{}", edge_id)),
+                kind: reference.kind,
+                blocking_filter: blocker_result.filter,
+                exception_filter: blocker_result.exception,
+            })
+        }).collect::<Vec<_>>();
+
         for (id, node) in self.nodes.iter() {
             match &node.node_type {
                 NodeType::Resource { url } => {
-                    let request_url = match url::Url::parse(url) {
-                        Ok(request_url) => request_url,
-                        Err(_) => continue,
-                    };
-                    let request_url_hostname = match request_url.host_str() {
-                        Some(host) => host,
-                        None => continue,
-                    };
-                    let request_url_domain = get_domain(request_url_hostname);
                     let request_types = self.resource_request_types(&id);
                     request_types.into_iter().for_each(|(request_type, _size)| {
-                        let third_party = if source_domain.is_empty() {
-                            None
-                        } else {
-                            Some(source_domain != request_url_domain)
-                        };
-                        let blocker_result = blocker
-                            .check_network_urls_with_hostnames_subset(url,
-                                                                      request_url_hostname,
-                                                                      source_hostname,
-                                                                      &request_type,
-                                                                      third_party,
-                                                                      false,
-                                                                      true);
-                        if blocker_result.matched || blocker_result.exception.is_some() {
+                        if let Some(blocker_result) = check(url, &request_type) {
                             let matching_request_types = graph.resource_request_types(&id).into_iter().map(|(ty, _)| ty).collect();
                             let requests = graph.incoming_edges(&node)
                                 .filter_map(|edge| {
@@ -504,10 +632,11 @@ impl PageGraph {
                                 }).collect::<Vec<_>>();
 
                             let matched_resource = MatchedResource {
-                                url: url.clone(),
+                                url: url.to_string(),
                                 node_id: format!("{}", id),
                                 request_types: matching_request_types,
-                                requests
+                                requests,
+                                references: matching_references(references_by_url.get(url.as_str()).map(Vec::as_slice).unwrap_or_default()),
                             };
                             matching_resources.push(matched_resource);
                         }
@@ -516,6 +645,27 @@ impl PageGraph {
                 _ => continue
             }
         }
+
+        // Referenced URLs that were never recorded as requested would otherwise go uncounted.
+        let resource_urls = self.nodes.values().filter_map(|node| match &node.node_type {
+            NodeType::Resource { url } => Some(url.as_str()),
+            _ => None,
+        }).collect::<std::collections::HashSet<_>>();
+        for (url, references) in references_by_url.iter().filter(|(url, _)| !resource_urls.contains(*url)) {
+            let matched_references = matching_references(references);
+            if let Some(first) = matched_references.first() {
+                let mut request_types = references.iter().map(|reference| reference.request_type().to_string()).collect::<Vec<_>>();
+                request_types.sort();
+                request_types.dedup();
+                matching_resources.push(MatchedResource {
+                    url: url.to_string(),
+                    node_id: first.node_id.clone(),
+                    request_types,
+                    requests: vec![],
+                    references: matched_references,
+                });
+            }
+        }
         matching_resources
     }
 
@@ -532,7 +682,7 @@ impl PageGraph {
 
                         // Find the single Parser node that belongs to the same local frame context
                         // as this DOM root
-                        let parsers = self.filter_nodes(|node_type| matches!(node_type, NodeType::Parser {}));
+                        let parsers = self.filter_nodes(crate::filter::node_kind(NodeKind::Parser)).collect::<Vec<_>>();
                         let mut same_context_parsers = parsers
                             .iter()
                             .filter(|parser| {
@@ -560,13 +710,9 @@ impl PageGraph {
                             // Then map each node to also include quick reference to its HTML node
                             // id, and an unpopulated Option<bool> flag to signify whether or not
                             // its part of this frame.
-                            .map(|node| (node, match node.node_type {
-                                NodeType::DomRoot { node_id, .. } => node_id,
-                                NodeType::HtmlElement { node_id, .. } => node_id,
-                                NodeType::TextNode { node_id, .. } => node_id,
-                                NodeType::FrameOwner { node_id, .. } => node_id,
-                                _ => panic!("Parser created a {:?}, which has no DOM node ID", node.node_type),
-                            }, None))
+                            .map(|node| (node, node.node_type.html_node_id().unwrap_or_else(||
+                                panic!("Parser created a {:?}, which has no DOM node ID", node.node_type)
+                            ), None))
                             .collect::<Vec<(_, _, Option<bool>)>>();    // (Node, Node id, optional flag)
 
                         // Now we need to filter out any nodes which are in a different local frame
@@ -660,10 +806,11 @@ impl PageGraph {
                     _ => panic!("Cross DOM edges should only point to DOM roots, parsers, and remote frames, {:?}", self.target_node(edge)),
                 }
             }
-            EdgeType::ResourceBlock {} => unimplemented!(),
+            // A blocked resource was never loaded, so it can't have had any effects.
+            EdgeType::ResourceBlock {} => vec![],
             EdgeType::Shield {} => unimplemented!(),
             EdgeType::TextChange {} => unimplemented!(),
-            EdgeType::RemoveNode {} => unimplemented!(),
+            EdgeType::RemoveNode { .. } => unimplemented!(),
             EdgeType::DeleteNode {} => unimplemented!(),
             EdgeType::InsertNode { parent: parent_id, .. } => {
                 // Inserting a node can cause certain elements with `src` attributes to trigger a
@@ -715,7 +862,7 @@ impl PageGraph {
                 // If RequestComplete has a "script" resource type, and points to an HTML script
                 // element, then attribute any Executions from that element to this edge.
                 let target = self.target_node(edge);
-                if resource_type == "script" && matches!(&target.node_type, NodeType::HtmlElement { tag_name, .. } if tag_name == "script") {
+                if *resource_type == ResourceType::Script && matches!(&target.node_type, NodeType::HtmlElement { tag_name, .. } if tag_name == "script") {
                     self.outgoing_edges(target).filter(|edge| matches!(edge.edge_type, EdgeType::Execute {})).collect::<Vec<_>>()
                 } else {
                     vec![]
@@ -743,6 +890,10 @@ impl PageGraph {
             EdgeType::ReadStorageCall { .. } => unimplemented!(),
             EdgeType::ClearStorage { .. } => unimplemented!(),
             EdgeType::StorageBucket {} => unimplemented!(),
+            EdgeType::AttachShadow { .. } => {
+                // Attaching a shadow root doesn't cause anything to happen by itself.
+                vec![]
+            }
             EdgeType::ExecuteFromAttribute { .. } => unimplemented!(),
             EdgeType::Execute {} => {
                 self.outgoing_edges(self.target_node(edge)).filter(|edge| match edge.edge_type {
@@ -813,6 +964,8 @@ impl PageGraph {
             EdgeType::DeleteAttribute { .. } => unimplemented!(),
             EdgeType::Binding { .. } => unimplemented!(),
             EdgeType::BindingEvent { .. } => unimplemented!(),
+            // There's no way to know what an unrecognized action would have caused.
+            EdgeType::Unknown { .. } => vec![],
         }
     }
 
@@ -887,3 +1040,12 @@ fn get_domain(host: &str) -> String {
     let source_domain = parse_domain_name(source_hostname).expect("Source URL domain could not be parsed");
     source_domain.root().expect("Registrable domain not found").to_string()
 }
+
+/// Returns the registrable domain (eTLD+1) of the given URL, if it has one.
+pub(crate) fn domain_of_url(url: &Url) -> Option<String> {
+    let host = url.parsed()?.host_str()?;
+    if let "localhost" = host {
+        return Some(host.to_string());
+    }
+    parse_domain_name(host).ok()?.root().map(|root| root.to_string())
+}
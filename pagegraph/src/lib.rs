@@ -1,4 +1,24 @@
 pub mod graph;
 mod graph_algos;
+mod describe;
+mod time;
+mod time_index;
+mod ordering;
+pub mod lifecycle;
+pub mod dom;
+pub mod source_location;
+pub mod requests;
+pub mod stats;
+pub mod redact;
+pub mod prune;
+pub mod export;
+#[cfg(feature = "schemars")]
+pub mod schema;
 pub mod types;
 pub mod from_xml;
+pub mod url_search;
+pub mod filter;
+pub mod builder;
+pub mod analysis;
+#[cfg(feature = "generate")]
+pub mod generate;
@@ -0,0 +1,211 @@
+//! Segmentation of a page load into lifecycle phases, so that render-critical activity can be
+//! distinguished from activity that happens once the page has finished loading.
+//!
+//! PageGraph does not record lifecycle events directly, so by default their timing is inferred
+//! from the graph:
+//! - `DOMContentLoaded` fires once the top-level parser has performed its last action
+//! - `load` fires once every request started before `DOMContentLoaded` has completed or failed
+//! - the page becomes idle after the first period of at least [`IDLE_THRESHOLD_MS`] following
+//!   `load` in which nothing happened
+//!
+//! If more accurate timings are available from elsewhere, [`Lifecycle`] can also be constructed
+//! directly.
+
+use crate::graph::{Edge, HasFrameId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// Minimum length of a period without any activity after `load` for the page to be considered
+/// idle, in milliseconds.
+pub const IDLE_THRESHOLD_MS: isize = 1000;
+
+/// Timestamps of lifecycle events during a page load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Lifecycle {
+    pub dom_content_loaded: Option<isize>,
+    pub load: Option<isize>,
+    pub idle: Option<isize>,
+}
+
+/// A phase of the page lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Before `DOMContentLoaded`, while the document is being parsed.
+    Parsing,
+    /// Between `DOMContentLoaded` and `load`, while subresources are still being fetched.
+    Loading,
+    /// After `load`, until the page becomes idle.
+    PostLoad,
+    /// After the page's activity first settled down.
+    Idle,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [Phase::Parsing, Phase::Loading, Phase::PostLoad, Phase::Idle];
+}
+
+impl Lifecycle {
+    /// Returns the phase containing the given timestamp.
+    pub fn phase_at(&self, timestamp: isize) -> Phase {
+        if self.idle.map(|idle| timestamp >= idle).unwrap_or(false) {
+            Phase::Idle
+        } else if self.load.map(|load| timestamp > load).unwrap_or(false) {
+            Phase::PostLoad
+        } else if self.dom_content_loaded.map(|dcl| timestamp > dcl).unwrap_or(false) {
+            Phase::Loading
+        } else {
+            Phase::Parsing
+        }
+    }
+
+    /// Returns the range of timestamps covered by the given phase, as inclusive start and
+    /// exclusive end bounds. `None` means unbounded.
+    fn bounds_of(&self, phase: Phase) -> (Option<isize>, Option<isize>) {
+        let after = |timestamp: Option<isize>| timestamp.map(|t| t + 1);
+        match phase {
+            Phase::Parsing => (None, after(self.dom_content_loaded)),
+            Phase::Loading => (after(self.dom_content_loaded), after(self.load)),
+            Phase::PostLoad => (after(self.load), self.idle),
+            Phase::Idle => (self.idle, None),
+        }
+    }
+}
+
+/// Activity recorded during a single lifecycle phase.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PhaseSummary {
+    pub phase: Phase,
+    /// Timestamp of the first action in the phase, if there were any.
+    pub first_action: Option<isize>,
+    /// Timestamp of the last action in the phase, if there were any.
+    pub last_action: Option<isize>,
+    pub actions: usize,
+    pub requests: usize,
+    pub third_party_requests: usize,
+    pub script_executions: usize,
+}
+
+impl PageGraph {
+    /// Returns the lifecycle event timings inferred from the graph. These are computed once and
+    /// then cached.
+    pub fn lifecycle(&self) -> Lifecycle {
+        *self.lifecycle.get_or_init(|| self.detect_lifecycle())
+    }
+
+    fn detect_lifecycle(&self) -> Lifecycle {
+        let parser = match self.nodes.values().find(|node| matches!(node.node_type, NodeType::Parser {}) && node.id.get_frame_id().is_none()) {
+            Some(parser) => parser,
+            None => return Lifecycle::default(),
+        };
+
+        let dom_content_loaded = self.outgoing_edges(parser).filter_map(|edge| edge.edge_timestamp).max();
+
+        let load = dom_content_loaded.map(|dcl| {
+            self.edges_in_range(..=dcl)
+                .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+                .filter_map(|start| {
+                    let request_id = start.edge_type.request_id();
+                    self.outgoing_edges(self.target_node(start))
+                        .filter(|edge| matches!(edge.edge_type, EdgeType::RequestComplete { .. } | EdgeType::RequestError { .. }))
+                        .find(|edge| edge.edge_type.request_id() == request_id)
+                        .and_then(|outcome| outcome.edge_timestamp)
+                })
+                .fold(dcl, isize::max)
+        });
+
+        let idle = load.and_then(|load| {
+            let mut previous = load;
+            self.edges_in_range(load + 1..).find_map(|edge| {
+                let timestamp = edge.edge_timestamp.unwrap();
+                let gap = timestamp - previous;
+                previous = timestamp;
+                if gap >= IDLE_THRESHOLD_MS {
+                    Some(timestamp - gap + IDLE_THRESHOLD_MS)
+                } else {
+                    None
+                }
+            })
+        });
+
+        Lifecycle { dom_content_loaded, load, idle }
+    }
+
+    /// Returns the lifecycle phase during which the given action occurred, based on the inferred
+    /// lifecycle event timings. Returns `None` for actions without a timestamp.
+    pub fn phase_of(&self, edge: &Edge) -> Option<Phase> {
+        edge.edge_timestamp.map(|timestamp| self.lifecycle().phase_at(timestamp))
+    }
+
+    /// Summarizes the activity recorded during each lifecycle phase, using the given lifecycle
+    /// event timings.
+    pub fn phase_summaries(&self, lifecycle: &Lifecycle) -> Vec<PhaseSummary> {
+        Phase::ALL.iter().map(|&phase| {
+            let edges: Vec<_> = match lifecycle.bounds_of(phase) {
+                (None, None) => self.edges_in_range(..).collect(),
+                (None, Some(end)) => self.edges_in_range(..end).collect(),
+                (Some(start), None) => self.edges_in_range(start..).collect(),
+                (Some(start), Some(end)) => self.edges_in_range(start..end).collect(),
+            };
+            let request_urls = edges.iter()
+                .filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }))
+                .filter_map(|edge| self.target_node(edge).node_type.url())
+                .collect::<Vec<_>>();
+
+            PhaseSummary {
+                phase,
+                first_action: edges.first().and_then(|edge| edge.edge_timestamp),
+                last_action: edges.last().and_then(|edge| edge.edge_timestamp),
+                actions: edges.len(),
+                requests: request_urls.len(),
+                third_party_requests: request_urls.iter().filter(|url| self.is_third_party(url) == Some(true)).count(),
+                script_executions: edges.iter().filter(|edge| matches!(edge.edge_type, EdgeType::Execute {} | EdgeType::ExecuteFromAttribute { .. })).count(),
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::{RequestType, ResourceType};
+
+    #[test]
+    fn test_lifecycle_phases() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let img = builder.add_element(parser, root, "img");
+        let image_request = builder.add_request(img, "https://example.com/a.png", RequestType::Image);
+        let script_element = builder.add_element(parser, root, "script");
+        let dcl = builder.now() - 1;
+        let script = builder.add_script(script_element, None, "setTimeout(track, 5000)");
+        builder.advance_time(50);
+        let image_complete = builder.complete_request(image_request, ResourceType::Image, None);
+        builder.advance_time(100);
+        let late_request = builder.add_request(script, "https://tracker.net/p.gif", RequestType::Image);
+        builder.advance_time(5000);
+        builder.fail_request(late_request);
+        let graph = builder.build();
+
+        let lifecycle = graph.lifecycle();
+        let image_complete = graph.edges.get(&image_complete).unwrap();
+        assert_eq!(lifecycle.dom_content_loaded, Some(dcl));
+        assert_eq!(lifecycle.load, image_complete.edge_timestamp);
+        assert_eq!(lifecycle.idle, Some(lifecycle.load.unwrap() + 102 + IDLE_THRESHOLD_MS));
+
+        assert_eq!(graph.phase_of(image_complete), Some(Phase::Loading));
+        let requests = graph.filter_edges(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. }));
+        let mut phases = requests.map(|edge| graph.phase_of(edge).unwrap()).collect::<Vec<_>>();
+        phases.sort();
+        assert_eq!(phases, vec![Phase::Parsing, Phase::PostLoad]);
+
+        let summaries = graph.phase_summaries(&lifecycle);
+        assert_eq!(summaries.iter().map(|summary| summary.requests).collect::<Vec<_>>(), vec![1, 0, 1, 0]);
+        assert_eq!(summaries[2].third_party_requests, 1);
+        assert_eq!(summaries[3].actions, 1);
+        assert_eq!(summaries.iter().map(|summary| summary.actions).sum::<usize>(), graph.edges.len());
+    }
+}
@@ -0,0 +1,137 @@
+//! Happens-before ordering of graph actions.
+//!
+//! Recorded timestamps only have millisecond granularity, and are occasionally inconsistent across
+//! threads. These queries combine timestamps with causal relationships that can be read directly
+//! from the structure of the graph, so that an effect is never ordered before its cause:
+//! - a node must be created before anything else can happen to or be done by it
+//! - a script must be executed before it can perform any actions
+//! - a request must be started before it can complete or fail
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::graph::{Edge, EdgeId, Node, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+impl PageGraph {
+    fn earliest_incoming_edge<'a, F: Fn(&EdgeType) -> bool>(&'a self, node: &Node, f: F) -> Option<&'a Edge> {
+        self.incoming_edges(node)
+            .filter(|edge| f(&edge.edge_type))
+            .min_by_key(|edge| (edge.edge_timestamp.is_none(), edge.edge_timestamp, edge.id))
+    }
+
+    /// Returns the actions that must have occurred before the given action could occur.
+    pub fn direct_causes_of(&self, edge: &Edge) -> Vec<&Edge> {
+        let source = self.source_node(edge);
+        let target = self.target_node(edge);
+        let mut causes = vec![];
+
+        // The actor must exist, and scripts must be running.
+        causes.extend(self.earliest_incoming_edge(source, |edge_type| matches!(edge_type, EdgeType::CreateNode {})));
+        if let NodeType::Script { .. } = source.node_type {
+            causes.extend(self.earliest_incoming_edge(source, |edge_type| matches!(edge_type, EdgeType::Execute {} | EdgeType::ExecuteFromAttribute { .. })));
+        }
+
+        // The node being acted upon must exist, unless this action is its creation.
+        if !matches!(edge.edge_type, EdgeType::CreateNode {}) {
+            causes.extend(self.earliest_incoming_edge(target, |edge_type| matches!(edge_type, EdgeType::CreateNode {})));
+        }
+
+        // Requests must be started before their outcomes are known.
+        if let EdgeType::RequestComplete { request_id, .. } | EdgeType::RequestError { request_id, .. } = edge.edge_type {
+            causes.extend(self.earliest_incoming_edge(source, |edge_type| matches!(edge_type, EdgeType::RequestStart { request_id: start_id, .. } if *start_id == request_id)));
+        }
+
+        causes.retain(|cause| cause.id != edge.id);
+        causes.sort_unstable_by_key(|cause| cause.id);
+        causes.dedup_by_key(|cause| cause.id);
+        causes
+    }
+
+    /// Returns true if there is a chain of causal relationships from `a` to `b`, i.e. `b` could
+    /// not have occurred until after `a` did.
+    pub fn causally_precedes(&self, a: &Edge, b: &Edge) -> bool {
+        let mut to_check = vec![b];
+        let mut checked: HashSet<EdgeId> = HashSet::new();
+
+        while let Some(edge) = to_check.pop() {
+            for cause in self.direct_causes_of(edge) {
+                if cause.id == a.id {
+                    return true;
+                }
+                if checked.insert(cause.id) {
+                    to_check.push(cause);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Orders two actions by when they occurred, or returns `None` if no order can be determined.
+    ///
+    /// Causal relationships take precedence over timestamps. Otherwise, actions are ordered by
+    /// timestamp, and actions with identical or missing timestamps are unordered.
+    pub fn happens_before_order(&self, a: &Edge, b: &Edge) -> Option<Ordering> {
+        if a.id == b.id {
+            Some(Ordering::Equal)
+        } else if self.causally_precedes(a, b) {
+            Some(Ordering::Less)
+        } else if self.causally_precedes(b, a) {
+            Some(Ordering::Greater)
+        } else {
+            match (a.edge_timestamp, b.edge_timestamp) {
+                (Some(a), Some(b)) if a != b => Some(a.cmp(&b)),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns true if `a` is known to have occurred before `b`.
+    pub fn happened_before(&self, a: &Edge, b: &Edge) -> bool {
+        self.happens_before_order(a, b) == Some(Ordering::Less)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::builder::PageGraphBuilder;
+    use crate::types::{EdgeType, RequestType, ResourceType};
+
+    #[test]
+    fn test_causality_overrides_timestamps() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, None, "fetch('/a')");
+        // Simulate clock skew between the script's execution and its network request.
+        builder.advance_time(-10);
+        let request = builder.add_request(script, "https://example.com/a", RequestType::AJAX);
+        let complete = builder.complete_request(request, ResourceType::Raw, None);
+        let unrelated = builder.add_text(parser, root, "hello");
+        let graph = builder.build();
+
+        let edge = |f: &dyn Fn(&EdgeType) -> bool| graph.edges.values().find(|edge| f(&edge.edge_type)).unwrap();
+        let execute = edge(&|edge_type| matches!(edge_type, EdgeType::Execute {}));
+        let start = edge(&|edge_type| matches!(edge_type, EdgeType::RequestStart { .. }));
+        let complete = graph.edges.get(&complete).unwrap();
+        let unrelated = graph.incoming_edges(graph.nodes.get(&unrelated).unwrap())
+            .find(|edge| matches!(edge.edge_type, EdgeType::CreateNode {}))
+            .unwrap();
+
+        assert!(start.edge_timestamp < execute.edge_timestamp);
+        assert!(graph.happened_before(execute, start));
+        assert!(!graph.happened_before(start, execute));
+        assert!(graph.happened_before(execute, complete));
+        assert_eq!(graph.happens_before_order(complete, start), Some(Ordering::Greater));
+        assert_eq!(graph.happens_before_order(start, start), Some(Ordering::Equal));
+
+        // Without a causal link, timestamps are used.
+        assert!(!graph.causally_precedes(execute, unrelated));
+        assert!(unrelated.edge_timestamp < execute.edge_timestamp);
+        assert!(graph.happened_before(unrelated, execute));
+    }
+}
@@ -0,0 +1,315 @@
+//! Reduction of a graph to only the nodes and edges an analysis needs.
+//!
+//! Large pages can record hundreds of thousands of text nodes and attribute edges, which many
+//! analyses never look at. Pruning them up front reduces memory use and the size of anything
+//! exported from the graph.
+//!
+//! [`reachable_from`](PageGraph::reachable_from) instead reduces a graph to everything connected
+//! to a single node, which is the natural unit to export or visualize when investigating a single
+//! script or resource. [`activity_of_domain`](PageGraph::activity_of_domain) similarly reduces a
+//! graph to everything attributable to a single third party.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+use petgraph::graphmap::DiGraphMap;
+
+use crate::graph::{Edge, EdgeId, HasFrameId, Node, NodeId, PageGraph};
+use crate::graph_algos::domain_of_url;
+use crate::types::{EdgeKind, EdgeType, NodeKind, NodeType};
+
+#[derive(Debug, Clone)]
+enum KindMask<K> {
+    AllExcept(HashSet<K>),
+    Only(HashSet<K>),
+}
+
+impl<K: Eq + Hash> KindMask<K> {
+    fn contains(&self, kind: &K) -> bool {
+        match self {
+            Self::AllExcept(excluded) => !excluded.contains(kind),
+            Self::Only(included) => included.contains(kind),
+        }
+    }
+
+    fn with(&mut self, kind: K) {
+        match self {
+            Self::AllExcept(excluded) => { excluded.remove(&kind); }
+            Self::Only(included) => { included.insert(kind); }
+        }
+    }
+
+    fn without(&mut self, kind: K) {
+        match self {
+            Self::AllExcept(excluded) => { excluded.insert(kind); }
+            Self::Only(included) => { included.remove(&kind); }
+        }
+    }
+}
+
+/// The kinds of nodes and edges to keep when pruning a graph. For example, to drop all text nodes
+/// and attribute edges, use `TypeMask::all().without_node(NodeKind::TextNode)` followed by
+/// `.without_edge(..)` for [`EdgeKind::SetAttribute`] and [`EdgeKind::DeleteAttribute`].
+#[derive(Debug, Clone)]
+pub struct TypeMask {
+    nodes: KindMask<NodeKind>,
+    edges: KindMask<EdgeKind>,
+}
+
+impl TypeMask {
+    /// A mask that keeps every node and edge.
+    pub fn all() -> Self {
+        Self {
+            nodes: KindMask::AllExcept(HashSet::new()),
+            edges: KindMask::AllExcept(HashSet::new()),
+        }
+    }
+
+    /// A mask that keeps nothing, to be built up with [`with_node`](TypeMask::with_node) and
+    /// [`with_edge`](TypeMask::with_edge).
+    pub fn none() -> Self {
+        Self {
+            nodes: KindMask::Only(HashSet::new()),
+            edges: KindMask::Only(HashSet::new()),
+        }
+    }
+
+    pub fn with_node(mut self, kind: NodeKind) -> Self {
+        self.nodes.with(kind);
+        self
+    }
+
+    pub fn without_node(mut self, kind: NodeKind) -> Self {
+        self.nodes.without(kind);
+        self
+    }
+
+    pub fn with_edge(mut self, kind: EdgeKind) -> Self {
+        self.edges.with(kind);
+        self
+    }
+
+    pub fn without_edge(mut self, kind: EdgeKind) -> Self {
+        self.edges.without(kind);
+        self
+    }
+
+    pub fn keeps_node(&self, kind: NodeKind) -> bool {
+        self.nodes.contains(&kind)
+    }
+
+    pub fn keeps_edge(&self, kind: EdgeKind) -> bool {
+        self.edges.contains(&kind)
+    }
+}
+
+/// Which edges to follow when finding the nodes reachable from a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reachability {
+    /// Follow edges forwards, to everything the node caused.
+    Outgoing,
+    /// Follow edges backwards, to everything that caused the node.
+    Incoming,
+    /// Follow edges in either direction.
+    Both,
+}
+
+/// Builds the graph structure over the given nodes and edges, keeping the order of parallel edges
+/// from the original graph.
+fn induced_graph(original: &DiGraphMap<NodeId, Vec<EdgeId>>, nodes: &HashMap<NodeId, Node>, edges: &HashMap<EdgeId, Edge>) -> DiGraphMap<NodeId, Vec<EdgeId>> {
+    let mut graph = DiGraphMap::with_capacity(nodes.len(), edges.len());
+    original.nodes().filter(|node_id| nodes.contains_key(node_id)).for_each(|node_id| { graph.add_node(node_id); });
+    original.all_edges().for_each(|(source, target, edge_ids)| {
+        let edge_ids = edge_ids.iter().filter(|edge_id| edges.contains_key(edge_id)).copied().collect::<Vec<_>>();
+        if !edge_ids.is_empty() {
+            graph.add_edge(source, target, edge_ids);
+        }
+    });
+    graph
+}
+
+impl PageGraph {
+    /// Returns the graph with every node and edge not kept by the mask removed. Edges to or from
+    /// a removed node are removed as well, even if their own kind is kept.
+    pub fn prune(self, keep: TypeMask) -> PageGraph {
+        let PageGraph { desc, mut nodes, mut edges, graph: old_graph, diagnostics, .. } = self;
+
+        nodes.retain(|_, node| keep.keeps_node(node.node_type.kind()));
+        edges.retain(|_, edge| keep.keeps_edge(edge.edge_type.kind()) && nodes.contains_key(&edge.source) && nodes.contains_key(&edge.target));
+
+        let graph = induced_graph(&old_graph, &nodes, &edges);
+        let mut pruned = PageGraph::new(desc, edges, nodes, graph);
+        pruned.diagnostics = diagnostics;
+        pruned
+    }
+
+    /// Returns the subgraph induced by every node transitively reachable from `node` in the given
+    /// direction, including `node` itself. Every edge between two reachable nodes is included,
+    /// even if it wasn't followed to reach them.
+    pub fn reachable_from(&self, node: NodeId, direction: Reachability) -> PageGraph {
+        assert!(self.nodes.contains_key(&node), "Node {} could not be found in the graph", node);
+        let directions: &[petgraph::Direction] = match direction {
+            Reachability::Outgoing => &[petgraph::Direction::Outgoing],
+            Reachability::Incoming => &[petgraph::Direction::Incoming],
+            Reachability::Both => &[petgraph::Direction::Outgoing, petgraph::Direction::Incoming],
+        };
+
+        let mut reachable = HashSet::new();
+        reachable.insert(node);
+        let mut queue = VecDeque::from(vec![node]);
+        while let Some(current) = queue.pop_front() {
+            directions.iter().for_each(|direction| self.graph.neighbors_directed(current, *direction).for_each(|neighbor| {
+                if reachable.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }));
+        }
+
+        self.induced_subgraph(&reachable)
+    }
+
+    /// Returns the subgraph induced by everything attributable to the given registrable domain
+    /// (eTLD+1), like `example-cdn.com`:
+    /// - resources, scripts, documents, and workers with URLs on the domain;
+    /// - the requests for those resources, along with everything they
+    ///   [caused](PageGraph::all_downstream_effects_of), like the execution of fetched scripts;
+    /// - every action of scripts from the domain, and the effects of their execution;
+    /// - the whole contents of frames whose documents are on the domain.
+    ///
+    /// For the page's own domain, that includes the top-level frame, and therefore most of the
+    /// graph.
+    pub fn activity_of_domain(&self, domain: &str) -> PageGraph {
+        let on_domain = |node: &Node| node.node_type.url().and_then(domain_of_url).map(|node_domain| node_domain == domain).unwrap_or(false);
+
+        let mut included = HashSet::new();
+        let include_edge = |included: &mut HashSet<NodeId>, edge: &Edge| {
+            included.insert(edge.source);
+            included.insert(edge.target);
+        };
+        for node in self.nodes.values().filter(|node| on_domain(node)) {
+            included.insert(node.id);
+            for edge in self.incoming_edges(node) {
+                let is_cause = matches!((&edge.edge_type, &node.node_type), (EdgeType::RequestStart { .. }, _) | (EdgeType::Execute {}, NodeType::Script { .. }));
+                if is_cause {
+                    include_edge(&mut included, edge);
+                    self.all_downstream_effects_of(edge).into_iter().for_each(|effect| include_edge(&mut included, effect));
+                }
+            }
+            if let NodeType::Script { .. } = node.node_type {
+                self.outgoing_edges(node).for_each(|edge| include_edge(&mut included, edge));
+            }
+        }
+
+        let frames = self.frame_stats().into_iter()
+            .filter(|frame| frame.url.as_ref().and_then(domain_of_url).map(|frame_domain| frame_domain == domain).unwrap_or(false))
+            .map(|frame| frame.frame_id)
+            .collect::<HashSet<_>>();
+        if !frames.is_empty() {
+            let frame_of = |node_id: &NodeId| node_id.get_frame_id().unwrap_or(self.desc.frame_id);
+            included.extend(self.nodes.keys().filter(|node_id| frames.contains(&frame_of(node_id))));
+        }
+
+        self.induced_subgraph(&included)
+    }
+
+    /// Returns the subgraph with only the given nodes, and every edge between them.
+    fn induced_subgraph(&self, included: &HashSet<NodeId>) -> PageGraph {
+        let nodes = self.nodes.iter()
+            .filter(|(node_id, _)| included.contains(node_id))
+            .map(|(node_id, node)| (*node_id, node.clone()))
+            .collect::<HashMap<_, _>>();
+        let edges = self.edges.iter()
+            .filter(|(_, edge)| included.contains(&edge.source) && included.contains(&edge.target))
+            .map(|(edge_id, edge)| (*edge_id, edge.clone()))
+            .collect::<HashMap<_, _>>();
+        let graph = induced_graph(&self.graph, &nodes, &edges);
+        PageGraph::new(self.desc.clone(), edges, nodes, graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_prune() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "img");
+        let text = builder.add_text(parser, root, "Hello");
+        let set_attribute = builder.set_attribute(parser, element, "src", "https://example.com/a.png");
+        builder.add_request(element, "https://example.com/a.png", RequestType::Image);
+        let graph = builder.build();
+        let edge_count = graph.edges.len();
+        let text_edges = graph.graph.edges_directed(text, petgraph::Direction::Incoming).map(|(_, _, edge_ids)| edge_ids.len()).sum::<usize>();
+
+        let graph = graph.prune(TypeMask::all().without_node(NodeKind::TextNode).without_edge(EdgeKind::SetAttribute));
+
+        assert!(!graph.nodes.contains_key(&text));
+        assert!(!graph.graph.contains_node(text));
+        assert!(!graph.edges.contains_key(&set_attribute));
+        assert_eq!(graph.edges.len(), edge_count - text_edges - 1);
+        assert_eq!(graph.graph.all_edges().map(|(_, _, edge_ids)| edge_ids.len()).sum::<usize>(), graph.edges.len());
+        assert_eq!(graph.requests().len(), 1);
+
+        let graph = graph.prune(TypeMask::none().with_node(NodeKind::Parser).with_node(NodeKind::DomRoot).with_edge(EdgeKind::CreateNode));
+        assert_eq!(graph.nodes.len(), 2);
+        assert!(graph.edges.values().all(|edge| edge.edge_type.kind() == EdgeKind::CreateNode));
+    }
+
+    #[test]
+    fn test_reachable_from() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let first = builder.add_element(parser, root, "script");
+        let second = builder.add_element(parser, root, "script");
+        let script = builder.add_script(first, None, "");
+        let request = builder.add_request(script, "https://example.com/a.png", RequestType::Image);
+        let resource = builder.resource_of(request);
+        let other_script = builder.add_script(second, None, "");
+        let graph = builder.build();
+
+        let downstream = graph.reachable_from(script, Reachability::Outgoing);
+        assert_eq!(downstream.nodes.len(), 2);
+        assert!(downstream.nodes.contains_key(&resource));
+        assert_eq!(downstream.edges.len(), 1);
+
+        let upstream = graph.reachable_from(script, Reachability::Incoming);
+        assert!(upstream.nodes.contains_key(&parser) && upstream.nodes.contains_key(&first));
+        assert!(!upstream.nodes.contains_key(&resource) && !upstream.nodes.contains_key(&other_script));
+        assert_eq!(upstream.graph.all_edges().map(|(_, _, edge_ids)| edge_ids.len()).sum::<usize>(), upstream.edges.len());
+
+        let connected = graph.reachable_from(resource, Reachability::Both);
+        assert_eq!(connected.nodes.len(), graph.nodes.len());
+    }
+
+    #[test]
+    fn test_activity_of_domain() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let first = builder.add_element(parser, root, "script");
+        let second = builder.add_element(parser, root, "script");
+        let tracker = builder.add_script(first, Some("https://cdn.tracker.net/t.js"), "");
+        let pixel = builder.add_request(tracker, "https://pixel.tracker.net/p.gif", RequestType::Image);
+        let pixel = builder.resource_of(pixel);
+        let tracker_element = builder.add_element(tracker, root, "img");
+        let app = builder.add_script(second, Some("https://example.com/app.js"), "");
+        let tracker_fetch = builder.add_request(app, "https://api.tracker.net/config.json", RequestType::AJAX);
+        let tracker_fetch = builder.resource_of(tracker_fetch);
+        let app_request = builder.add_request(app, "https://example.com/data.json", RequestType::AJAX);
+        let app_request = builder.resource_of(app_request);
+        let graph = builder.build();
+
+        let activity = graph.activity_of_domain("tracker.net");
+        assert!([tracker, first, tracker_element, pixel, tracker_fetch, app].iter().all(|node| activity.nodes.contains_key(node)));
+        assert!(!activity.nodes.contains_key(&app_request) && !activity.nodes.contains_key(&second));
+        assert_eq!(activity.graph.all_edges().map(|(_, _, edge_ids)| edge_ids.len()).sum::<usize>(), activity.edges.len());
+
+        assert_eq!(graph.activity_of_domain("example.com").nodes.len(), graph.nodes.len());
+    }
+}
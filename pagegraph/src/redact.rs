@@ -0,0 +1,177 @@
+//! Redaction of user-specific data from a graph, so that it can be shared publicly.
+//!
+//! Redaction only rewrites values, and never adds or removes nodes or edges, so analyses of the
+//! graph's structure give the same results before and after redaction. Hashed values are
+//! deterministic, so the same value is replaced by the same hash everywhere it appears, and in
+//! every graph redacted with the same options.
+
+use crate::graph::PageGraph;
+use crate::types::{EdgeType, NodeKind, NodeType, Url};
+use crate::url_search::URL_ATTRIBUTES;
+
+/// How a kind of sensitive value should be redacted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Redaction {
+    /// Leave the value as-is.
+    #[default]
+    Keep,
+    /// Remove the value entirely.
+    Strip,
+    /// Replace the value with a hash of itself.
+    Hash,
+}
+
+/// Which values should be redacted from a graph, and how. By default, nothing is redacted.
+#[derive(Debug, Clone, Default)]
+pub struct RedactOptions {
+    /// Query strings of every URL in the graph, including URL-valued attributes.
+    pub query_strings: Redaction,
+    /// Values written to or read from cookies and storage, including those passed to or returned
+    /// from cookie and storage Web APIs.
+    pub storage_values: Redaction,
+    /// The source text of inline scripts.
+    pub inline_scripts: Redaction,
+    /// Mixed into every hash, so that hashes can't be reversed by hashing guessed values without
+    /// knowing the salt.
+    pub salt: String,
+}
+
+impl RedactOptions {
+    /// Options that apply the same redaction to every kind of value.
+    pub fn all(redaction: Redaction) -> Self {
+        Self {
+            query_strings: redaction,
+            storage_values: redaction,
+            inline_scripts: redaction,
+            salt: String::new(),
+        }
+    }
+
+    fn hash(&self, value: &str) -> String {
+        format!("redacted-{:016x}", seahash::hash(format!("{}{}", self.salt, value).as_bytes()))
+    }
+
+    /// Redacts a value, returning `None` if it should be stripped.
+    fn apply(&self, redaction: Redaction, value: &str) -> Option<String> {
+        match redaction {
+            Redaction::Keep => Some(value.to_string()),
+            Redaction::Strip => None,
+            Redaction::Hash => Some(self.hash(value)),
+        }
+    }
+
+    fn redact_value(&self, redaction: Redaction, value: &mut Option<String>) {
+        *value = value.as_deref().and_then(|value| self.apply(redaction, value));
+    }
+
+    /// The URL with its query string redacted, if it has one that should be.
+    fn redacted_url(&self, url: &url::Url) -> Option<String> {
+        if self.query_strings == Redaction::Keep {
+            return None;
+        }
+        let query = url.query()?;
+        let mut redacted = url.clone();
+        redacted.set_query(self.apply(self.query_strings, query).as_deref());
+        Some(redacted.to_string())
+    }
+
+    fn redact_url(&self, url: &mut Url) {
+        if let Some(redacted) = url.parsed().and_then(|parsed| self.redacted_url(parsed)) {
+            *url = Url::from(redacted);
+        }
+    }
+
+    /// Like [`redact_url`](Self::redact_url), for URLs in attribute values.
+    fn redact_attribute_url(&self, value: &mut String) {
+        if let Some(redacted) = url::Url::parse(value).ok().and_then(|parsed| self.redacted_url(&parsed)) {
+            *value = redacted;
+        }
+    }
+}
+
+fn is_storage_api(method: &str) -> bool {
+    method.contains("cookie") || method.starts_with("Storage.") || method.contains("localStorage") || method.contains("sessionStorage")
+}
+
+impl PageGraph {
+    /// Redacts sensitive values from the graph in place.
+    pub fn redact(&mut self, options: &RedactOptions) {
+        self.time_index.take();
+        self.lifecycle.take();
+        self.request_index.take();
+        self.dom_tree.take();
+
+        options.redact_url(&mut self.desc.url);
+
+        let storage_apis = self.nodes.values()
+            .filter(|node| node.node_type.method().map(is_storage_api).unwrap_or(false))
+            .map(|node| node.id)
+            .collect::<std::collections::HashSet<_>>();
+        let storage_nodes = self.nodes.values()
+            .filter(|node| matches!(node.node_type.kind(), NodeKind::CookieJar | NodeKind::LocalStorage | NodeKind::SessionStorage | NodeKind::Storage))
+            .map(|node| node.id)
+            .collect::<std::collections::HashSet<_>>();
+
+        self.nodes.values_mut().for_each(|node| match &mut node.node_type {
+            NodeType::Resource { url } => options.redact_url(url),
+            NodeType::Script { url: None, source, .. } => {
+                *source = options.apply(options.inline_scripts, source).unwrap_or_default();
+            }
+            NodeType::Script { url: Some(url), .. } |
+            NodeType::DomRoot { url: Some(url), .. } |
+            NodeType::Worker { url: Some(url), .. } => options.redact_url(url),
+            _ => (),
+        });
+
+        self.edges.values_mut().for_each(|edge| {
+            let touches_storage_api = storage_apis.contains(&edge.source) || storage_apis.contains(&edge.target);
+            match &mut edge.edge_type {
+                EdgeType::SetAttribute { key, value: Some(value), .. } if URL_ATTRIBUTES.contains(&key.as_str()) => options.redact_attribute_url(value),
+                EdgeType::StorageSet { value, .. } |
+                EdgeType::StorageReadResult { value, .. } if storage_nodes.contains(&edge.source) || storage_nodes.contains(&edge.target) => {
+                    options.redact_value(options.storage_values, value);
+                }
+                EdgeType::JsCall { args, .. } if touches_storage_api => options.redact_value(options.storage_values, args),
+                EdgeType::JsResult { value } if touches_storage_api => options.redact_value(options.storage_values, value),
+                _ => (),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_redact() {
+        let mut builder = PageGraphBuilder::new("https://example.com/?session=abc");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, None, "document.cookie = 'uid=1234'");
+        let request = builder.add_request(script, "https://t.net/p?uid=1234", RequestType::Image);
+        let cookie = builder.call_web_api(script, "Document.cookie", Some("[\"uid=1234\"]"));
+        let cookie_jar = builder.add_node(NodeType::CookieJar {});
+        let storage_set = builder.add_edge(script, cookie_jar, EdgeType::StorageSet { key: "uid".to_string(), value: Some("1234".to_string()) });
+        let mut graph = builder.build();
+        let edge_count = graph.edges.len();
+
+        graph.redact(&RedactOptions { query_strings: Redaction::Hash, ..RedactOptions::all(Redaction::Strip) });
+
+        assert_eq!(graph.edges.len(), edge_count);
+        assert!(graph.desc.url.starts_with("https://example.com/?redacted-"));
+        let resource = graph.request(request).unwrap().url.unwrap();
+        assert!(resource.starts_with("https://t.net/p?redacted-"));
+        assert!(matches!(&graph.nodes.get(&script).unwrap().node_type, NodeType::Script { source, .. } if source.is_empty()));
+        assert!(matches!(&graph.edges.get(&cookie).unwrap().edge_type, EdgeType::JsCall { args: None, .. }));
+        assert!(matches!(&graph.edges.get(&storage_set).unwrap().edge_type, EdgeType::StorageSet { key, value: None } if key == "uid"));
+
+        // Hashes are deterministic
+        let mut url = Url::from("https://t.net/p?uid=1234");
+        RedactOptions::all(Redaction::Hash).redact_url(&mut url);
+        assert_eq!(url, resource);
+    }
+}
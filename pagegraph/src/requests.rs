@@ -0,0 +1,301 @@
+//! Lookup of network requests by their Blink request id.
+//!
+//! Every edge recording part of a request's lifetime carries the same request id. Redirects
+//! reuse the request id of the original request, with a new
+//! [`RequestStart`](EdgeType::RequestStart) edge pointing to the redirect target. Request ids are
+//! only unique within a single frame.
+
+use std::collections::HashMap;
+
+use crate::graph::{EdgeId, FrameId, HasFrameId, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeKind, NodeType, RequestType, ResourceType, ResponseMetadata, Url};
+
+/// Every edge belonging to each request, keyed by frame and request id.
+pub(crate) type RequestIndex = HashMap<(Option<FrameId>, usize), Vec<EdgeId>>;
+
+/// How a request ended.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum RequestOutcome {
+    Completed,
+    /// The request failed with an error other than being aborted.
+    Failed { status: String },
+    /// The request was cancelled before it finished, or had not finished by the end of the
+    /// recording.
+    Aborted,
+    /// The requested resource was blocked by Brave Shields.
+    Blocked {
+        /// The filter list rule responsible for the block, if one was recorded.
+        rule: Option<String>,
+        shield: Option<NodeKind>,
+    },
+}
+
+/// The number of requests with each kind of outcome.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RequestOutcomeCounts {
+    pub completed: usize,
+    pub failed: usize,
+    pub aborted: usize,
+    pub blocked: usize,
+}
+
+/// All of the information recorded about a single request.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RequestInfo {
+    pub request_id: usize,
+    pub frame_id: Option<FrameId>,
+    pub request_type: RequestType,
+    /// The final URL requested, after following any redirects.
+    pub url: Option<Url>,
+    /// The [`Resource`](crate::types::NodeType::Resource) node for the final URL.
+    pub resource: NodeId,
+    /// URLs that were redirected away from before reaching the final URL, in order.
+    pub redirects: Vec<Url>,
+    pub initiators: Vec<NodeId>,
+    /// Every [`RequestStart`](EdgeType::RequestStart) edge for the request, in chronological
+    /// order.
+    pub starts: Vec<EdgeId>,
+    pub completion: Option<EdgeId>,
+    pub error: Option<EdgeId>,
+    /// The status of the response, or of the error if the request failed.
+    pub status: Option<String>,
+    pub resource_type: Option<ResourceType>,
+    pub response_hash: Option<String>,
+    pub headers: Option<String>,
+    /// Response metadata parsed from `headers`.
+    pub response: Option<ResponseMetadata>,
+    /// The size of the response body in bytes, if it could be determined.
+    pub size: Option<usize>,
+    pub outcome: RequestOutcome,
+}
+
+impl PageGraph {
+    pub(crate) fn request_index(&self) -> &RequestIndex {
+        self.request_index.get_or_init(|| {
+            let mut index = RequestIndex::new();
+            self.edges.values().for_each(|edge| if let Some(request_id) = edge.edge_type.request_id() {
+                index.entry((edge.id.get_frame_id(), request_id)).or_default().push(edge.id);
+            });
+            index.values_mut().for_each(|edges| edges.sort_by_key(|edge| (self.edges.get(edge).unwrap().edge_timestamp, *edge)));
+            index
+        })
+    }
+
+    /// Returns the request with the given id from the root frame, if it exists.
+    pub fn request(&self, request_id: usize) -> Option<RequestInfo> {
+        self.request_in_frame(request_id, None)
+    }
+
+    /// Returns the request with the given id from the given frame, if it exists. Pass `None` for
+    /// the root frame.
+    pub fn request_in_frame(&self, request_id: usize, frame_id: Option<FrameId>) -> Option<RequestInfo> {
+        let edges = self.request_index().get(&(frame_id, request_id))?
+            .iter()
+            .map(|edge| self.edges.get(edge).unwrap())
+            .collect::<Vec<_>>();
+
+        let starts = edges.iter().filter(|edge| matches!(edge.edge_type, EdgeType::RequestStart { .. })).collect::<Vec<_>>();
+        let last_start = starts.last()?;
+        let request_type = match &starts[0].edge_type {
+            EdgeType::RequestStart { request_type, .. } => request_type.clone(),
+            _ => unreachable!(),
+        };
+
+        let mut urls = vec![];
+        starts.iter().for_each(|edge| if let Some(url) = self.target_node(edge).node_type.url() {
+            if urls.last() != Some(&url) {
+                urls.push(url);
+            }
+        });
+        let url = urls.pop().cloned();
+
+        let mut initiators = starts.iter().map(|edge| edge.source).collect::<Vec<_>>();
+        initiators.sort();
+        initiators.dedup();
+
+        let completion = edges.iter().find(|edge| matches!(edge.edge_type, EdgeType::RequestComplete { .. }));
+        let error = edges.iter().find(|edge| matches!(edge.edge_type, EdgeType::RequestError { .. }));
+
+        let mut info = RequestInfo {
+            request_id,
+            frame_id,
+            request_type,
+            url,
+            resource: last_start.target,
+            redirects: urls.into_iter().cloned().collect(),
+            initiators,
+            starts: starts.iter().map(|edge| edge.id).collect(),
+            completion: completion.map(|edge| edge.id),
+            error: error.map(|edge| edge.id),
+            status: None,
+            resource_type: None,
+            response_hash: None,
+            headers: None,
+            response: None,
+            size: None,
+            outcome: RequestOutcome::Aborted,
+        };
+        info.response = completion.or(error).and_then(|edge| edge.edge_type.response_metadata().cloned());
+        info.size = completion.or(error).and_then(|edge| edge.edge_type.response_size());
+        match completion.or(error).map(|edge| &edge.edge_type) {
+            Some(EdgeType::RequestComplete { resource_type, status, response_hash, headers, .. }) => {
+                info.status = Some(status.clone());
+                info.resource_type = Some(resource_type.clone());
+                info.response_hash = response_hash.clone();
+                info.headers = Some(headers.raw.clone());
+            }
+            Some(EdgeType::RequestError { status, headers, .. }) => {
+                info.status = Some(status.clone());
+                info.headers = Some(headers.raw.clone());
+            }
+            _ => (),
+        }
+        info.outcome = self.outcome_of(&info);
+        Some(info)
+    }
+
+    fn outcome_of(&self, info: &RequestInfo) -> RequestOutcome {
+        let block = info.starts.iter()
+            .map(|edge| self.edges.get(edge).unwrap().target)
+            .flat_map(|resource| self.incoming_edges(self.nodes.get(&resource).unwrap()))
+            .find(|edge| matches!(edge.edge_type, EdgeType::ResourceBlock {}));
+        if let Some(block) = block {
+            return RequestOutcome::Blocked {
+                rule: match &self.source_node(block).node_type {
+                    NodeType::AdFilter { rule } => Some(rule.clone()),
+                    _ => None,
+                },
+                shield: self.shield_of_blocker(block.source),
+            };
+        }
+        match (info.completion, &info.status) {
+            (Some(_), _) => RequestOutcome::Completed,
+            (None, Some(status)) if status.to_ascii_uppercase().contains("BLOCKED_BY_CLIENT") => RequestOutcome::Blocked { rule: None, shield: None },
+            (None, Some(status)) if status.to_ascii_uppercase().contains("ABORTED") => RequestOutcome::Aborted,
+            (None, Some(status)) => RequestOutcome::Failed { status: status.clone() },
+            (None, None) => RequestOutcome::Aborted,
+        }
+    }
+
+    /// Returns every request in the graph, ordered by frame and request id.
+    pub fn requests(&self) -> Vec<RequestInfo> {
+        let mut ids = self.request_index().keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        ids.into_iter()
+            .filter_map(|(frame_id, request_id)| self.request_in_frame(request_id, frame_id))
+            .collect()
+    }
+
+    /// Counts the requests in the graph with each kind of outcome.
+    pub fn request_outcome_counts(&self) -> RequestOutcomeCounts {
+        let mut counts = RequestOutcomeCounts::default();
+        self.requests().iter().for_each(|request| match request.outcome {
+            RequestOutcome::Completed => counts.completed += 1,
+            RequestOutcome::Failed { .. } => counts.failed += 1,
+            RequestOutcome::Aborted => counts.aborted += 1,
+            RequestOutcome::Blocked { .. } => counts.blocked += 1,
+        });
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+
+    #[test]
+    fn test_request_lookup() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "img");
+        let request = builder.add_request(element, "https://example.com/pixel.gif", RequestType::Image);
+        let redirect_target = builder.add_node(NodeType::Resource { url: "https://cdn.example.com/pixel.gif".into() });
+        builder.add_edge(element, redirect_target, EdgeType::RequestStart { request_type: RequestType::Image, status: "started".to_string(), request_id: request });
+        let complete = builder.complete_request(request, ResourceType::Image, Some(43));
+        let failed = builder.add_request(element, "https://example.com/missing.gif", RequestType::Image);
+        let error = builder.fail_request(failed);
+        let graph = builder.build();
+
+        let info = graph.request(request).unwrap();
+        assert_eq!(info.url.as_deref(), Some("https://cdn.example.com/pixel.gif"));
+        assert_eq!(info.resource, redirect_target);
+        assert_eq!(info.initiators, vec![element]);
+        assert_eq!(info.redirects, vec!["https://example.com/pixel.gif".to_string()]);
+        assert_eq!(info.starts.len(), 2);
+        assert_eq!(info.completion, Some(complete));
+        assert_eq!(info.error, None);
+        assert_eq!(info.resource_type, Some(ResourceType::Image));
+        assert_eq!(info.size, Some(43));
+
+        let info = graph.request(failed).unwrap();
+        assert_eq!(info.completion, None);
+        assert_eq!(info.error, Some(error));
+        assert_eq!(info.status.as_deref(), Some("error"));
+
+        assert!(graph.request(100).is_none());
+    }
+
+    #[test]
+    fn test_request_outcomes() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "img");
+        let completed = builder.add_request(element, "https://example.com/a.gif", RequestType::Image);
+        builder.complete_request(completed, ResourceType::Image, None);
+        let failed = builder.add_request(element, "https://example.com/b.gif", RequestType::Image);
+        builder.fail_request(failed);
+        let unfinished = builder.add_request(element, "https://example.com/c.gif", RequestType::Image);
+        let blocked = builder.add_request(element, "https://ads.net/d.gif", RequestType::Image);
+        let filter = builder.add_node(NodeType::AdFilter { rule: "||ads.net^".to_string() });
+        builder.add_edge(filter, builder.resource_of(blocked), EdgeType::ResourceBlock {});
+        builder.fail_request(blocked);
+        let graph = builder.build();
+
+        assert_eq!(graph.request(completed).unwrap().outcome, RequestOutcome::Completed);
+        assert_eq!(graph.request(failed).unwrap().outcome, RequestOutcome::Failed { status: "error".to_string() });
+        assert_eq!(graph.request(unfinished).unwrap().outcome, RequestOutcome::Aborted);
+        assert_eq!(graph.request(blocked).unwrap().outcome, RequestOutcome::Blocked { rule: Some("||ads.net^".to_string()), shield: None });
+        assert_eq!(graph.request_outcome_counts(), RequestOutcomeCounts { completed: 1, failed: 1, aborted: 1, blocked: 1 });
+    }
+
+    #[test]
+    fn test_response_metadata() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let request = builder.add_request(element, "https://example.com/app.js", RequestType::Script);
+        let resource = builder.resource_of(request);
+        builder.add_edge(resource, element, EdgeType::RequestComplete {
+            resource_type: ResourceType::Script,
+            status: "complete".to_string(),
+            value: None,
+            response_hash: None,
+            request_id: request,
+            headers: ResponseMetadata::from("HTTP/1.1 404 Not Found\nContent-Type: text/javascript; charset=utf-8\nCache-Control: no-cache\n"),
+            size: Some(10),
+        });
+        let graph = builder.build();
+
+        let response = graph.request(request).unwrap().response.unwrap();
+        assert_eq!(response.status_code, Some(404));
+        assert_eq!(response.mime_type.as_deref(), Some("text/javascript"));
+        assert_eq!(response.header("cache-control"), Some("no-cache"));
+        assert_eq!(ResponseMetadata::from(":status: 204\n").status_code, Some(204));
+
+        assert!(response.mime_type_matches("text/*"));
+        assert!(response.mime_type_matches("Text/JavaScript"));
+        assert!(!response.mime_type_matches("image/*"));
+        assert_eq!(graph.resources_of_type(RequestType::Script).iter().map(|node| node.id).collect::<Vec<_>>(), vec![resource]);
+        assert!(graph.resources_of_type(RequestType::Image).is_empty());
+        assert_eq!(graph.resources_with_mime_type("text/*").iter().map(|node| node.id).collect::<Vec<_>>(), vec![resource]);
+        assert!(graph.resources_with_mime_type("image/*").is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+//! JSON Schemas describing the serialized results of analyses, with the `schemars` feature.
+//!
+//! Result types implement [`JsonSchema`], describing the JSON they serialize to, so that pipelines
+//! consuming `pagegraph` output can validate it, or generate code against it. Named types are
+//! placed under `definitions` and referenced with `$ref`, which also allows recursive types like
+//! [`DownstreamRequests`](crate::graph::DownstreamRequests). [`schema_for`] produces a complete,
+//! standalone schema in the [draft-07](https://json-schema.org/specification-links.html#draft-7)
+//! format.
+//!
+//! Types building on these results can derive `JsonSchema` as well, which takes `serde`
+//! attributes like `skip_serializing_if` into account:
+//!
+//! ```
+//! use pagegraph::graph::NodeId;
+//!
+//! #[derive(serde::Serialize, schemars::JsonSchema)]
+//! struct ScriptReport {
+//!     script: NodeId,
+//!     url: Option<String>,
+//!     #[serde(skip_serializing_if = "Vec::is_empty")]
+//!     apis: Vec<String>,
+//! }
+//!
+//! let schema = pagegraph::schema::schema_for::<ScriptReport>();
+//! assert!(schema.definitions.contains_key("NodeId"));
+//! ```
+
+use schemars::gen::SchemaSettings;
+use schemars::schema::{SchemaObject, SubschemaValidation};
+
+pub use schemars::gen::SchemaGenerator;
+pub use schemars::schema::{RootSchema, Schema};
+pub use schemars::JsonSchema;
+
+/// A generator for draft-07 schemas, in which named types are defined.
+pub fn generator() -> SchemaGenerator {
+    SchemaSettings::draft07().into_generator()
+}
+
+/// Turns `schema` into a standalone schema, including every definition in `generator`.
+pub fn into_root_schema(mut generator: SchemaGenerator, schema: Schema) -> RootSchema {
+    let schema = match schema {
+        // Properties alongside a `$ref` are ignored, including `definitions`.
+        Schema::Object(schema) if !schema.is_ref() => schema,
+        schema => SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation { all_of: Some(vec![schema]), ..Default::default() })),
+            ..Default::default()
+        },
+    };
+    RootSchema {
+        meta_schema: generator.settings().meta_schema.clone(),
+        schema,
+        definitions: generator.take_definitions(),
+    }
+}
+
+/// Returns a standalone schema for `T`.
+pub fn schema_for<T: JsonSchema + ?Sized>() -> RootSchema {
+    generator().into_root_schema_for::<T>()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::analysis::registry::AnalysisRegistry;
+    use crate::builder::PageGraphBuilder;
+    use crate::graph::DownstreamRequests;
+    use crate::requests::RequestOutcome;
+    use crate::types::NodeKind;
+
+    /// Whether `value` is of the named JSON Schema type.
+    fn is_of_type(value: &Value, kind: &str) -> Result<bool, String> {
+        Ok(match kind {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64() || value.as_f64().map(|number| number.fract() == 0.0).unwrap_or(false),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            kind => return Err(format!("unsupported type {}", kind)),
+        })
+    }
+
+    /// Checks `value` against the subset of JSON Schema generated for analysis results, returning
+    /// the path to the first mismatch.
+    fn validate(value: &Value, schema: &Value, root: &Value) -> Result<(), String> {
+        let schema = match schema {
+            Value::Bool(true) => return Ok(()),
+            Value::Object(schema) => schema,
+            schema => return Err(format!("unsupported schema {}", schema)),
+        };
+        if let Some(reference) = schema.get("$ref").and_then(|reference| reference.as_str()) {
+            let name = reference.trim_start_matches("#/definitions/");
+            let definition = root["definitions"].get(name).ok_or_else(|| format!("missing definition {}", name))?;
+            return validate(value, definition, root);
+        }
+        for all_of in schema.get("allOf").and_then(|all_of| all_of.as_array()).into_iter().flatten() {
+            validate(value, all_of, root)?;
+        }
+        if let Some(alternatives) = schema.get("anyOf").or_else(|| schema.get("oneOf")).and_then(|alternatives| alternatives.as_array()) {
+            let matching = alternatives.iter().filter(|alternative| validate(value, alternative, root).is_ok()).count();
+            return match (matching, schema.contains_key("oneOf")) {
+                (0, _) => Err(format!("{} matches none of {:?}", value, alternatives)),
+                (1, _) | (_, false) => Ok(()),
+                _ => Err(format!("{} matches several of {:?}", value, alternatives)),
+            };
+        }
+        if let Some(constant) = schema.get("const") {
+            return if constant == value { Ok(()) } else { Err(format!("{} is not {}", value, constant)) };
+        }
+        if let Some(variants) = schema.get("enum").and_then(|variants| variants.as_array()) {
+            if !variants.contains(value) {
+                return Err(format!("{} is not one of {:?}", value, variants));
+            }
+        }
+        let matches_type = match schema.get("type") {
+            None => true,
+            Some(Value::Array(kinds)) => kinds.iter().map(|kind| is_of_type(value, kind.as_str().unwrap())).collect::<Result<Vec<_>, _>>()?.contains(&true),
+            Some(kind) => is_of_type(value, kind.as_str().unwrap())?,
+        };
+        if !matches_type {
+            return Err(format!("{} is not of type {}", value, schema["type"]));
+        }
+        if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(|minimum| minimum.as_f64()), value.as_f64()) {
+            if number < minimum {
+                return Err(format!("{} is less than {}", value, minimum));
+            }
+        }
+        if let Some(items) = value.as_array() {
+            for (i, item) in items.iter().enumerate() {
+                let item_schema = match schema.get("items") {
+                    Some(Value::Array(tuple)) => tuple.get(i).ok_or_else(|| format!("unexpected tuple item {}", i))?,
+                    Some(item_schema) => item_schema,
+                    None => continue,
+                };
+                validate(item, item_schema, root).map_err(|error| format!("[{}]: {}", i, error))?;
+            }
+        }
+        if let Some(object) = value.as_object() {
+            let properties = schema.get("properties").and_then(|properties| properties.as_object());
+            for required in schema.get("required").and_then(|required| required.as_array()).into_iter().flatten() {
+                if !object.contains_key(required.as_str().unwrap()) {
+                    return Err(format!("missing property {}", required));
+                }
+            }
+            for (key, property) in object {
+                let property_schema = match properties.and_then(|properties| properties.get(key)) {
+                    Some(property_schema) => property_schema,
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => return Err(format!("unexpected property {}", key)),
+                        Some(additional) => additional,
+                        None => continue,
+                    },
+                };
+                validate(property, property_schema, root).map_err(|error| format!(".{}: {}", key, error))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_schema() {
+        let schema = serde_json::to_value(schema_for::<Vec<DownstreamRequests>>()).unwrap();
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert_eq!(schema["items"], json!({ "$ref": "#/definitions/DownstreamRequests" }));
+        assert_eq!(schema["definitions"]["DownstreamRequests"]["properties"]["children"]["items"], schema["items"]);
+
+        let schema = serde_json::to_value(schema_for::<RequestOutcome>()).unwrap();
+        assert_eq!(schema["title"], "RequestOutcome");
+        let blocked = serde_json::to_value(RequestOutcome::Blocked { rule: None, shield: Some(NodeKind::AdsShield) }).unwrap();
+        assert_eq!(validate(&blocked, &schema, &schema), Ok(()));
+        assert!(validate(&json!({ "outcome": "failed" }), &schema, &schema).is_err());
+        assert!(validate(&json!({ "outcome": "blocked", "shield": "NotAShield" }), &schema, &schema).is_err());
+    }
+
+    #[test]
+    fn test_analysis_schemas() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, Some("https://tracker.net/t.js"), "");
+        builder.call_web_api(script, "HTMLCanvasElement.toDataURL", Some("[]"));
+        builder.call_web_api(script, "Navigator.userAgent", None);
+        let cookies = builder.add_node(crate::types::NodeType::CookieJar {});
+        builder.add_edge(script, cookies, crate::types::EdgeType::StorageSet { key: "uid".to_string(), value: Some("uid=a1b2c3d4e5f6; path=/".to_string()) });
+        builder.call_web_api(script, "Window.open", Some("[\"https://ads.net/\"]"));
+        let stylesheet = builder.add_request(parser, "https://example.com/style.css", crate::types::RequestType::CSS);
+        builder.complete_request(stylesheet, crate::types::ResourceType::Css, Some(100));
+        let pixel = builder.add_request(script, "https://tracker.net/pixel.gif", crate::types::RequestType::Image);
+        builder.fail_request(pixel);
+        builder.add_text(parser, root, "Done");
+        let graph = builder.build();
+
+        let registry = AnalysisRegistry::with_builtins();
+        for analysis in registry.analyses() {
+            let mut generator = generator();
+            let schema = analysis.schema(&mut generator).unwrap_or_else(|| panic!("{} has no schema", analysis.name()));
+            let schema = serde_json::to_value(into_root_schema(generator, schema)).unwrap();
+            let results = registry.run(analysis.name(), &graph).unwrap();
+            assert_eq!(validate(&results, &schema, &schema), Ok(()), "{} results don't match its schema", analysis.name());
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Mapping of the script positions recorded on edges back to lines in the acting script's source.
+
+use crate::graph::{Edge, NodeId, PageGraph};
+use crate::types::NodeType;
+
+/// A position within a script's source text.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SourceLocation {
+    /// The script whose source contains this location.
+    pub script: NodeId,
+    /// The character offset into the script's source.
+    pub offset: usize,
+    /// The 1-based line number of the offset.
+    pub line: usize,
+    /// The 1-based column number of the offset, in characters.
+    pub column: usize,
+    /// The full text of the line containing the offset.
+    pub line_text: String,
+}
+
+impl SourceLocation {
+    fn in_source(script: NodeId, source: &str, offset: usize) -> Option<Self> {
+        let mut line = 1;
+        let mut column = 1;
+        let mut line_start = 0;
+        let mut chars = source.char_indices();
+        for _ in 0..offset {
+            let (i, c) = chars.next()?;
+            if c == '\n' {
+                line += 1;
+                column = 1;
+                line_start = i + 1;
+            } else {
+                column += 1;
+            }
+        }
+        let line_text = source[line_start..].lines().next().unwrap_or_default().to_string();
+        Some(Self { script, offset, line, column, line_text })
+    }
+}
+
+impl PageGraph {
+    /// Returns the location in the acting script's source where the action recorded by the given
+    /// edge took place.
+    ///
+    /// Returns `None` if the edge has no recorded script position, if its actor is not a script,
+    /// or if the position is outside of the script's recorded source text.
+    pub fn source_location(&self, edge: &Edge) -> Option<SourceLocation> {
+        let offset = edge.edge_type.script_position()?;
+        match &self.source_node(edge).node_type {
+            NodeType::Script { source, .. } => SourceLocation::in_source(edge.source, source, offset),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::EdgeType;
+
+    #[test]
+    fn test_source_location() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        let script = builder.add_script(element, None, "var a = 1;\n  document.cookie;\nfoo();");
+        let cookie = builder.add_node(NodeType::WebApi { method: "Document.cookie".to_string() });
+        let call = builder.add_edge(script, cookie, EdgeType::JsCall { args: None, script_position: 13 });
+        let out_of_range = builder.add_edge(script, cookie, EdgeType::JsCall { args: None, script_position: 100 });
+        let insert = builder.insert_node(parser, element, root);
+        let graph = builder.build();
+
+        let location = graph.source_location(graph.edges.get(&call).unwrap()).unwrap();
+        assert_eq!(location.script, script);
+        assert_eq!((location.line, location.column), (2, 3));
+        assert_eq!(location.line_text, "  document.cookie;");
+
+        assert_eq!(graph.source_location(graph.edges.get(&out_of_range).unwrap()), None);
+        assert_eq!(graph.source_location(graph.edges.get(&insert).unwrap()), None);
+    }
+}
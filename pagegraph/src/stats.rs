@@ -0,0 +1,317 @@
+//! Summary statistics over the contents of a graph.
+
+use std::collections::BTreeMap;
+
+use crate::analysis::page_weight::ByteAttribution;
+use crate::graph::{is_same_frame_context, FrameId, HasFrameId, PageGraph};
+use crate::requests::RequestOutcomeCounts;
+use crate::types::{EdgeKind, EdgeType, NodeKind, NodeType, Url};
+
+/// The number of domains to include in [`GraphSummary::top_domains`].
+const TOP_DOMAINS: usize = 10;
+
+/// An overview of the contents of a whole graph.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GraphSummary {
+    pub url: String,
+    pub nodes: usize,
+    pub edges: usize,
+    pub nodes_by_kind: BTreeMap<NodeKind, usize>,
+    pub edges_by_kind: BTreeMap<EdgeKind, usize>,
+    pub degrees: DegreeDistribution,
+    pub time_span: TimeSpan,
+    /// The number of frames whose contents are part of the graph, including the root frame and
+    /// any merged remote frames.
+    pub frames: usize,
+    /// Remote frames whose contents have not been merged into the graph.
+    pub unmerged_remote_frames: usize,
+    pub requests: RequestOutcomeCounts,
+    /// The domains that the most bytes were downloaded from, from most to fewest bytes.
+    pub top_domains: Vec<ByteAttribution>,
+}
+
+/// The distribution of node degrees, counting every parallel edge separately.
+#[derive(Debug, Default, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DegreeDistribution {
+    pub max_in_degree: usize,
+    pub max_out_degree: usize,
+    pub mean_degree: f64,
+    /// Maps each total (incoming plus outgoing) degree to the number of nodes with that degree.
+    pub histogram: BTreeMap<usize, usize>,
+}
+
+/// When the graph was recorded, and the range of timestamps recorded on its edges.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TimeSpan {
+    /// Milliseconds since the Unix epoch.
+    pub recording_start: u64,
+    /// Milliseconds since the Unix epoch.
+    pub recording_end: u64,
+    /// Milliseconds relative to the start of the page load.
+    pub first_edge_timestamp: Option<isize>,
+    /// Milliseconds relative to the start of the page load.
+    pub last_edge_timestamp: Option<isize>,
+}
+
+/// Statistics about the activity within a single frame.
+///
+/// Frames are distinguished by the frame ids of their nodes and edges, so local frames, which are
+/// recorded as part of their parent's graph, are counted towards the parent frame. Remote frames
+/// are only counted separately once they've been merged with [`PageGraph::merge_frame`].
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct FrameStats {
+    pub frame_id: FrameId,
+    pub is_root: bool,
+    /// The URL of the frame's top-level document, if it was recorded.
+    pub url: Option<Url>,
+    pub nodes: usize,
+    pub edges: usize,
+    pub nodes_by_kind: BTreeMap<NodeKind, usize>,
+    pub edges_by_kind: BTreeMap<EdgeKind, usize>,
+    pub requests: usize,
+    /// Requests to a different registrable domain than the top-level page.
+    pub third_party_requests: usize,
+    /// The fraction of requests that were third-party, or `None` if the frame made no requests.
+    pub third_party_share: Option<f64>,
+    pub scripts: usize,
+}
+
+impl FrameStats {
+    fn new(frame_id: Option<FrameId>, root_frame_id: FrameId) -> Self {
+        Self {
+            frame_id: frame_id.unwrap_or(root_frame_id),
+            is_root: frame_id.is_none(),
+            url: None,
+            nodes: 0,
+            edges: 0,
+            nodes_by_kind: BTreeMap::new(),
+            edges_by_kind: BTreeMap::new(),
+            requests: 0,
+            third_party_requests: 0,
+            third_party_share: None,
+            scripts: 0,
+        }
+    }
+}
+
+/// Counts of page activity over time, in fixed-size buckets of edge timestamps.
+#[derive(Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ActivityHistogram {
+    pub bucket_ms: usize,
+    /// The buckets, contiguous and in chronological order, from the first bucket with any
+    /// activity to the last.
+    pub buckets: Vec<ActivityBucket>,
+}
+
+/// The activity recorded within one bucket of an [`ActivityHistogram`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ActivityBucket {
+    /// Milliseconds relative to the start of the page load. The bucket ends just before the next
+    /// bucket's start.
+    pub start: isize,
+    /// [`RequestStart`](EdgeType::RequestStart) edges.
+    pub requests: usize,
+    /// [`Execute`](EdgeType::Execute) and [`ExecuteFromAttribute`](EdgeType::ExecuteFromAttribute)
+    /// edges.
+    pub script_executions: usize,
+    /// Edges creating, inserting, removing, or deleting nodes, or changing their attributes or
+    /// text.
+    pub dom_mutations: usize,
+}
+
+impl PageGraph {
+    /// Returns an overview of the contents of the graph.
+    pub fn summary(&self) -> GraphSummary {
+        let mut nodes_by_kind = BTreeMap::new();
+        self.nodes.values().for_each(|node| *nodes_by_kind.entry(node.node_type.kind()).or_default() += 1);
+        let mut edges_by_kind = BTreeMap::new();
+        self.edges.values().for_each(|edge| *edges_by_kind.entry(edge.edge_type.kind()).or_default() += 1);
+
+        let mut degrees = DegreeDistribution::default();
+        self.nodes.values().for_each(|node| {
+            let in_degree = self.incoming_edges(node).count();
+            let out_degree = self.outgoing_edges(node).count();
+            degrees.max_in_degree = degrees.max_in_degree.max(in_degree);
+            degrees.max_out_degree = degrees.max_out_degree.max(out_degree);
+            *degrees.histogram.entry(in_degree + out_degree).or_default() += 1;
+        });
+        if !self.nodes.is_empty() {
+            degrees.mean_degree = 2.0 * self.edges.len() as f64 / self.nodes.len() as f64;
+        }
+
+        let timestamps = self.edges.values().filter_map(|edge| edge.edge_timestamp);
+        let time_span = TimeSpan {
+            recording_start: self.desc.time.start,
+            recording_end: self.desc.time.end,
+            first_edge_timestamp: timestamps.clone().min(),
+            last_edge_timestamp: timestamps.max(),
+        };
+
+        let mut frames = self.nodes.keys().map(|node_id| node_id.get_frame_id()).collect::<std::collections::BTreeSet<_>>();
+        frames.insert(None);
+        let unmerged_remote_frames = self.all_remote_frame_ids().into_iter().filter(|frame_id| !frames.contains(&Some(*frame_id))).count();
+
+        GraphSummary {
+            url: self.desc.url.to_string(),
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            nodes_by_kind,
+            edges_by_kind,
+            degrees,
+            time_span,
+            frames: frames.len(),
+            unmerged_remote_frames,
+            requests: self.request_outcome_counts(),
+            top_domains: self.bytes_by_domain().into_iter().take(TOP_DOMAINS).collect(),
+        }
+    }
+
+    /// Returns counts of requests, script executions, and DOM mutations in buckets of the given
+    /// number of milliseconds. Buckets are aligned to multiples of `bucket_ms` from the start of
+    /// the page load. Edges without timestamps aren't counted.
+    pub fn activity_histogram(&self, bucket_ms: usize) -> ActivityHistogram {
+        assert!(bucket_ms > 0, "Buckets must be at least 1ms long");
+        let bucket_ms_signed = bucket_ms as isize;
+        let mut buckets = Vec::<ActivityBucket>::new();
+
+        for edge in self.edges_in_range(..) {
+            let is_request = matches!(edge.edge_type, EdgeType::RequestStart { .. });
+            let is_execution = matches!(edge.edge_type, EdgeType::Execute {} | EdgeType::ExecuteFromAttribute { .. });
+            let is_mutation = matches!(edge.edge_type.kind(),
+                EdgeKind::CreateNode | EdgeKind::InsertNode | EdgeKind::RemoveNode | EdgeKind::DeleteNode |
+                EdgeKind::SetAttribute | EdgeKind::DeleteAttribute | EdgeKind::TextChange);
+            if !(is_request || is_execution || is_mutation) {
+                continue;
+            }
+
+            // Edges are in chronological order, so buckets only need to be added at the end.
+            let start = edge.edge_timestamp.unwrap().div_euclid(bucket_ms_signed) * bucket_ms_signed;
+            let next_start = buckets.last().map(|bucket| bucket.start + bucket_ms_signed).unwrap_or(start);
+            buckets.extend((next_start..=start).step_by(bucket_ms).map(|start| ActivityBucket { start, ..Default::default() }));
+
+            let bucket = buckets.last_mut().unwrap();
+            bucket.requests += is_request as usize;
+            bucket.script_executions += is_execution as usize;
+            bucket.dom_mutations += is_mutation as usize;
+        }
+
+        ActivityHistogram { bucket_ms, buckets }
+    }
+
+    /// Returns statistics for each frame in the graph, starting with the root frame and followed
+    /// by merged remote frames in order of frame id.
+    pub fn frame_stats(&self) -> Vec<FrameStats> {
+        let mut frames = BTreeMap::<Option<FrameId>, FrameStats>::new();
+        let root_frame_id = self.desc.frame_id;
+
+        self.nodes.values().for_each(|node| {
+            let stats = frames.entry(node.id.get_frame_id()).or_insert_with(|| FrameStats::new(node.id.get_frame_id(), root_frame_id));
+            stats.nodes += 1;
+            *stats.nodes_by_kind.entry(node.node_type.kind()).or_default() += 1;
+            match node.node_type {
+                NodeType::Script { .. } => stats.scripts += 1,
+                // The top-level document is the only DOM root without a parent frame in its graph.
+                NodeType::DomRoot { .. } if stats.url.is_none() && !self.incoming_edges(node).any(|edge| matches!(edge.edge_type, EdgeType::CrossDom {}) && is_same_frame_context(edge.source, node.id)) => {
+                    stats.url = node.node_type.url().cloned();
+                }
+                _ => (),
+            }
+        });
+
+        self.edges.values().for_each(|edge| {
+            let stats = frames.entry(edge.id.get_frame_id()).or_insert_with(|| FrameStats::new(edge.id.get_frame_id(), root_frame_id));
+            stats.edges += 1;
+            *stats.edges_by_kind.entry(edge.edge_type.kind()).or_default() += 1;
+            if let EdgeType::RequestStart { .. } = edge.edge_type {
+                stats.requests += 1;
+                if let Some(true) = self.target_node(edge).node_type.url().and_then(|url| self.is_third_party(url)) {
+                    stats.third_party_requests += 1;
+                }
+            }
+        });
+
+        frames.into_values()
+            .map(|stats| FrameStats {
+                third_party_share: if stats.requests > 0 { Some(stats.third_party_requests as f64 / stats.requests as f64) } else { None },
+                ..stats
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_frame_stats() {
+        let build = |url: &str, frame_id: FrameId, is_root: bool| {
+            let mut builder = PageGraphBuilder::new(url).frame_id(frame_id).is_root(is_root);
+            let parser = builder.parser();
+            let root = builder.dom_root();
+            let element = builder.add_element(parser, root, "script");
+            let script = builder.add_script(element, None, "");
+            builder.add_request(script, "https://ads.net/pixel.gif", RequestType::Image);
+            if is_root {
+                builder.add_request(script, "https://example.com/data.json", RequestType::AJAX);
+                let iframe = builder.add_frame_owner(parser, root, "iframe");
+                let remote_frame = builder.add_node(NodeType::RemoteFrame { frame_id: FrameId::try_from("000000000000000000000000000000AD").unwrap() });
+                builder.add_edge(iframe, remote_frame, EdgeType::CrossDom {});
+            }
+            builder.build()
+        };
+        let frame_id = FrameId::try_from("000000000000000000000000000000AD").unwrap();
+        let mut graph = build("https://example.com/", FrameId::try_from("0000000000000000000000000000000A").unwrap(), true);
+        graph.merge_frame(build("https://ads.net/frame.html", frame_id, false), &frame_id);
+
+        let stats = graph.frame_stats();
+        assert_eq!(stats.len(), 2);
+        assert!(stats[0].is_root);
+        assert_eq!(stats[0].url.as_deref(), Some("https://example.com/"));
+        assert_eq!((stats[0].requests, stats[0].third_party_requests), (2, 1));
+        assert_eq!(stats[0].third_party_share, Some(0.5));
+        assert_eq!(stats[0].nodes_by_kind.get(&NodeKind::RemoteFrame), Some(&1));
+
+        assert_eq!(stats[1].frame_id, frame_id);
+        assert_eq!(stats[1].url.as_deref(), Some("https://ads.net/frame.html"));
+        assert_eq!(stats[1].scripts, 1);
+        assert_eq!(stats[1].third_party_share, Some(1.0));
+
+        let summary = graph.summary();
+        assert_eq!((summary.frames, summary.unmerged_remote_frames), (2, 0));
+        assert_eq!(summary.nodes, stats.iter().map(|stats| stats.nodes).sum::<usize>());
+        assert_eq!(summary.nodes_by_kind.get(&NodeKind::Script), Some(&2));
+        assert_eq!(summary.degrees.histogram.values().sum::<usize>(), summary.nodes);
+        assert_eq!(summary.top_domains[0].name.as_deref(), Some("ads.net"));
+    }
+
+    #[test]
+    fn test_activity_histogram() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let element = builder.add_element(parser, root, "script");
+        builder.add_script(element, None, "");
+        builder.advance_time(250);
+        builder.add_request(parser, "https://tracker.net/pixel.gif", RequestType::Image);
+        builder.add_request(parser, "https://tracker.net/beacon", RequestType::AJAX);
+        let graph = builder.build();
+
+        let histogram = graph.activity_histogram(100);
+        let counts = histogram.buckets.iter()
+            .map(|bucket| (bucket.start, bucket.requests, bucket.script_executions, bucket.dom_mutations))
+            .collect::<Vec<_>>();
+        // The DOM root's creation, and the script element's creation and insertion
+        assert_eq!(counts, vec![(0, 0, 1, 3), (100, 0, 0, 0), (200, 2, 0, 0)]);
+    }
+}
@@ -0,0 +1,116 @@
+//! Conversions between the relative timestamps recorded on graph items and wall-clock time.
+//!
+//! Node and edge timestamps are recorded in milliseconds relative to the start of the page load,
+//! while the graph's [`PageGraphTime`] descriptor records the absolute start and end of the
+//! recording in milliseconds since the Unix epoch.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::graph::{Edge, Node, PageGraph, PageGraphTime};
+
+impl PageGraphTime {
+    /// The wall-clock time at which recording started.
+    pub fn start_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.start)
+    }
+
+    /// The wall-clock time at which recording ended.
+    pub fn end_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.end)
+    }
+
+    /// How long the recording lasted.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.end.saturating_sub(self.start))
+    }
+}
+
+impl PageGraph {
+    /// Converts a graph timestamp into the wall-clock time at which it was recorded.
+    pub fn to_system_time(&self, timestamp: isize) -> SystemTime {
+        let start = self.desc.time.start_time();
+        let offset = Duration::from_millis(timestamp.unsigned_abs() as u64);
+        if timestamp >= 0 {
+            start + offset
+        } else {
+            start - offset
+        }
+    }
+
+    /// Converts a wall-clock time into the equivalent graph timestamp.
+    pub fn from_system_time(&self, time: SystemTime) -> isize {
+        let start = self.desc.time.start_time();
+        match time.duration_since(start) {
+            Ok(after) => after.as_millis() as isize,
+            Err(before) => -(before.duration().as_millis() as isize),
+        }
+    }
+
+    /// How long after the start of navigation the given graph timestamp occurred. Returns `None`
+    /// for timestamps from before navigation started.
+    pub fn duration_since_navigation(&self, timestamp: isize) -> Option<Duration> {
+        if timestamp >= 0 {
+            Some(Duration::from_millis(timestamp as u64))
+        } else {
+            None
+        }
+    }
+
+    /// The wall-clock time at which the given node was recorded.
+    pub fn node_time(&self, node: &Node) -> SystemTime {
+        self.to_system_time(node.node_timestamp)
+    }
+
+    /// The wall-clock time at which the given edge was recorded, if it has a timestamp.
+    pub fn edge_time(&self, edge: &Edge) -> Option<SystemTime> {
+        edge.edge_timestamp.map(|timestamp| self.to_system_time(timestamp))
+    }
+
+    /// Converts a graph timestamp into a UTC date and time.
+    #[cfg(feature = "chrono")]
+    pub fn to_datetime(&self, timestamp: isize) -> chrono::DateTime<chrono::Utc> {
+        self.to_system_time(timestamp).into()
+    }
+
+    /// Converts a date and time into the equivalent graph timestamp.
+    #[cfg(feature = "chrono")]
+    pub fn from_datetime<Tz: chrono::TimeZone>(&self, datetime: &chrono::DateTime<Tz>) -> isize {
+        let start = datetime.timezone().timestamp_millis_opt(self.desc.time.start as i64).unwrap();
+        (datetime.clone() - start).num_milliseconds() as isize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::PageGraphBuilder;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_timestamp_round_trip() {
+        let mut builder = PageGraphBuilder::new("https://example.com/").start_time(1_600_000_000_000);
+        builder.advance_time(250);
+        let graph = builder.build();
+
+        let start = UNIX_EPOCH + Duration::from_millis(1_600_000_000_000);
+        assert_eq!(graph.desc.time.start_time(), start);
+        assert_eq!(graph.desc.time.duration(), Duration::from_millis(253));
+
+        assert_eq!(graph.to_system_time(812), start + Duration::from_millis(812));
+        assert_eq!(graph.to_system_time(-5), start - Duration::from_millis(5));
+        for timestamp in [-5, 0, 812] {
+            assert_eq!(graph.from_system_time(graph.to_system_time(timestamp)), timestamp);
+        }
+
+        assert_eq!(graph.duration_since_navigation(812), Some(Duration::from_millis(812)));
+        assert_eq!(graph.duration_since_navigation(-1), None);
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_datetime_round_trip() {
+        let graph = PageGraphBuilder::new("https://example.com/").start_time(1_600_000_000_000).build();
+
+        assert_eq!(graph.to_datetime(812).timestamp_millis(), 1_600_000_000_812);
+        assert_eq!(graph.from_datetime(&graph.to_datetime(812)), 812);
+    }
+}
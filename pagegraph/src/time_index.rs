@@ -0,0 +1,176 @@
+//! Timestamp-sorted indexes over graph nodes and edges, for efficient temporal queries.
+//!
+//! The indexes are built when a graph is read from GraphML, or otherwise on first use, so that
+//! analyses can walk the graph in chronological order without collecting and sorting its nodes
+//! or edges themselves.
+
+use std::collections::HashMap;
+use std::ops::{Bound, RangeBounds};
+
+use crate::graph::{Edge, EdgeId, Node, NodeId, PageGraph};
+
+type SortedEdges = Vec<(isize, EdgeId)>;
+
+/// Every timestamped edge in the graph sorted by timestamp, both globally and per node, along
+/// with every node sorted by timestamp.
+#[derive(Debug, Default)]
+pub(crate) struct TimeIndex {
+    edges: SortedEdges,
+    /// Edges without timestamps, sorted by id.
+    untimed_edges: Vec<EdgeId>,
+    nodes: Vec<(isize, NodeId)>,
+    incoming: HashMap<NodeId, SortedEdges>,
+    outgoing: HashMap<NodeId, SortedEdges>,
+}
+
+impl TimeIndex {
+    fn build(graph: &PageGraph) -> Self {
+        let mut index = Self::default();
+        graph.edges.values().for_each(|edge| {
+            if let Some(timestamp) = edge.edge_timestamp {
+                index.edges.push((timestamp, edge.id));
+                index.incoming.entry(edge.target).or_default().push((timestamp, edge.id));
+                index.outgoing.entry(edge.source).or_default().push((timestamp, edge.id));
+            } else {
+                index.untimed_edges.push(edge.id);
+            }
+        });
+        index.nodes = graph.nodes.values().map(|node| (node.node_timestamp, node.id)).collect();
+        index.nodes.sort_unstable();
+        index.untimed_edges.sort_unstable();
+        index.edges.sort_unstable();
+        index.incoming.values_mut().for_each(|edges| edges.sort_unstable());
+        index.outgoing.values_mut().for_each(|edges| edges.sort_unstable());
+        index
+    }
+}
+
+/// Returns the subslice of `edges` with timestamps within `range`.
+fn slice_in_range<R: RangeBounds<isize>>(edges: &[(isize, EdgeId)], range: R) -> &[(isize, EdgeId)] {
+    let start = match range.start_bound() {
+        Bound::Included(start) => edges.partition_point(|(t, _)| t < start),
+        Bound::Excluded(start) => edges.partition_point(|(t, _)| t <= start),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => edges.partition_point(|(t, _)| t <= end),
+        Bound::Excluded(end) => edges.partition_point(|(t, _)| t < end),
+        Bound::Unbounded => edges.len(),
+    };
+    &edges[start..end.max(start)]
+}
+
+impl Edge {
+    /// The key edges are ordered by in [`edges_chronological`](PageGraph::edges_chronological):
+    /// the timestamp, with edges without one first, and then the id.
+    pub fn timestamp_key(&self) -> (Option<isize>, EdgeId) {
+        (self.edge_timestamp, self.id)
+    }
+}
+
+impl PageGraph {
+    pub(crate) fn time_index(&self) -> &TimeIndex {
+        self.time_index.get_or_init(|| TimeIndex::build(self))
+    }
+
+    fn sorted_edges<'a>(&'a self, edges: &'a [(isize, EdgeId)]) -> impl Iterator<Item=&'a Edge> {
+        edges.iter().map(move |(_, edge_id)| self.edges.get(edge_id).unwrap())
+    }
+
+    /// Returns every node in the graph, in chronological order. Nodes with the same timestamp are
+    /// ordered by id.
+    pub fn nodes_chronological(&self) -> impl Iterator<Item=&Node> {
+        self.time_index().nodes.iter().map(move |(_, node_id)| self.nodes.get(node_id).unwrap())
+    }
+
+    /// Returns every edge in the graph, in chronological order. Edges with the same timestamp are
+    /// ordered by id. Edges without timestamps come first, ordered by id, matching how optional
+    /// timestamps compare.
+    pub fn edges_chronological(&self) -> impl Iterator<Item=&Edge> {
+        let index = self.time_index();
+        index.untimed_edges.iter()
+            .map(move |edge_id| self.edges.get(edge_id).unwrap())
+            .chain(self.sorted_edges(&index.edges))
+    }
+
+    /// Returns every edge with a timestamp within the given range, in chronological order.
+    /// Edges without timestamps are never included.
+    ///
+    /// ```
+    /// # let graph = pagegraph::builder::PageGraphBuilder::new("https://example.com/").build();
+    /// // All edges recorded during the first second of the page load
+    /// let early_edges = graph.edges_in_range(0..1000).collect::<Vec<_>>();
+    /// # assert!(early_edges.iter().all(|edge| edge.edge_timestamp < Some(1000)));
+    /// ```
+    pub fn edges_in_range<R: RangeBounds<isize>>(&self, range: R) -> impl Iterator<Item=&Edge> {
+        self.sorted_edges(slice_in_range(&self.time_index().edges, range))
+    }
+
+    /// Returns every incoming edge of the given node with a timestamp within the given range, in
+    /// chronological order.
+    pub fn incoming_edges_in_range<R: RangeBounds<isize>>(&self, node: &Node, range: R) -> impl Iterator<Item=&Edge> {
+        let edges = self.time_index().incoming.get(&node.id).map(|edges| slice_in_range(edges, range)).unwrap_or_default();
+        self.sorted_edges(edges)
+    }
+
+    /// Returns every outgoing edge of the given node with a timestamp within the given range, in
+    /// chronological order.
+    pub fn outgoing_edges_in_range<R: RangeBounds<isize>>(&self, node: &Node, range: R) -> impl Iterator<Item=&Edge> {
+        let edges = self.time_index().outgoing.get(&node.id).map(|edges| slice_in_range(edges, range)).unwrap_or_default();
+        self.sorted_edges(edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::PageGraphBuilder;
+    use crate::types::{RequestType, ResourceType};
+
+    #[test]
+    fn test_edges_in_range() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        builder.advance_time(100);
+        let img = builder.add_element(parser, root, "img");  // node at 103, edges at 104 and 105
+        builder.advance_time(100);
+        let request = builder.add_request(img, "https://example.com/a.png", RequestType::Image);  // node at 206, edge at 207
+        builder.complete_request(request, ResourceType::Image, None);  // edge at 208
+        let graph = builder.build();
+
+        let timestamps = |edges: Vec<&crate::graph::Edge>| edges.iter().map(|edge| edge.edge_timestamp.unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(timestamps(graph.edges_in_range(..).collect()), vec![2, 104, 105, 207, 208]);
+        assert_eq!(timestamps(graph.edges_in_range(104..207).collect()), vec![104, 105]);
+        assert_eq!(timestamps(graph.edges_in_range(104..=207).collect()), vec![104, 105, 207]);
+        assert_eq!(timestamps(graph.edges_in_range(150..).collect()), vec![207, 208]);
+        assert_eq!(timestamps(graph.edges_in_range(300..).collect()), Vec::<isize>::new());
+
+        let img = graph.nodes.get(&img).unwrap();
+        assert_eq!(timestamps(graph.incoming_edges_in_range(img, 0..1000).collect()), vec![104, 105, 208]);
+        assert_eq!(timestamps(graph.outgoing_edges_in_range(img, 0..1000).collect()), vec![207]);
+        assert_eq!(timestamps(graph.outgoing_edges_in_range(img, 0..200).collect()), Vec::<isize>::new());
+    }
+
+    #[test]
+    fn test_chronological() {
+        let mut builder = PageGraphBuilder::new("https://example.com/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let img = builder.add_element(parser, root, "img");
+        let untimed = builder.add_edge(parser, img, crate::types::EdgeType::Structure {});
+        let mut graph = builder.build();
+        graph.edges.get_mut(&untimed).unwrap().edge_timestamp = None;
+
+        let nodes = graph.nodes_chronological().map(|node| node.id).collect::<Vec<_>>();
+        assert_eq!(nodes, vec![parser, root, img]);
+        let timestamps = graph.edges_chronological().map(|edge| edge.edge_timestamp).collect::<Vec<_>>();
+        assert_eq!(timestamps[0], None);
+        assert!(timestamps[1..].windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(timestamps.len(), graph.edges.len());
+
+        let keys = graph.edges_chronological().map(|edge| edge.timestamp_key()).collect::<Vec<_>>();
+        assert_eq!(keys[0], (None, untimed));
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::graph::FrameId;
 
 /// HtmlElementId represents the unsigned integer identifier that Blink uses
@@ -19,9 +21,130 @@ pub type HtmlElementId = usize;
 /// attributes like "onclick" defined in HTML elements, etc.).
 pub type ScriptId = usize;
 
-/// A string encoding a URL. May either be a full URL (protocol, host, port.
+/// A URL recorded in the graph. May either be a full URL (protocol, host, port.
 /// path, etc.) or a relative one, depending on the context in the graph.
-pub type Url = String;
+///
+/// URLs are parsed when the graph is read. The recorded string is kept as well,
+/// since relative or malformed URLs can't be parsed on their own, and since
+/// parsing normalizes the URL. Comparisons, hashing, and serialization all use
+/// the recorded string.
+#[derive(Clone, Debug)]
+pub struct Url {
+    raw: String,
+    parsed: Option<url::Url>,
+}
+
+impl Url {
+    /// The URL as recorded.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The URL parsed as an absolute URL, or `None` if it is relative or could
+    /// not be parsed.
+    pub fn parsed(&self) -> Option<&url::Url> {
+        self.parsed.as_ref()
+    }
+}
+
+impl From<String> for Url {
+    fn from(raw: String) -> Self {
+        let parsed = url::Url::parse(&raw).ok();
+        Self { raw, parsed }
+    }
+}
+
+impl From<&str> for Url {
+    fn from(raw: &str) -> Self {
+        Self::from(raw.to_string())
+    }
+}
+
+impl std::ops::Deref for Url {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl AsRef<str> for Url {
+    fn as_ref(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl std::fmt::Display for Url {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl PartialEq for Url {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for Url {}
+
+impl std::hash::Hash for Url {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.raw.hash(state)
+    }
+}
+
+impl PartialOrd for Url {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Url {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.raw.cmp(&other.raw)
+    }
+}
+
+impl PartialEq<String> for Url {
+    fn eq(&self, other: &String) -> bool {
+        self.raw == *other
+    }
+}
+
+impl PartialEq<str> for Url {
+    fn eq(&self, other: &str) -> bool {
+        self.raw == other
+    }
+}
+
+impl PartialEq<&str> for Url {
+    fn eq(&self, other: &&str) -> bool {
+        self.raw == *other
+    }
+}
+
+impl serde::Serialize for Url {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+/// URLs serialize as the string PageGraph recorded.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Url {
+    fn schema_name() -> String {
+        "Url".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(generator: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(generator)
+    }
+}
 
 /// A string encoding the name of an HTML tag (e.g., `"a"` for an anchor tag,
 /// or `"img"` for an image tag).
@@ -53,7 +176,7 @@ pub enum NodeType {
     /// URL succeeded, and if so, what was returned).
     Resource {
         /// The URL represented by this node.
-        url: String
+        url: Url
     },
     /// WebApi nodes represent [Web APIs](https://developer.mozilla.org/en-US/docs/Web/API)
     /// provided by the browser that JavaScript code can call. There will be at
@@ -255,7 +378,7 @@ pub enum NodeType {
         url: Option<Url>,
         /// The type of script being executed, either a "module" script
         /// or a "classic" script.
-        script_type: String,
+        script_type: ScriptType,
         /// The V8 identifier for this JavaScript code unit.
         script_id: ScriptId,
         /// The text of the script as passed to the v8 compiler.
@@ -287,10 +410,398 @@ pub enum NodeType {
     FingerprintingShield {},
     FingerprintingV2Shield {},
     Extensions {},
+    /// The root of a shadow tree attached to an HTML element. Nodes in the shadow tree are
+    /// inserted beneath it using its `node_id`, like any other parent node.
+    ShadowRoot {
+        is_deleted: bool,
+        node_id: HtmlElementId,
+    },
+    /// A web worker, shared worker, or service worker, which executes scripts off of the main
+    /// thread. Scripts run by the worker are pointed to by outgoing
+    /// [`Execute`](EdgeType::Execute) edges.
+    Worker {
+        worker_type: WorkerType,
+        /// The URL of the worker's top-level script.
+        url: Option<Url>,
+    },
+    /// Fallback for node types this crate does not recognize, typically emitted by PageGraph
+    /// builds newer than this crate. Such nodes are kept in the graph so that they remain
+    /// queryable, but their attributes are left uninterpreted.
+    Unknown {
+        /// The `node type` string recorded for this node.
+        kind: String,
+        /// Every other attribute recorded for this node, keyed by its attribute name.
+        attributes: HashMap<String, String>,
+    },
+}
+
+/// The variant of a [`NodeType`], without any associated data.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum NodeKind {
+    Resource,
+    WebApi,
+    JsBuiltin,
+    HtmlElement,
+    TextNode,
+    DomRoot,
+    FrameOwner,
+    LocalStorage,
+    SessionStorage,
+    CookieJar,
+    Script,
+    Parser,
+    Binding,
+    BindingEvent,
+    RemoteFrame,
+    AdFilter,
+    TrackerFilter,
+    FingerprintingFilter,
+    Storage,
+    BraveShields,
+    AdsShield,
+    TrackersShield,
+    JavascriptShield,
+    FingerprintingShield,
+    FingerprintingV2Shield,
+    Extensions,
+    ShadowRoot,
+    Worker,
+    Unknown,
+}
+
+impl NodeKind {
+    /// Every node kind, in declaration order.
+    pub const ALL: [NodeKind; 29] = [
+        Self::Resource, Self::WebApi, Self::JsBuiltin, Self::HtmlElement, Self::TextNode, Self::DomRoot,
+        Self::FrameOwner, Self::LocalStorage, Self::SessionStorage, Self::CookieJar, Self::Script, Self::Parser,
+        Self::Binding, Self::BindingEvent, Self::RemoteFrame, Self::AdFilter, Self::TrackerFilter,
+        Self::FingerprintingFilter, Self::Storage, Self::BraveShields, Self::AdsShield, Self::TrackersShield,
+        Self::JavascriptShield, Self::FingerprintingShield, Self::FingerprintingV2Shield, Self::Extensions,
+        Self::ShadowRoot, Self::Worker, Self::Unknown,
+    ];
+}
+
+impl NodeType {
+    /// Returns the variant of this node type, without any associated data.
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            Self::Resource { .. } => NodeKind::Resource,
+            Self::WebApi { .. } => NodeKind::WebApi,
+            Self::JsBuiltin { .. } => NodeKind::JsBuiltin,
+            Self::HtmlElement { .. } => NodeKind::HtmlElement,
+            Self::TextNode { .. } => NodeKind::TextNode,
+            Self::DomRoot { .. } => NodeKind::DomRoot,
+            Self::FrameOwner { .. } => NodeKind::FrameOwner,
+            Self::LocalStorage {} => NodeKind::LocalStorage,
+            Self::SessionStorage {} => NodeKind::SessionStorage,
+            Self::CookieJar {} => NodeKind::CookieJar,
+            Self::Script { .. } => NodeKind::Script,
+            Self::Parser {} => NodeKind::Parser,
+            Self::Binding { .. } => NodeKind::Binding,
+            Self::BindingEvent { .. } => NodeKind::BindingEvent,
+            Self::RemoteFrame { .. } => NodeKind::RemoteFrame,
+            Self::AdFilter { .. } => NodeKind::AdFilter,
+            Self::TrackerFilter => NodeKind::TrackerFilter,
+            Self::FingerprintingFilter => NodeKind::FingerprintingFilter,
+            Self::Storage {} => NodeKind::Storage,
+            Self::BraveShields {} => NodeKind::BraveShields,
+            Self::AdsShield {} => NodeKind::AdsShield,
+            Self::TrackersShield {} => NodeKind::TrackersShield,
+            Self::JavascriptShield {} => NodeKind::JavascriptShield,
+            Self::FingerprintingShield {} => NodeKind::FingerprintingShield,
+            Self::FingerprintingV2Shield {} => NodeKind::FingerprintingV2Shield,
+            Self::Extensions {} => NodeKind::Extensions,
+            Self::ShadowRoot { .. } => NodeKind::ShadowRoot,
+            Self::Worker { .. } => NodeKind::Worker,
+            Self::Unknown { .. } => NodeKind::Unknown,
+        }
+    }
+
+    /// The URL associated with this node, for [`Resource`](NodeType::Resource) nodes, remotely
+    /// fetched [`Script`](NodeType::Script) nodes, [`DomRoot`](NodeType::DomRoot) nodes with
+    /// a document URL, and [`Worker`](NodeType::Worker) nodes.
+    pub fn url(&self) -> Option<&Url> {
+        match self {
+            Self::Resource { url } => Some(url),
+            Self::Script { url, .. } | Self::DomRoot { url, .. } | Self::Worker { url, .. } => url.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The URL associated with this node, parsed as an absolute URL. Returns `None` if the node
+    /// has no URL, or if the recorded URL is relative or otherwise could not be parsed.
+    pub fn parsed_url(&self) -> Option<&url::Url> {
+        self.url().and_then(Url::parsed)
+    }
+
+    /// The tag name of this node, for any node representing an element in the DOM.
+    pub fn tag_name(&self) -> Option<&str> {
+        match self {
+            Self::HtmlElement { tag_name, .. } |
+            Self::DomRoot { tag_name, .. } |
+            Self::FrameOwner { tag_name, .. } => Some(tag_name),
+            _ => None,
+        }
+    }
+
+    /// The Blink identifier of this node, for any node representing an element in the DOM,
+    /// including text nodes.
+    pub fn html_node_id(&self) -> Option<HtmlElementId> {
+        match self {
+            Self::HtmlElement { node_id, .. } |
+            Self::TextNode { node_id, .. } |
+            Self::DomRoot { node_id, .. } |
+            Self::FrameOwner { node_id, .. } |
+            Self::ShadowRoot { node_id, .. } => Some(*node_id),
+            _ => None,
+        }
+    }
+
+    /// Whether or not this node was deleted by the time the graph was serialized, for any node
+    /// representing an element in the DOM, including text nodes.
+    pub fn is_deleted(&self) -> Option<bool> {
+        match self {
+            Self::HtmlElement { is_deleted, .. } |
+            Self::TextNode { is_deleted, .. } |
+            Self::DomRoot { is_deleted, .. } |
+            Self::FrameOwner { is_deleted, .. } |
+            Self::ShadowRoot { is_deleted, .. } => Some(*is_deleted),
+            _ => None,
+        }
+    }
+
+    /// The V8 identifier of this node, for [`Script`](NodeType::Script) nodes.
+    pub fn script_id(&self) -> Option<ScriptId> {
+        match self {
+            Self::Script { script_id, .. } => Some(*script_id),
+            _ => None,
+        }
+    }
+
+    /// The method name of this node, for [`WebApi`](NodeType::WebApi) and
+    /// [`JsBuiltin`](NodeType::JsBuiltin) nodes.
+    pub fn method(&self) -> Option<&str> {
+        match self {
+            Self::WebApi { method } | Self::JsBuiltin { method } => Some(method),
+            _ => None,
+        }
+    }
+
+    /// The frame identifier of this node, for [`RemoteFrame`](NodeType::RemoteFrame) nodes.
+    pub fn frame_id(&self) -> Option<FrameId> {
+        match self {
+            Self::RemoteFrame { frame_id } => Some(*frame_id),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of script represented by a [`Script`](NodeType::Script) node.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptType {
+    Classic,
+    Module,
+    /// Any other script type string recorded by PageGraph.
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl From<&str> for ScriptType {
+    fn from(v: &str) -> Self {
+        match v {
+            "classic" => Self::Classic,
+            "module" => Self::Module,
+            o => Self::Other(o.to_string()),
+        }
+    }
+}
+
+impl ScriptType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Classic => "classic",
+            Self::Module => "module",
+            Self::Other(o) => o,
+        }
+    }
+}
+
+/// The kind of worker represented by a [`Worker`](NodeType::Worker) node.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum WorkerType {
+    Dedicated,
+    Shared,
+    Service,
+}
+
+impl WorkerType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dedicated => "dedicated worker",
+            Self::Shared => "shared worker",
+            Self::Service => "service worker",
+        }
+    }
+}
+
+/// The type of resource that was loaded, as recorded on a
+/// [`RequestComplete`](EdgeType::RequestComplete) edge.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceType {
+    Image,
+    Css,
+    Script,
+    Font,
+    Raw,
+    Svg,
+    Xsl,
+    Prefetch,
+    #[serde(rename = "text track")]
+    TextTrack,
+    Audio,
+    Video,
+    Manifest,
+    Mock,
+    /// Any other resource type string recorded by PageGraph.
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// Resource types serialize as the string PageGraph recorded, which may be any string.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for ResourceType {
+    fn schema_name() -> String {
+        "ResourceType".to_string()
+    }
+
+    fn json_schema(generator: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(generator)
+    }
+}
+
+impl From<&str> for ResourceType {
+    fn from(v: &str) -> Self {
+        match v {
+            "image" => Self::Image,
+            "css" => Self::Css,
+            "script" => Self::Script,
+            "font" => Self::Font,
+            "raw" => Self::Raw,
+            "svg" => Self::Svg,
+            "xsl" => Self::Xsl,
+            "prefetch" => Self::Prefetch,
+            "text track" => Self::TextTrack,
+            "audio" => Self::Audio,
+            "video" => Self::Video,
+            "manifest" => Self::Manifest,
+            "mock" => Self::Mock,
+            o => Self::Other(o.to_string()),
+        }
+    }
+}
+
+impl ResourceType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Image => "image",
+            Self::Css => "css",
+            Self::Script => "script",
+            Self::Font => "font",
+            Self::Raw => "raw",
+            Self::Svg => "svg",
+            Self::Xsl => "xsl",
+            Self::Prefetch => "prefetch",
+            Self::TextTrack => "text track",
+            Self::Audio => "audio",
+            Self::Video => "video",
+            Self::Manifest => "manifest",
+            Self::Mock => "mock",
+            Self::Other(o) => o,
+        }
+    }
+}
+
+/// Response metadata parsed from the raw `headers` string recorded on
+/// [`RequestComplete`](EdgeType::RequestComplete) and [`RequestError`](EdgeType::RequestError)
+/// edges, when the graph is read.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ResponseMetadata {
+    /// The HTTP status code, from an `HTTP/1.1 200 OK` style status line or a `:status`
+    /// pseudo-header.
+    pub status_code: Option<u16>,
+    /// The MIME type from the `Content-Type` header, lowercased and without parameters.
+    pub mime_type: Option<String>,
+    /// Every header, in the order recorded, with lowercased names.
+    pub headers: Vec<(String, String)>,
+    /// The headers string as recorded.
+    #[serde(skip)]
+    pub raw: String,
+}
+
+impl From<&str> for ResponseMetadata {
+    fn from(raw: &str) -> Self {
+        let mut metadata = Self { raw: raw.to_string(), ..Self::default() };
+        raw.lines().map(str::trim).filter(|line| !line.is_empty()).for_each(|line| {
+            if line.starts_with("HTTP/") {
+                metadata.status_code = line.split_whitespace().nth(1).and_then(|code| code.parse().ok());
+                return;
+            }
+            // Pseudo-headers like `:status` start with a colon of their own.
+            let split_at = line.char_indices().skip(1).find(|(_, c)| *c == ':').map(|(i, _)| i);
+            if let Some(split_at) = split_at {
+                let name = line[..split_at].trim().to_ascii_lowercase();
+                let value = line[split_at + 1..].trim().to_string();
+                match &name[..] {
+                    ":status" => metadata.status_code = value.parse().ok(),
+                    "content-type" => metadata.mime_type = value.split(';').next().map(|mime| mime.trim().to_ascii_lowercase()).filter(|mime| !mime.is_empty()),
+                    _ => (),
+                }
+                metadata.headers.push((name, value));
+            }
+        });
+        metadata
+    }
+}
+
+impl ResponseMetadata {
+    /// Serializes the headers string as recorded, for the `headers` of edges.
+    fn serialize_raw<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+
+    /// Returns the value of the first header with the given name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| &value[..])
+    }
+
+    /// Returns whether the response's MIME type matches the given pattern, ignoring case. The
+    /// pattern is either a full MIME type, like `image/png`, or a type with a wildcard subtype,
+    /// like `image/*`.
+    pub fn mime_type_matches(&self, pattern: &str) -> bool {
+        let mime_type = match &self.mime_type {
+            Some(mime_type) => mime_type,
+            None => return false,
+        };
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_suffix("/*") {
+            Some(top_level) => mime_type.split('/').next() == Some(top_level),
+            None => *mime_type == pattern,
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
 #[derive(serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum RequestType {
     Image,
     Script,
@@ -342,7 +853,11 @@ pub enum EdgeType {
     ///
     /// The actee node will be the `HtmlElement`](NodeType::HtmlElement) node
     /// being removed from the document.
-    RemoveNode {},
+    RemoveNode {
+        /// The character offset in the JavaScript text where the node was
+        /// removed, if recorded.
+        script_position: Option<usize>,
+    },
     /// `DeleteNode` edges encode a HTML element being deleted by JavaScript
     /// code. Note that this is a distinct action from merely removing an
     /// HTML element from a document (which is encoded with a
@@ -376,6 +891,9 @@ pub enum EdgeType {
         /// that the actee node was the first child of the parent node at
         /// insertion time,
         before: Option<HtmlElementId>,
+        /// The character offset in the JavaScript text where the node was
+        /// inserted, if it was inserted by a script and the offset was recorded.
+        script_position: Option<usize>,
     },
     /// `CreateNode` edges encode that an HTML element that was created during
     /// the execution of the page.
@@ -427,20 +945,25 @@ pub enum EdgeType {
         script_position: usize,
     },
     RequestComplete {
-        resource_type: String,
+        resource_type: ResourceType,
         status: String,
         value: Option<String>,
         response_hash: Option<String>,
         request_id: usize,
-        headers: String,
-        size: String,
+        #[serde(serialize_with = "ResponseMetadata::serialize_raw")]
+        headers: ResponseMetadata,
+        /// The size of the response body in bytes. Some requests, like streamed fetches, video,
+        /// or audio cannot be properly sized, in which case this will be `None`.
+        size: Option<usize>,
     },
     RequestError {
         status: String,
         request_id: usize,
         value: Option<String>,
-        headers: String,
-        size: String,
+        #[serde(serialize_with = "ResponseMetadata::serialize_raw")]
+        headers: ResponseMetadata,
+        /// The size of the response body in bytes, if it could be determined.
+        size: Option<usize>,
     },
     RequestStart {
         request_type: RequestType,
@@ -500,6 +1023,9 @@ pub enum EdgeType {
         /// If the attribute being set is part of the the element's
         /// CSS style definition.
         is_style: bool,
+        /// The character offset in the JavaScript text where the attribute
+        /// was set, if recorded.
+        script_position: Option<usize>,
     },
     /// `DeleteAttribute` edges encode JavaScript code deleting an attribute
     /// from a HTML element.
@@ -516,6 +1042,9 @@ pub enum EdgeType {
         /// If the attribute being deleted is part of the the element's
         /// CSS style definition.
         is_style: bool,
+        /// The character offset in the JavaScript text where the attribute
+        /// was deleted, if recorded.
+        script_position: Option<usize>,
     },
     Binding {},
     BindingEvent {
@@ -526,4 +1055,296 @@ pub enum EdgeType {
     Shield {},
     ResourceBlock {},
     StorageBucket {},
+    /// `AttachShadow` edges encode an HTML element having a shadow root attached to it, e.g. by
+    /// JavaScript code calling `Element.attachShadow()`.
+    ///
+    /// The actee node will be a [`ShadowRoot`](NodeType::ShadowRoot) node.
+    AttachShadow {
+        /// The identifier of the DOM element hosting the shadow root.
+        host: HtmlElementId,
+        /// The shadow root's encapsulation mode, `open` or `closed`, if recorded.
+        mode: Option<String>,
+    },
+    /// Fallback for edge types this crate does not recognize, typically emitted by PageGraph
+    /// builds newer than this crate. Such edges are kept in the graph so that they remain
+    /// queryable, but their attributes are left uninterpreted.
+    Unknown {
+        /// The `edge type` string recorded for this edge.
+        kind: String,
+        /// Every other attribute recorded for this edge, keyed by its attribute name.
+        attributes: HashMap<String, String>,
+    },
+}
+
+/// The variant of an [`EdgeType`], without any associated data.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum EdgeKind {
+    CrossDom,
+    TextChange,
+    RemoveNode,
+    DeleteNode,
+    InsertNode,
+    CreateNode,
+    JsResult,
+    JsCall,
+    RequestComplete,
+    RequestError,
+    RequestStart,
+    RequestResponse,
+    AddEventListener,
+    RemoveEventListener,
+    EventListener,
+    StorageSet,
+    StorageReadResult,
+    DeleteStorage,
+    ReadStorageCall,
+    ClearStorage,
+    ExecuteFromAttribute,
+    Execute,
+    SetAttribute,
+    DeleteAttribute,
+    Binding,
+    BindingEvent,
+    Filter,
+    Structure,
+    Shield,
+    ResourceBlock,
+    StorageBucket,
+    AttachShadow,
+    Unknown,
+}
+
+impl EdgeKind {
+    /// Every edge kind, in declaration order.
+    pub const ALL: [EdgeKind; 33] = [
+        Self::CrossDom, Self::TextChange, Self::RemoveNode, Self::DeleteNode, Self::InsertNode, Self::CreateNode,
+        Self::JsResult, Self::JsCall, Self::RequestComplete, Self::RequestError, Self::RequestStart,
+        Self::RequestResponse, Self::AddEventListener, Self::RemoveEventListener, Self::EventListener,
+        Self::StorageSet, Self::StorageReadResult, Self::DeleteStorage, Self::ReadStorageCall, Self::ClearStorage,
+        Self::ExecuteFromAttribute, Self::Execute, Self::SetAttribute, Self::DeleteAttribute, Self::Binding,
+        Self::BindingEvent, Self::Filter, Self::Structure, Self::Shield, Self::ResourceBlock, Self::StorageBucket,
+        Self::AttachShadow, Self::Unknown,
+    ];
+}
+
+impl EdgeType {
+    /// Returns the variant of this edge type, without any associated data.
+    pub fn kind(&self) -> EdgeKind {
+        match self {
+            Self::CrossDom {} => EdgeKind::CrossDom,
+            Self::TextChange {} => EdgeKind::TextChange,
+            Self::RemoveNode { .. } => EdgeKind::RemoveNode,
+            Self::DeleteNode {} => EdgeKind::DeleteNode,
+            Self::InsertNode { .. } => EdgeKind::InsertNode,
+            Self::CreateNode {} => EdgeKind::CreateNode,
+            Self::JsResult { .. } => EdgeKind::JsResult,
+            Self::JsCall { .. } => EdgeKind::JsCall,
+            Self::RequestComplete { .. } => EdgeKind::RequestComplete,
+            Self::RequestError { .. } => EdgeKind::RequestError,
+            Self::RequestStart { .. } => EdgeKind::RequestStart,
+            Self::RequestResponse => EdgeKind::RequestResponse,
+            Self::AddEventListener { .. } => EdgeKind::AddEventListener,
+            Self::RemoveEventListener { .. } => EdgeKind::RemoveEventListener,
+            Self::EventListener { .. } => EdgeKind::EventListener,
+            Self::StorageSet { .. } => EdgeKind::StorageSet,
+            Self::StorageReadResult { .. } => EdgeKind::StorageReadResult,
+            Self::DeleteStorage { .. } => EdgeKind::DeleteStorage,
+            Self::ReadStorageCall { .. } => EdgeKind::ReadStorageCall,
+            Self::ClearStorage { .. } => EdgeKind::ClearStorage,
+            Self::ExecuteFromAttribute { .. } => EdgeKind::ExecuteFromAttribute,
+            Self::Execute {} => EdgeKind::Execute,
+            Self::SetAttribute { .. } => EdgeKind::SetAttribute,
+            Self::DeleteAttribute { .. } => EdgeKind::DeleteAttribute,
+            Self::Binding {} => EdgeKind::Binding,
+            Self::BindingEvent { .. } => EdgeKind::BindingEvent,
+            Self::Filter {} => EdgeKind::Filter,
+            Self::Structure {} => EdgeKind::Structure,
+            Self::Shield {} => EdgeKind::Shield,
+            Self::ResourceBlock {} => EdgeKind::ResourceBlock,
+            Self::StorageBucket {} => EdgeKind::StorageBucket,
+            Self::AttachShadow { .. } => EdgeKind::AttachShadow,
+            Self::Unknown { .. } => EdgeKind::Unknown,
+        }
+    }
+
+    /// The Blink request identifier of this edge, for [`RequestStart`](EdgeType::RequestStart),
+    /// [`RequestComplete`](EdgeType::RequestComplete), and [`RequestError`](EdgeType::RequestError)
+    /// edges.
+    pub fn request_id(&self) -> Option<usize> {
+        match self {
+            Self::RequestStart { request_id, .. } |
+            Self::RequestComplete { request_id, .. } |
+            Self::RequestError { request_id, .. } => Some(*request_id),
+            _ => None,
+        }
+    }
+
+    /// The parsed response headers, for [`RequestComplete`](EdgeType::RequestComplete) and
+    /// [`RequestError`](EdgeType::RequestError) edges.
+    pub fn response_metadata(&self) -> Option<&ResponseMetadata> {
+        match self {
+            Self::RequestComplete { headers, .. } |
+            Self::RequestError { headers, .. } => Some(headers),
+            _ => None,
+        }
+    }
+
+    /// The size of the response body in bytes, for [`RequestComplete`](EdgeType::RequestComplete)
+    /// and [`RequestError`](EdgeType::RequestError) edges whose response could be sized.
+    pub fn response_size(&self) -> Option<usize> {
+        match self {
+            Self::RequestComplete { size, .. } |
+            Self::RequestError { size, .. } => *size,
+            _ => None,
+        }
+    }
+
+    /// The `key` recorded with this edge, i.e. the storage key for storage edges, the attribute
+    /// name for attribute edges, or the event type for event listener edges.
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            Self::StorageSet { key, .. } |
+            Self::StorageReadResult { key, .. } |
+            Self::DeleteStorage { key } |
+            Self::ReadStorageCall { key } |
+            Self::ClearStorage { key } |
+            Self::SetAttribute { key, .. } |
+            Self::DeleteAttribute { key, .. } |
+            Self::AddEventListener { key, .. } |
+            Self::RemoveEventListener { key, .. } |
+            Self::EventListener { key, .. } => Some(key),
+            Self::ExecuteFromAttribute { attr_name } => Some(attr_name),
+            _ => None,
+        }
+    }
+
+    /// The value carried by this edge, if any, e.g. a JavaScript return value, a stored value, or
+    /// an assigned attribute value.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            Self::JsResult { value } |
+            Self::RequestComplete { value, .. } |
+            Self::RequestError { value, .. } |
+            Self::StorageSet { value, .. } |
+            Self::StorageReadResult { value, .. } |
+            Self::SetAttribute { value, .. } => value.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The V8 identifier of the script responsible for this edge, for event listener edges.
+    pub fn script_id(&self) -> Option<ScriptId> {
+        match self {
+            Self::AddEventListener { script_id, .. } |
+            Self::RemoveEventListener { script_id, .. } => Some(*script_id),
+            _ => None,
+        }
+    }
+
+    /// The character offset in the acting script's source, for [`JsCall`](EdgeType::JsCall) and
+    /// [`BindingEvent`](EdgeType::BindingEvent) edges, and for DOM mutation edges where it was
+    /// recorded.
+    pub fn script_position(&self) -> Option<usize> {
+        match self {
+            Self::JsCall { script_position, .. } |
+            Self::BindingEvent { script_position } => Some(*script_position),
+            Self::InsertNode { script_position, .. } |
+            Self::RemoveNode { script_position } |
+            Self::SetAttribute { script_position, .. } |
+            Self::DeleteAttribute { script_position, .. } => *script_position,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_type_accessors() {
+        let script = NodeType::Script {
+            url: Some("https://example.com/lib.js".into()),
+            script_type: ScriptType::Classic,
+            script_id: 7,
+            source: String::new(),
+        };
+        assert_eq!(script.kind(), NodeKind::Script);
+        assert_eq!(script.url().map(|url| url.as_str()), Some("https://example.com/lib.js"));
+        assert_eq!(script.parsed_url().map(|url| url.host_str().unwrap().to_string()).as_deref(), Some("example.com"));
+        assert_eq!(script.script_id(), Some(7));
+        assert_eq!(script.tag_name(), None);
+
+        let element = NodeType::HtmlElement { tag_name: "img".to_string(), is_deleted: true, node_id: 3 };
+        assert_eq!(element.kind(), NodeKind::HtmlElement);
+        assert_eq!(element.tag_name(), Some("img"));
+        assert_eq!(element.html_node_id(), Some(3));
+        assert_eq!(element.is_deleted(), Some(true));
+        assert_eq!(element.url(), None);
+
+        let relative = NodeType::Resource { url: "/pixel.gif".into() };
+        assert_eq!(relative.url().map(|url| url.as_str()), Some("/pixel.gif"));
+        assert!(relative.parsed_url().is_none());
+        assert_eq!(NodeType::WebApi { method: "Navigator.userAgent".to_string() }.method(), Some("Navigator.userAgent"));
+        assert_eq!(NodeType::Parser {}.kind(), NodeKind::Parser);
+    }
+
+    #[test]
+    fn test_url() {
+        let url = Url::from("HTTPS://Example.com");
+        assert_eq!(url.as_str(), "HTTPS://Example.com");
+        assert_eq!(url.parsed().map(|url| url.as_str()), Some("https://example.com/"));
+        assert_eq!(serde_json::to_value(&url).unwrap(), "HTTPS://Example.com");
+        assert_ne!(url, Url::from("https://example.com/"));
+
+        let unparseable = Url::from("http://[::1");
+        assert!(unparseable.parsed().is_none());
+        assert_eq!(unparseable, "http://[::1");
+    }
+
+    #[test]
+    fn test_edge_type_accessors() {
+        let start = EdgeType::RequestStart { request_type: RequestType::Image, status: "started".to_string(), request_id: 4 };
+        assert_eq!(start.kind(), EdgeKind::RequestStart);
+        assert_eq!(start.request_id(), Some(4));
+        assert_eq!(start.key(), None);
+        assert_eq!(start.response_size(), None);
+
+        let set = EdgeType::SetAttribute { key: "src".to_string(), value: Some("a.png".to_string()), is_style: false, script_position: Some(12) };
+        assert_eq!(set.kind(), EdgeKind::SetAttribute);
+        assert_eq!(set.key(), Some("src"));
+        assert_eq!(set.value(), Some("a.png"));
+        assert_eq!(set.script_position(), Some(12));
+        assert_eq!(set.request_id(), None);
+
+        let execute = EdgeType::ExecuteFromAttribute { attr_name: "onload".to_string() };
+        assert_eq!(execute.kind(), EdgeKind::ExecuteFromAttribute);
+        assert_eq!(execute.key(), Some("onload"));
+
+        let listener = EdgeType::AddEventListener { key: "message".to_string(), event_listener_id: 1, script_id: 9 };
+        assert_eq!(listener.script_id(), Some(9));
+        assert_eq!(listener.key(), Some("message"));
+
+        // Every kind is listed exactly once.
+        let kinds = EdgeKind::ALL.iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(kinds.len(), EdgeKind::ALL.len());
+        assert_eq!(NodeKind::ALL.iter().collect::<std::collections::HashSet<_>>().len(), NodeKind::ALL.len());
+    }
+
+    #[test]
+    fn test_script_and_resource_types() {
+        for script_type in ["classic", "module", "importmap"] {
+            assert_eq!(ScriptType::from(script_type).as_str(), script_type);
+            assert_eq!(serde_json::to_value(ScriptType::from(script_type)).unwrap(), script_type);
+        }
+        assert_eq!(ScriptType::from("module"), ScriptType::Module);
+
+        for resource_type in ["image", "css", "text track", "manifest", "beacon"] {
+            assert_eq!(ResourceType::from(resource_type).as_str(), resource_type);
+            assert_eq!(serde_json::to_value(ResourceType::from(resource_type)).unwrap(), resource_type);
+        }
+        assert_eq!(ResourceType::from("text track"), ResourceType::TextTrack);
+        assert_eq!(ResourceType::from("beacon"), ResourceType::Other("beacon".to_string()));
+    }
 }
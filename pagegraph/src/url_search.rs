@@ -0,0 +1,343 @@
+//! Utilities for searching URLs recorded anywhere in a PageGraph.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::graph::{EdgeId, Node, NodeId, PageGraph};
+use crate::types::{EdgeType, NodeType};
+
+/// HTML attributes whose values are interpreted as URLs when set on an element.
+pub(crate) const URL_ATTRIBUTES: [&str; 4] = ["src", "href", "action", "data"];
+
+/// HTML attributes holding a list of image candidates, as on `<img>` elements and on `<source>`
+/// elements within a `<picture>`.
+pub(crate) const SRCSET_ATTRIBUTES: [&str; 2] = ["srcset", "imagesrcset"];
+
+/// Splits a `srcset` attribute value into the URLs of its image candidates, dropping their width
+/// and density descriptors. Follows the HTML parsing rules, so URLs may contain commas as long as
+/// they don't end with one.
+pub fn srcset_urls(srcset: &str) -> Vec<&str> {
+    let mut urls = vec![];
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+        let (candidate, after) = rest.split_at(rest.find(|c: char| c.is_ascii_whitespace()).unwrap_or(rest.len()));
+        let url = candidate.trim_end_matches(',');
+        urls.push(url);
+        rest = if url.len() < candidate.len() {
+            // Trailing commas end a candidate without descriptors.
+            after
+        } else {
+            after.split_once(',').map(|(_descriptors, rest)| rest).unwrap_or("")
+        };
+    }
+}
+
+/// Matches a CSS `url()`, capturing its double-quoted, single-quoted, or unquoted URL.
+static CSS_URL_PATTERN: OnceLock<Regex> = OnceLock::new();
+
+/// Returns the URL of every CSS `url()` in a stylesheet or `style` attribute, quoted or not.
+/// References to fragments within the document, like `url(#clip)`, are skipped.
+pub fn css_urls(css: &str) -> Vec<&str> {
+    let pattern = CSS_URL_PATTERN.get_or_init(|| {
+        Regex::new(r#"(?i)\burl\(\s*(?:"([^"]*)"|'([^']*)'|([^"'()\s]*))\s*\)"#).unwrap()
+    });
+    pattern.captures_iter(css)
+        .filter_map(|captures| captures.iter().skip(1).flatten().next())
+        .map(|url| url.as_str())
+        .filter(|url| !url.is_empty() && !url.starts_with('#'))
+        .collect()
+}
+
+/// The URLs in the value of an attribute, which may hold a single URL, a `srcset` list, or inline
+/// CSS.
+pub(crate) fn attribute_urls<'a>(key: &str, value: &'a str) -> Vec<&'a str> {
+    if URL_ATTRIBUTES.contains(&key) {
+        vec![value]
+    } else if SRCSET_ATTRIBUTES.contains(&key) {
+        srcset_urls(value)
+    } else if key == "style" {
+        css_urls(value)
+    } else {
+        vec![]
+    }
+}
+
+/// A pattern that can be matched against URLs from the graph.
+#[derive(Debug, Clone)]
+pub struct UrlPattern(Regex);
+
+impl UrlPattern {
+    /// Builds a pattern from a glob, where `*` matches any sequence of characters and `?` matches
+    /// any single character. The glob must match the entire URL.
+    pub fn glob(glob: &str) -> Self {
+        let mut re = String::with_capacity(glob.len() + 2);
+        re.push('^');
+        glob.chars().for_each(|c| match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        });
+        re.push('$');
+        Self(Regex::new(&re).expect("escaped glob should always be a valid regex"))
+    }
+
+    /// Builds a pattern from a regular expression, which can match anywhere within the URL.
+    pub fn regex(re: &str) -> Result<Self, regex::Error> {
+        Ok(Self(Regex::new(re)?))
+    }
+
+    pub fn is_match(&self, url: &str) -> bool {
+        self.0.is_match(url)
+    }
+}
+
+/// Describes where in the graph a matching URL was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum UrlLocation {
+    /// The `url` of a [`Resource`](NodeType::Resource) node.
+    Resource(NodeId),
+    /// The `url` of a [`Script`](NodeType::Script) node fetched from a remote source.
+    ScriptSrc(NodeId),
+    /// The `url` of a [`DomRoot`](NodeType::DomRoot) node, i.e. the URL of a frame's document.
+    FrameUrl(NodeId),
+    /// The value of a URL-bearing attribute (e.g. `src` or `href`), a `srcset` candidate, or a CSS
+    /// `url()` in a `style` attribute, assigned by a [`SetAttribute`](EdgeType::SetAttribute) edge.
+    Attribute(EdgeId),
+    /// A CSS `url()` in the text of an inline `<style>` element, identified by the element.
+    Stylesheet(NodeId),
+}
+
+/// A single URL matching a [`UrlPattern`].
+#[derive(Debug, Clone, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct UrlHit<'a> {
+    pub url: &'a str,
+    pub location: UrlLocation,
+}
+
+/// How a [`UrlReference`] refers to its URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceKind {
+    /// An image candidate in a `srcset` attribute.
+    Srcset,
+    /// A CSS `url()`, in a `style` attribute or an inline stylesheet.
+    CssUrl,
+}
+
+/// A URL fetched on behalf of an element or stylesheet, without being the element's `src` or
+/// `href`. PageGraph doesn't always attribute these fetches to a request, so they're found from
+/// the markup instead.
+#[derive(Debug, Clone)]
+pub struct UrlReference {
+    /// The URL, resolved against the URL of the owner's document.
+    pub url: String,
+    /// The element with the `srcset` or `style` attribute, or the `<style>` element.
+    pub owner: NodeId,
+    /// The [`SetAttribute`](EdgeType::SetAttribute) edge the URL was found in, if it wasn't found
+    /// in an inline stylesheet.
+    pub edge: Option<EdgeId>,
+    pub kind: ReferenceKind,
+}
+
+impl UrlReference {
+    /// The adblock request type the URL would be fetched with.
+    pub fn request_type(&self) -> &'static str {
+        if self.kind == ReferenceKind::Srcset {
+            return "image";
+        }
+        let path = self.url.split(['?', '#']).next().unwrap().to_ascii_lowercase();
+        if [".woff", ".woff2", ".ttf", ".otf", ".eot"].iter().any(|extension| path.ends_with(extension)) {
+            "font"
+        } else if path.ends_with(".css") {
+            "stylesheet"
+        } else {
+            "image"
+        }
+    }
+}
+
+impl PageGraph {
+    /// Returns every URL referenced by a `srcset` candidate or a CSS `url()`, in the attributes of
+    /// elements or in inline stylesheets. Relative URLs are resolved against the URL of the
+    /// owner's document, and those that can't be resolved are skipped.
+    pub fn url_references(&self) -> Vec<UrlReference> {
+        let mut references = vec![];
+        let mut add = |owner: &Node, edge: Option<EdgeId>, kind: ReferenceKind, urls: Vec<&str>| {
+            let base = self.dom_root_for_html_node(owner)
+                .and_then(|root| root.node_type.url())
+                .unwrap_or(&self.desc.url);
+            let base = match url::Url::parse(base) {
+                Ok(base) => base,
+                Err(_) => return,
+            };
+            for url in urls {
+                if let Ok(url) = base.join(url) {
+                    references.push(UrlReference { url: url.to_string(), owner: owner.id, edge, kind });
+                }
+            }
+        };
+
+        for edge in self.edges.values() {
+            let (key, value) = match &edge.edge_type {
+                EdgeType::SetAttribute { key, value: Some(value), .. } => (key.as_str(), value),
+                _ => continue,
+            };
+            let (kind, urls) = if SRCSET_ATTRIBUTES.contains(&key) {
+                (ReferenceKind::Srcset, srcset_urls(value))
+            } else if key == "style" {
+                (ReferenceKind::CssUrl, css_urls(value))
+            } else {
+                continue;
+            };
+            add(self.target_node(edge), Some(edge.id), kind, urls);
+        }
+        for node in self.nodes.values() {
+            if let Some((style, urls)) = self.stylesheet_urls(node) {
+                add(style, None, ReferenceKind::CssUrl, urls);
+            }
+        }
+        references.sort_by_key(|reference| (reference.owner, reference.edge));
+        references
+    }
+
+    /// Returns every URL recorded in the graph that matches the given pattern.
+    ///
+    /// In addition to [`Resource`](NodeType::Resource) URLs, this searches the sources of fetched
+    /// scripts, the URLs of frame documents, URL-bearing attributes set on HTML elements including
+    /// `srcset` candidates and inline styles, and the `url()`s of inline stylesheets.
+    pub fn find_urls<'a>(&'a self, pattern: &UrlPattern) -> Vec<UrlHit<'a>> {
+        let node_hits = self.nodes.values().flat_map(|node| {
+            let (url, location) = match &node.node_type {
+                NodeType::Resource { url } => (url, UrlLocation::Resource(node.id)),
+                NodeType::Script { url: Some(url), .. } => (url, UrlLocation::ScriptSrc(node.id)),
+                NodeType::DomRoot { url: Some(url), .. } => (url, UrlLocation::FrameUrl(node.id)),
+                _ => return self.stylesheet_urls(node)
+                    .map(|(style, urls)| urls.into_iter().map(|url| UrlHit { url, location: UrlLocation::Stylesheet(style.id) }).collect())
+                    .unwrap_or_default(),
+            };
+            vec![UrlHit { url, location }]
+        });
+
+        let edge_hits = self.edges.values().flat_map(|edge| match &edge.edge_type {
+            EdgeType::SetAttribute { key, value: Some(value), .. } => attribute_urls(key, value).into_iter()
+                .map(|url| UrlHit { url, location: UrlLocation::Attribute(edge.id) })
+                .collect(),
+            _ => vec![],
+        });
+
+        node_hits
+            .chain(edge_hits)
+            .filter(|hit| pattern.is_match(hit.url))
+            .collect()
+    }
+
+    /// The `url()`s in a text node within an inline `<style>` element, along with the element.
+    fn stylesheet_urls<'a>(&'a self, node: &'a Node) -> Option<(&'a Node, Vec<&'a str>)> {
+        let text = match &node.node_type {
+            NodeType::TextNode { text: Some(text), .. } => text,
+            _ => return None,
+        };
+        let style = self.dom_parent(node)?;
+        match &style.node_type {
+            NodeType::HtmlElement { tag_name, .. } if tag_name.eq_ignore_ascii_case("style") => Some((style, css_urls(text))),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PageGraphBuilder;
+    use crate::types::RequestType;
+
+    #[test]
+    fn test_glob_patterns() {
+        let pattern = UrlPattern::glob("https://*.example.com/*.js");
+        assert!(pattern.is_match("https://cdn.example.com/lib.js"));
+        assert!(pattern.is_match("https://a.b.example.com/path/to/lib.js"));
+        assert!(!pattern.is_match("https://example.com/lib.js"));
+        assert!(!pattern.is_match("https://cdn.example.com/lib.js?v=1"));
+
+        let pattern = UrlPattern::glob("http?://example.com/a+b(c)");
+        assert!(pattern.is_match("https://example.com/a+b(c)"));
+        assert!(!pattern.is_match("http://example.com/a+b(c)"));
+    }
+
+    #[test]
+    fn test_regex_patterns() {
+        let pattern = UrlPattern::regex(r"doubleclick\.net").unwrap();
+        assert!(pattern.is_match("https://ad.doubleclick.net/pixel"));
+        assert!(!pattern.is_match("https://example.com/"));
+
+        assert!(UrlPattern::regex("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_srcset_and_css_urls() {
+        assert_eq!(srcset_urls("a.jpg"), vec!["a.jpg"]);
+        assert_eq!(srcset_urls(" small.jpg 480w,\n large.jpg 1080w "), vec!["small.jpg", "large.jpg"]);
+        assert_eq!(srcset_urls("a.jpg,b.jpg 2x"), vec!["a.jpg,b.jpg"]);
+        assert_eq!(srcset_urls("a.jpg, b.jpg 2x,,"), vec!["a.jpg", "b.jpg"]);
+        assert!(srcset_urls(" , ").is_empty());
+
+        let css = r#"background: URL( "bg.png" ) no-repeat; mask: url('m.svg'); src: url(f.woff2) format("woff2"); clip-path: url(#c)"#;
+        assert_eq!(css_urls(css), vec!["bg.png", "m.svg", "f.woff2"]);
+        assert!(css_urls("curl(x.png) url()").is_empty());
+    }
+
+    #[test]
+    fn test_srcset_and_css_url_references() {
+        let mut builder = PageGraphBuilder::new("https://example.com/page/");
+        let parser = builder.parser();
+        let root = builder.dom_root();
+        let picture = builder.add_element(parser, root, "picture");
+        let source = builder.add_element(parser, picture, "source");
+        let srcset = builder.set_attribute(parser, source, "srcset", "https://ads.net/wide.jpg 2x, narrow.jpg");
+        let img = builder.add_element(parser, picture, "img");
+        builder.set_attribute(parser, img, "src", "https://ads.net/fallback.jpg");
+        let div = builder.add_element(parser, root, "div");
+        let style_attribute = builder.set_attribute(parser, div, "style", "background: url(/banner.png)");
+        let style = builder.add_element(parser, root, "style");
+        builder.add_text(parser, style, "@font-face { src: url(https://ads.net/f.woff) }");
+        let request_id = builder.add_request(picture, "https://ads.net/wide.jpg", RequestType::Image);
+        let resource = builder.resource_of(request_id);
+        let graph = builder.build();
+
+        let hits = graph.find_urls(&UrlPattern::glob("*ads.net*"));
+        let locations = hits.iter().map(|hit| (hit.url, hit.location)).collect::<Vec<_>>();
+        assert_eq!(locations.len(), 4);
+        assert!(locations.contains(&("https://ads.net/f.woff", UrlLocation::Stylesheet(style))));
+        assert!(locations.contains(&("https://ads.net/wide.jpg", UrlLocation::Attribute(srcset))));
+        assert!(locations.contains(&("https://ads.net/wide.jpg", UrlLocation::Resource(resource))));
+
+        let references = graph.url_references().into_iter()
+            .map(|reference| (reference.url.clone(), reference.owner, reference.edge, reference.request_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(references.len(), 4);
+        assert!(references.contains(&("https://example.com/page/narrow.jpg".to_string(), source, Some(srcset), "image")));
+        assert!(references.contains(&("https://example.com/banner.png".to_string(), div, Some(style_attribute), "image")));
+        assert!(references.contains(&("https://ads.net/f.woff".to_string(), style, None, "font")));
+
+        let matches = graph.resources_matching_filters(&graph, vec!["||ads.net^$image".to_string(), "||ads.net^$font".to_string()]);
+        let matches = serde_json::to_value(&matches).unwrap();
+        let urls = matches.as_array().unwrap().iter().map(|matched| matched["url"].as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(urls, vec!["https://ads.net/wide.jpg", "https://ads.net/f.woff"]);
+        // The fetched image is attributed to the `<source>` that referenced it.
+        assert_eq!(matches[0]["requests"].as_array().unwrap().len(), 1);
+        assert_eq!(matches[0]["references"][0]["node_id"], format!("{}", source));
+        assert_eq!(matches[0]["references"][0]["kind"], "srcset");
+        // The font was never recorded as requested, so the stylesheet stands in for it.
+        assert_eq!(matches[1]["node_id"], format!("{}", style));
+        assert_eq!(matches[1]["request_types"], serde_json::json!(["font"]));
+        assert_eq!(matches[1]["references"][0]["blocking_filter"], "||ads.net^$font");
+    }
+}